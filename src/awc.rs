@@ -0,0 +1,493 @@
+// Audio wave containers (.awc).
+//
+// An AWC holds streams, each identified by the low 29 bits of the JOAAT of its name and made
+// of typed chunks: `format` (sample count, rate and codec) and `data` (the samples) for
+// ordinary single-channel streams, or one stream with a `streamformat` chunk describing
+// several channels interleaved in blocks. Each block starts with a 0x18-byte header per
+// channel (start entry, entry count, samples to skip, sample count), then a seek table of
+// one u32 per entry for each channel, padding to 0x800, and each channel's entries of 0x800
+// bytes in turn. Header: "ADAT", version u16, flags u16, stream
+// count u32, data offset u32, optional u16 chunk indices, then one u32 per stream
+// (`chunk count << 29 | id`) and one u64 per chunk (`type << 56 | size << 28 | offset`).
+// Layouts follow CodeWalker's `AwcFile`.
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::{u16_at, u32_at, u64_at};
+
+const MAGIC: &[u8; 4] = b"ADAT";
+/// Chunk indices follow the header.
+const FLAG_CHUNK_INDICES: u16 = 0x1;
+/// One stream holds several channels, interleaved in blocks.
+const FLAG_MULTI_CHANNEL: u16 = 0x4;
+/// Flags bits set in every file.
+const FLAGS_BASE: u16 = 0xFF00;
+
+pub const ID_MASK: u32 = 0x1FFF_FFFF;
+
+/// Chunk types: the low byte of the JOAAT of the chunk name.
+pub mod chunk {
+    pub const DATA: u8 = 0x55;
+    pub const FORMAT: u8 = 0xFA;
+    pub const STREAM_FORMAT: u8 = 0x48;
+    pub const PEAK: u8 = 0x36;
+    pub const SEEK_TABLE: u8 = 0xA3;
+    pub const MARKERS: u8 = 0xBD;
+    pub const ANIMATION: u8 = 0x5C;
+}
+
+pub fn chunk_name(kind: u8) -> String {
+    match kind {
+        chunk::DATA          => "data".into(),
+        chunk::FORMAT        => "format".into(),
+        chunk::STREAM_FORMAT => "streamformat".into(),
+        chunk::PEAK          => "peak".into(),
+        chunk::SEEK_TABLE    => "seektable".into(),
+        chunk::MARKERS       => "markers".into(),
+        chunk::ANIMATION     => "animation".into(),
+        k                    => format!("0x{:02X}", k),
+    }
+}
+
+pub const CODEC_PCM: u8 = 0;
+pub const CODEC_ADPCM: u8 = 4;
+
+/// Size of a channel's data entries inside a multi-channel block.
+const BLOCK_ENTRY: usize = 0x800;
+const CHANNEL_HEADER_SIZE: usize = 0x18;
+
+pub fn codec_name(codec: u8) -> String {
+    match codec {
+        CODEC_PCM   => "PCM".into(),
+        CODEC_ADPCM => "ADPCM".into(),
+        5           => "XMA2".into(),
+        7           => "MPEG".into(),
+        8           => "Vorbis".into(),
+        c           => format!("codec {}", c),
+    }
+}
+
+pub struct Chunk {
+    pub kind: u8,
+    pub data: Vec<u8>,
+}
+
+pub struct Stream {
+    pub id    : u32,
+    pub chunks: Vec<Chunk>,
+}
+
+/// The `format` chunk of a single-channel stream.
+#[derive(Clone, Copy)]
+pub struct Format {
+    pub samples    : u32,
+    pub loop_point : i32,
+    pub sample_rate: u16,
+    pub headroom   : i16,
+    pub codec      : u8,
+}
+
+/// One channel of an interleaved multi-channel stream.
+pub struct Channel {
+    pub id         : u32,
+    pub samples    : u32,
+    pub sample_rate: u16,
+    pub codec      : u8,
+}
+
+pub struct Awc {
+    pub version: u16,
+    pub flags  : u16,
+    pub streams: Vec<Stream>,
+}
+
+impl Stream {
+    pub fn chunk(&self, kind: u8) -> Option<&[u8]> {
+        self.chunks.iter().find(|c| c.kind == kind).map(|c| c.data.as_slice())
+    }
+
+    pub fn format(&self) -> Option<Format> {
+        let f = self.chunk(chunk::FORMAT).filter(|f| f.len() >= 20)?;
+        Some(Format {
+            samples    : u32_at(f, 0),
+            loop_point : u32_at(f, 4) as i32,
+            sample_rate: u16_at(f, 8),
+            headroom   : u16_at(f, 10) as i16,
+            codec      : f[19],
+        })
+    }
+
+    /// Block count and size of a multi-channel stream, from its `streamformat` chunk.
+    pub fn blocks(&self) -> Option<(usize, usize)> {
+        let f = self.chunk(chunk::STREAM_FORMAT).filter(|f| f.len() >= 12)?;
+        Some((u32_at(f, 0) as usize, u32_at(f, 4) as usize))
+    }
+
+    /// Channels of a multi-channel stream, from its `streamformat` chunk.
+    pub fn channels(&self) -> Vec<Channel> {
+        let Some(f) = self.chunk(chunk::STREAM_FORMAT).filter(|f| f.len() >= 12) else { return Vec::new() };
+        f[12..].chunks_exact(16).take(u32_at(f, 8) as usize).map(|c| Channel {
+            id         : u32_at(c, 0),
+            samples    : u32_at(c, 4),
+            sample_rate: u16_at(c, 10),
+            codec      : c[12],
+        }).collect()
+    }
+
+    /// De-interleave a multi-channel stream and decode each channel, in `channels()` order;
+    /// `None` for channels in codecs other than PCM and ADPCM.
+    pub fn decode_channels(&self) -> Result<Vec<Option<Vec<i16>>>> {
+        let channels = self.channels();
+        let (count, size) = self.blocks().context("stream has no streamformat chunk")?;
+        if size == 0 { bail!("streamformat chunk gives a block size of 0"); }
+        let data = self.chunk(chunk::DATA).context("multi-channel stream has no data chunk")?;
+
+        let mut out: Vec<Option<Vec<i16>>> = channels.iter().map(|c| is_decoded(c.codec).then(Vec::new)).collect();
+        for (b, block) in data.chunks(size).take(count).enumerate() {
+            let headers = block.get(..channels.len() * CHANNEL_HEADER_SIZE)
+                .with_context(|| format!("block {}: channel headers run past the block", b))?;
+            let seek_tables: usize = headers.chunks_exact(CHANNEL_HEADER_SIZE).map(|h| u32_at(h, 4) as usize * 4).sum();
+            let mut pos = (headers.len() + seek_tables).next_multiple_of(BLOCK_ENTRY);
+
+            for (i, h) in headers.chunks_exact(CHANNEL_HEADER_SIZE).enumerate() {
+                let (entries, skip, samples) = (u32_at(h, 4) as usize, u32_at(h, 8) as usize, u32_at(h, 12) as usize);
+                let part = block.get(pos..pos + entries * BLOCK_ENTRY)
+                    .with_context(|| format!("block {}: data of channel {} runs past the block", b, i))?;
+                pos += part.len();
+                if let Some(decoded) = &mut out[i] {
+                    let all = decode(channels[i].codec, part, usize::MAX).unwrap_or_default();
+                    let take = if samples == 0 { usize::MAX } else { samples };
+                    decoded.extend(all.into_iter().skip(skip).take(take));
+                }
+            }
+        }
+        for (samples, c) in out.iter_mut().zip(&channels) {
+            if let Some(s) = samples { s.truncate(c.samples as usize); }
+        }
+        Ok(out)
+    }
+}
+
+impl Awc {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if !data.starts_with(MAGIC) {
+            if data.starts_with(b"TADA") {
+                bail!("big-endian (console) AWC files are not supported");
+            }
+            bail!("not an AWC file (no ADAT magic; it may be encrypted)");
+        }
+        let header = data.get(..16).context("truncated header")?;
+        let (version, flags, count) = (u16_at(header, 4), u16_at(header, 6), u32_at(header, 8) as usize);
+
+        let mut pos = 16;
+        if flags & FLAG_CHUNK_INDICES != 0 { pos += count * 2; }
+        let infos = data.get(pos..pos + count * 4).context("stream table runs past the end of the file")?;
+        pos += count * 4;
+
+        let mut streams = Vec::with_capacity(count);
+        for info in infos.chunks_exact(4) {
+            let info = u32_at(info, 0);
+            let (chunk_count, id) = ((info >> 29) as usize, info & ID_MASK);
+            let table = data.get(pos..pos + chunk_count * 8).context("chunk table runs past the end of the file")?;
+            pos += chunk_count * 8;
+
+            let chunks = table.chunks_exact(8).map(|c| {
+                let raw = u64_at(c, 0);
+                let (kind, size, offset) = ((raw >> 56) as u8, (raw >> 28 & 0x0FFF_FFFF) as usize, (raw & 0x0FFF_FFFF) as usize);
+                let data = data.get(offset..offset + size)
+                    .with_context(|| format!("chunk 0x{:02X} of stream 0x{:08X} runs past the end of the file", kind, id))?;
+                Ok(Chunk { kind, data: data.to_vec() })
+            }).collect::<Result<Vec<_>>>()?;
+            streams.push(Stream { id, chunks });
+        }
+        Ok(Self { version, flags, streams })
+    }
+
+    pub fn is_multi_channel(&self) -> bool {
+        self.flags & FLAG_MULTI_CHANNEL != 0
+    }
+
+    /// Build a single-channel AWC; streams are sorted by ID and chunks 16-byte aligned.
+    pub fn build(mut streams: Vec<Stream>) -> Result<Vec<u8>> {
+        streams.sort_by_key(|s| s.id);
+        if let Some(w) = streams.windows(2).find(|w| w[0].id == w[1].id) {
+            bail!("stream ID 0x{:08X} appears more than once", w[0].id);
+        }
+        let chunk_count: usize = streams.iter().map(|s| s.chunks.len()).sum();
+        let header_size = 16 + streams.len() * 4 + chunk_count * 8;
+
+        let mut out = Vec::with_capacity(header_size);
+        out.extend(MAGIC);
+        out.extend(1u16.to_le_bytes());
+        out.extend(FLAGS_BASE.to_le_bytes());
+        out.extend(u32::try_from(streams.len())?.to_le_bytes());
+        out.extend(u32::try_from(header_size)?.to_le_bytes());
+        for s in &streams {
+            if s.chunks.len() > 7 { bail!("stream 0x{:08X} has more than 7 chunks", s.id); }
+            out.extend(((s.chunks.len() as u32) << 29 | (s.id & ID_MASK)).to_le_bytes());
+        }
+
+        let mut offset = header_size;
+        let mut body = Vec::new();
+        for c in streams.iter().flat_map(|s| &s.chunks) {
+            offset = offset.next_multiple_of(16);
+            if offset + c.data.len() > 0x0FFF_FFFF {
+                bail!("AWC data exceeds 256 MB");
+            }
+            out.extend((u64::from(c.kind) << 56 | (c.data.len() as u64) << 28 | offset as u64).to_le_bytes());
+            body.resize(offset - header_size, 0);
+            body.extend_from_slice(&c.data);
+            offset += c.data.len();
+        }
+        out.extend(body);
+        Ok(out)
+    }
+}
+
+impl Format {
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut f = Vec::with_capacity(20);
+        f.extend(self.samples.to_le_bytes());
+        f.extend(self.loop_point.to_le_bytes());
+        f.extend(self.sample_rate.to_le_bytes());
+        f.extend(self.headroom.to_le_bytes());
+        // Loop begin/end, play end and play begin, all unused.
+        f.extend([0u8; 7]);
+        f.push(self.codec);
+        f
+    }
+}
+
+// ─── IMA ADPCM ───────────────────────────────────────────────────────────────
+
+/// ADPCM data comes in blocks of a 4-byte header (step index, unused, predictor i16) and
+/// 2044 bytes of nibbles, low nibble first.
+const ADPCM_BLOCK: usize = 2048;
+
+const STEPS: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60,
+    66, 73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371,
+    408, 449, 494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707,
+    1878, 2066, 2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132,
+    7845, 8630, 9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623,
+    27086, 29794, 32767,
+];
+const INDEX_STEPS: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Predictor and step index.
+struct AdpcmState(i32, i32);
+
+impl AdpcmState {
+    fn apply(&mut self, nibble: u8) -> i16 {
+        let step = STEPS[self.1 as usize];
+        let mut diff = step >> 3;
+        if nibble & 4 != 0 { diff += step; }
+        if nibble & 2 != 0 { diff += step >> 1; }
+        if nibble & 1 != 0 { diff += step >> 2; }
+        if nibble & 8 != 0 { diff = -diff; }
+        self.0 = (self.0 + diff).clamp(-32768, 32767);
+        self.1 = (self.1 + INDEX_STEPS[nibble as usize]).clamp(0, 88);
+        self.0 as i16
+    }
+
+    fn encode(&mut self, sample: i16) -> u8 {
+        let mut diff = sample as i32 - self.0;
+        let mut nibble = 0;
+        if diff < 0 { nibble = 8; diff = -diff; }
+        let mut step = STEPS[self.1 as usize];
+        for bit in [4, 2, 1] {
+            if diff >= step { nibble |= bit; diff -= step; }
+            step >>= 1;
+        }
+        self.apply(nibble);
+        nibble
+    }
+}
+
+/// Whether `decode` handles the codec.
+pub fn is_decoded(codec: u8) -> bool {
+    matches!(codec, CODEC_PCM | CODEC_ADPCM)
+}
+
+/// Decode up to `samples` samples of PCM or ADPCM data; `None` for other codecs.
+pub fn decode(codec: u8, data: &[u8], samples: usize) -> Option<Vec<i16>> {
+    match codec {
+        CODEC_PCM   => Some(data.chunks_exact(2).take(samples).map(|b| i16::from_le_bytes([b[0], b[1]])).collect()),
+        CODEC_ADPCM => Some(decode_adpcm(data, samples)),
+        _           => None,
+    }
+}
+
+/// Decode `samples` samples of ADPCM data to 16-bit PCM.
+pub fn decode_adpcm(data: &[u8], samples: usize) -> Vec<i16> {
+    let mut out = Vec::with_capacity(samples.min(data.len() * 2));
+    for block in data.chunks(ADPCM_BLOCK) {
+        if block.len() < 4 { break; }
+        let mut state = AdpcmState(u16_at(block, 2) as i16 as i32, (block[0] as i32).clamp(0, 88));
+        for &b in &block[4..] {
+            for nibble in [b & 0xF, b >> 4] {
+                if out.len() == samples { return out; }
+                out.push(state.apply(nibble));
+            }
+        }
+    }
+    out
+}
+
+/// Encode 16-bit PCM as ADPCM blocks.
+pub fn encode_adpcm(samples: &[i16]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut state = AdpcmState(0, 0);
+    for block in samples.chunks((ADPCM_BLOCK - 4) * 2) {
+        out.push(state.1 as u8);
+        out.push(0);
+        out.extend((state.0 as i16).to_le_bytes());
+        for pair in block.chunks(2) {
+            let low = state.encode(pair[0]);
+            let high = pair.get(1).map_or(0, |&s| state.encode(s));
+            out.push(low | high << 4);
+        }
+    }
+    out
+}
+
+// ─── WAV ─────────────────────────────────────────────────────────────────────
+
+/// A mono 16-bit PCM WAV file.
+pub fn to_wav(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend(b"RIFF");
+    out.extend((36 + data_len).to_le_bytes());
+    out.extend(b"WAVEfmt ");
+    out.extend(16u32.to_le_bytes());
+    out.extend(1u16.to_le_bytes());  // PCM
+    out.extend(1u16.to_le_bytes());  // mono
+    out.extend(sample_rate.to_le_bytes());
+    out.extend((sample_rate * 2).to_le_bytes());
+    out.extend(2u16.to_le_bytes());  // block align
+    out.extend(16u16.to_le_bytes()); // bits per sample
+    out.extend(b"data");
+    out.extend(data_len.to_le_bytes());
+    out.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+    out
+}
+
+/// Read a mono 16-bit PCM WAV file: `(sample rate, samples)`.
+pub fn from_wav(data: &[u8]) -> Result<(u32, Vec<i16>)> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        bail!("not a RIFF WAVE file");
+    }
+    let (mut pos, mut format, mut samples) = (12, None, None);
+    while pos + 8 <= data.len() {
+        let (id, len) = (&data[pos..pos + 4], u32_at(data, pos + 4) as usize);
+        let body = data.get(pos + 8..pos + 8 + len).context("WAV chunk runs past the end of the file")?;
+        match id {
+            b"fmt " if len >= 16 => format = Some((u16_at(body, 0), u16_at(body, 2), u32_at(body, 4), u16_at(body, 14))),
+            b"data" => samples = Some(body.chunks_exact(2).map(|s| u16_at(s, 0) as i16).collect::<Vec<_>>()),
+            _ => {}
+        }
+        pos += 8 + len + (len & 1);
+    }
+    let (tag, channels, rate, bits) = format.context("WAV file has no fmt chunk")?;
+    if tag != 1 || bits != 16 {
+        bail!("only 16-bit PCM WAV files are supported (format {}, {} bits)", tag, bits);
+    }
+    if channels != 1 {
+        bail!("{} channels; AWC streams are mono, so split stereo sounds into one WAV per channel", channels);
+    }
+    Ok((rate, samples.context("WAV file has no data chunk")?))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn sine(len: usize) -> Vec<i16> {
+        (0..len).map(|i| ((i as f32 * 0.05).sin() * 12000.0) as i16).collect()
+    }
+
+    fn stream(id: u32, codec: u8, data: Vec<u8>, samples: u32) -> Stream {
+        let format = Format { samples, loop_point: -1, sample_rate: 32000, headroom: -200, codec };
+        Stream { id, chunks: vec![Chunk { kind: chunk::FORMAT, data: format.to_bytes() }, Chunk { kind: chunk::DATA, data }] }
+    }
+
+    #[test]
+    fn build_round_trips() {
+        let pcm: Vec<u8> = sine(1000).iter().flat_map(|s| s.to_le_bytes()).collect();
+        let built = Awc::build(vec![
+            stream(0x0BAD_F00D, CODEC_ADPCM, encode_adpcm(&sine(5000)), 5000),
+            stream(0x0000_1234, CODEC_PCM, pcm.clone(), 1000),
+        ]).unwrap();
+
+        let awc = Awc::parse(&built).unwrap();
+        assert!(!awc.is_multi_channel());
+        let ids: Vec<_> = awc.streams.iter().map(|s| s.id).collect();
+        assert_eq!(ids, [0x1234, 0x0BAD_F00D]);
+        let f = awc.streams[0].format().unwrap();
+        assert_eq!((f.samples, f.loop_point, f.sample_rate, f.headroom, f.codec), (1000, -1, 32000, -200, CODEC_PCM));
+        assert_eq!(awc.streams[0].chunk(chunk::DATA).unwrap(), pcm);
+
+        assert_eq!(Awc::build(awc.streams).unwrap(), built);
+    }
+
+    #[test]
+    fn rejects_duplicate_streams() {
+        let streams = vec![stream(5, CODEC_PCM, vec![0; 4], 2), stream(5, CODEC_PCM, vec![0; 4], 2)];
+        assert!(Awc::build(streams).is_err());
+    }
+
+    #[test]
+    fn adpcm_round_trips() {
+        let samples = sine(5000);
+        let decoded = decode_adpcm(&encode_adpcm(&samples), samples.len());
+        assert_eq!(decoded.len(), samples.len());
+        // The step size starts at its smallest and needs a few samples to catch up.
+        let worst = samples.iter().zip(&decoded).skip(32).map(|(&a, &b)| (a as i32 - b as i32).abs()).max().unwrap();
+        assert!(worst < 600, "largest ADPCM error {}", worst);
+    }
+
+    #[test]
+    fn wav_round_trips() {
+        let samples = sine(777);
+        assert_eq!(from_wav(&to_wav(44100, &samples)).unwrap(), (44100, samples));
+    }
+
+    /// A multi-channel stream of one block holding two PCM channels, IDs 0x111 and 0x222, of
+    /// one 0x800-byte entry each; the first skips 10 samples and keeps 1000.
+    pub(crate) fn interleaved(left: &[i16], right: &[i16]) -> Stream {
+        let mut format = Vec::new();
+        for v in [1u32, 0x2000, 2] { format.extend(v.to_le_bytes()); }
+        for (id, samples) in [(0x111u32, 1000u32), (0x222, 1024)] {
+            format.extend(id.to_le_bytes());
+            format.extend(samples.to_le_bytes());
+            format.extend([0, 0, 0x80, 0xBB, CODEC_PCM, 0, 0, 0]);
+        }
+
+        let mut block = vec![0u8; 0x2000];
+        for (i, skip) in [(0usize, 10u32), (1, 0)] {
+            let h = &mut block[i * CHANNEL_HEADER_SIZE..];
+            h[4..8].copy_from_slice(&1u32.to_le_bytes());
+            h[8..12].copy_from_slice(&skip.to_le_bytes());
+        }
+        for (i, samples) in [left, right].into_iter().enumerate() {
+            let pcm: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+            block[BLOCK_ENTRY * (i + 1)..BLOCK_ENTRY * (i + 2)].copy_from_slice(&pcm);
+        }
+        Stream { id: 0, chunks: vec![
+            Chunk { kind: chunk::STREAM_FORMAT, data: format },
+            Chunk { kind: chunk::DATA, data: block },
+        ] }
+    }
+
+    #[test]
+    fn decodes_interleaved_channels() {
+        let (left, right) = (sine(1024), sine(1024).iter().map(|s| -s).collect::<Vec<_>>());
+        let stream = interleaved(&left, &right);
+        let channels = stream.channels();
+        assert_eq!(channels.iter().map(|c| (c.id, c.sample_rate)).collect::<Vec<_>>(), [(0x111, 48000), (0x222, 48000)]);
+        let decoded = stream.decode_channels().unwrap();
+        assert_eq!(decoded[0].as_deref(), Some(&left[10..1010]));
+        assert_eq!(decoded[1].as_deref(), Some(&right[..]));
+    }
+}
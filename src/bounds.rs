@@ -0,0 +1,371 @@
+// Collision bounds (.ybn, and the bounds embedded in fragments and drawables).
+//
+// Every bound starts with the same 0x70-byte header: type at 0x10, sphere radius at 0x14,
+// box max and margin at 0x20, box min at 0x30, box centre and the primitive's material at
+// 0x40, sphere centre and its flags at 0x50. Primitive bounds (sphere, capsule, box, disc,
+// cylinder) are described by that header alone. Geometry and BVH bounds add quantised
+// vertices and 16-byte polygons (triangles or primitives over those vertices) with a
+// material per polygon; composites hold child bounds with a transform and collision flags
+// each. Offsets follow CodeWalker's `Bounds`.
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::{f32_at, u16_at, u32_at, u64_at, Resource, SYSTEM_BASE};
+
+const HEADER_SIZE: usize = 0x70;
+const GEOMETRY_SIZE: usize = 0x130;
+const COMPOSITE_SIZE: usize = 0xB0;
+/// Composites nest rarely more than once; anything deeper is corrupt.
+const MAX_DEPTH: usize = 8;
+
+pub mod kind {
+    pub const SPHERE: u8 = 0;
+    pub const CAPSULE: u8 = 1;
+    pub const BOX: u8 = 3;
+    pub const GEOMETRY: u8 = 4;
+    pub const BVH: u8 = 8;
+    pub const COMPOSITE: u8 = 10;
+    pub const DISC: u8 = 12;
+    pub const CYLINDER: u8 = 13;
+}
+
+pub fn kind_name(kind: u8) -> String {
+    match kind {
+        kind::SPHERE    => "sphere".into(),
+        kind::CAPSULE   => "capsule".into(),
+        kind::BOX       => "box".into(),
+        kind::GEOMETRY  => "geometry".into(),
+        kind::BVH       => "bvh".into(),
+        kind::COMPOSITE => "composite".into(),
+        kind::DISC      => "disc".into(),
+        kind::CYLINDER  => "cylinder".into(),
+        15              => "cloth".into(),
+        k               => format!("type {}", k),
+    }
+}
+
+pub struct Bound {
+    pub kind         : u8,
+    pub box_min      : [f32; 3],
+    pub box_max      : [f32; 3],
+    pub sphere_center: [f32; 3],
+    pub sphere_radius: f32,
+    pub margin       : f32,
+    /// Material of a primitive bound.
+    pub material     : Material,
+    pub geometry     : Option<Geometry>,
+    pub children     : Vec<Child>,
+}
+
+/// A bound inside a composite.
+pub struct Child {
+    /// `None` for empty slots.
+    pub bound        : Option<Bound>,
+    /// Rows of the child-to-parent transform: the X, Y and Z axes, then the translation.
+    pub transform    : [[f32; 3]; 4],
+    /// What the child is (`COMPOSITE_FLAGS`).
+    pub type_flags   : u32,
+    /// What the child collides with (`COMPOSITE_FLAGS`).
+    pub include_flags: u32,
+}
+
+pub struct Geometry {
+    pub vertices : Vec<[f32; 3]>,
+    pub polygons : Vec<Polygon>,
+    pub materials: Vec<Material>,
+}
+
+pub struct Polygon {
+    pub shape   : Shape,
+    /// Index into the geometry's materials.
+    pub material: u8,
+}
+
+/// A polygon: a triangle or a primitive over the geometry's vertices.
+pub enum Shape {
+    Triangle([u16; 3]),
+    Sphere { center: u16, radius: f32 },
+    Capsule { ends: [u16; 2], radius: f32 },
+    /// Four alternate corners of the box; the other four mirror them through its centre.
+    Box([u16; 4]),
+    Cylinder { ends: [u16; 2], radius: f32 },
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Material {
+    /// Index into materials.dat (see `material_name`).
+    pub id           : u8,
+    pub procedural_id: u8,
+    pub room_id      : u8,
+    pub ped_density  : u8,
+    /// `MATERIAL_FLAGS` bits.
+    pub flags        : u16,
+    pub colour       : u8,
+}
+
+impl Material {
+    fn from_words(a: u32, b: u32) -> Self {
+        Self {
+            id           : a as u8,
+            procedural_id: (a >> 8) as u8,
+            room_id      : (a >> 16 & 0x1F) as u8,
+            ped_density  : (a >> 21 & 0x7) as u8,
+            flags        : (a >> 24) as u16 | ((b & 0xFF) as u16) << 8,
+            colour       : (b >> 8) as u8,
+        }
+    }
+}
+
+/// A bound reached by `Bound::walk`.
+pub struct Node<'a> {
+    /// Child indices from the root.
+    pub path     : Vec<usize>,
+    pub bound    : &'a Bound,
+    /// Into the root's space.
+    pub transform: [[f32; 3]; 4],
+    /// Type and include flags, for children of a composite.
+    pub flags    : Option<(u32, u32)>,
+}
+
+impl Bound {
+    /// This bound and every bound below it, depth first.
+    pub fn walk(&self) -> Vec<Node<'_>> {
+        let mut out = vec![Node { path: Vec::new(), bound: self, transform: IDENTITY, flags: None }];
+        let mut i = 0;
+        while i < out.len() {
+            let (path, bound, transform) = (out[i].path.clone(), out[i].bound, out[i].transform);
+            let children = bound.children.iter().enumerate().filter_map(|(c, child)| Some(Node {
+                path     : [path.as_slice(), &[c]].concat(),
+                bound    : child.bound.as_ref()?,
+                transform: compose(&child.transform, &transform),
+                flags    : Some((child.type_flags, child.include_flags)),
+            })).collect::<Vec<_>>();
+            out.splice(i + 1..i + 1, children);
+            i += 1;
+        }
+        out
+    }
+}
+
+pub const IDENTITY: [[f32; 3]; 4] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 0.0]];
+
+/// `p` taken through a transform of rows (X, Y, Z axes, translation).
+pub fn transform_point(m: &[[f32; 3]; 4], p: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| p[0] * m[0][i] + p[1] * m[1][i] + p[2] * m[2][i] + m[3][i])
+}
+
+/// The transform applying `inner`, then `outer`.
+fn compose(inner: &[[f32; 3]; 4], outer: &[[f32; 3]; 4]) -> [[f32; 3]; 4] {
+    let axis = |v: [f32; 3]| std::array::from_fn(|i| v[0] * outer[0][i] + v[1] * outer[1][i] + v[2] * outer[2][i]);
+    [axis(inner[0]), axis(inner[1]), axis(inner[2]), transform_point(outer, inner[3])]
+}
+
+/// Read the bound at the root of a .ybn resource.
+pub fn parse(res: &Resource) -> Result<Bound> {
+    read_bound(res, SYSTEM_BASE, 0)
+}
+
+fn vec3(data: &[u8], off: usize) -> [f32; 3] {
+    [f32_at(data, off), f32_at(data, off + 4), f32_at(data, off + 8)]
+}
+
+fn read_bound(res: &Resource, va: u64, depth: usize) -> Result<Bound> {
+    if depth > MAX_DEPTH { bail!("composites nest more than {} deep", MAX_DEPTH); }
+    let h = res.slice(va, HEADER_SIZE).with_context(|| format!("bound out of bounds (0x{:X})", va))?;
+    let kind = h[0x10];
+    let mut bound = Bound {
+        kind,
+        box_min      : vec3(h, 0x30),
+        box_max      : vec3(h, 0x20),
+        sphere_center: vec3(h, 0x50),
+        sphere_radius: f32_at(h, 0x14),
+        margin       : f32_at(h, 0x2C),
+        material     : Material {
+            id           : h[0x4C],
+            procedural_id: h[0x4D],
+            room_id      : h[0x4E] & 0x1F,
+            ped_density  : h[0x4E] >> 5,
+            flags        : h[0x5C] as u16,
+            colour       : h[0x5D],
+        },
+        geometry     : None,
+        children     : Vec::new(),
+    };
+
+    match kind {
+        kind::GEOMETRY | kind::BVH => bound.geometry = Some(read_geometry(res, va)?),
+        kind::COMPOSITE => bound.children = read_children(res, va, depth)?,
+        _ => {}
+    }
+    Ok(bound)
+}
+
+fn read_geometry(res: &Resource, va: u64) -> Result<Geometry> {
+    let g = res.slice(va, GEOMETRY_SIZE).with_context(|| format!("geometry bound out of bounds (0x{:X})", va))?;
+    let (quantum, center) = (vec3(g, 0x90), vec3(g, 0xA0));
+    let (vert_ptr, vert_count) = (u64_at(g, 0xB0), u32_at(g, 0xD0) as usize);
+    let (poly_ptr, poly_count) = (u64_at(g, 0x88), u32_at(g, 0xD4) as usize);
+    let (mat_ptr, mat_count) = (u64_at(g, 0xF0), g[0x120] as usize);
+
+    // Vertices are three i16s each, scaled by the quantum around the centre.
+    let vdata = res.slice(vert_ptr, vert_count * 6)
+        .with_context(|| format!("{} vertices out of bounds (0x{:X})", vert_count, vert_ptr))?;
+    let vertices: Vec<[f32; 3]> = vdata.chunks_exact(6)
+        .map(|v| std::array::from_fn(|i| u16_at(v, i * 2) as i16 as f32 * quantum[i] + center[i]))
+        .collect();
+
+    let pdata = res.slice(poly_ptr, poly_count * 16)
+        .with_context(|| format!("{} polygons out of bounds (0x{:X})", poly_count, poly_ptr))?;
+    let poly_materials = res.slice(u64_at(g, 0x118), poly_count);
+    let materials = match res.slice(mat_ptr, mat_count * 8) {
+        Some(m) => m.chunks_exact(8).map(|m| Material::from_words(u32_at(m, 0), u32_at(m, 4))).collect(),
+        None    => Vec::new(),
+    };
+
+    let index = |p: &[u8], off: usize| u16_at(p, off) & 0x7FFF;
+    let polygons = pdata.chunks_exact(16).enumerate().map(|(i, p)| {
+        let shape = match p[0] & 0x7 {
+            0 => Shape::Triangle([index(p, 4), index(p, 6), index(p, 8)]),
+            1 => Shape::Sphere { center: u16_at(p, 2), radius: f32_at(p, 4) },
+            2 => Shape::Capsule { ends: [u16_at(p, 2), u16_at(p, 8)], radius: f32_at(p, 4) },
+            3 => Shape::Box([u16_at(p, 4), u16_at(p, 6), u16_at(p, 8), u16_at(p, 10)]),
+            4 => Shape::Cylinder { ends: [u16_at(p, 2), u16_at(p, 8)], radius: f32_at(p, 4) },
+            t => bail!("polygon {} has unknown type {}", i, t),
+        };
+        let material = poly_materials.map_or(0, |m| m[i]);
+        Ok(Polygon { shape, material })
+    }).collect::<Result<Vec<_>>>()?;
+
+    let used = |p: &Polygon| match p.shape {
+        Shape::Triangle(v) => v.to_vec(),
+        Shape::Sphere { center, .. } => vec![center],
+        Shape::Capsule { ends, .. } | Shape::Cylinder { ends, .. } => ends.to_vec(),
+        Shape::Box(v) => v.to_vec(),
+    };
+    if let Some((i, v)) = polygons.iter().enumerate().flat_map(|(i, p)| used(p).into_iter().map(move |v| (i, v)))
+        .find(|&(_, v)| v as usize >= vertices.len()) {
+        bail!("polygon {} uses vertex {} of {}", i, v, vertices.len());
+    }
+    Ok(Geometry { vertices, polygons, materials })
+}
+
+fn read_children(res: &Resource, va: u64, depth: usize) -> Result<Vec<Child>> {
+    let c = res.slice(va, COMPOSITE_SIZE).with_context(|| format!("composite out of bounds (0x{:X})", va))?;
+    let count = u16_at(c, 0xA0) as usize;
+    let pointers = res.slice(u64_at(c, 0x70), count * 8).context("child list out of bounds")?;
+    let transforms = res.slice(u64_at(c, 0x78), count * 64);
+    let flags = res.slice(u64_at(c, 0x90), count * 8);
+
+    (0..count).map(|i| {
+        let bound = match u64_at(pointers, i * 8) {
+            0   => None,
+            ptr => Some(read_bound(res, ptr, depth + 1).with_context(|| format!("child {}", i))?),
+        };
+        // 4x4 matrices whose fourth column is unused.
+        let transform = transforms.map_or(IDENTITY, |t| std::array::from_fn(|row| vec3(t, i * 64 + row * 16)));
+        let (type_flags, include_flags) = flags.map_or((0, 0), |f| (u32_at(f, i * 8), u32_at(f, i * 8 + 4)));
+        Ok(Child { bound, transform, type_flags, include_flags })
+    }).collect()
+}
+
+// ─── Names ───────────────────────────────────────────────────────────────────
+
+/// Polygon and primitive flags, by bit.
+pub const MATERIAL_FLAGS: [&str; 16] = [
+    "STAIRS", "NOT_CLIMBABLE", "SEE_THROUGH", "SHOOT_THROUGH", "NOT_COVER", "WALKABLE_PATH",
+    "NO_CAM_COLLISION", "SHOOT_THROUGH_FX", "NO_DECAL", "NO_NAVMESH", "NO_RAGDOLL",
+    "VEHICLE_WHEEL", "NO_PTFX", "TOO_STEEP_FOR_PLAYER", "NO_NETWORK_SPAWN",
+    "NO_CAM_COLLISION_ALLOW_CLIPPING",
+];
+
+/// Composite child type and include flags, by bit.
+pub const COMPOSITE_FLAGS: [&str; 32] = [
+    "UNKNOWN", "MAP_WEAPON", "MAP_DYNAMIC", "MAP_ANIMAL", "MAP_COVER", "MAP_VEHICLE",
+    "VEHICLE_NOT_BVH", "VEHICLE_BVH", "VEHICLE_BOX", "PED", "RAGDOLL", "ANIMAL",
+    "ANIMAL_RAGDOLL", "OBJECT", "OBJECT_ENV_CLOTH", "PLANT", "PROJECTILE", "EXPLOSION",
+    "PICKUP", "FOLIAGE", "FORKLIFT_FORKS", "TEST_WEAPON", "TEST_CAMERA", "TEST_AI",
+    "TEST_SCRIPT", "TEST_VEHICLE_WHEEL", "GLASS", "MAP_RIVER", "SMOKE", "UNSMASHED",
+    "MAP_STAIRS", "MAP_DEEP_SURFACE",
+];
+
+/// Names of the set bits of `flags`.
+pub fn flag_names(flags: u32, names: &[&str]) -> Vec<String> {
+    (0..32).filter(|b| flags >> b & 1 != 0)
+        .map(|b| names.get(b).map_or_else(|| format!("BIT_{}", b), |n| n.to_string()))
+        .collect()
+}
+
+/// The materials of the base game's materials.dat, in ID order.
+const MATERIALS: [&str; 170] = [
+    "DEFAULT", "CONCRETE", "CONCRETE_POLISHED", "CONCRETE_PAVEMENT", "TARMAC", "TARMAC_PAINTED",
+    "TARMAC_POTHOLE", "ROCK", "ROCK_MOSSY", "STONE", "COBBLESTONE", "BRICK", "MARBLE",
+    "PAVING_SLAB", "SANDSTONE_SOLID", "SANDSTONE_BRITTLE", "SAND_LOOSE", "SAND_COMPACT",
+    "SAND_WET", "SAND_TRACK", "SAND_UNDERWATER", "SAND_DRY_DEEP", "SAND_WET_DEEP", "ICE",
+    "ICE_TARMAC", "SNOW_LOOSE", "SNOW_COMPACT", "SNOW_DEEP", "SNOW_TARMAC", "GRAVEL_SMALL",
+    "GRAVEL_LARGE", "GRAVEL_DEEP", "GRAVEL_TRAIN_TRACK", "DIRT_TRACK", "MUD_HARD",
+    "MUD_POTHOLE", "MUD_SOFT", "MUD_UNDERWATER", "MUD_DEEP", "MARSH", "MARSH_DEEP", "SOIL",
+    "CLAY_HARD", "CLAY_SOFT", "GRASS_LONG", "GRASS", "GRASS_SHORT", "HAY", "BUSHES", "TWIGS",
+    "LEAVES", "WOODCHIPS", "TREE_BARK", "METAL_SOLID_SMALL", "METAL_SOLID_MEDIUM",
+    "METAL_SOLID_LARGE", "METAL_HOLLOW_SMALL", "METAL_HOLLOW_MEDIUM", "METAL_HOLLOW_LARGE",
+    "METAL_CHAINLINK_SMALL", "METAL_CHAINLINK_LARGE", "METAL_CORRUGATED_IRON", "METAL_GRILLE",
+    "METAL_RAILING", "METAL_DUCT", "METAL_GARAGE_DOOR", "METAL_MANHOLE", "WOOD_SOLID_SMALL",
+    "WOOD_SOLID_MEDIUM", "WOOD_SOLID_LARGE", "WOOD_SOLID_POLISHED", "WOOD_FLOOR_DUSTY",
+    "WOOD_HOLLOW_SMALL", "WOOD_HOLLOW_MEDIUM", "WOOD_HOLLOW_LARGE", "WOOD_CHIPBOARD",
+    "WOOD_OLD_CREAKY", "WOOD_HIGH_DENSITY", "WOOD_LATTICE", "CERAMIC", "ROOF_TILE",
+    "ROOF_FELT", "FIBREGLASS", "TARPAULIN", "PLASTIC", "PLASTIC_HOLLOW",
+    "PLASTIC_HIGH_DENSITY", "PLASTIC_CLEAR", "PLASTIC_HOLLOW_CLEAR",
+    "PLASTIC_HIGH_DENSITY_CLEAR", "FIBREGLASS_HOLLOW", "RUBBER", "RUBBER_HOLLOW", "LINOLEUM",
+    "LAMINATE", "CARPET_SOLID", "CARPET_SOLID_DUSTY", "CARPET_FLOORBOARD", "CLOTH",
+    "PLASTER_SOLID", "PLASTER_BRITTLE", "CARDBOARD_SHEET", "CARDBOARD_BOX", "PAPER", "FOAM",
+    "FEATHER_PILLOW", "POLYSTYRENE", "LEATHER", "TVSCREEN", "SLATTED_BLINDS",
+    "GLASS_SHOOT_THROUGH", "GLASS_BULLETPROOF", "GLASS_OPAQUE", "PERSPEX", "CAR_METAL",
+    "CAR_PLASTIC", "CAR_SOFTTOP", "CAR_SOFTTOP_CLEAR", "CAR_GLASS_WEAK", "CAR_GLASS_MEDIUM",
+    "CAR_GLASS_STRONG", "CAR_GLASS_BULLETPROOF", "CAR_GLASS_OPAQUE", "WATER", "BLOOD", "OIL",
+    "PETROL", "FRESH_MEAT", "DRIED_MEAT", "EMISSIVE_GLASS", "EMISSIVE_PLASTIC",
+    "VFX_METAL_ELECTRIFIED", "VFX_METAL_WATER_TOWER", "VFX_METAL_STEAM", "VFX_METAL_FLAME",
+    "PHYS_NO_FRICTION", "PHYS_GOLF_BALL", "PHYS_TENNIS_BALL", "PHYS_CASTER",
+    "PHYS_CASTER_RUSTY", "PHYS_CAR_VOID", "PHYS_PED_CAPSULE", "PHYS_ELECTRIC_FENCE",
+    "PHYS_ELECTRIC_METAL", "PHYS_BARBED_WIRE", "PHYS_POOLTABLE_SURFACE",
+    "PHYS_POOLTABLE_CUSHION", "PHYS_POOLTABLE_BALL", "BUTTOCKS", "THIGH_LEFT", "SHIN_LEFT",
+    "FOOT_LEFT", "THIGH_RIGHT", "SHIN_RIGHT", "FOOT_RIGHT", "SPINE0", "SPINE1", "SPINE2",
+    "SPINE3", "CLAVICLE_LEFT", "UPPER_ARM_LEFT", "LOWER_ARM_LEFT", "HAND_LEFT",
+    "CLAVICLE_RIGHT", "UPPER_ARM_RIGHT", "LOWER_ARM_RIGHT", "HAND_RIGHT", "NECK", "HEAD",
+    "ANIMAL_DEFAULT",
+];
+
+/// The materials.dat name of a material ID, or `MATERIAL_<id>` past the known list.
+pub fn material_name(id: u8) -> String {
+    MATERIALS.get(id as usize).map_or_else(|| format!("MATERIAL_{}", id), |n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quarter turn about Z (X to Y), then a move by `t`.
+    fn turn(t: [f32; 3]) -> [[f32; 3]; 4] {
+        [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], t]
+    }
+
+    #[test]
+    fn composes_transforms_inner_first() {
+        assert_eq!(transform_point(&IDENTITY, [1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+        assert_eq!(transform_point(&turn([10.0, 0.0, 0.0]), [1.0, 0.0, 5.0]), [10.0, 1.0, 5.0]);
+
+        let (inner, outer) = (turn([1.0, 0.0, 0.0]), turn([0.0, 0.0, 2.0]));
+        let both = compose(&inner, &outer);
+        let p = [1.0, 2.0, 3.0];
+        assert_eq!(transform_point(&both, p), transform_point(&outer, transform_point(&inner, p)));
+        assert_eq!(transform_point(&both, [1.0, 0.0, 0.0]), [-1.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn names_flags_and_materials() {
+        assert_eq!(flag_names(0b101, &MATERIAL_FLAGS), ["STAIRS", "SEE_THROUGH"]);
+        assert_eq!(flag_names(1 << 20, &MATERIAL_FLAGS), ["BIT_20"]);
+        assert_eq!(flag_names(1 << 31, &COMPOSITE_FLAGS), ["MAP_DEEP_SURFACE"]);
+        assert_eq!(material_name(0), "DEFAULT");
+        assert_eq!(material_name(169), "ANIMAL_DEFAULT");
+        assert_eq!(material_name(200), "MATERIAL_200");
+    }
+}
@@ -0,0 +1,533 @@
+// Clip dictionaries (.ycd): named clips and the animations they play.
+//
+// The 0x40-byte `ClipDictionary` holds a hash table of clips: an array of bucket pointers,
+// each the head of a chain of 0x20-byte entries (clip hash, clip, next entry). A clip is
+// either a single animation played over a time range at a rate, or a list of such
+// animations played together. An animation has a frame count, a duration, the sequences
+// holding its compressed channel data, and one 4-byte ID per track: the bone tag, the value
+// format and the track kind (position, rotation, ...).
+//
+// Each sequence covers a run of frames (the animation's frame limit each) for every track.
+// Its data holds the channel headers, one bit-packed record per frame, the channel count of
+// each of the nine channel types, and per channel a u16 naming its track (high 14 bits) and
+// the component it fills (low 2 bits). A track's value is either one static channel or one
+// float channel per component; a cached quaternion component is rebuilt from the other
+// three. Offsets follow CodeWalker's `ClipDictionary`, `ClipMapEntry`, `ClipBase`,
+// `ClipAnimation`, `ClipAnimationList`, `Animation`, `Sequence` and `AnimChannel` types.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+
+use crate::resource::{f32_at, u16_at, u32_at, u64_at, Resource, SYSTEM_BASE};
+
+const DICTIONARY_SIZE: usize = 0x40;
+const MAP_ENTRY_SIZE: usize = 0x20;
+const CLIP_SIZE: usize = 0x70;
+const LIST_ENTRY_SIZE: usize = 0x18;
+const ANIMATION_SIZE: usize = 0x60;
+const SEQUENCE_HEADER_SIZE: usize = 0x20;
+
+/// `ClipBase` types.
+const CLIP_ANIMATION: u32 = 1;
+const CLIP_ANIMATION_LIST: u32 = 2;
+
+/// Longest hash chain followed before the table is considered corrupt.
+const MAX_CHAIN: usize = 4096;
+
+pub struct Clip {
+    pub hash      : u32,
+    /// Name as stored, e.g. `pack:/walk.clip`.
+    pub name      : String,
+    /// More than one for animation list clips.
+    pub animations: Vec<ClipAnimation>,
+    /// Seconds, taking the time range and rate into account.
+    pub duration  : f32,
+}
+
+pub struct ClipAnimation {
+    /// Range of the animation played, in seconds.
+    pub start    : f32,
+    pub end      : f32,
+    pub rate     : f32,
+    pub animation: Animation,
+}
+
+pub struct Animation {
+    pub frames     : u16,
+    /// Frames per sequence; the last frame of one sequence is the first of the next.
+    pub frame_limit: u16,
+    pub duration   : f32,
+    /// Pointers to the sequences, decoded by `keyframes`.
+    pub sequences  : Vec<u64>,
+    pub tracks     : Vec<Track>,
+}
+
+pub struct Track {
+    pub bone_id: u16,
+    /// Value format: 0 vector, 1 quaternion, 2 float.
+    pub format : u8,
+    pub kind   : u8,
+}
+
+/// Name of a track kind.
+pub fn track_name(kind: u8) -> String {
+    match kind {
+        0 => "position".into(),
+        1 => "rotation".into(),
+        2 => "scale".into(),
+        5 => "mover_translation".into(),
+        6 => "mover_rotation".into(),
+        k => format!("track_{}", k),
+    }
+}
+
+pub fn format_name(format: u8) -> String {
+    match format {
+        0 => "vector3".into(),
+        1 => "quaternion".into(),
+        2 => "float".into(),
+        f => format!("format_{}", f),
+    }
+}
+
+impl Clip {
+    /// The name without the `pack:/` prefix and `.clip` extension.
+    pub fn short_name(&self) -> &str {
+        let name = self.name.strip_prefix("pack:/").unwrap_or(&self.name);
+        name.strip_suffix(".clip").unwrap_or(name)
+    }
+}
+
+impl Animation {
+    /// Frames per second, from the frame count and duration.
+    pub fn fps(&self) -> f32 {
+        if self.duration > 0.0 && self.frames > 1 { (self.frames - 1) as f32 / self.duration } else { 0.0 }
+    }
+
+    /// Decode the sequences: for each track, its value at every frame (3 floats for vectors,
+    /// 4 for quaternions as x y z w, 1 for floats).
+    pub fn keyframes(&self, res: &Resource) -> Result<Vec<Vec<Vec<f32>>>> {
+        let sequences = self.sequences.iter().enumerate()
+            .map(|(i, &ptr)| read_sequence(res, ptr).with_context(|| format!("sequence {}", i)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut out: Vec<Vec<Vec<f32>>> = self.tracks.iter().map(|_| Vec::with_capacity(self.frames as usize)).collect();
+        if sequences.is_empty() { return Ok(out); }
+
+        let limit = self.frame_limit.max(1) as usize;
+        for frame in 0..self.frames as usize {
+            let index = (frame / limit).min(sequences.len() - 1);
+            let seq = &sequences[index];
+            let local = (frame - index * limit).min(seq.frames.saturating_sub(1));
+            for (t, track) in self.tracks.iter().enumerate() {
+                out[t].push(seq.evaluate(t, track.width(), local));
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Track {
+    /// Floats in one value of the track.
+    pub fn width(&self) -> usize {
+        match self.format {
+            0 => 3,
+            1 => 4,
+            _ => 1,
+        }
+    }
+}
+
+fn read_animation(res: &Resource, ptr: u64) -> Result<Animation> {
+    let a = res.slice(ptr, ANIMATION_SIZE).with_context(|| format!("animation out of bounds (0x{:X})", ptr))?;
+    let count = u16_at(a, 0x58) as usize;
+    let ids = if count == 0 { &[][..] } else {
+        res.slice(u64_at(a, 0x50), count * 4).context("track IDs out of bounds")?
+    };
+    let sequence_count = u16_at(a, 0x48) as usize;
+    let sequences = if sequence_count == 0 { &[][..] } else {
+        res.slice(u64_at(a, 0x40), sequence_count * 8).context("sequence list out of bounds")?
+    };
+    Ok(Animation {
+        frames     : u16_at(a, 0x12),
+        frame_limit: u16_at(a, 0x14),
+        duration   : f32_at(a, 0x18),
+        sequences  : sequences.chunks_exact(8).map(|p| u64_at(p, 0)).collect(),
+        tracks     : ids.chunks_exact(4).map(|t| Track { bone_id: u16_at(t, 0), format: t[2], kind: t[3] }).collect(),
+    })
+}
+
+fn read_clip(res: &Resource, hash: u32, ptr: u64) -> Result<Clip> {
+    let c = res.slice(ptr, CLIP_SIZE).with_context(|| format!("clip out of bounds (0x{:X})", ptr))?;
+    let name = match u64_at(c, 0x18) {
+        0   => String::new(),
+        ptr => res.c_str(ptr).unwrap_or_default(),
+    };
+
+    let (animations, duration) = match u32_at(c, 0x10) {
+        CLIP_ANIMATION => {
+            let (start, end, rate) = (f32_at(c, 0x58), f32_at(c, 0x5C), f32_at(c, 0x60));
+            let animation = read_animation(res, u64_at(c, 0x50))?;
+            let duration = if rate > 0.0 { (end - start) / rate } else { end - start };
+            (vec![ClipAnimation { start, end, rate, animation }], duration)
+        }
+        CLIP_ANIMATION_LIST => {
+            let count = u16_at(c, 0x58) as usize;
+            let entries = if count == 0 { &[][..] } else {
+                res.slice(u64_at(c, 0x50), count * LIST_ENTRY_SIZE).context("clip animation list out of bounds")?
+            };
+            let animations = entries.chunks_exact(LIST_ENTRY_SIZE).map(|e| Ok(ClipAnimation {
+                start    : f32_at(e, 0x00),
+                end      : f32_at(e, 0x04),
+                rate     : f32_at(e, 0x08),
+                animation: read_animation(res, u64_at(e, 0x10))?,
+            })).collect::<Result<_>>()?;
+            (animations, f32_at(c, 0x60))
+        }
+        kind => bail!("clip type {} is not supported", kind),
+    };
+    Ok(Clip { hash, name, animations, duration })
+}
+
+/// Parse a .ycd resource; clips come in hash table order.
+pub fn parse(res: &Resource) -> Result<Vec<Clip>> {
+    let d = res.slice(SYSTEM_BASE, DICTIONARY_SIZE).context("system section too small for a clip dictionary")?;
+    let buckets = u16_at(d, 0x30) as usize;
+    if buckets == 0 { return Ok(Vec::new()); }
+    let table = res.slice(u64_at(d, 0x28), buckets * 8).context("clip table out of bounds")?;
+
+    let mut clips = Vec::new();
+    let mut seen = HashSet::new();
+    for bucket in table.chunks_exact(8) {
+        let mut next = u64_at(bucket, 0);
+        let mut chain = 0;
+        while next != 0 {
+            chain += 1;
+            if chain > MAX_CHAIN || !seen.insert(next) { bail!("clip table chain loops at 0x{:X}", next); }
+            let e = res.slice(next, MAP_ENTRY_SIZE).with_context(|| format!("clip entry out of bounds (0x{:X})", next))?;
+            let hash = u32_at(e, 0);
+            let clip = read_clip(res, hash, u64_at(e, 0x08)).with_context(|| format!("clip 0x{:08X}", hash))?;
+            clips.push(clip);
+            next = u64_at(e, 0x10);
+        }
+    }
+    Ok(clips)
+}
+
+// ─── Sequences ───────────────────────────────────────────────────────────────
+
+/// Channel types, in the order their counts are stored.
+const STATIC_QUATERNION: usize = 0;
+const STATIC_VECTOR3: usize = 1;
+const STATIC_FLOAT: usize = 2;
+const RAW_FLOAT: usize = 3;
+const QUANTIZE_FLOAT: usize = 4;
+const INDIRECT_QUANTIZE_FLOAT: usize = 5;
+const LINEAR_FLOAT: usize = 6;
+const CACHED_QUATERNION1: usize = 7;
+const CACHED_QUATERNION2: usize = 8;
+const CHANNEL_TYPES: usize = 9;
+
+/// Little-endian reads from the channel headers, in order.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos : usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8]> {
+        let b = self.data.get(self.pos..self.pos + len)
+            .with_context(|| format!("channel data ends at byte {}", self.data.len()))?;
+        self.pos += len;
+        Ok(b)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32_at(self.bytes(4)?, 0))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+}
+
+/// A bit stream, least significant bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos : usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, n: usize) -> Result<u32> {
+        if n > 32 { bail!("{}-bit field", n); }
+        if self.pos + n > self.data.len() * 8 { bail!("bit stream ends at bit {}", self.data.len() * 8); }
+        let bytes = &self.data[self.pos / 8..(self.pos + n).div_ceil(8)];
+        let word = bytes.iter().enumerate().fold(0u64, |w, (i, &b)| w | (b as u64) << (i * 8));
+        let value = (word >> (self.pos % 8)) & ((1u64 << n) - 1);
+        self.pos += n;
+        Ok(value as u32)
+    }
+}
+
+enum Channel {
+    /// A whole value for every frame: a float, vector or quaternion.
+    Static(Vec<f32>),
+    Raw(Vec<f32>),
+    Quantized { bits: usize, quantum: f32, offset: f32, values: Vec<f32> },
+    /// Per frame, an index into a table of quantized values.
+    Indirect { bits: usize, table: Vec<f32>, values: Vec<f32> },
+    Linear(Vec<f32>),
+    /// The component of a unit quaternion rebuilt from the other three.
+    Cached,
+}
+
+impl Channel {
+    fn read(kind: usize, r: &mut Reader, frames: usize, chunk_size: usize) -> Result<Self> {
+        Ok(match kind {
+            STATIC_QUATERNION => {
+                let (x, y, z) = (r.f32()?, r.f32()?, r.f32()?);
+                Channel::Static(vec![x, y, z, (1.0 - (x * x + y * y + z * z)).max(0.0).sqrt()])
+            }
+            STATIC_VECTOR3 => Channel::Static(vec![r.f32()?, r.f32()?, r.f32()?]),
+            STATIC_FLOAT => Channel::Static(vec![r.f32()?]),
+            RAW_FLOAT => Channel::Raw(Vec::with_capacity(frames)),
+            QUANTIZE_FLOAT => Channel::Quantized {
+                bits   : r.u32()? as usize,
+                quantum: r.f32()?,
+                offset : r.f32()?,
+                values : Vec::with_capacity(frames),
+            },
+            INDIRECT_QUANTIZE_FLOAT => {
+                let (bits, value_bits, ints) = (r.u32()? as usize, r.u32()? as usize, r.u32()? as usize);
+                let (quantum, offset) = (r.f32()?, r.f32()?);
+                let mut stream = Bits { data: r.bytes(ints * 4)?, pos: 0 };
+                let count = (ints * 32).checked_div(value_bits).map_or(1, |n| n.min(1 << bits.min(24)));
+                let table = (0..count).map(|_| Ok(stream.read(value_bits)? as f32 * quantum + offset)).collect::<Result<_>>()?;
+                Channel::Indirect { bits, table, values: Vec::with_capacity(frames) }
+            }
+            LINEAR_FLOAT => {
+                let (ints, counts) = (r.u32()? as usize, r.u32()?);
+                let (quantum, offset) = (r.f32()?, r.f32()?);
+                Channel::Linear(linear(r.bytes(ints * 4)?, counts, quantum, offset, frames, chunk_size)?)
+            }
+            CACHED_QUATERNION1 | CACHED_QUATERNION2 => Channel::Cached,
+            kind => bail!("channel type {} is not known", kind),
+        })
+    }
+
+    /// Read this channel's part of one frame record.
+    fn read_frame(&mut self, bits: &mut Bits) -> Result<()> {
+        match self {
+            Channel::Raw(values) => values.push(f32::from_bits(bits.read(32)?)),
+            Channel::Quantized { bits: n, quantum, offset, values } => values.push(bits.read(*n)? as f32 * *quantum + *offset),
+            Channel::Indirect { bits: n, table, values } => {
+                let i = bits.read(*n)? as usize;
+                values.push(*table.get(i).with_context(|| format!("value index {} past a table of {}", i, table.len()))?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn float(&self, frame: usize) -> f32 {
+        match self {
+            Channel::Static(v) => v[0],
+            Channel::Raw(v) | Channel::Quantized { values: v, .. } | Channel::Indirect { values: v, .. } | Channel::Linear(v) => {
+                v.get(frame).or(v.last()).copied().unwrap_or(0.0)
+            }
+            Channel::Cached => 0.0,
+        }
+    }
+}
+
+/// Decode a linear float channel: per chunk of frames, a start value and bit offset, then
+/// second-order deltas, each a fixed low part, a unary high part and a sign bit.
+fn linear(data: &[u8], counts: u32, quantum: f32, offset: f32, frames: usize, chunk_size: usize) -> Result<Vec<f32>> {
+    let (offset_bits, value_bits, delta_bits) = ((counts & 0xFF) as usize, (counts >> 8 & 0xFF) as usize, (counts >> 16 & 0xFF) as usize);
+    if chunk_size == 0 { bail!("linear channel with a chunk size of 0"); }
+    let chunks = frames.div_ceil(chunk_size);
+    let mut bits = Bits { data, pos: 0 };
+    let starts = (0..chunks).map(|_| bits.read(offset_bits)).collect::<Result<Vec<_>>>()?;
+    let firsts = (0..chunks).map(|_| bits.read(value_bits)).collect::<Result<Vec<_>>>()?;
+    let deltas = chunks * (offset_bits + value_bits);
+
+    let mut values = Vec::with_capacity(frames);
+    for (chunk, (&start, &first)) in starts.iter().zip(&firsts).enumerate() {
+        bits.pos = deltas + start as usize;
+        let (mut value, mut step) = (first as i32, 0i32);
+        for j in 0..chunk_size {
+            let frame = chunk * chunk_size + j;
+            if frame >= frames { break; }
+            values.push(value as f32 * quantum + offset);
+            if j + 1 >= chunk_size || frame + 1 >= frames { break; }
+
+            let mut delta = bits.read(delta_bits)? as i32;
+            let scan = bits.pos;
+            let end = (scan + 32 - delta_bits).min(data.len() * 8);
+            while bits.read(1)? == 0 && bits.pos < end {}
+            delta |= ((bits.pos - scan - 1) << delta_bits) as i32;
+            if delta != 0 && bits.read(1)? == 1 { delta = -delta; }
+            step += delta;
+            value += step;
+        }
+    }
+    Ok(values)
+}
+
+/// One decoded sequence: its channels with the track and component each fills.
+struct Sequence {
+    frames  : usize,
+    channels: Vec<(usize, usize, Channel)>,
+}
+
+fn read_sequence(res: &Resource, ptr: u64) -> Result<Sequence> {
+    let header = res.slice(ptr, SEQUENCE_HEADER_SIZE).with_context(|| format!("sequence out of bounds (0x{:X})", ptr))?;
+    let len = u32_at(header, 0x04) as usize;
+    let data = res.slice(ptr + SEQUENCE_HEADER_SIZE as u64, len).context("sequence data out of bounds")?;
+    let (frame_offset, frames, frame_length) = (u32_at(header, 0x0C) as usize, u16_at(header, 0x16) as usize, u16_at(header, 0x18) as usize);
+    let chunk_size = header[0x1E] as usize;
+
+    // Channel counts by type, then per channel its track and component, each type's list
+    // padded to 4 entries.
+    let counts_at = frame_offset + frame_length * frames;
+    let counts = data.get(counts_at..counts_at + CHANNEL_TYPES * 2).context("channel counts out of bounds")?;
+    let counts: Vec<usize> = counts.chunks_exact(2).map(|c| u16_at(c, 0) as usize).collect();
+    let mut targets_at = counts_at + CHANNEL_TYPES * 2;
+
+    let mut reader = Reader { data, pos: 0 };
+    let mut channels = Vec::new();
+    for (kind, &count) in counts.iter().enumerate() {
+        let targets = data.get(targets_at..targets_at + count * 2).context("channel targets out of bounds")?;
+        targets_at += count.next_multiple_of(4) * 2;
+        for target in targets.chunks_exact(2) {
+            let target = u16_at(target, 0) as usize;
+            let channel = Channel::read(kind, &mut reader, frames, chunk_size)
+                .with_context(|| format!("channel of type {} for track {}", kind, target >> 2))?;
+            channels.push((target >> 2, target & 3, channel));
+        }
+    }
+
+    for frame in 0..frames {
+        let mut bits = Bits { data, pos: (frame_offset + frame_length * frame) * 8 };
+        for (_, _, channel) in &mut channels {
+            channel.read_frame(&mut bits).with_context(|| format!("frame {}", frame))?;
+        }
+    }
+    Ok(Sequence { frames, channels })
+}
+
+impl Sequence {
+    /// The value of `track` at `frame`; tracks without channels read as zero.
+    fn evaluate(&self, track: usize, width: usize, frame: usize) -> Vec<f32> {
+        let channels = self.channels.iter().filter(|(t, _, _)| *t == track);
+        let mut value = vec![0.0; width];
+        let mut cached = None;
+        for (_, component, channel) in channels {
+            match channel {
+                Channel::Static(v) if v.len() > 1 => return v.clone(),
+                Channel::Cached => cached = Some(*component),
+                c => if let Some(slot) = value.get_mut(*component) { *slot = c.float(frame); },
+            }
+        }
+        if let Some(c) = cached.filter(|&c| c < width) {
+            let rest: f32 = value.iter().enumerate().filter(|&(i, _)| i != c).map(|(_, v)| v * v).sum();
+            value[c] = (1.0 - rest).max(0.0).sqrt();
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ResourceBuilder, Section};
+
+    /// Pack `(value, bits)` fields into a bit stream, least significant bit first.
+    fn pack(fields: &[(u32, usize)], len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; len];
+        let mut pos = 0;
+        for &(value, n) in fields {
+            for i in 0..n {
+                out[(pos + i) / 8] |= ((value >> i & 1) as u8) << ((pos + i) % 8);
+            }
+            pos += n;
+        }
+        out
+    }
+
+    fn le(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Three frames of: a static vector for track 0; a quaternion for track 1 from a
+    /// quantized x, raw y, indirect z and cached w; a linear float for track 2.
+    fn sequence() -> Sequence {
+        let mut data = le(&[1.5, -2.0, 3.0]);
+        data.extend(4u32.to_le_bytes());
+        data.extend(le(&[0.125, -0.5]));
+        for v in [1u32, 8, 1] { data.extend(v.to_le_bytes()); }
+        data.extend(le(&[0.25, 0.0]));
+        data.extend([0, 2, 0, 0]);
+        // One chunk: starting at bit 0 from 10, then deltas of +3 (11, "1", +) and -1 (01, "1", -).
+        for v in [1u32, 8 | 8 << 8 | 2 << 16] { data.extend(v.to_le_bytes()); }
+        data.extend(le(&[0.5, 1.0]));
+        data.extend([0, 10, 0b1101_0111, 0]);
+        let frame_offset = data.len();
+
+        for (q, y, i) in [(4, 0.0f32, 0), (5, 0.25, 1), (6, 0.5, 1)] {
+            data.extend(pack(&[(y.to_bits(), 32), (q, 4), (i, 1)], 5));
+        }
+        for count in [0u16, 1, 0, 1, 1, 1, 1, 1, 0] { data.extend(count.to_le_bytes()); }
+        for target in [0u16, 1 << 2 | 1, 1 << 2, 1 << 2 | 2, 2 << 2, 1 << 2 | 3] {
+            data.extend([target, 0, 0, 0].iter().flat_map(|t| t.to_le_bytes()));
+        }
+
+        let mut block = vec![0u8; SEQUENCE_HEADER_SIZE];
+        block[0x04..0x08].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        block[0x0C..0x10].copy_from_slice(&(frame_offset as u32).to_le_bytes());
+        block[0x16..0x18].copy_from_slice(&3u16.to_le_bytes());
+        block[0x18..0x1A].copy_from_slice(&5u16.to_le_bytes());
+        block[0x1E] = 4;
+        block.extend(data);
+
+        let mut builder = ResourceBuilder::new();
+        builder.add(Section::System, block);
+        let res = Resource::parse(&builder.build(46).unwrap()).unwrap();
+        read_sequence(&res, SYSTEM_BASE).unwrap()
+    }
+
+    #[test]
+    fn reads_bits_across_bytes() {
+        let mut bits = Bits { data: &[0b1010_1100, 0b0000_0011], pos: 2 };
+        assert_eq!(bits.read(3).unwrap(), 0b011);
+        assert_eq!(bits.read(5).unwrap(), 0b11101);
+        assert_eq!(bits.read(6).unwrap(), 0);
+        assert!(bits.read(1).is_err());
+    }
+
+    #[test]
+    fn decodes_sequence_channels() {
+        let seq = sequence();
+        assert_eq!((seq.frames, seq.channels.len()), (3, 6));
+        for frame in 0..3 {
+            assert_eq!(seq.evaluate(0, 3, frame), [1.5, -2.0, 3.0]);
+        }
+        assert_eq!(seq.evaluate(2, 1, 0), [6.0]);
+        assert_eq!(seq.evaluate(2, 1, 1), [7.5]);
+        assert_eq!(seq.evaluate(2, 1, 2), [8.5]);
+        assert_eq!(seq.evaluate(3, 1, 0), [0.0], "tracks without channels read as zero");
+
+        for (frame, xyz) in [[0.0, 0.0, 0.0], [0.125, 0.25, 0.5], [0.25, 0.5, 0.5]].into_iter().enumerate() {
+            let q = seq.evaluate(1, 4, frame);
+            assert_eq!(q[..3], xyz, "frame {}", frame);
+            let w = (1.0 - xyz.iter().map(|v| v * v).sum::<f32>()).sqrt();
+            assert!((q[3] - w).abs() < 1e-6, "frame {}: w {} != {}", frame, q[3], w);
+        }
+    }
+
+    #[test]
+    fn rejects_indirect_indices_past_the_table() {
+        let mut bits = Bits { data: &[0b11], pos: 0 };
+        let mut channel = Channel::Indirect { bits: 2, table: vec![1.0, 2.0], values: Vec::new() };
+        assert!(channel.read_frame(&mut bits).is_err());
+    }
+}
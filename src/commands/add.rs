@@ -0,0 +1,78 @@
+use anyhow::{bail, Context, Result};
+use std::{fs, path::Path};
+use crate::editor::{self, RpfEditor};
+use crate::rbf;
+use crate::rpf::GtaKeys;
+
+/// Add a file, or every file under a directory, to an existing archive in place. With
+/// `xml_to_rbf`, text XML meta files are stored as RBF.
+pub fn run(
+    archive_path: &Path,
+    source: &Path,
+    dest: Option<&str>,
+    force: bool,
+    xml_to_rbf: bool,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let source_name = source.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+
+    let mut files = Vec::new();
+    if source.is_dir() {
+        let prefix = dest.map(|d| d.trim_end_matches('/').to_string()).unwrap_or(source_name);
+        collect(source, &prefix, &mut files)?;
+    } else if source.is_file() {
+        let target = match dest {
+            Some(d) if d.ends_with('/') => format!("{}{}", d, source_name),
+            Some(d)                     => d.to_string(),
+            None                        => source_name,
+        };
+        files.push((target, source.to_path_buf()));
+    } else {
+        bail!("{} does not exist", source.display());
+    }
+
+    if files.is_empty() {
+        println!("Nothing to add");
+        return Ok(());
+    }
+
+    let count = editor::edit_file(archive_path, keys, |ed: &mut RpfEditor<'_>| {
+        for (target, file) in &files {
+            let mut data = fs::read(file)?;
+            let mut note = "";
+            if xml_to_rbf && rbf::is_xml_meta(target, &data) {
+                data = rbf::from_xml(&String::from_utf8_lossy(&data))
+                    .with_context(|| format!("cannot convert {} to RBF", file.display()))?;
+                note = ", as RBF";
+            }
+            let len = data.len();
+            if ed.exists(target) {
+                if !force { bail!("'{}' already exists (use --force to replace it)", target); }
+                ed.replace_file(target, data)?;
+                println!("Replaced {} ({} bytes{})", target, len, note);
+            } else {
+                ed.add_file(target, data)?;
+                println!("Added {} ({} bytes{})", target, len, note);
+            }
+        }
+        Ok(())
+    })?;
+
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
+
+fn collect(dir: &Path, prefix: &str, out: &mut Vec<(String, std::path::PathBuf)>) -> Result<()> {
+    let mut paths = fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let target = if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) };
+        if path.is_dir() {
+            collect(&path, &target, out)?;
+        } else {
+            out.push((target, path));
+        }
+    }
+    Ok(())
+}
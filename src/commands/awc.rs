@@ -0,0 +1,216 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::awc::{self, chunk, Awc, Chunk, Format as AwcFormat, Stream, CODEC_ADPCM, CODEC_PCM, ID_MASK};
+use crate::editor;
+use crate::names::{self, Names};
+use crate::output::{self, Format};
+use crate::rpf::{Archive, GtaKeys};
+
+/// One stream (or one channel of a multi-channel stream), as listed by `awc list`.
+#[derive(Serialize)]
+struct StreamRecord {
+    /// Stream name, or the 29-bit ID as `0xXXXXXXXX`.
+    name       : String,
+    id         : u32,
+    /// Set for the channels of an interleaved multi-channel stream.
+    channel    : Option<usize>,
+    codec      : String,
+    sample_rate: u32,
+    samples    : u32,
+    /// Seconds.
+    duration   : f64,
+    /// Bytes of sample data; null for interleaved channels.
+    data_size  : Option<usize>,
+    chunks     : Vec<String>,
+}
+
+fn load(archive_path: &Path, path: &str, keys: Option<&GtaKeys>) -> Result<(String, Awc)> {
+    let archive = Archive::open(archive_path, keys)?;
+    let file = archive.find_file(path)
+        .with_context(|| format!("'{}' not found in archive", path))?;
+    let data = archive.extract(file, keys)
+        .with_context(|| format!("failed to extract '{}'", path))?;
+    let awc = Awc::parse(&data).with_context(|| format!("failed to read {}", file.path))?;
+    Ok((file.path.clone(), awc))
+}
+
+fn stream_name(names: &Names, id: u32) -> String {
+    names.get_masked(id, ID_MASK).map(str::to_string).unwrap_or_else(|| format!("0x{:08X}", id))
+}
+
+/// List the streams of an .awc inside an archive with their codec, rate and length.
+pub fn list(archive_path: &Path, path: &str, format: Format, names: &Names, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, awc) = load(archive_path, path, keys)?;
+
+    let mut records = Vec::new();
+    for s in &awc.streams {
+        let chunks: Vec<String> = s.chunks.iter().map(|c| awc::chunk_name(c.kind)).collect();
+        let record = |name, id, channel, codec, sample_rate: u16, samples, data_size| StreamRecord {
+            name, id, channel, data_size,
+            codec      : awc::codec_name(codec),
+            sample_rate: sample_rate as u32,
+            samples,
+            duration   : if sample_rate == 0 { 0.0 } else { samples as f64 / sample_rate as f64 },
+            chunks     : chunks.clone(),
+        };
+        let data_size = s.chunk(chunk::DATA).map(<[u8]>::len);
+        if let Some(f) = s.format() {
+            records.push(record(stream_name(names, s.id), s.id, None, f.codec, f.sample_rate, f.samples, data_size));
+        }
+        for (i, c) in s.channels().iter().enumerate() {
+            records.push(record(stream_name(names, c.id & ID_MASK), c.id & ID_MASK, Some(i), c.codec, c.sample_rate, c.samples, None));
+        }
+    }
+
+    if format != Format::Text {
+        return output::print_records(format, &records);
+    }
+    println!("{}: version {}, {} stream(s){}", full, awc.version, awc.streams.len(),
+        if awc.is_multi_channel() { ", interleaved multi-channel" } else { "" });
+    println!("{:<32} {:<8} {:>6} {:>10} {:>9} {:>10}  Chunks", "Name", "Codec", "Rate", "Samples", "Duration", "Size");
+    println!("{}", "-".repeat(100));
+    for r in &records {
+        let name = match r.channel {
+            Some(c) => format!("{} [ch {}]", r.name, c),
+            None    => r.name.clone(),
+        };
+        println!("{:<32} {:<8} {:>6} {:>10} {:>8.2}s {:>10}  {}", name, r.codec, r.sample_rate, r.samples,
+            r.duration, r.data_size.map_or("-".to_string(), |s| s.to_string()), r.chunks.join(", "));
+    }
+    Ok(())
+}
+
+/// Write every stream of an .awc inside an archive to `output` (default: the awc name):
+/// PCM and ADPCM streams as WAV files, one per channel for interleaved multi-channel
+/// streams, anything else as the raw data chunk.
+pub fn extract(archive_path: &Path, path: &str, output: Option<&Path>, names: &Names, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, awc) = load(archive_path, path, keys)?;
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| {
+        PathBuf::from(Path::new(&full).file_stem().and_then(|s| s.to_str()).unwrap_or("awc"))
+    });
+    fs::create_dir_all(&output)?;
+
+    let (mut wav, mut raw) = (0, 0);
+    let write_wav = |name: &str, codec: u8, sample_rate: u16, samples: &[i16]| -> Result<()> {
+        let dest = output.join(format!("{}.wav", name));
+        fs::write(&dest, awc::to_wav(sample_rate as u32, samples))
+            .with_context(|| format!("failed to write {}", dest.display()))?;
+        println!("  {} — {} {} Hz, {} samples", dest.display(), awc::codec_name(codec), sample_rate, samples.len());
+        Ok(())
+    };
+    for s in &awc.streams {
+        let Some(data) = s.chunk(chunk::DATA) else { continue };
+        let name = stream_name(names, s.id);
+
+        let why = if let Some(f) = s.format() {
+            match awc::decode(f.codec, data, f.samples as usize) {
+                Some(samples) => {
+                    write_wav(&name, f.codec, f.sample_rate, &samples)?;
+                    wav += 1;
+                    continue;
+                }
+                None => format!("{} is not decoded", awc::codec_name(f.codec)),
+            }
+        } else {
+            let channels = s.channels();
+            let decoded = s.decode_channels().with_context(|| format!("failed to de-interleave stream {}", name))?;
+            let mut skipped = Vec::new();
+            for (c, samples) in channels.iter().zip(&decoded) {
+                match samples {
+                    Some(samples) => {
+                        write_wav(&stream_name(names, c.id & ID_MASK), c.codec, c.sample_rate, samples)?;
+                        wav += 1;
+                    }
+                    None => skipped.push(awc::codec_name(c.codec)),
+                }
+            }
+            if skipped.is_empty() { continue; }
+            skipped.dedup();
+            format!("interleaved {} channels are not decoded", skipped.join("/"))
+        };
+
+        let dest = output.join(format!("{}.raw", name));
+        fs::write(&dest, data).with_context(|| format!("failed to write {}", dest.display()))?;
+        println!("  {} — raw, {} ({} bytes)", dest.display(), why, data.len());
+        raw += 1;
+    }
+    println!("Extracted {} WAV and {} raw stream(s) from {} to {}", wav, raw, full, output.display());
+    Ok(())
+}
+
+/// Build an .awc from every mono 16-bit .wav in a directory, one stream per file named after
+/// it, writing it to `output` or, with `archive`, adding or replacing the entry `output`.
+pub fn build(input: &Path, output: &str, archive: Option<&Path>, adpcm: bool, keys: Option<&GtaKeys>) -> Result<()> {
+    let mut paths: Vec<_> = fs::read_dir(input)
+        .with_context(|| format!("cannot read {}", input.display()))?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("wav")));
+    paths.sort();
+    if paths.is_empty() { bail!("no .wav files in {}", input.display()); }
+
+    let mut streams = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let name = path.file_stem().and_then(|s| s.to_str()).context("non-UTF-8 file name")?;
+        let (rate, samples) = awc::from_wav(&fs::read(path)?)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let sample_rate = u16::try_from(rate).with_context(|| format!("{}: {} Hz is above 65535 Hz", path.display(), rate))?;
+        let (codec, data) = if adpcm {
+            (CODEC_ADPCM, awc::encode_adpcm(&samples))
+        } else {
+            (CODEC_PCM, samples.iter().flat_map(|s| s.to_le_bytes()).collect())
+        };
+        let format = AwcFormat { samples: u32::try_from(samples.len())?, loop_point: -1, sample_rate, headroom: 0, codec };
+        println!("  {} — {} Hz, {} samples, {} ({} bytes)", name, rate, samples.len(), awc::codec_name(codec), data.len());
+        streams.push(Stream {
+            id    : names::hash_of(name) & ID_MASK,
+            chunks: vec![Chunk { kind: chunk::DATA, data }, Chunk { kind: chunk::FORMAT, data: format.to_bytes() }],
+        });
+    }
+
+    let count = streams.len();
+    let data = Awc::build(streams)?;
+    let len = data.len();
+
+    match archive {
+        None => {
+            fs::write(output, &data).with_context(|| format!("failed to write {}", output))?;
+            println!("Packed {} stream(s) into {} ({} bytes)", count, output, len);
+        }
+        Some(archive_path) => {
+            let entries = editor::edit_file(archive_path, keys, |ed| {
+                if ed.exists(output) { ed.replace_file(output, data) } else { ed.add_file(output, data) }
+            })?;
+            println!("Packed {} stream(s) into {} ({} bytes)", count, output, len);
+            println!("Updated {} ({} entries)", archive_path.display(), entries);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::awc::tests::{interleaved, sine};
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+
+    #[test]
+    fn extracts_a_wav_per_channel() {
+        let (left, right) = (sine(1024), sine(1024).iter().map(|s| -s).collect::<Vec<_>>());
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("music.awc", Awc::build(vec![interleaved(&left, &right)]).unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, builder.build(None).unwrap()).unwrap();
+
+        let out = dir.path().join("music");
+        extract(&path, "music.awc", Some(&out), &Names::load(&[]).unwrap(), None).unwrap();
+        for (name, samples) in [("0x00000111", &left[10..1010]), ("0x00000222", &right[..])] {
+            let (rate, decoded) = awc::from_wav(&fs::read(out.join(format!("{}.wav", name))).unwrap()).unwrap();
+            assert_eq!((rate, decoded.as_slice()), (48000, samples));
+        }
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 2);
+    }
+}
@@ -1,15 +1,18 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use rpf_archive::{RpfBuilder, RpfEncryption, RpfVersion};
 use std::fs;
 use std::path::Path;
 
 use rpf_archive::GtaKeys;
 
+use crate::rbf;
+
 pub fn run(
     input_dir: &Path,
     output: &Path,
     version: u8,
     encryption: &str,
+    xml_to_rbf: bool,
     keys: Option<&GtaKeys>,
 ) -> Result<()> {
     if !input_dir.is_dir() {
@@ -35,27 +38,38 @@ pub fn run(
 
     let mut builder = RpfBuilder::for_version(rpf_version, rpf_encryption);
 
-    add_dir(&mut builder, input_dir, input_dir)?;
+    let converted = add_dir(&mut builder, input_dir, input_dir, xml_to_rbf)?;
 
     let data = builder.build(keys)?;
     fs::write(output, &data)?;
 
+    if converted > 0 {
+        println!("Converted {} XML file(s) to RBF", converted);
+    }
     println!("Created {} ({} bytes)", output.display(), data.len());
     Ok(())
 }
 
-fn add_dir(builder: &mut RpfBuilder, base: &Path, dir: &Path) -> Result<()> {
+/// Add every file under `dir`; with `xml_to_rbf`, text XML meta files are stored as RBF.
+/// Returns the number of files converted.
+fn add_dir(builder: &mut RpfBuilder, base: &Path, dir: &Path, xml_to_rbf: bool) -> Result<usize> {
+    let mut converted = 0;
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
         if path.is_dir() {
-            add_dir(builder, base, &path)?;
+            converted += add_dir(builder, base, &path, xml_to_rbf)?;
         } else {
             let rel = path.strip_prefix(base)?;
             let archive_path = rel.to_string_lossy().replace('\\', "/");
-            let data = fs::read(&path)?;
+            let mut data = fs::read(&path)?;
+            if xml_to_rbf && rbf::is_xml_meta(&archive_path, &data) {
+                data = rbf::from_xml(&String::from_utf8_lossy(&data))
+                    .with_context(|| format!("cannot convert {} to RBF", path.display()))?;
+                converted += 1;
+            }
             builder.add_file(&archive_path, data);
         }
     }
-    Ok(())
+    Ok(converted)
 }
@@ -1,9 +1,49 @@
 use anyhow::{Context, Result};
-use std::{cell::Cell, fs, io::{self, Write}, path::{Path, PathBuf}};
-use crate::rpf::{Archive, FileRef, GtaKeys};
-use crate::utils::matches_pattern;
+use rayon::prelude::*;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use crate::rbf;
+use crate::resource::{self, Resource, RSC7_MAGIC};
+use crate::rpf::{Archive, FileRef, GtaKeys, MAX_DEPTH};
+use crate::utils::{is_literal, FilterArgs, PathFilter};
 
-pub fn run(archive_path: &Path, output_dir: Option<&Path>, pattern: Option<&str>, recursive: bool, keys: Option<&GtaKeys>) -> Result<()> {
+/// How `extract` walks the archive and writes entries.
+#[derive(clap::Args)]
+pub struct ExtractArgs {
+    /// Recurse into nested RPF archives, extracting them to loose files
+    /// (resource files get a valid RSC7 header, like CodeWalker)
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Number of worker threads (default: one per CPU)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Write resources as their raw system and graphics sections (`<file>.system`,
+    /// `<file>.graphics`) plus a `<file>.json` sidecar with the version and flags,
+    /// instead of RSC7 files; `resource-pack` rebuilds them
+    #[arg(long)]
+    pub raw_resource: bool,
+
+    /// Convert RBF (binary XML) files to text XML; they keep their names
+    #[arg(long)]
+    pub rbf_to_xml: bool,
+}
+
+pub fn run(
+    archive_path: &Path,
+    output_dir: Option<&Path>,
+    pattern: Option<&str>,
+    filter_args: &FilterArgs,
+    args: &ExtractArgs,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let filter = PathFilter::new(pattern, filter_args)?;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.jobs.unwrap_or(0)).build()?;
     let archive = Archive::open(archive_path, keys)?;
 
     let output_path = output_dir.map(Path::to_path_buf).unwrap_or_else(|| {
@@ -17,32 +57,30 @@ pub fn run(archive_path: &Path, output_dir: Option<&Path>, pattern: Option<&str>
     // Recursive mode: descend into nested RPFs and write every leaf file, preserving the
     // FULL internal directory path (incl. the nested .rpf names as folders) and giving
     // resources a valid RSC7 header. Produces the same loose-file layout as CodeWalker.
-    if recursive {
+    if args.recursive {
         // Pre-count: walk the whole tree (descending into nested RPFs) up front so we can
         // report the recursive totals before extracting, like CodeWalker does.
-        let (total_files, total_resources, nested_rpfs) = count_recursive(&archive, keys, 0);
+        let (total_files, total_resources, nested_rpfs) = count_recursive(&archive, "", &filter, keys, 0);
         println!("Recursive: {} files, {} resources, {} nested rpf(s)",
             total_files, total_resources, nested_rpfs);
 
-        let ok = Cell::new(0usize);
-        let fail = Cell::new(0usize);
-        extract_recursive(&archive, "", &output_path, pattern, keys, &ok, &fail, 0);
-        println!("\n\nExtracted: {} / {}  Failed: {}", ok.get(), total_files, fail.get());
+        let job = Job { output_path: &output_path, filter: &filter, args, keys, ok: AtomicUsize::new(0), fail: AtomicUsize::new(0), rbf: AtomicUsize::new(0) };
+        pool.install(|| extract_recursive(&archive, "", &job, 0));
+        println!("\n\nExtracted: {} / {}  Failed: {}", job.ok.into_inner(), total_files, job.fail.into_inner());
+        print_rbf_summary(job.rbf.into_inner(), args.rbf_to_xml);
         return Ok(());
     }
 
     let all_files = archive.list_files();
-    let to_extract: Vec<&FileRef> = if let Some(pat) = pattern {
-        if !pat.contains('*') && !pat.contains('?') {
+    let to_extract: Vec<&FileRef> = match pattern {
+        // A plain path names one file; look it up directly instead of scanning.
+        Some(pat) if is_literal(pat) && !filter_args.regex && filter_args.include.is_empty() => {
             match archive.find_file(pat) {
-                Some(f) => vec![f],
+                Some(f) => if filter.matches(&f.path) { vec![f] } else { Vec::new() },
                 None    => { println!("File not found: {}", pat); return Ok(()); }
             }
-        } else {
-            all_files.into_iter().filter(|f| matches_pattern(&f.path, pat)).collect()
         }
-    } else {
-        all_files
+        _ => all_files.into_iter().filter(|f| filter.matches(&f.path)).collect(),
     };
 
     if to_extract.is_empty() {
@@ -53,53 +91,57 @@ pub fn run(archive_path: &Path, output_dir: Option<&Path>, pattern: Option<&str>
     println!("Extracting {} files...", to_extract.len());
 
     let total = to_extract.len();
-    let mut ok = 0usize;
-    let mut fail = 0usize;
+    let ok = AtomicUsize::new(0);
+    let fail = AtomicUsize::new(0);
+    let rbf = AtomicUsize::new(0);
 
-    for (i, file) in to_extract.iter().enumerate() {
+    pool.install(|| to_extract.par_iter().for_each(|file| {
         let dest = output_path.join(&file.path);
-        if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
-
-        match archive.extract(file, keys) {
-            Ok(data) => {
-                fs::write(&dest, &data)
-                    .with_context(|| format!("Write failed: {}", dest.display()))?;
-                ok += 1;
-                print_progress(i + 1, total, &file.name);
+        let written = archive.extract(file, keys)
+            .with_context(|| format!("Failed to extract {}", file.path))
+            .and_then(|data| {
+                if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
+                write_entry(&dest, &data, args).with_context(|| format!("Write failed: {}", dest.display()))
+            });
+        match written {
+            Ok(is_rbf) => {
+                if is_rbf { rbf.fetch_add(1, Ordering::Relaxed); }
+                let n = ok.fetch_add(1, Ordering::Relaxed) + fail.load(Ordering::Relaxed) + 1;
+                print_progress(n, total, &file.name);
             }
             Err(e) => {
-                eprintln!("\nFailed to extract {}: {}", file.path, e);
-                fail += 1;
+                eprintln!("\n{:#}", e);
+                fail.fetch_add(1, Ordering::Relaxed);
             }
         }
-    }
+    }));
 
-    println!("\n\nExtracted: {}  Failed: {}", ok, fail);
+    println!("\n\nExtracted: {}  Failed: {}", ok.into_inner(), fail.into_inner());
+    print_rbf_summary(rbf.into_inner(), args.rbf_to_xml);
     Ok(())
 }
 
 /// Recursively count leaf files, resources and nested archives without extracting any
 /// file data (it does parse each nested RPF's table of contents). Returns
-/// `(leaf_files, resources, nested_rpfs)`. `leaf_files` is the number that will be written.
-fn count_recursive(archive: &Archive, keys: Option<&GtaKeys>, depth: usize) -> (usize, usize, usize) {
-    const MAX_DEPTH: usize = 16;
+/// `(leaf_files, resources, nested_rpfs)`. `leaf_files` is the number that will be written,
+/// so only files passing `filter` are counted.
+fn count_recursive(archive: &Archive, prefix: &str, filter: &PathFilter, keys: Option<&GtaKeys>, depth: usize) -> (usize, usize, usize) {
     if depth > MAX_DEPTH { return (0, 0, 0); }
 
     let (mut files, mut resources, mut nested) = (0usize, 0usize, 0usize);
     let refs: Vec<FileRef> = archive.list_files().into_iter().cloned().collect();
 
     for file in &refs {
+        let full = if prefix.is_empty() { file.path.clone() } else { format!("{}/{}", prefix, file.path) };
         if file.name.to_lowercase().ends_with(".rpf") {
             nested += 1;
-            if let Ok(data) = archive.extract(file, keys) {
-                if let Ok(child) = Archive::from_bytes(data, &file.name, keys) {
-                    let (f, r, n) = count_recursive(&child, keys, depth + 1);
-                    files += f;
-                    resources += r;
-                    nested += n;
-                }
+            if let Ok(child) = archive.open_nested(file, keys) {
+                let (f, r, n) = count_recursive(&child, &full, filter, keys, depth + 1);
+                files += f;
+                resources += r;
+                nested += n;
             }
-        } else {
+        } else if filter.matches(&full) {
             files += 1;
             if file.is_resource { resources += 1; }
         }
@@ -108,82 +150,126 @@ fn count_recursive(archive: &Archive, keys: Option<&GtaKeys>, depth: usize) -> (
     (files, resources, nested)
 }
 
+/// State shared by every worker of a recursive extraction.
+struct Job<'a> {
+    output_path: &'a Path,
+    filter     : &'a PathFilter,
+    args       : &'a ExtractArgs,
+    keys       : Option<&'a GtaKeys>,
+    ok         : AtomicUsize,
+    fail       : AtomicUsize,
+    /// RBF files seen.
+    rbf        : AtomicUsize,
+}
+
+impl Job<'_> {
+    fn failed(&self) {
+        self.fail.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 /// Recursively extract every file from `archive`, descending into nested .rpf entries.
 /// `prefix` is the path of this archive within the parent tree (empty for the root).
-#[allow(clippy::too_many_arguments)]
-fn extract_recursive(
-    archive: &Archive,
-    prefix: &str,
-    output_path: &Path,
-    pattern: Option<&str>,
-    keys: Option<&GtaKeys>,
-    ok: &Cell<usize>,
-    fail: &Cell<usize>,
-    depth: usize,
-) {
-    const MAX_DEPTH: usize = 16;
+/// Entries and nested archives are spread over the current rayon pool.
+fn extract_recursive(archive: &Archive, prefix: &str, job: &Job<'_>, depth: usize) {
     if depth > MAX_DEPTH {
         eprintln!("\n[RPF] max nesting depth reached at {}", prefix);
         return;
     }
 
     // Clone the FileRefs so we don't hold an immutable borrow of `archive` across the
-    // nested `Archive::from_bytes` recursion.
+    // nested `Archive::open_nested` recursion.
     let files: Vec<FileRef> = archive.list_files().into_iter().cloned().collect();
 
-    for file in &files {
+    files.par_iter().for_each(|file| {
         let full = if prefix.is_empty() {
             file.path.clone()
         } else {
             format!("{}/{}", prefix, file.path)
         };
 
-        let data = match archive.extract(file, keys) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("\nFailed to extract {}: {}", full, e);
-                fail.set(fail.get() + 1);
-                continue;
-            }
-        };
-
         if file.name.to_lowercase().ends_with(".rpf") {
-            // Nested archive: parse the extracted bytes and recurse under its full path.
-            match Archive::from_bytes(data, &file.name, keys) {
-                Ok(nested) => extract_recursive(&nested, &full, output_path, pattern, keys, ok, fail, depth + 1),
+            // Nested archive: open it in place and recurse under its full path.
+            match archive.open_nested(file, job.keys) {
+                Ok(nested) => extract_recursive(&nested, &full, job, depth + 1),
                 Err(e) => {
                     eprintln!("\nFailed to parse nested {}: {}", full, e);
-                    fail.set(fail.get() + 1);
+                    job.failed();
                 }
             }
-            continue;
+            return;
         }
 
-        if let Some(pat) = pattern {
-            if !matches_pattern(&full, pat) { continue; }
-        }
+        if !job.filter.matches(&full) { return; }
 
-        let dest = output_path.join(&full);
-        if let Some(parent) = dest.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                eprintln!("\nmkdir failed {}: {}", parent.display(), e);
-                fail.set(fail.get() + 1);
-                continue;
+        let data = match archive.extract(file, job.keys) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("\nFailed to extract {}: {}", full, e);
+                job.failed();
+                return;
             }
+        };
+
+        let dest = job.output_path.join(&full);
+        if let Some(parent) = dest.parent()
+            && let Err(e) = fs::create_dir_all(parent) {
+            eprintln!("\nmkdir failed {}: {}", parent.display(), e);
+            job.failed();
+            return;
         }
-        match fs::write(&dest, &data) {
-            Ok(_) => {
-                let n = ok.get() + 1;
-                ok.set(n);
-                let label = if file.name.len() > 40 { format!("...{}", &file.name[file.name.len() - 37..]) } else { file.name.clone() };
-                print!("\r[recursive] extracted {} {:<42}", n, label);
+        match write_entry(&dest, &data, job.args) {
+            Ok(is_rbf) => {
+                if is_rbf { job.rbf.fetch_add(1, Ordering::Relaxed); }
+                let n = job.ok.fetch_add(1, Ordering::Relaxed) + 1;
+                print!("\r[recursive] extracted {} {:<42}", n, short_label(&file.name));
                 io::stdout().flush().ok();
             }
             Err(e) => {
                 eprintln!("\nWrite failed {}: {}", dest.display(), e);
-                fail.set(fail.get() + 1);
+                job.failed();
             }
         }
+    });
+}
+
+/// Write an extracted entry to `dest`, splitting resources into their sections with
+/// `--raw-resource` and converting RBF to XML with `--rbf-to-xml`. Returns whether the
+/// entry is RBF (and, when converting, was converted).
+fn write_entry(dest: &Path, data: &[u8], args: &ExtractArgs) -> Result<bool> {
+    if args.raw_resource && data.len() >= 16 && data[..4] == RSC7_MAGIC.to_le_bytes() {
+        resource::write_raw(dest, &Resource::parse(data)?)?;
+        return Ok(false);
+    }
+    if !rbf::is_rbf(data) {
+        fs::write(dest, data)?;
+        return Ok(false);
+    }
+    match args.rbf_to_xml.then(|| rbf::to_xml(data)) {
+        Some(Ok(xml)) => fs::write(dest, xml)?,
+        Some(Err(e))  => {
+            eprintln!("\nCannot convert {} to XML, keeping RBF: {:#}", dest.display(), e);
+            fs::write(dest, data)?;
+            return Ok(false);
+        }
+        None => fs::write(dest, data)?,
+    }
+    Ok(true)
+}
+
+fn print_rbf_summary(count: usize, converted: bool) {
+    match (count, converted) {
+        (0, _)     => {}
+        (n, true)  => println!("Converted {} RBF file(s) to XML", n),
+        (n, false) => println!("{} RBF (binary XML) file(s); use --rbf-to-xml to convert them", n),
+    }
+}
+
+/// `name` cut to its last 37 characters behind "..." when longer than 40.
+fn short_label(name: &str) -> String {
+    match name.char_indices().rev().nth(36) {
+        Some((at, _)) if name.chars().count() > 40 => format!("...{}", &name[at..]),
+        _ => name.to_string(),
     }
 }
 
@@ -195,7 +281,108 @@ fn print_progress(n: usize, total: usize, name: &str) {
     } else {
         format!("{}>{}",  "=".repeat(filled), " ".repeat(29 - filled))
     };
-    let label = if name.len() > 40 { format!("...{}", &name[name.len()-37..]) } else { name.to_string() };
-    print!("\r[{}] {}/{} {:<40}", bar, n, total, label);
+    print!("\r[{}] {}/{} {:<40}", bar, n, total, short_label(name));
     io::stdout().flush().ok();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+
+    fn archive(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        for (path, data) in files {
+            builder.add_file(path, data.clone());
+        }
+        builder.build(None).unwrap()
+    }
+
+    fn args(recursive: bool) -> ExtractArgs {
+        ExtractArgs { recursive, jobs: Some(4), raw_resource: false, rbf_to_xml: false }
+    }
+
+    fn sample(dir: &Path) -> PathBuf {
+        let inner = archive(&[("x/y.txt", vec![3; 300])]);
+        let files: Vec<(String, Vec<u8>)> = (0..40).map(|i| (format!("d{}/f{}.bin", i % 4, i), vec![i as u8; 100 + i])).collect();
+        let mut files: Vec<(&str, Vec<u8>)> = files.iter().map(|(p, d)| (p.as_str(), d.clone())).collect();
+        files.push(("inner.rpf", inner));
+        let path = dir.join("test.rpf");
+        fs::write(&path, archive(&files)).unwrap();
+        path
+    }
+
+    #[test]
+    fn extracts_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample(dir.path());
+        let out = dir.path().join("flat");
+        run(&path, Some(&out), Some("d*/*.bin"), &FilterArgs::default(), &args(false), None).unwrap();
+
+        for i in 0..40 {
+            let data = fs::read(out.join(format!("d{}/f{}.bin", i % 4, i))).unwrap();
+            assert_eq!(data, vec![i as u8; 100 + i]);
+        }
+        assert!(!out.join("inner.rpf").exists());
+    }
+
+    #[test]
+    fn extracts_nested_archives_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample(dir.path());
+        let out = dir.path().join("deep");
+        run(&path, Some(&out), None, &FilterArgs::default(), &args(true), None).unwrap();
+
+        assert_eq!(fs::read(out.join("inner.rpf/x/y.txt")).unwrap(), vec![3; 300]);
+        assert_eq!(fs::read(out.join("d3/f39.bin")).unwrap(), vec![39; 139]);
+    }
+
+    #[test]
+    fn converts_rbf_to_xml() {
+        let dir = tempfile::tempdir().unwrap();
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<CHandlingDataMgr>\n  <name>adder</name>\n</CHandlingDataMgr>\n";
+        let rbf = crate::rbf::from_xml(xml).unwrap();
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, archive(&[("data/handling.meta", rbf.clone())])).unwrap();
+
+        let out = dir.path().join("out");
+        run(&path, Some(&out), None, &FilterArgs::default(), &args(false), None).unwrap();
+        assert_eq!(fs::read(out.join("data/handling.meta")).unwrap(), rbf, "kept as RBF by default");
+
+        let convert = ExtractArgs { rbf_to_xml: true, ..args(true) };
+        run(&path, Some(&out), None, &FilterArgs::default(), &convert, None).unwrap();
+        let text = fs::read_to_string(out.join("data/handling.meta")).unwrap();
+        assert!(text.contains("<name>adder</name>"), "{}", text);
+    }
+
+    #[test]
+    fn keeps_going_past_a_bad_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut data = fs::read(sample(dir.path())).unwrap();
+        // Point d0/f0.bin's 24-bit block offset past the end of the archive.
+        let index = Archive::from_bytes(data.clone(), "test.rpf", None).unwrap().find_file("d0/f0.bin").unwrap().entry_index;
+        let at = 16 + index * 16 + 5;
+        data[at..at + 3].copy_from_slice(&[0x00, 0x00, 0x10]);
+        let path = dir.path().join("bad.rpf");
+        fs::write(&path, data).unwrap();
+
+        // A directory where d1/f1.bin should go makes its write fail.
+        let out = dir.path().join("out");
+        fs::create_dir_all(out.join("d1/f1.bin")).unwrap();
+        run(&path, Some(&out), Some("d*/*.bin"), &FilterArgs::default(), &args(false), None).unwrap();
+        assert!(!out.join("d0/f0.bin").exists());
+        assert!(out.join("d1/f1.bin").is_dir());
+        for i in 2..40 {
+            assert!(out.join(format!("d{}/f{}.bin", i % 4, i)).exists(), "f{}.bin", i);
+        }
+    }
+
+    #[test]
+    fn shortens_labels_on_char_boundaries() {
+        assert_eq!(short_label("short.ydr"), "short.ydr");
+        let exact = "é".repeat(40);
+        assert_eq!(short_label(&exact), exact);
+        let long = format!("{}{}", "é".repeat(30), "ü".repeat(20));
+        assert_eq!(short_label(&long), format!("...{}{}", "é".repeat(17), "ü".repeat(20)));
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::editor;
+use crate::gxt2;
+use crate::names::{self, Names};
+use crate::rpf::{Archive, GtaKeys};
+
+/// Write the strings of a .gxt2 inside an archive to `output` (default `<name>.txt`): JSON
+/// when it ends in `.json`, else one `LABEL = text` line per entry. Labels are named from
+/// the dictionary where possible and written as `0xXXXXXXXX` otherwise.
+pub fn export(archive_path: &Path, path: &str, output: Option<&Path>, names: &Names, keys: Option<&GtaKeys>) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let file = archive.find_file(path)
+        .with_context(|| format!("'{}' not found in archive", path))?;
+    let data = archive.extract(file, keys)
+        .with_context(|| format!("failed to extract '{}'", path))?;
+    let entries = gxt2::parse(&data).with_context(|| format!("failed to read {}", file.path))?;
+
+    let label = |hash: u32| names.get(hash).map(str::to_string).unwrap_or_else(|| format!("0x{:08X}", hash));
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.txt", file.name)));
+    let text = if is_json(&output) {
+        let map: Map<String, Value> = entries.iter().map(|(h, t)| (label(*h), Value::String(t.clone()))).collect();
+        serde_json::to_string_pretty(&map)? + "\n"
+    } else {
+        entries.iter().map(|(h, t)| format!("{} = {}\n", label(*h), escape(t))).collect()
+    };
+    fs::write(&output, text).with_context(|| format!("failed to write {}", output.display()))?;
+
+    let named = entries.iter().filter(|(h, _)| names.get(*h).is_some()).count();
+    println!("Exported {} string(s) from {} to {} ({} label(s) named)", entries.len(), file.path, output.display(), named);
+    Ok(())
+}
+
+/// Build a .gxt2 from a file written by `export` and add or replace it inside the archive.
+pub fn import(archive_path: &Path, path: &str, input: &Path, keys: Option<&GtaKeys>) -> Result<()> {
+    let text = fs::read_to_string(input).with_context(|| format!("cannot read {}", input.display()))?;
+    let entries = if is_json(input) {
+        let map: Map<String, Value> = serde_json::from_str(&text).context("expected a JSON object of label: text")?;
+        map.into_iter().map(|(label, value)| match value {
+            Value::String(t) => Ok((names::hash_of(&label), t)),
+            _ => bail!("value of '{}' is not a string", label),
+        }).collect::<Result<Vec<_>>>()?
+    } else {
+        text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()).map(|(i, line)| {
+            let (label, value) = line.split_once('=')
+                .with_context(|| format!("line {}: expected 'LABEL = text'", i + 1))?;
+            Ok((names::hash_of(label.trim()), unescape(value.strip_prefix(' ').unwrap_or(value))))
+        }).collect::<Result<Vec<_>>>()?
+    };
+    let data = gxt2::build(&entries).with_context(|| format!("failed to build from {}", input.display()))?;
+
+    let target = {
+        let archive = Archive::open(archive_path, keys)?;
+        archive.find_file(path).map(|f| f.path.clone()).unwrap_or_else(|| path.to_string())
+    };
+    let len = data.len();
+    let count = editor::edit_file(archive_path, keys, |ed| {
+        if ed.exists(&target) { ed.replace_file(&target, data) } else { ed.add_file(&target, data) }
+    })?;
+    println!("Wrote {} ({} string(s), {} bytes)", target, entries.len(), len);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+/// Keep each entry on one line in the text format.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' { out.push(c); continue; }
+        match chars.next() {
+            Some('n')   => out.push('\n'),
+            Some('r')   => out.push('\r'),
+            Some(other) => out.push(other),
+            None        => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+
+    fn read(path: &Path, name: &str) -> Vec<(u32, String)> {
+        let archive = Archive::open(path, None).unwrap();
+        gxt2::parse(&archive.extract(archive.find_file(name).unwrap(), None).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn export_import_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = vec![(names::hash("HELLO"), "Hi\nthere \\o/".to_string()), (0x1234_5678, "Café".to_string())];
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("lang/american.gxt2", gxt2::build(&entries).unwrap());
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, builder.build(None).unwrap()).unwrap();
+
+        let mut names = Names::load(&[]).unwrap();
+        names.add("HELLO");
+        for file in ["strings.txt", "strings.json"] {
+            let out = dir.path().join(file);
+            export(&path, "lang/american.gxt2", Some(&out), &names, None).unwrap();
+            let text = fs::read_to_string(&out).unwrap();
+            assert!(text.contains("HELLO") && text.contains("0x12345678"), "{}", text);
+
+            let target = format!("lang/{}.gxt2", file.replace('.', "_"));
+            import(&path, &target, &out, None).unwrap();
+            let mut sorted = entries.clone();
+            sorted.sort_by_key(|e| e.0);
+            assert_eq!(read(&path, &target), sorted);
+        }
+    }
+
+    #[test]
+    fn escapes_line_breaks() {
+        let text = "a\\b\nc\rd";
+        assert_eq!(escape(text), "a\\\\b\\nc\\rd");
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+}
@@ -1,10 +1,40 @@
 use anyhow::Result;
+use serde::Serialize;
 use std::path::Path;
+use crate::output::{self, Format};
 use crate::rpf::{Archive, GtaKeys};
 
-pub fn run(archive_path: &Path, keys: Option<&GtaKeys>) -> Result<()> {
+#[derive(Serialize)]
+struct InfoRecord {
+    path      : String,
+    version   : &'static str,
+    encryption: &'static str,
+    entries   : usize,
+    dirs      : usize,
+    files     : usize,
+    resources : usize,
+    total_size: u64,
+}
+
+pub fn run(archive_path: &Path, format: Format, keys: Option<&GtaKeys>) -> Result<()> {
     let archive = Archive::open(archive_path, keys)?;
 
+    let files = archive.list_files();
+    let total_size: u64 = files.iter().map(|f| f.mem_size as u64).sum();
+
+    if format != Format::Text {
+        return output::print_record(format, &InfoRecord {
+            path      : archive_path.display().to_string(),
+            version   : output::version_name(archive.version()),
+            encryption: output::encryption_name(archive.encryption),
+            entries   : archive.entry_count,
+            dirs      : archive.dir_count,
+            files     : files.len(),
+            resources : files.iter().filter(|f| f.is_resource).count(),
+            total_size,
+        });
+    }
+
     println!("RPF Archive Information");
     println!("======================");
     println!("Path:        {}", archive_path.display());
@@ -17,8 +47,6 @@ pub fn run(archive_path: &Path, keys: Option<&GtaKeys>) -> Result<()> {
         v          => return Err(anyhow::anyhow!("Unknown encryption type 0x{:08X}", v)),
     });
 
-    let files = archive.list_files();
-    let total_size: u64 = files.iter().map(|f| f.mem_size as u64).sum();
     println!("Files:       {}", files.len());
     println!("Total size:  {} bytes ({:.2} MB)", total_size, total_size as f64 / (1024.0 * 1024.0));
 
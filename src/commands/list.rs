@@ -1,29 +1,62 @@
 use anyhow::Result;
+use rpf_archive::resource_version_from_flags;
 use std::path::Path;
-use crate::rpf::{Archive, GtaKeys};
-use crate::utils::matches_pattern;
+use crate::output::{self, EntryRecord, Format};
+use crate::rbf;
+use crate::resource;
+use crate::rpf::{Archive, GtaKeys, RpfEntryKind};
+use crate::utils::{FilterArgs, PathFilter};
 
-pub fn run(archive_path: &Path, pattern: Option<&str>, detailed: bool, keys: Option<&GtaKeys>) -> Result<()> {
+pub fn run(
+    archive_path: &Path,
+    pattern: Option<&str>,
+    filter: &FilterArgs,
+    detailed: bool,
+    format: Format,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let filter = PathFilter::new(pattern, filter)?;
     let archive = Archive::open(archive_path, keys)?;
 
     let mut files: Vec<_> = archive.list_files()
         .into_iter()
-        .filter(|f| pattern.map_or(true, |p| matches_pattern(&f.path, p)))
+        .filter(|f| filter.matches(&f.path))
         .collect();
 
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    if format != Format::Text {
+        let records: Vec<EntryRecord> = files.iter().map(|f| EntryRecord::new(&archive, f)).collect();
+        return output::print_records(format, &records);
+    }
+
     if files.is_empty() {
         println!("No files found");
         return Ok(());
     }
 
-    files.sort_by(|a, b| a.path.cmp(&b.path));
-
     if detailed {
-        println!("{:<60} {:>12} {:>12} {}", "Path", "Size", "Compressed", "Type");
-        println!("{}", "-".repeat(100));
+        println!("{:<60} {:>12} {:>12} {:<8} {:>7} {:>9}", "Path", "Size", "Compressed", "Type", "Version", "Pages");
+        println!("{}", "-".repeat(112));
         for f in files {
-            let kind = if f.is_resource { "Resource" } else { "Binary" };
-            println!("{:<60} {:>12} {:>12} {}", f.path, f.mem_size, f.size, kind);
+            // Meta files are read to tell RBF from text XML.
+            let kind = if f.is_resource {
+                "Resource"
+            } else if rbf::EXTENSIONS.iter().any(|e| f.name.to_lowercase().ends_with(&format!(".{}", e))) {
+                archive.extract(f, keys).ok().as_deref().and_then(rbf::describe).unwrap_or("Binary")
+            } else {
+                "Binary"
+            };
+            // Resources: the version and the system+graphics page counts from the flags.
+            let (version, pages) = match *archive.entry_kind(f) {
+                RpfEntryKind::ResourceFile { system_flags, graphics_flags, .. } => {
+                    let count = |flags| resource::pages(flags).iter().map(|p| p.1).sum::<u32>();
+                    (resource_version_from_flags(system_flags, graphics_flags).to_string(),
+                        format!("{}+{}", count(system_flags), count(graphics_flags)))
+                }
+                _ => ("-".to_string(), "-".to_string()),
+            };
+            println!("{:<60} {:>12} {:>12} {:<8} {:>7} {:>9}", f.path, f.mem_size, f.size, kind, version, pages);
         }
     } else {
         for f in files {
@@ -0,0 +1,85 @@
+use anyhow::{bail, Context, Result};
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::editor;
+use crate::meta::{pso, schema, xml, Meta};
+use crate::names::Names;
+use crate::resource::{Resource, RSC7_MAGIC};
+use crate::rpf::{Archive, GtaKeys};
+
+/// Convert a meta file (.ymap, .ytyp, .ymt) inside an archive to CodeWalker XML, written
+/// to `output` or `<name>.xml`.
+pub fn export(archive_path: &Path, path: &str, output: Option<&Path>, names: &Names, keys: Option<&GtaKeys>) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let file = archive.find_file(path)
+        .with_context(|| format!("'{}' not found in archive", path))?;
+    let data = archive.extract(file, keys)
+        .with_context(|| format!("failed to extract '{}'", path))?;
+    let meta = parse(&data).with_context(|| format!("failed to read {}", file.path))?;
+    let text = xml::to_xml(&meta, names).with_context(|| format!("failed to convert {}", file.path))?;
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.xml", file.name)));
+    fs::write(&output, &text).with_context(|| format!("failed to write {}", output.display()))?;
+    println!("Exported {} ({} structure type(s), {} data block(s)) to {}",
+        file.path, meta.structs.len(), meta.blocks.len(), output.display());
+    Ok(())
+}
+
+/// Convert CodeWalker XML back to a meta file and write it into the archive as `path`.
+///
+/// The structure and enum definitions come from the entry being replaced, or from
+/// `schema` (any meta file of the same kind) when the entry is new, or else from the
+/// built-in .ymap and .ytyp definitions. The output is PSO when the definitions come from
+/// a PSO file and RSC7 otherwise.
+pub fn import(archive_path: &Path, path: &str, xml_path: &Path, schema: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let text = fs::read_to_string(xml_path)
+        .with_context(|| format!("cannot read {}", xml_path.display()))?;
+
+    let existing = {
+        let archive = Archive::open(archive_path, keys)?;
+        match archive.find_file(path) {
+            Some(file) => Some((file.path.clone(), archive.extract(file, keys)
+                .with_context(|| format!("failed to extract '{}'", path))?)),
+            None => None,
+        }
+    };
+    let builtin = schema.is_none() && existing.is_none();
+    let schema = match (schema, &existing) {
+        (Some(schema), _) => {
+            let data = fs::read(schema).with_context(|| format!("cannot read {}", schema.display()))?;
+            parse(&data).with_context(|| format!("failed to read schema {}", schema.display()))?
+        }
+        (None, Some((entry, data))) => parse(data).with_context(|| format!("failed to read {}", entry))?,
+        (None, None) => schema::builtin(),
+    };
+
+    let mut meta = xml::from_xml(&text, schema).with_context(|| if builtin {
+        format!("failed to convert {} with the built-in .ymap/.ytyp definitions; give --schema <FILE> with a meta file of the same kind", xml_path.display())
+    } else {
+        format!("failed to convert {}", xml_path.display())
+    })?;
+    if builtin {
+        schema::prune(&mut meta);
+    }
+    let data = meta.build()?;
+
+    let len = data.len();
+    let target = existing.map(|(entry, _)| entry).unwrap_or_else(|| path.to_string());
+    let count = editor::edit_file(archive_path, keys, |ed| {
+        if ed.exists(&target) { ed.replace_file(&target, data) } else { ed.add_file(&target, data) }
+    })?;
+    println!("Wrote {} ({} data block(s), {} bytes)", target, meta.blocks.len(), len);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
+
+/// Parse a meta file, RSC7 or PSO.
+pub fn parse(data: &[u8]) -> Result<Meta> {
+    if pso::is_pso(data) {
+        return pso::read(data);
+    }
+    if data.len() < 16 || data[..4] != RSC7_MAGIC.to_le_bytes() {
+        bail!("not an RSC7 resource");
+    }
+    Meta::read(&Resource::parse(data)?)
+}
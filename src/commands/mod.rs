@@ -4,4 +4,22 @@ pub mod extract;
 pub mod verify;
 pub mod tree;
 pub mod ytd;
-pub mod create;
\ No newline at end of file
+pub mod ytd_all;
+pub mod create;
+pub mod add;
+pub mod replace;
+pub mod rm;
+pub mod mv;
+pub mod ytd_pack;
+pub mod ytd_edit;
+pub mod resinfo;
+pub mod resource_pack;
+pub mod meta;
+pub mod gxt2;
+pub mod awc;
+pub mod model;
+pub mod ybn;
+pub mod ymap;
+pub mod ynd;
+pub mod ynv;
+pub mod ycd;
@@ -0,0 +1,348 @@
+use anyhow::{bail, Context, Result};
+use rpf_archive::TextureFormat;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use crate::drawable::{self, Drawable, Geometry, LOD_NAMES};
+use crate::gltf::Gltf;
+use crate::names::{self, Names};
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys, RpfEntryKind};
+use crate::texture::{codec, Texture};
+
+/// Rotates GTA's Z-up space into glTF's Y-up.
+const Z_UP_TO_Y_UP: [f32; 4] = [-std::f32::consts::FRAC_1_SQRT_2, 0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2];
+
+/// Which levels of detail to export.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lod {
+    High,
+    Medium,
+    Low,
+    VeryLow,
+    /// Every LOD, each under its own node
+    All,
+}
+
+impl Lod {
+    fn indices(self) -> Vec<usize> {
+        match self {
+            Self::High    => vec![0],
+            Self::Medium  => vec![1],
+            Self::Low     => vec![2],
+            Self::VeryLow => vec![3],
+            Self::All     => (0..4).collect(),
+        }
+    }
+}
+
+/// Export the drawables of a .ydr, .ydd or .yft inside an archive to glTF 2.0: a .glb, or a
+/// .gltf with a sibling .bin when `output` ends in .gltf (default `<name>.glb`). Textures
+/// come from the embedded dictionary, then the sibling .ytd of the same name, then `ytds`.
+pub fn export(
+    archive_path: &Path,
+    path: &str,
+    output: Option<&Path>,
+    lod: Lod,
+    ytds: &[String],
+    names: &Names,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let file = archive.find_file(path)
+        .with_context(|| format!("'{}' not found in archive", path))?;
+    if !matches!(archive.entry_kind(file), RpfEntryKind::ResourceFile { .. }) {
+        bail!("'{}' is not a resource file", file.path);
+    }
+    let (stem, extension) = file.name.rsplit_once('.').unwrap_or((&file.name, ""));
+    let res = Resource::parse(&archive.extract(file, keys)?)?;
+    let drawables = drawable::read_all(&res, &extension.to_lowercase())
+        .with_context(|| format!("failed to read {}", file.path))?;
+
+    let lods = lod.indices();
+    if !drawables.iter().any(|d| lods.iter().any(|&l| !d.lods[l].is_empty())) {
+        let present: Vec<&str> = (0..4).filter(|&l| drawables.iter().any(|d| !d.lods[l].is_empty())).map(|l| LOD_NAMES[l]).collect();
+        bail!("{} has no models at that LOD (present: {})", file.path, if present.is_empty() { "none".to_string() } else { present.join(", ") });
+    }
+
+    // External dictionaries: the sibling .ytd (vehicles drop the "_hi" of their high-detail
+    // fragment), then those named on the command line.
+    let dir = file.path.rsplit_once('/').map_or("", |(d, _)| d);
+    let base = stem.strip_suffix("_hi").unwrap_or(stem);
+    let mut dictionaries: Vec<(String, Vec<Texture>)> = Vec::new();
+    let sibling = [format!("{}/{}.ytd", dir, stem), format!("{}/{}.ytd", dir, base)];
+    for candidate in sibling.iter().map(|p| p.trim_start_matches('/')) {
+        if let Some(f) = archive.find_file(candidate) && !dictionaries.iter().any(|(p, _)| *p == f.path) {
+            dictionaries.push((f.path.clone(), read_ytd(&archive, &f.path, keys)?));
+        }
+    }
+    for name in ytds {
+        let f = archive.find_file(name).with_context(|| format!("'{}' not found in archive", name))?;
+        dictionaries.push((f.path.clone(), read_ytd(&archive, &f.path, keys)?));
+    }
+
+    let mut gltf = Gltf::new();
+    let root = gltf.add_node(json!({ "name": stem, "rotation": Z_UP_TO_Y_UP }), None);
+    let mut images = Images { gltf: &mut gltf, cache: HashMap::new(), missing: Vec::new() };
+    let (mut meshes, mut triangles, mut bones) = (0, 0, 0);
+    for d in &drawables {
+        let parent = if drawables.len() > 1 { Some(images.gltf.add_node(json!({ "name": d.name }), Some(root))) } else { None };
+        let mut lookup: HashMap<String, &Texture> = HashMap::new();
+        for tex in dictionaries.iter().rev().flat_map(|(_, t)| t).chain(&d.textures) {
+            lookup.insert(tex.name.to_lowercase(), tex);
+        }
+        meshes += add_drawable(&mut images, d, &lods, &lookup, names, parent.unwrap_or(root))?;
+        triangles += lods.iter().map(|&l| d.triangle_count(l)).sum::<usize>();
+        bones += d.bones.len();
+    }
+    let (textures, missing) = (images.cache.values().flatten().count(), images.missing);
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.glb", stem)));
+    if output.extension().is_some_and(|e| e.eq_ignore_ascii_case("gltf")) {
+        let bin = output.with_extension("bin");
+        let uri = bin.file_name().unwrap().to_string_lossy().into_owned();
+        let (json, data) = gltf.to_gltf(&uri)?;
+        fs::write(&bin, data).with_context(|| format!("failed to write {}", bin.display()))?;
+        fs::write(&output, json).with_context(|| format!("failed to write {}", output.display()))?;
+    } else {
+        fs::write(&output, gltf.to_glb()?).with_context(|| format!("failed to write {}", output.display()))?;
+    }
+
+    for (path, _) in &dictionaries {
+        println!("  textures from {}", path);
+    }
+    for name in &missing {
+        println!("  missing texture: {}", name);
+    }
+    println!("Exported {} ({} drawable(s), {} mesh(es), {} triangles, {} bone(s), {} texture(s)) to {}",
+        file.path, drawables.len(), meshes, triangles, bones, textures, output.display());
+    Ok(())
+}
+
+fn read_ytd(archive: &Archive, path: &str, keys: Option<&GtaKeys>) -> Result<Vec<Texture>> {
+    let file = archive.find_file(path).with_context(|| format!("'{}' not found in archive", path))?;
+    Resource::parse(&archive.extract(file, keys)?)
+        .and_then(|res| crate::texture::read_dictionary(&res))
+        .with_context(|| format!("failed to read {}", path))
+}
+
+/// Textures converted to PNG and embedded so far, by lower-case name (`None` if missing).
+struct Images<'a> {
+    gltf   : &'a mut Gltf,
+    cache  : HashMap<String, Option<usize>>,
+    missing: Vec<String>,
+}
+
+impl Images<'_> {
+    fn texture(&mut self, name: &str, lookup: &HashMap<String, &Texture>, normal_map: bool) -> Option<usize> {
+        let key = name.to_lowercase();
+        if let Some(&index) = self.cache.get(&key) { return index; }
+
+        let index = match lookup.get(&key) {
+            Some(tex) => match to_png(tex, normal_map) {
+                Ok(png) => Some(self.gltf.add_texture(name, &png)),
+                Err(e) => {
+                    eprintln!("  cannot convert texture {}: {:#}", name, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        if index.is_none() { self.missing.push(name.to_string()); }
+        self.cache.insert(key, index);
+        index
+    }
+}
+
+/// The top mip as PNG. Two-channel (ATI2) normal maps get their Z rebuilt, as glTF
+/// expects all three.
+fn to_png(tex: &Texture, normal_map: bool) -> Result<Vec<u8>> {
+    let top = tex.image(0, 0).context("texture has no pixel data")?;
+    let mut rgba = codec::decode(tex.format, tex.width as u32, tex.height as u32, top)?;
+    if normal_map && tex.format == TextureFormat::ATI2 {
+        for px in rgba.chunks_exact_mut(4) {
+            let (x, y) = (px[0] as f32 / 127.5 - 1.0, px[1] as f32 / 127.5 - 1.0);
+            px[2] = (((1.0 - x * x - y * y).max(0.0).sqrt() + 1.0) * 127.5) as u8;
+        }
+    }
+    codec::to_png(tex.width as u32, tex.height as u32, &rgba)
+}
+
+/// Add one drawable under `parent`: its skeleton as a node tree, then the models of each
+/// LOD in `lods`. Returns the number of meshes added.
+fn add_drawable(
+    images: &mut Images,
+    d: &Drawable,
+    lods: &[usize],
+    lookup: &HashMap<String, &Texture>,
+    names: &Names,
+    parent: usize,
+) -> Result<usize> {
+    let mut bone_nodes = Vec::with_capacity(d.bones.len());
+    for b in &d.bones {
+        let node = json!({ "name": b.name, "translation": b.translation, "rotation": b.rotation, "scale": b.scale, "extras": { "tag": b.tag } });
+        let node_parent = b.parent.map_or(parent, |p| bone_nodes[p]);
+        bone_nodes.push(images.gltf.add_node(node, Some(node_parent)));
+    }
+
+    let skinned = lods.iter().any(|&l| d.lods[l].iter().any(|m| m.skinned)) && !d.bones.is_empty();
+    let skin = skinned.then(|| {
+        let inverse_binds = inverse_bind_matrices(d);
+        let accessor = images.gltf.add_floats(&inverse_binds, false);
+        images.gltf.add_skin(json!({ "joints": bone_nodes, "inverseBindMatrices": accessor, "skeleton": bone_nodes[0] }))
+    });
+
+    let mut materials: HashMap<u16, usize> = HashMap::new();
+    let mut meshes = 0;
+    for &l in lods {
+        if d.lods[l].is_empty() { continue; }
+        let lod_parent = if lods.len() > 1 {
+            images.gltf.add_node(json!({ "name": format!("{}_{}", d.name, LOD_NAMES[l]) }), Some(parent))
+        } else {
+            parent
+        };
+
+        for (i, model) in d.lods[l].iter().enumerate() {
+            let mut primitives = Vec::new();
+            for g in model.geometries.iter().filter(|g| !g.indices.is_empty()) {
+                let material = match materials.get(&g.shader) {
+                    Some(&m) => m,
+                    None => {
+                        let m = add_material(images, d, g.shader, lookup, names);
+                        materials.insert(g.shader, m);
+                        m
+                    }
+                };
+                primitives.push(add_primitive(images.gltf, g, material, skin.is_some() && model.skinned, d.bones.len()));
+            }
+            if primitives.is_empty() { continue; }
+
+            let name = format!("{}_{}_{}", if d.name.is_empty() { "model" } else { &d.name }, LOD_NAMES[l], i);
+            let mesh = images.gltf.add_mesh(json!({ "name": name, "primitives": primitives }));
+            let mut node = json!({ "name": name, "mesh": mesh });
+            let node_parent = match skin {
+                Some(skin) if model.skinned => {
+                    node["skin"] = json!(skin);
+                    lod_parent
+                }
+                // Rigid models are stored relative to the bone they move with.
+                _ => bone_nodes.get(model.bone as usize).copied().filter(|_| model.bone != 0).unwrap_or(lod_parent),
+            };
+            images.gltf.add_node(node, Some(node_parent));
+            meshes += 1;
+        }
+    }
+    Ok(meshes)
+}
+
+/// A PBR material using the shader's diffuse and bump textures; the first texture stands in
+/// for the diffuse one when no parameter has that name.
+fn add_material(images: &mut Images, d: &Drawable, shader: u16, lookup: &HashMap<String, &Texture>, names: &Names) -> usize {
+    let Some(s) = d.shaders.get(shader as usize) else {
+        return images.gltf.add_material(json!({ "name": format!("shader_{}", shader) }));
+    };
+    let (diffuse, bump) = (names::hash("diffusesampler"), names::hash("bumpsampler"));
+    let base = s.textures.iter().find(|t| t.0 == diffuse).or_else(|| s.textures.iter().find(|t| t.0 != bump));
+    let normal = s.textures.iter().find(|t| t.0 == bump);
+
+    let mut pbr = json!({ "metallicFactor": 0.0, "roughnessFactor": 1.0 });
+    if let Some((_, name)) = base && let Some(t) = images.texture(name, lookup, false) {
+        pbr["baseColorTexture"] = json!({ "index": t });
+    }
+    let mut material = json!({ "name": names.display(s.name), "pbrMetallicRoughness": pbr });
+    if let Some((_, name)) = normal && let Some(t) = images.texture(name, lookup, true) {
+        material["normalTexture"] = json!({ "index": t });
+    }
+    images.gltf.add_material(material)
+}
+
+fn add_primitive(gltf: &mut Gltf, g: &Geometry, material: usize, skinned: bool, bone_count: usize) -> Value {
+    let v = &g.vertices;
+    let mut attributes = json!({ "POSITION": gltf.add_floats(&v.positions, true) });
+    if !v.normals.is_empty() {
+        let normals: Vec<[f32; 3]> = v.normals.iter().map(|n| {
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            if len > 1e-6 { n.map(|c| c / len) } else { [0.0, 0.0, 1.0] }
+        }).collect();
+        attributes["NORMAL"] = json!(gltf.add_floats(&normals, false));
+    }
+    for (set, uvs) in v.uvs.iter().enumerate().filter(|(_, uvs)| !uvs.is_empty()) {
+        attributes[format!("TEXCOORD_{}", set)] = json!(gltf.add_floats(uvs, false));
+    }
+    if !v.colours.is_empty() {
+        attributes["COLOR_0"] = json!(gltf.add_bytes(&v.colours, true));
+    }
+    if skinned && !v.weights.is_empty() {
+        let joints: Vec<[u16; 4]> = v.indices.iter().map(|j| j.map(|j| {
+            let bone = g.bone_ids.get(j as usize).copied().unwrap_or(j as u16);
+            bone.min(bone_count as u16 - 1)
+        })).collect();
+        let weights: Vec<[f32; 4]> = v.weights.iter().map(|w| {
+            let sum: f32 = w.iter().map(|&b| b as f32).sum();
+            if sum > 0.0 { w.map(|b| b as f32 / sum) } else { [1.0, 0.0, 0.0, 0.0] }
+        }).collect();
+        attributes["JOINTS_0"] = json!(gltf.add_joints(&joints));
+        attributes["WEIGHTS_0"] = json!(gltf.add_floats(&weights, false));
+    }
+    json!({ "attributes": attributes, "indices": gltf.add_indices(&g.indices), "material": material })
+}
+
+// ─── Bone matrices ───────────────────────────────────────────────────────────
+
+/// Inverse of every bone's bind pose in model space, column-major.
+fn inverse_bind_matrices(d: &Drawable) -> Vec<[f32; 16]> {
+    let mut world: Vec<[f32; 16]> = Vec::with_capacity(d.bones.len());
+    for b in &d.bones {
+        let local = trs(b.translation, b.rotation, b.scale);
+        let m = match b.parent {
+            Some(p) => mul(&world[p], &local),
+            None    => local,
+        };
+        world.push(m);
+    }
+    world.iter().map(invert_affine).collect()
+}
+
+fn trs(t: [f32; 3], q: [f32; 4], s: [f32; 3]) -> [f32; 16] {
+    let [x, y, z, w] = q;
+    [
+        (1.0 - 2.0 * (y * y + z * z)) * s[0], 2.0 * (x * y + z * w) * s[0], 2.0 * (x * z - y * w) * s[0], 0.0,
+        2.0 * (x * y - z * w) * s[1], (1.0 - 2.0 * (x * x + z * z)) * s[1], 2.0 * (y * z + x * w) * s[1], 0.0,
+        2.0 * (x * z + y * w) * s[2], 2.0 * (y * z - x * w) * s[2], (1.0 - 2.0 * (x * x + y * y)) * s[2], 0.0,
+        t[0], t[1], t[2], 1.0,
+    ]
+}
+
+fn mul(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    std::array::from_fn(|i| {
+        let (col, row) = (i / 4, i % 4);
+        (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum()
+    })
+}
+
+/// Inverse of an affine matrix: the inverted 3x3 part and the translation taken back
+/// through it. A singular matrix (a zero scale) yields the identity.
+fn invert_affine(m: &[f32; 16]) -> [f32; 16] {
+    let a = |r: usize, c: usize| m[c * 4 + r];
+    let cof = |r: usize, c: usize| {
+        let (r1, r2, c1, c2) = ((r + 1) % 3, (r + 2) % 3, (c + 1) % 3, (c + 2) % 3);
+        a(r1, c1) * a(r2, c2) - a(r1, c2) * a(r2, c1)
+    };
+    let det = a(0, 0) * cof(0, 0) + a(0, 1) * cof(0, 1) + a(0, 2) * cof(0, 2);
+    let mut out = [0.0; 16];
+    if det.abs() < 1e-12 {
+        for i in 0..4 { out[i * 5] = 1.0; }
+        return out;
+    }
+    // inv[r][c] = cof(c, r) / det
+    for r in 0..3 {
+        for c in 0..3 {
+            out[c * 4 + r] = cof(c, r) / det;
+        }
+    }
+    for r in 0..3 {
+        out[12 + r] = -(0..3).map(|c| out[c * 4 + r] * m[12 + c]).sum::<f32>();
+    }
+    out[15] = 1.0;
+    out
+}
@@ -0,0 +1,13 @@
+use anyhow::Result;
+use std::path::Path;
+use crate::editor;
+use crate::rpf::GtaKeys;
+
+/// Move or rename a file or directory inside an archive in place.
+pub fn run(archive_path: &Path, from: &str, to: &str, keys: Option<&GtaKeys>) -> Result<()> {
+    let count = editor::edit_file(archive_path, keys, |ed| ed.rename(from, to))?;
+
+    println!("Moved {} -> {}", from, to);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+use crate::editor;
+use crate::rpf::GtaKeys;
+
+/// Replace the contents of an existing entry in place, keeping its kind and encryption.
+pub fn run(archive_path: &Path, entry_path: &str, source: &Path, keys: Option<&GtaKeys>) -> Result<()> {
+    let data = fs::read(source).with_context(|| format!("cannot read {}", source.display()))?;
+    let len = data.len();
+
+    let count = editor::edit_file(archive_path, keys, |ed| ed.replace_file(entry_path, data))?;
+
+    println!("Replaced {} ({} bytes)", entry_path, len);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
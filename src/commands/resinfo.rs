@@ -0,0 +1,138 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::DeflateDecoder;
+use serde::Serialize;
+use std::{io::Read, path::Path};
+
+use rpf_archive::{resource_size_from_flags, resource_version_from_flags};
+
+use crate::commands::verify;
+use crate::output::{self, Format};
+use crate::resource::{self, RSC7_MAGIC};
+use crate::rpf::{Archive, GtaKeys, RpfEntryKind, RpfVersion};
+
+#[derive(Serialize)]
+struct ResInfoRecord {
+    path            : String,
+    /// Extension of the identified type, from the file name or else the version.
+    #[serde(rename = "type")]
+    kind            : Option<&'static str>,
+    description     : Option<&'static str>,
+    version         : u32,
+    /// Version GTA V expects for this extension.
+    expected_version: Option<u32>,
+    /// Bytes stored in the archive (header and deflated body).
+    stored_size     : u32,
+    encrypted       : bool,
+    system_flags    : u32,
+    graphics_flags  : u32,
+    system_size     : usize,
+    graphics_size   : usize,
+    /// `<count> x <page size>` per size class, largest first.
+    system_pages    : Vec<String>,
+    graphics_pages  : Vec<String>,
+    /// Bytes the body actually inflates to; null if it does not inflate.
+    inflated_size   : Option<usize>,
+    /// Inconsistencies between the flags, the stored header and the payload.
+    problems        : Vec<String>,
+}
+
+/// Decode the RSC7 header and page flags of one resource in an archive, and check them
+/// against the stored header and the payload.
+pub fn run(archive_path: &Path, path: &str, format: Format, keys: Option<&GtaKeys>) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let file = archive.find_file(path)
+        .with_context(|| format!("'{}' not found in archive", path))?;
+    let RpfEntryKind::ResourceFile { file_size, system_flags, graphics_flags, is_encrypted, .. } = *archive.entry_kind(file) else {
+        bail!("'{}' is not a resource file", file.path);
+    };
+
+    let version = resource_version_from_flags(system_flags, graphics_flags);
+    let (system_size, graphics_size) = (resource_size_from_flags(system_flags), resource_size_from_flags(graphics_flags));
+    let mut problems = Vec::new();
+
+    let candidates = resource::types_with_version(version);
+    let (kind, description, expected_version, type_line) = match resource::resource_type(&file.name) {
+        Some((ext, desc, expected)) => {
+            if version != expected {
+                problems.push(format!("version {} does not match .{} (expected {})", version, ext, expected));
+            }
+            (Some(ext), Some(desc), Some(expected), format!(".{} ({})", ext, desc))
+        }
+        None => match candidates.as_slice() {
+            [(ext, desc)] => (Some(*ext), Some(*desc), None, format!("probably .{} ({}), from the version", ext, desc)),
+            []            => (None, None, None, "unknown".to_string()),
+            types         => (None, None, None, format!("unknown (version {} is used by .{})", version,
+                types.iter().map(|t| t.0).collect::<Vec<_>>().join(", ."))),
+        },
+    };
+
+    if archive.version() == RpfVersion::V7
+        && let Err(e) = verify::check_resource_header(&archive, file, file_size, system_flags, graphics_flags) {
+        problems.push(e.to_string());
+    }
+
+    let inflated_size = match archive.extract(file, keys) {
+        Ok(data) if data.len() >= 16 && data[..4] == RSC7_MAGIC.to_le_bytes() => {
+            let mut body = Vec::new();
+            match DeflateDecoder::new(&data[16..]).read_to_end(&mut body) {
+                Ok(_) => Some(body.len()),
+                Err(e) => { problems.push(format!("body does not inflate: {}", e)); None }
+            }
+        }
+        Ok(_)  => { problems.push("extracted data has no RSC7 header".into()); None }
+        Err(e) => { problems.push(format!("extract failed: {:#}", e)); None }
+    };
+    if let Some(size) = inflated_size
+        && size != system_size + graphics_size {
+        problems.push(format!("body inflates to {} bytes, the page flags describe {}", size, system_size + graphics_size));
+    }
+
+    let page_list = |flags: u32| -> Vec<String> {
+        resource::pages(flags).iter().map(|(size, count)| format!("{} x {}", count, size)).collect()
+    };
+    let record = ResInfoRecord {
+        path            : file.path.clone(),
+        kind,
+        description,
+        version,
+        expected_version,
+        stored_size     : file.size,
+        encrypted       : is_encrypted && archive.encryption.is_encrypted(),
+        system_flags,
+        graphics_flags,
+        system_size,
+        graphics_size,
+        system_pages    : page_list(system_flags),
+        graphics_pages  : page_list(graphics_flags),
+        inflated_size,
+        problems,
+    };
+
+    if format != Format::Text {
+        return output::print_record(format, &record);
+    }
+
+    let r = &record;
+    println!("Resource:        {}", r.path);
+    println!("Type:            {}", type_line);
+    println!("Version:         {}", r.version);
+    println!("Stored size:     {} bytes{}", r.stored_size, if r.encrypted { " (encrypted)" } else { "" });
+    let total = r.system_size + r.graphics_size;
+    println!("Decompressed:    {} bytes (system {} + graphics {}){}", total, r.system_size, r.graphics_size,
+        if total > 0 { format!(", {:.1}% stored", r.stored_size as f64 * 100.0 / total as f64) } else { String::new() });
+    for (name, flags, pages) in [("System", r.system_flags, &r.system_pages), ("Graphics", r.graphics_flags, &r.graphics_pages)] {
+        println!("{:<16} 0x{:08X}  base 0x{:X}, pages: {}", format!("{} flags:", name), flags,
+            resource::page_base(flags), if pages.is_empty() { "none".to_string() } else { pages.join(", ") });
+    }
+    match r.inflated_size {
+        Some(size) => println!("Payload:         inflates to {} bytes", size),
+        None       => println!("Payload:         does not inflate"),
+    }
+
+    if r.problems.is_empty() {
+        println!("✓ Flags, header and payload are consistent");
+    } else {
+        for p in &r.problems { println!("✗ {}", p); }
+    }
+    Ok(())
+}
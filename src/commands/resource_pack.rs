@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+use crate::editor;
+use crate::resource;
+use crate::rpf::GtaKeys;
+
+/// Rebuild an RSC7 resource from the raw sections and sidecar written by
+/// `extract --raw-resource`, writing it to `output` or, with `archive`, adding or replacing
+/// the entry `output` inside that archive.
+pub fn run(sidecar: &Path, output: &str, archive: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let res = resource::read_raw(sidecar)?;
+    let data = res.to_rsc7()
+        .with_context(|| format!("cannot rebuild {}", sidecar.display()))?;
+    let len = data.len();
+    let summary = format!("version {}, system {} + graphics {} bytes", res.version, res.system.len(), res.graphics.len());
+
+    match archive {
+        None => {
+            fs::write(output, &data).with_context(|| format!("failed to write {}", output))?;
+            println!("Packed {} ({}, {} bytes)", output, summary, len);
+        }
+        Some(archive_path) => {
+            let count = editor::edit_file(archive_path, keys, |ed| {
+                if ed.exists(output) { ed.replace_file(output, data) } else { ed.add_file(output, data) }
+            })?;
+            println!("Packed {} ({}, {} bytes)", output, summary, len);
+            println!("Updated {} ({} entries)", archive_path.display(), count);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+    use crate::commands::extract::{self, ExtractArgs};
+    use crate::resource::{Resource, ResourceBuilder, Section};
+    use crate::rpf::Archive;
+    use crate::utils::FilterArgs;
+
+    fn ytd() -> Vec<u8> {
+        let mut b = ResourceBuilder::new();
+        let root = b.add(Section::System, vec![1; 0x40]);
+        let data = b.add(Section::Graphics, vec![2; 0x1000]);
+        b.pointer(root, 0x10, data);
+        b.build(13).unwrap()
+    }
+
+    #[test]
+    fn raw_resources_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.rpf");
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("stream/a.ytd", ytd());
+        builder.add_file("notes.txt", b"text".to_vec());
+        fs::write(&path, builder.build(None).unwrap()).unwrap();
+
+        let out = dir.path().join("out");
+        let args = ExtractArgs { recursive: false, jobs: None, raw_resource: true, rbf_to_xml: false };
+        extract::run(&path, Some(&out), None, &FilterArgs::default(), &args, None).unwrap();
+        assert!(out.join("stream/a.ytd.system").is_file() && out.join("stream/a.ytd.graphics").is_file());
+        assert!(!out.join("stream/a.ytd").exists());
+        assert_eq!(fs::read(out.join("notes.txt")).unwrap(), b"text", "binaries are written as is");
+
+        // Edit the graphics section, then pack it back into the archive under a new name.
+        let mut graphics = fs::read(out.join("stream/a.ytd.graphics")).unwrap();
+        graphics[0] = 9;
+        fs::write(out.join("stream/a.ytd.graphics"), &graphics).unwrap();
+        run(&out.join("stream/a.ytd.json"), "stream/b.ytd", Some(&path), None).unwrap();
+
+        let archive = Archive::open(&path, None).unwrap();
+        let read = |name: &str| Resource::parse(&archive.extract(archive.find_file(name).unwrap(), None).unwrap()).unwrap();
+        let (a, b) = (read("stream/a.ytd"), read("stream/b.ytd"));
+        assert_eq!((b.version, b.system_flags, b.graphics_flags), (a.version, a.system_flags, a.graphics_flags));
+        assert_eq!(b.system, a.system);
+        assert_eq!(b.graphics, graphics);
+        assert_ne!(a.graphics, graphics);
+    }
+
+    #[test]
+    fn packs_to_a_loose_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let res = Resource::parse(&ytd()).unwrap();
+        let sidecar = resource::write_raw(&dir.path().join("a.ytd"), &res).unwrap();
+        let output = dir.path().join("packed.ytd");
+        run(&sidecar, output.to_str().unwrap(), None, None).unwrap();
+        let packed = Resource::parse(&fs::read(&output).unwrap()).unwrap();
+        assert_eq!((packed.system, packed.graphics), (res.system, res.graphics));
+    }
+}
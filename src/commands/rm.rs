@@ -0,0 +1,18 @@
+use anyhow::Result;
+use std::path::Path;
+use crate::editor;
+use crate::rpf::GtaKeys;
+
+/// Delete files or directories from an archive in place.
+pub fn run(archive_path: &Path, entry_paths: &[String], keys: Option<&GtaKeys>) -> Result<()> {
+    let count = editor::edit_file(archive_path, keys, |ed| {
+        for p in entry_paths {
+            ed.remove(p)?;
+            println!("Removed {}", p);
+        }
+        Ok(())
+    })?;
+
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
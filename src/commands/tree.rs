@@ -1,22 +1,63 @@
 use anyhow::Result;
+use serde::Serialize;
 use std::path::Path;
+use crate::output::{self, EntryRecord, Format};
 use crate::rpf::{Archive, DirNode, GtaKeys};
+use crate::utils::{FilterArgs, PathFilter};
 
-pub fn run(archive_path: &Path, max_depth: Option<usize>, keys: Option<&GtaKeys>) -> Result<()> {
+#[derive(Serialize)]
+struct TreeNode {
+    name : String,
+    path : String,
+    dirs : Vec<TreeNode>,
+    files: Vec<EntryRecord>,
+}
+
+pub fn run(
+    archive_path: &Path,
+    max_depth: Option<usize>,
+    filter: &FilterArgs,
+    format: Format,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let filter = PathFilter::new(None, filter)?;
     let archive = Archive::open(archive_path, keys)?;
 
+    let pruned;
+    let root = if filter.is_empty() {
+        &archive.root
+    } else {
+        pruned = prune(&archive.root, &filter);
+        &pruned
+    };
+
+    match format {
+        Format::Text => {}
+        Format::Json => {
+            let mut root = tree_node(&archive, root, 0, max_depth);
+            root.name = archive_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            return output::print_record(format, &root);
+        }
+        // Flat formats: one row per file, in tree order; directories are implied by the paths.
+        _ => {
+            let mut records = Vec::new();
+            flatten(tree_node(&archive, root, 0, max_depth), &mut records);
+            return output::print_records(format, &records);
+        }
+    }
+
     println!("{}", archive_path.file_name().unwrap_or_default().to_string_lossy());
-    print_tree(&archive.root, "", 0, max_depth);
+    print_tree(root, "", 0, max_depth);
 
-    let file_count = archive.list_files().len();
-    let dir_count  = count_dirs(&archive.root).saturating_sub(1);
+    let file_count = count_files(root);
+    let dir_count  = count_dirs(root).saturating_sub(1);
     println!("\n{} directories, {} files", dir_count, file_count);
 
     Ok(())
 }
 
 fn print_tree(dir: &DirNode, prefix: &str, depth: usize, max: Option<usize>) {
-    if max.map_or(false, |m| depth >= m) { return; }
+    if max.is_some_and(|m| depth >= m) { return; }
 
     let mut subdirs = dir.subdirs.clone();
     subdirs.sort_by(|a, b| a.name.cmp(&b.name));
@@ -45,6 +86,42 @@ fn print_tree(dir: &DirNode, prefix: &str, depth: usize, max: Option<usize>) {
     }
 }
 
+fn tree_node(archive: &Archive, dir: &DirNode, depth: usize, max: Option<usize>) -> TreeNode {
+    let mut node = TreeNode { name: dir.name.clone(), path: dir.path.clone(), dirs: Vec::new(), files: Vec::new() };
+    if max.is_some_and(|m| depth >= m) { return node; }
+
+    let mut subdirs: Vec<&DirNode> = dir.subdirs.iter().collect();
+    subdirs.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut files: Vec<_> = dir.files.iter().collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    node.dirs = subdirs.into_iter().map(|d| tree_node(archive, d, depth + 1, max)).collect();
+    node.files = files.into_iter().map(|f| EntryRecord::new(archive, f)).collect();
+    node
+}
+
+fn flatten(node: TreeNode, out: &mut Vec<EntryRecord>) {
+    for d in node.dirs { flatten(d, out); }
+    out.extend(node.files);
+}
+
+/// Copy of `dir` with only the files that pass `filter`, dropping directories left empty.
+fn prune(dir: &DirNode, filter: &PathFilter) -> DirNode {
+    DirNode {
+        name   : dir.name.clone(),
+        path   : dir.path.clone(),
+        files  : dir.files.iter().filter(|f| filter.matches(&f.path)).cloned().collect(),
+        subdirs: dir.subdirs.iter()
+            .map(|d| prune(d, filter))
+            .filter(|d| !d.files.is_empty() || !d.subdirs.is_empty())
+            .collect(),
+    }
+}
+
+fn count_files(dir: &DirNode) -> usize {
+    dir.files.len() + dir.subdirs.iter().map(count_files).sum::<usize>()
+}
+
 fn count_dirs(dir: &DirNode) -> usize {
     1 + dir.subdirs.iter().map(count_dirs).sum::<usize>()
 }
@@ -1,32 +1,456 @@
-use anyhow::Result;
-use std::path::Path;
-use crate::rpf::{Archive, GtaKeys};
+use anyhow::{bail, Context, Result};
+use flate2::read::DeflateDecoder;
+use rpf_archive::{resource_size_from_flags, resource_version_from_flags};
+use serde::Serialize;
+use std::{fs, io::Read, ops::Range, path::Path};
+use crate::output::{self, Format};
+use crate::rpf::{Archive, FileRef, GtaKeys, RpfEntryKind, RpfVersion, MAX_DEPTH};
+use crate::utils::{xml_escape, FilterArgs, PathFilter};
 
-pub fn run(archive_path: &Path, keys: Option<&GtaKeys>) -> Result<()> {
-    println!("Verifying: {}", archive_path.display());
+const RSC7_MAGIC: u32 = 0x37435352;
 
-    let archive = match Archive::open(archive_path, keys) {
-        Ok(a)  => { println!("✓ Archive opened and header parsed"); a }
-        Err(e) => { println!("✗ Failed to open archive: {}", e); return Ok(()); }
-    };
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+}
+
+/// Results for one archive (the top-level file or a nested `.rpf`).
+#[derive(Serialize)]
+struct Suite {
+    /// Virtual path of the archive; the file name for the top-level archive.
+    archive: String,
+    /// Errors that stopped the archive from being checked at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error  : Option<String>,
+    files  : Vec<Case>,
+}
+
+impl Suite {
+    fn failures(&self) -> usize {
+        self.error.is_some() as usize + self.files.iter().filter(|c| !c.errors.is_empty()).count()
+    }
+}
+
+#[derive(Serialize)]
+struct Case {
+    /// Full virtual path, including the names of any nested archives.
+    path  : String,
+    errors: Vec<String>,
+}
+
+/// One NDJSON line / CSV row of `verify --format ndjson|csv`.
+#[derive(Serialize)]
+struct Row<'a> {
+    archive: &'a str,
+    path   : &'a str,
+    passed : bool,
+    errors : &'a [String],
+}
+
+/// Settings shared by every level of the walk.
+struct Walk<'k> {
+    recursive: bool,
+    text     : bool,
+    filter   : PathFilter,
+    keys     : Option<&'k GtaKeys>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    archive  : String,
+    passed   : bool,
+    archives : usize,
+    files    : usize,
+    failures : usize,
+    suites   : &'a [Suite],
+}
+
+/// Check every entry's data: bounds, overlaps, decryption, inflate and resource headers.
+/// Fails with a per-entry error list so the exit status can gate CI jobs.
+pub fn run(
+    archive_path: &Path,
+    recursive: bool,
+    filter: &FilterArgs,
+    report: Option<&Path>,
+    report_format: Option<ReportFormat>,
+    format: Format,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let walk = Walk { recursive, text: format == Format::Text, filter: PathFilter::new(None, filter)?, keys };
+    if walk.text { println!("Verifying: {}", archive_path.display()); }
+    let name = archive_path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+
+    let mut suites = Vec::new();
+    match Archive::open(archive_path, keys) {
+        Ok(archive) => {
+            if walk.text {
+                println!("✓ Archive opened and header parsed");
+                println!("  {} entries ({} files, {} dirs)",
+                    archive.entry_count, archive.entry_count - archive.dir_count, archive.dir_count);
+            }
+            verify_archive(&archive, &name, "", &walk, 0, &mut suites);
+        }
+        Err(e) => {
+            if walk.text { println!("✗ Failed to open archive: {:#}", e); }
+            suites.push(Suite { archive: name.clone(), error: Some(format!("{:#}", e)), files: Vec::new() });
+        }
+    }
+
+    let files: usize = suites.iter().map(|s| s.files.len()).sum();
+    let failures: usize = suites.iter().map(Suite::failures).sum();
+    let summary = Report { archive: name.clone(), passed: failures == 0, archives: suites.len(), files, failures, suites: &suites };
+
+    if let Some(path) = report {
+        let report_format = report_format.unwrap_or(match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("xml") => ReportFormat::Junit,
+            _                                         => ReportFormat::Json,
+        });
+        let text = match report_format {
+            ReportFormat::Json  => serde_json::to_string_pretty(&summary)?,
+            ReportFormat::Junit => junit(&name, &suites),
+        };
+        fs::write(path, text).with_context(|| format!("cannot write report {}", path.display()))?;
+        if walk.text { println!("Report written to {}", path.display()); }
+    }
+
+    match format {
+        Format::Text => {}
+        Format::Json => output::print_record(format, &summary)?,
+        _ => {
+            let mut rows = Vec::new();
+            for s in &suites {
+                if let Some(e) = &s.error {
+                    rows.push(Row { archive: &s.archive, path: &s.archive, passed: false, errors: std::slice::from_ref(e) });
+                }
+                rows.extend(s.files.iter().map(|c| Row { archive: &s.archive, path: &c.path, passed: c.errors.is_empty(), errors: &c.errors }));
+            }
+            output::print_records(format, &rows)?;
+        }
+    }
+    if walk.text {
+        if recursive { println!("  {} archive(s), {} files checked", suites.len(), files); }
+        if failures == 0 { println!("✓ All {} file entries valid", files); }
+        for suite in &suites {
+            if let Some(e) = &suite.error { println!("  ✗ {}: {}", suite.archive, e); }
+            for case in &suite.files {
+                for e in &case.errors { println!("  ✗ {}: {}", case.path, e); }
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{} failing entr{} in {}", failures, if failures == 1 { "y" } else { "ies" }, archive_path.display());
+    }
+    Ok(())
+}
+
+/// Check one archive and, when `recursive`, every archive nested in it. `prefix` is the
+/// virtual path of `archive` (empty for the top level), as in `extract --recursive`.
+fn verify_archive(
+    archive: &Archive,
+    label: &str,
+    prefix: &str,
+    walk: &Walk<'_>,
+    depth: usize,
+    suites: &mut Vec<Suite>,
+) {
+    let files: Vec<FileRef> = archive.list_files().into_iter().cloned().collect();
+    let full = |f: &FileRef| if prefix.is_empty() { f.path.clone() } else { format!("{}/{}", prefix, f.path) };
 
-    let files = archive.list_files();
-    println!("  {} entries ({} files, {} dirs)", archive.entry_count, files.len(), archive.dir_count);
+    let mut cases: Vec<Case> = files.iter().map(|f| Case { path: full(f), errors: Vec::new() }).collect();
+    // Filtered-out entries still take part in the overlap check but are not reported.
+    let selected: Vec<bool> = cases.iter().map(|c| walk.filter.matches(&c.path)).collect();
+    let mut ranges: Vec<(Range<usize>, usize)> = Vec::new();
+    let len = archive.bytes().len();
+    let header_end = header_len(archive);
 
-    let mut errors = 0usize;
     for (i, f) in files.iter().enumerate() {
+        let errors = &mut cases[i].errors;
         if f.size == 0 && f.mem_size == 0 {
-            eprintln!("  ✗ {} has zero size", f.path);
-            errors += 1;
+            errors.push("zero size".into());
+        }
+
+        match archive.entry_range(f) {
+            Some(r) if r.end > len => {
+                errors.push(format!("data {}..{} runs past end of archive ({} bytes)", r.start, r.end, len));
+            }
+            Some(r) => {
+                if r.start < header_end {
+                    errors.push(format!("data at {} overlaps the archive header", r.start));
+                }
+                ranges.push((r, i));
+                if selected[i] && let Err(e) = check_content(archive, f, walk.keys) {
+                    errors.push(format!("{:#}", e));
+                }
+            }
+            None => {}
+        }
+
+        if walk.text && i % 1000 == 999 { print!("\r  Checked {}/{}...", i + 1, files.len()); }
+    }
+    if walk.text && files.len() >= 1000 { println!(); }
+
+    ranges.sort_by_key(|(r, _)| r.start);
+    let mut last: Option<&(Range<usize>, usize)> = None;
+    for entry in &ranges {
+        if let Some(prev) = last && entry.0.start < prev.0.end {
+            let msg = format!("data overlaps {} ({}..{})", cases[prev.1].path, prev.0.start, prev.0.end);
+            cases[entry.1].errors.push(msg);
         }
-        if i % 1000 == 999 { print!("\r  Checked {}/{}...", i + 1, files.len()); }
+        if last.is_none_or(|prev| entry.0.end > prev.0.end) { last = Some(entry); }
     }
 
-    if errors == 0 {
-        println!("✓ All {} file entries valid", files.len());
+    let nested: Vec<usize> = if walk.recursive {
+        (0..files.len()).filter(|&i| files[i].name.to_lowercase().ends_with(".rpf")).collect()
     } else {
-        println!("✗ {} error(s) found", errors);
+        Vec::new()
+    };
+    suites.push(Suite { archive: label.to_string(), error: None, files: cases });
+    let this = suites.len() - 1;
+
+    let mut keep = selected.into_iter();
+    suites[this].files.retain(|_| keep.next().unwrap_or(false));
+
+    // Nested archives report as suites of their own, so `--include`/`--exclude` never hide a
+    // child that cannot be checked.
+    for i in nested {
+        let path = full(&files[i]);
+        let error = |e: String| Suite { archive: path.clone(), error: Some(e), files: Vec::new() };
+        if depth + 1 > MAX_DEPTH {
+            suites.push(error(format!("max nesting depth ({}) reached", MAX_DEPTH)));
+            continue;
+        }
+        match archive.open_nested(&files[i], walk.keys) {
+            Ok(child) => {
+                if walk.text { println!("  → {} ({} entries)", path, child.entry_count); }
+                verify_archive(&child, &path, &path, walk, depth + 1, suites);
+            }
+            Err(e) => suites.push(error(format!("cannot open nested archive: {:#}", e))),
+        }
+    }
+}
+
+/// Size of the RPF7 header (TOC + names table); other versions are not checked.
+fn header_len(archive: &Archive) -> usize {
+    let b = archive.bytes();
+    if archive.version() != RpfVersion::V7 || b.len() < 16 { return 0; }
+    let entries = u32::from_le_bytes(b[4..8].try_into().unwrap()) as usize;
+    let names = u32::from_le_bytes(b[8..12].try_into().unwrap()) as usize;
+    16 + entries * 16 + names
+}
+
+fn check_content(archive: &Archive, f: &FileRef, keys: Option<&GtaKeys>) -> Result<()> {
+    let data = archive.extract(f, keys).context("extract failed")?;
+
+    match *archive.entry_kind(f) {
+        RpfEntryKind::BinaryFile { file_size, uncompressed_size, .. } => {
+            let compressed = file_size > 0 && file_size < uncompressed_size;
+            if compressed && data.len() == file_size as usize {
+                // rpf_archive hands back the raw bytes when inflate fails.
+                bail!("inflate failed (corrupt or wrongly decrypted data)");
+            }
+            if data.len() != uncompressed_size as usize {
+                bail!("extracted {} bytes, expected {}", data.len(), uncompressed_size);
+            }
+        }
+        RpfEntryKind::ResourceFile { file_size, system_flags, graphics_flags, .. } => {
+            if archive.version() != RpfVersion::V7 { return Ok(()); }
+            check_resource_header(archive, f, file_size, system_flags, graphics_flags)?;
+
+            let mut body = Vec::new();
+            DeflateDecoder::new(&data[16..]).read_to_end(&mut body)
+                .context("inflate failed (corrupt or wrongly decrypted data)")?;
+            let expected = resource_size_from_flags(system_flags) + resource_size_from_flags(graphics_flags);
+            if body.len() != expected {
+                bail!("inflated to {} bytes, expected {} from the page flags", body.len(), expected);
+            }
+        }
+        RpfEntryKind::Directory { .. } => {}
     }
+    Ok(())
+}
+
+/// Compare the RSC7 header stored in the archive with the TOC entry's flags.
+pub fn check_resource_header(archive: &Archive, f: &FileRef, file_size: u32, system_flags: u32, graphics_flags: u32) -> Result<()> {
+    let start = archive.entry_range(f).map(|r| r.start).unwrap_or(0);
+    let stored = archive.bytes().get(start..start + 16).context("resource header out of bounds")?;
+
+    let mut expected = [0u8; 16];
+    expected[0..4].copy_from_slice(&RSC7_MAGIC.to_le_bytes());
+    expected[4..8].copy_from_slice(&resource_version_from_flags(system_flags, graphics_flags).to_le_bytes());
+    expected[8..12].copy_from_slice(&system_flags.to_le_bytes());
+    expected[12..16].copy_from_slice(&graphics_flags.to_le_bytes());
 
+    // Resources of 16 MB and up keep their real size in header bytes 2, 5, 7 and 14.
+    let large = file_size >= 0xFFFFFF;
+    let mismatch = (0..16).any(|i| !(large && matches!(i, 2 | 5 | 7 | 14)) && stored[i] != expected[i]);
+    if mismatch {
+        bail!("RSC7 header {:02X?} does not match the entry flags (sys 0x{:08X}, gfx 0x{:08X})", stored, system_flags, graphics_flags);
+    }
     Ok(())
 }
+
+// ─── JUnit ───────────────────────────────────────────────────────────────────
+
+/// One `<testsuite>` per archive and one `<testcase>` per file entry.
+fn junit(name: &str, suites: &[Suite]) -> String {
+    let tests: usize = suites.iter().map(|s| s.files.len() + s.error.is_some() as usize).sum();
+    let failures: usize = suites.iter().map(Suite::failures).sum();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out += &format!("<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\">\n", xml_escape(name), tests, failures);
+    for s in suites {
+        out += &format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            xml_escape(&s.archive), s.files.len() + s.error.is_some() as usize, s.failures());
+        if let Some(e) = &s.error {
+            out += &format!("    <testcase classname=\"{0}\" name=\"{0}\">\n      <failure message=\"{1}\"/>\n    </testcase>\n",
+                xml_escape(&s.archive), xml_escape(e));
+        }
+        for c in &s.files {
+            let case = format!("    <testcase classname=\"{}\" name=\"{}\"", xml_escape(&s.archive), xml_escape(&c.path));
+            if c.errors.is_empty() {
+                out += &format!("{}/>\n", case);
+            } else {
+                out += &format!("{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                    case, xml_escape(&c.errors[0]), xml_escape(&c.errors.join("\n")));
+            }
+        }
+        out += "  </testsuite>\n";
+    }
+    out += "</testsuites>\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+    use std::path::PathBuf;
+
+    fn sample() -> Vec<u8> {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("a.bin", vec![1; 1500]);
+        builder.add_file("dir/b.bin", vec![2; 700]);
+        builder.build(None).unwrap()
+    }
+
+    fn verify(path: &Path, recursive: bool, report: Option<&Path>) -> Result<()> {
+        run(path, recursive, &FilterArgs::default(), report, None, Format::Text, None)
+    }
+
+    fn write(dir: &tempfile::TempDir, data: &[u8]) -> PathBuf {
+        let path = dir.path().join("test.rpf");
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    /// Position of `file`'s 24-bit block offset in the TOC.
+    fn block_at(data: &[u8], file: &str) -> usize {
+        let archive = Archive::from_bytes(data.to_vec(), "test.rpf", None).unwrap();
+        16 + archive.find_file(file).unwrap().entry_index * 16 + 5
+    }
+
+    fn set_block(data: &mut [u8], file: &str, block: [u8; 3]) {
+        let at = block_at(data, file);
+        data[at..at + 3].copy_from_slice(&block);
+    }
+
+    #[test]
+    fn accepts_a_clean_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(verify(&write(&dir, &sample()), false, None).is_ok());
+    }
+
+    #[test]
+    fn rejects_data_past_the_end() {
+        let mut data = sample();
+        set_block(&mut data, "a.bin", [0x00, 0x00, 0x10]);
+        let dir = tempfile::tempdir().unwrap();
+        let err = verify(&write(&dir, &data), false, None).unwrap_err();
+        assert!(err.to_string().contains("1 failing entry"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_overlapping_data() {
+        let mut data = sample();
+        let at = block_at(&data, "a.bin");
+        let block = data[at..at + 3].try_into().unwrap();
+        set_block(&mut data, "dir/b.bin", block);
+        let dir = tempfile::tempdir().unwrap();
+        assert!(verify(&write(&dir, &data), false, None).is_err());
+    }
+
+    #[test]
+    fn rejects_data_inside_the_header() {
+        let mut data = sample();
+        set_block(&mut data, "dir/b.bin", [0, 0, 0]);
+        let archive = Archive::from_bytes(data.clone(), "test.rpf", None).unwrap();
+        let file = archive.find_file("dir/b.bin").unwrap();
+        assert_eq!(archive.entry_range(file).unwrap().start, 0);
+        assert!(header_len(&archive) > 0);
+        let dir = tempfile::tempdir().unwrap();
+        assert!(verify(&write(&dir, &data), false, None).is_err());
+    }
+
+    /// An archive holding a clean file and `inner.rpf`, whose `a.bin` runs past its end.
+    fn nested_sample() -> Vec<u8> {
+        let mut inner = sample();
+        set_block(&mut inner, "a.bin", [0x00, 0x00, 0x10]);
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("top.bin", vec![3; 100]);
+        builder.add_file("inner.rpf", inner);
+        builder.build(None).unwrap()
+    }
+
+    #[test]
+    fn recursive_json_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(&dir, &nested_sample());
+        assert!(verify(&path, false, None).is_ok(), "nested archives are skipped by default");
+
+        let report = dir.path().join("report.json");
+        assert!(verify(&path, true, Some(&report)).is_err());
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["archives"], 2);
+        assert_eq!(json["files"], 4);
+        assert_eq!(json["failures"], 1);
+        let inner = &json["suites"][1];
+        assert_eq!(inner["archive"], "inner.rpf");
+        let failing: Vec<_> = inner["files"].as_array().unwrap().iter()
+            .filter(|c| !c["errors"].as_array().unwrap().is_empty()).map(|c| c["path"].clone()).collect();
+        assert_eq!(failing, ["inner.rpf/a.bin"]);
+    }
+
+    #[test]
+    fn recursive_junit_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(&dir, &nested_sample());
+        let report = dir.path().join("report.xml");
+        assert!(verify(&path, true, Some(&report)).is_err());
+
+        let xml = std::fs::read_to_string(&report).unwrap();
+        assert!(xml.contains("<testsuites name=\"test.rpf\" tests=\"4\" failures=\"1\">"), "{}", xml);
+        assert!(xml.contains("<testsuite name=\"inner.rpf\" tests=\"2\" failures=\"1\">"), "{}", xml);
+        assert!(xml.contains("<testcase classname=\"inner.rpf\" name=\"inner.rpf/a.bin\">\n      <failure"), "{}", xml);
+        assert!(xml.contains("<testcase classname=\"test.rpf\" name=\"top.bin\"/>"), "{}", xml);
+    }
+
+    #[test]
+    fn filters_never_hide_unopenable_archives() {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("top.bin", vec![3; 100]);
+        builder.add_file("bad.rpf", b"RPF7 but not really".to_vec());
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(&dir, &builder.build(None).unwrap());
+        let report = dir.path().join("report.json");
+        let filter = FilterArgs { include: vec!["*.bin".to_string()], ..FilterArgs::default() };
+        assert!(run(&path, true, &filter, Some(&report), None, Format::Text, None).is_err());
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(json["suites"][0]["files"].as_array().unwrap().len(), 1);
+        assert_eq!(json["suites"][1]["archive"], "bad.rpf");
+        assert!(json["suites"][1]["error"].as_str().unwrap().starts_with("cannot open nested archive"));
+    }
+}
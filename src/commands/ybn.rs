@@ -0,0 +1,300 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{collections::BTreeMap, f32::consts::PI, fs, path::{Path, PathBuf}};
+
+use crate::bounds::{self, kind, transform_point, Bound, Material, Shape};
+use crate::obj::Obj;
+use crate::output::{self, Format};
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys};
+
+/// Segments around curved primitives in exported meshes.
+const SEGMENTS: usize = 12;
+
+#[derive(Serialize)]
+struct BoundRecord {
+    /// Child indices from the root, e.g. "2/0"; "root" for the root bound.
+    path         : String,
+    #[serde(rename = "type")]
+    kind         : String,
+    box_min      : [f32; 3],
+    box_max      : [f32; 3],
+    margin       : f32,
+    vertices     : usize,
+    triangles    : usize,
+    /// Spheres, capsules, boxes and cylinders among a geometry's polygons.
+    primitives   : usize,
+    /// Composite children only.
+    type_flags   : Option<Vec<String>>,
+    include_flags: Option<Vec<String>>,
+    materials    : Vec<MaterialRecord>,
+}
+
+#[derive(Serialize)]
+struct MaterialRecord {
+    id           : u8,
+    name         : String,
+    flags        : Vec<String>,
+    procedural_id: u8,
+    room_id      : u8,
+    ped_density  : u8,
+    /// Index into the bound's material colour palette.
+    colour       : u8,
+    /// Polygons using it (1 for a primitive bound's own material).
+    polygons     : usize,
+}
+
+fn load(archive_path: &Path, path: &str, keys: Option<&GtaKeys>) -> Result<(String, Bound)> {
+    let archive = Archive::open(archive_path, keys)?;
+    let (full, data) = archive.read_path(path, keys)?;
+    let bound = Resource::parse(&data)
+        .and_then(|res| bounds::parse(&res))
+        .with_context(|| format!("failed to read {}", full))?;
+    Ok((full, bound))
+}
+
+fn path_label(path: &[usize]) -> String {
+    if path.is_empty() { return "root".into(); }
+    path.iter().map(usize::to_string).collect::<Vec<_>>().join("/")
+}
+
+fn material_record(m: &Material, polygons: usize) -> MaterialRecord {
+    MaterialRecord {
+        id           : m.id,
+        name         : bounds::material_name(m.id),
+        flags        : bounds::flag_names(m.flags as u32, &bounds::MATERIAL_FLAGS),
+        procedural_id: m.procedural_id,
+        room_id      : m.room_id,
+        ped_density  : m.ped_density,
+        colour       : m.colour,
+        polygons,
+    }
+}
+
+/// Decode the bounds of a .ybn inside an archive (nested archives allowed in `path`):
+/// types, extents, polygon counts, materials and flags.
+pub fn info(archive_path: &Path, path: &str, format: Format, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, root) = load(archive_path, path, keys)?;
+
+    let records: Vec<BoundRecord> = root.walk().iter().map(|node| {
+        let b = node.bound;
+        let (vertices, triangles, primitives, materials) = match &b.geometry {
+            Some(g) => {
+                let mut uses: BTreeMap<u8, usize> = BTreeMap::new();
+                for p in &g.polygons { *uses.entry(p.material).or_default() += 1; }
+                let materials = uses.into_iter().map(|(i, n)| {
+                    material_record(&g.materials.get(i as usize).copied().unwrap_or_default(), n)
+                }).collect();
+                let triangles = g.polygons.iter().filter(|p| matches!(p.shape, Shape::Triangle(_))).count();
+                (g.vertices.len(), triangles, g.polygons.len() - triangles, materials)
+            }
+            None if b.kind == kind::COMPOSITE => (0, 0, 0, Vec::new()),
+            None => (0, 0, 0, vec![material_record(&b.material, 1)]),
+        };
+        let flags = |f: u32| bounds::flag_names(f, &bounds::COMPOSITE_FLAGS);
+        BoundRecord {
+            path         : path_label(&node.path),
+            kind         : bounds::kind_name(b.kind),
+            box_min      : b.box_min,
+            box_max      : b.box_max,
+            margin       : b.margin,
+            vertices, triangles, primitives,
+            type_flags   : node.flags.map(|f| flags(f.0)),
+            include_flags: node.flags.map(|f| flags(f.1)),
+            materials,
+        }
+    }).collect();
+
+    if format != Format::Text {
+        return output::print_records(format, &records);
+    }
+    let (vertices, triangles) = (records.iter().map(|r| r.vertices).sum::<usize>(), records.iter().map(|r| r.triangles).sum::<usize>());
+    println!("{}: {} bound(s), {} vertices, {} triangles", full, records.len(), vertices, triangles);
+    println!("{:<10} {:<10} {:>9} {:>10} {:>10}  Extents", "Bound", "Type", "Vertices", "Triangles", "Primitives");
+    println!("{}", "-".repeat(100));
+    let v = |p: [f32; 3]| format!("({:.2}, {:.2}, {:.2})", p[0], p[1], p[2]);
+    for r in &records {
+        println!("{:<10} {:<10} {:>9} {:>10} {:>10}  {} .. {}", r.path, r.kind, r.vertices, r.triangles, r.primitives, v(r.box_min), v(r.box_max));
+        if let (Some(t), Some(i)) = (&r.type_flags, &r.include_flags) {
+            println!("{:<10} type: {}", "", if t.is_empty() { "-".to_string() } else { t.join(" ") });
+            println!("{:<10} includes: {}", "", if i.is_empty() { "-".to_string() } else { i.join(" ") });
+        }
+        for m in &r.materials {
+            let flags = if m.flags.is_empty() { String::new() } else { format!(" [{}]", m.flags.join(" ")) };
+            println!("{:<10} material {} {} x{}{}", "", m.id, m.name, m.polygons, flags);
+        }
+    }
+    Ok(())
+}
+
+/// Write the collision of a .ybn inside an archive to OBJ (default `<name>.obj`), with one
+/// object per bound and faces grouped by material; primitives are tessellated.
+pub fn export(archive_path: &Path, path: &str, output: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, root) = load(archive_path, path, keys)?;
+    let name = full.rsplit('/').next().unwrap_or(&full);
+    let stem = name.rsplit_once('.').map_or(name, |(s, _)| s);
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.obj", stem)));
+
+    let mut obj = Obj::new();
+    let mut exported = 0;
+    for node in root.walk() {
+        let (b, m) = (node.bound, &node.transform);
+        if b.kind == kind::COMPOSITE { continue; }
+        obj.object(&format!("{}_{}", bounds::kind_name(b.kind), path_label(&node.path).replace('/', "_")));
+        exported += 1;
+
+        let Some(g) = &b.geometry else {
+            let material = bounds::material_name(b.material.id);
+            add_primitive(&mut obj, b, m, &material);
+            continue;
+        };
+        let base = obj.vertex_count();
+        for &v in &g.vertices { obj.vertex(transform_point(m, v)); }
+        for p in &g.polygons {
+            let material = bounds::material_name(g.materials.get(p.material as usize).map_or(0, |m| m.id));
+            let at = |i: u16| transform_point(m, g.vertices[i as usize]);
+            match p.shape {
+                Shape::Triangle(v) => obj.face(&v.map(|i| base + i as usize), Some(&material)),
+                Shape::Sphere { center, radius } => sphere(&mut obj, at(center), radius, &material),
+                Shape::Capsule { ends, radius } => capsule(&mut obj, at(ends[0]), at(ends[1]), radius, &material),
+                Shape::Cylinder { ends, radius } => cylinder(&mut obj, at(ends[0]), at(ends[1]), radius, &material),
+                Shape::Box(corners) => corner_box(&mut obj, corners.map(|i| base + i as usize), corners.map(at), &material),
+            }
+        }
+    }
+
+    let mtl = output.with_extension("mtl");
+    let mtl_name = mtl.file_name().unwrap().to_string_lossy().into_owned();
+    fs::write(&output, obj.to_obj(&mtl_name)).with_context(|| format!("failed to write {}", output.display()))?;
+    if let Some(text) = obj.to_mtl() {
+        fs::write(&mtl, text).with_context(|| format!("failed to write {}", mtl.display()))?;
+    }
+    println!("Exported {} bound(s) from {} to {} ({} vertices, {} faces)",
+        exported, full, output.display(), obj.vertex_count(), obj.face_count());
+    Ok(())
+}
+
+// ─── Primitives ──────────────────────────────────────────────────────────────
+
+/// A primitive bound, shaped from its header: spheres from the sphere fields, the rest
+/// from the box, with capsules and cylinders along its longest axis and discs across its
+/// shortest.
+fn add_primitive(obj: &mut Obj, b: &Bound, m: &[[f32; 3]; 4], material: &str) {
+    let (lo, hi) = (b.box_min, b.box_max);
+    let size: [f32; 3] = std::array::from_fn(|i| hi[i] - lo[i]);
+    let center: [f32; 3] = std::array::from_fn(|i| (hi[i] + lo[i]) / 2.0);
+    let longest = (0..3).max_by(|&a, &b| size[a].total_cmp(&size[b])).unwrap();
+    let shortest = (0..3).min_by(|&a, &b| size[a].total_cmp(&size[b])).unwrap();
+    let along = |axis: usize, half: f32| -> [[f32; 3]; 2] {
+        let mut a = center;
+        let mut b = center;
+        a[axis] -= half;
+        b[axis] += half;
+        [transform_point(m, a), transform_point(m, b)]
+    };
+    let across = |axis: usize, f: fn(f32, f32) -> f32| {
+        let others: Vec<f32> = (0..3).filter(|&i| i != axis).map(|i| size[i]).collect();
+        f(others[0], others[1]) / 2.0
+    };
+
+    match b.kind {
+        kind::SPHERE => sphere(obj, transform_point(m, b.sphere_center), b.sphere_radius, material),
+        kind::CAPSULE => {
+            let radius = across(longest, f32::min);
+            let [a, e] = along(longest, (size[longest] / 2.0 - radius).max(0.0));
+            capsule(obj, a, e, radius, material);
+        }
+        kind::CYLINDER => {
+            let [a, e] = along(longest, size[longest] / 2.0);
+            cylinder(obj, a, e, across(longest, f32::min), material);
+        }
+        kind::DISC => {
+            let [a, e] = along(shortest, b.margin.max(size[shortest] / 2.0));
+            cylinder(obj, a, e, across(shortest, f32::max), material);
+        }
+        _ => {
+            let corner = |i: usize| transform_point(m, std::array::from_fn(|a| if i >> a & 1 == 0 { lo[a] } else { hi[a] }));
+            let v: Vec<usize> = (0..8).map(|i| obj.vertex(corner(i))).collect();
+            for face in [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]] {
+                obj.face(&face.map(|i| v[i]), Some(material));
+            }
+        }
+    }
+}
+
+fn sphere(obj: &mut Obj, center: [f32; 3], radius: f32, material: &str) {
+    let profile: Vec<(f32, f32)> = (0..=SEGMENTS / 2).map(|k| {
+        let a = -PI / 2.0 + PI * k as f32 / (SEGMENTS / 2) as f32;
+        (radius * a.cos(), radius * a.sin())
+    }).collect();
+    lathe(obj, center, [0.0, 0.0, 1.0], &profile, material);
+}
+
+fn capsule(obj: &mut Obj, a: [f32; 3], b: [f32; 3], radius: f32, material: &str) {
+    let (axis, length) = direction(a, b);
+    let quarter = SEGMENTS / 4;
+    // A hemisphere at each end, joined by the cylinder between their equators.
+    let arc = |k: usize| PI / 2.0 * k as f32 / quarter as f32;
+    let profile: Vec<(f32, f32)> = (0..=quarter).map(|k| (radius * arc(k).sin(), -radius * arc(k).cos()))
+        .chain((0..=quarter).map(|k| (radius * arc(k).cos(), length + radius * arc(k).sin())))
+        .collect();
+    lathe(obj, a, axis, &profile, material);
+}
+
+fn cylinder(obj: &mut Obj, a: [f32; 3], b: [f32; 3], radius: f32, material: &str) {
+    let (axis, length) = direction(a, b);
+    lathe(obj, a, axis, &[(0.0, 0.0), (radius, 0.0), (radius, length), (0.0, length)], material);
+}
+
+/// The box through four alternate corners and their reflections through its centre. Each
+/// face holds two of the given corners and the reflections of the other two.
+fn corner_box(obj: &mut Obj, given: [usize; 4], points: [[f32; 3]; 4], material: &str) {
+    let center: [f32; 3] = std::array::from_fn(|i| points.iter().map(|p| p[i]).sum::<f32>() / 4.0);
+    let mirrored = points.map(|p| obj.vertex(std::array::from_fn(|i| 2.0 * center[i] - p[i])));
+    for (p, q) in [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)] {
+        let (r, s) = match (p, q) {
+            (0, 1) => (2, 3), (0, 2) => (1, 3), (0, 3) => (1, 2),
+            (1, 2) => (0, 3), (1, 3) => (0, 2), _ => (0, 1),
+        };
+        obj.face(&[given[p], mirrored[r], given[q], mirrored[s]], Some(material));
+    }
+}
+
+fn direction(a: [f32; 3], b: [f32; 3]) -> ([f32; 3], f32) {
+    let d: [f32; 3] = std::array::from_fn(|i| b[i] - a[i]);
+    let length = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+    if length < 1e-6 { return ([0.0, 0.0, 1.0], 0.0); }
+    (d.map(|c| c / length), length)
+}
+
+/// A surface of revolution around `axis` through `base`: `profile` gives (radius, height)
+/// pairs from one end to the other; zero radii become single pole vertices.
+fn lathe(obj: &mut Obj, base: [f32; 3], axis: [f32; 3], profile: &[(f32, f32)], material: &str) {
+    // Two unit vectors perpendicular to the axis.
+    let helper = if axis[2].abs() < 0.9 { [0.0, 0.0, 1.0] } else { [1.0, 0.0, 0.0] };
+    let cross = |a: [f32; 3], b: [f32; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+    let (u, _) = direction([0.0; 3], cross(axis, helper));
+    let v = cross(axis, u);
+
+    let rings: Vec<Vec<usize>> = profile.iter().map(|&(radius, height)| {
+        let count = if radius.abs() < 1e-6 { 1 } else { SEGMENTS };
+        (0..count).map(|s| {
+            let a = 2.0 * PI * s as f32 / SEGMENTS as f32;
+            let (c, sn) = (radius * a.cos(), radius * a.sin());
+            obj.vertex(std::array::from_fn(|i| base[i] + axis[i] * height + u[i] * c + v[i] * sn))
+        }).collect()
+    }).collect();
+
+    for pair in rings.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        for s in 0..SEGMENTS {
+            let n = (s + 1) % SEGMENTS;
+            match (a.len(), b.len()) {
+                (1, 1) => {}
+                (1, _) => obj.face(&[a[0], b[n], b[s]], Some(material)),
+                (_, 1) => obj.face(&[a[s], a[n], b[0]], Some(material)),
+                _      => obj.face(&[a[s], a[n], b[n], b[s]], Some(material)),
+            }
+        }
+    }
+}
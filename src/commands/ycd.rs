@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::clips::{self, Clip};
+use crate::names;
+use crate::output::{self, Format};
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys};
+
+#[derive(Serialize)]
+struct ClipRecord {
+    name      : String,
+    hash      : String,
+    /// Seconds.
+    duration  : f32,
+    /// Frames of the first animation.
+    frames    : u16,
+    fps       : f32,
+    animations: usize,
+    /// `bone ID:track` of every track of the first animation.
+    tracks    : Vec<String>,
+}
+
+/// The export format of one clip.
+#[derive(Serialize)]
+struct ClipDocument {
+    name      : String,
+    hash      : String,
+    duration  : f32,
+    animations: Vec<AnimationEntry>,
+}
+
+#[derive(Serialize)]
+struct AnimationEntry {
+    start    : f32,
+    end      : f32,
+    rate     : f32,
+    frames   : u16,
+    fps      : f32,
+    duration : f32,
+    sequences: usize,
+    tracks   : Vec<TrackEntry>,
+}
+
+#[derive(Serialize)]
+struct TrackEntry {
+    bone_id: u16,
+    track  : String,
+    format : String,
+    /// The value at every frame of the animation: x y z, x y z w or a single float.
+    values : Vec<Vec<f32>>,
+}
+
+/// One CSV row of `ycd export`: a track's value at one frame.
+#[derive(Serialize)]
+struct KeyframeRow<'a> {
+    clip     : &'a str,
+    animation: usize,
+    frame    : usize,
+    /// Seconds into the animation.
+    time     : f32,
+    bone_id  : u16,
+    track    : &'a str,
+    format   : &'a str,
+    x        : Option<f32>,
+    y        : Option<f32>,
+    z        : Option<f32>,
+    w        : Option<f32>,
+}
+
+fn load(archive_path: &Path, path: &str, keys: Option<&GtaKeys>) -> Result<(String, Resource, Vec<Clip>)> {
+    let archive = Archive::open(archive_path, keys)?;
+    let (full, data) = archive.read_path(path, keys)?;
+    let res = Resource::parse(&data).with_context(|| format!("failed to read {}", full))?;
+    let clips = clips::parse(&res).with_context(|| format!("failed to read {}", full))?;
+    Ok((full, res, clips))
+}
+
+fn hash_label(hash: u32) -> String {
+    format!("0x{:08X}", hash)
+}
+
+/// List the clips of a .ycd inside an archive (nested archives allowed in `path`): names,
+/// hashes, durations, frame counts and bone tracks.
+pub fn list(archive_path: &Path, path: &str, format: Format, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, _, mut clips) = load(archive_path, path, keys)?;
+    clips.sort_by(|a, b| a.short_name().cmp(b.short_name()));
+
+    let records: Vec<ClipRecord> = clips.iter().map(|c| {
+        let first = c.animations.first().map(|a| &a.animation);
+        ClipRecord {
+            name      : c.short_name().to_string(),
+            hash      : hash_label(c.hash),
+            duration  : c.duration,
+            frames    : first.map_or(0, |a| a.frames),
+            fps       : first.map_or(0.0, |a| a.fps()),
+            animations: c.animations.len(),
+            tracks    : first.map(|a| a.tracks.iter()
+                .map(|t| format!("{}:{}", t.bone_id, clips::track_name(t.kind)))
+                .collect()).unwrap_or_default(),
+        }
+    }).collect();
+
+    if format != Format::Text {
+        return output::print_records(format, &records);
+    }
+    println!("{}: {} clip(s)", full, records.len());
+    println!("{:<40} {:<10} {:>9} {:>7} {:>6} {:>7}", "Clip", "Hash", "Duration", "Frames", "FPS", "Tracks");
+    println!("{}", "-".repeat(84));
+    for r in &records {
+        println!("{:<40} {:<10} {:>8.3}s {:>7} {:>6.1} {:>7}", r.name, r.hash, r.duration, r.frames, r.fps, r.tracks.len());
+        if !r.tracks.is_empty() { println!("    {}", r.tracks.join(" ")); }
+    }
+    Ok(())
+}
+
+/// Write the keyframes of one clip of a .ycd (by name, full `pack:/` name or hash; optional
+/// when the dictionary holds a single clip) to `output` (default `<clip>.json`): CSV with a
+/// row per track and frame when it ends in `.csv`, else JSON.
+pub fn export(archive_path: &Path, path: &str, clip: Option<&str>, output: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let (full, res, clips) = load(archive_path, path, keys)?;
+    let clip = match clip {
+        Some(name) => {
+            let hash = names::hash_of(name);
+            clips.iter()
+                .find(|c| c.name.eq_ignore_ascii_case(name) || c.short_name().eq_ignore_ascii_case(name) || c.hash == hash)
+                .with_context(|| format!("no clip '{}' in {}", name, full))?
+        }
+        None if clips.len() == 1 => &clips[0],
+        None => bail!("{} holds {} clips; name one (see `ycd list`)", full, clips.len()),
+    };
+
+    let mut animations = Vec::with_capacity(clip.animations.len());
+    for (i, a) in clip.animations.iter().enumerate() {
+        let keyframes = a.animation.keyframes(&res)
+            .with_context(|| format!("failed to decode animation {} of clip {}", i, clip.short_name()))?;
+        animations.push(AnimationEntry {
+            start    : a.start,
+            end      : a.end,
+            rate     : a.rate,
+            frames   : a.animation.frames,
+            fps      : a.animation.fps(),
+            duration : a.animation.duration,
+            sequences: a.animation.sequences.len(),
+            tracks   : a.animation.tracks.iter().zip(keyframes).map(|(t, values)| TrackEntry {
+                bone_id: t.bone_id,
+                track  : clips::track_name(t.kind),
+                format : clips::format_name(t.format),
+                values,
+            }).collect(),
+        });
+    }
+    let doc = ClipDocument {
+        name      : clip.short_name().to_string(),
+        hash      : hash_label(clip.hash),
+        duration  : clip.duration,
+        animations,
+    };
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.json", doc.name.replace(['/', '\\'], "_"))));
+    let text = if output.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+        let clip = doc.name.as_str();
+        let mut rows = Vec::new();
+        for (animation, a) in doc.animations.iter().enumerate() {
+            let step = if a.frames > 1 { a.duration / (a.frames - 1) as f32 } else { 0.0 };
+            for t in &a.tracks {
+                rows.extend(t.values.iter().enumerate().map(|(frame, v)| KeyframeRow {
+                    clip,
+                    animation,
+                    frame,
+                    time     : frame as f32 * step,
+                    bone_id  : t.bone_id,
+                    track    : &t.track,
+                    format   : &t.format,
+                    x        : v.first().copied(),
+                    y        : v.get(1).copied(),
+                    z        : v.get(2).copied(),
+                    w        : v.get(3).copied(),
+                }));
+            }
+        }
+        output::to_csv(&rows)?
+    } else {
+        serde_json::to_string_pretty(&doc)? + "\n"
+    };
+    fs::write(&output, text).with_context(|| format!("failed to write {}", output.display()))?;
+
+    let tracks: usize = doc.animations.iter().map(|a| a.tracks.len()).sum();
+    let frames: usize = doc.animations.iter().map(|a| a.frames as usize).sum();
+    println!("Exported clip {} ({} animation(s), {} track(s), {} frame(s), {:.3}s) from {} to {}",
+        doc.name, doc.animations.len(), tracks, frames, doc.duration, full, output.display());
+    Ok(())
+}
@@ -0,0 +1,345 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::{collections::HashMap, path::Path};
+
+use crate::commands::meta;
+use crate::names::Names;
+use crate::output::{self, Format};
+use crate::rpf::{Archive, FileRef, GtaKeys, MAX_DEPTH};
+use crate::ymap::{self, Archetype, Extents, MapData};
+
+/// Slack allowed when checking that stored extents cover the recomputed ones, in metres.
+const TOLERANCE: f32 = 0.01;
+
+/// How far stored extents may reach past the recomputed ones before they count as too
+/// large, in metres.
+const SLACK: f32 = 1.0;
+
+#[derive(Serialize)]
+struct MapRecord {
+    /// Full virtual path, including the names of any nested archives.
+    path              : String,
+    name              : String,
+    parent            : Option<String>,
+    flags             : u32,
+    content_flags     : u32,
+    streaming_extents : [[f32; 3]; 2],
+    entities_extents  : [[f32; 3]; 2],
+    /// Smallest extents that cover every entity's archetype box (and LOD distance, for
+    /// streaming); `null` without entities.
+    computed_streaming: Option<[[f32; 3]; 2]>,
+    computed_entities : Option<[[f32; 3]; 2]>,
+    /// Entities whose archetype is in no .ytyp of the archive, counted as points.
+    unknown_archetypes: usize,
+    /// Stored extents that do not cover the computed ones, or reach well past them.
+    stale             : bool,
+    issues            : Vec<String>,
+    entities          : Vec<EntityRecord>,
+}
+
+#[derive(Serialize)]
+struct EntityRecord {
+    index         : usize,
+    #[serde(rename = "type")]
+    kind          : String,
+    archetype     : String,
+    guid          : u32,
+    position      : [f32; 3],
+    /// Quaternion x y z w, as stored.
+    rotation      : [f32; 4],
+    lod_level     : String,
+    lod_dist      : f32,
+    child_lod_dist: f32,
+    /// Index into the parent map's entities, or -1.
+    parent_index  : i32,
+    num_children  : u32,
+}
+
+/// One CSV row of `ymap inspect`: an entity with the path of its map.
+#[derive(Serialize)]
+struct EntityRow<'a> {
+    map   : &'a str,
+    stale : bool,
+    #[serde(flatten)]
+    entity: &'a EntityRecord,
+}
+
+/// List the entities and extents of a .ymap inside an archive (nested archives allowed in
+/// `path`), or of every .ymap in the archive without one, and flag stale extents and broken
+/// LOD parent links. Archetype boxes come from every .ytyp in the archive, nested ones too.
+pub fn inspect(
+    archive_path: &Path,
+    path: Option<&str>,
+    recursive: bool,
+    format: Format,
+    names: &Names,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let mut maps = Vec::new();
+    let mut errors = 0;
+    match path {
+        Some(path) => {
+            let (full, data) = archive.read_path(path, keys)?;
+            let map = read(&data).with_context(|| format!("failed to read {}", full))?;
+            maps.push((full, map));
+        }
+        None => walk(&archive, "", ".ymap", recursive, 0, keys, &mut |full, data| {
+            match data.and_then(|data| read(&data)) {
+                Ok(map) => maps.push((full, map)),
+                Err(e) => {
+                    eprintln!("Failed to read {}: {:#}", full, e);
+                    errors += 1;
+                }
+            }
+        }),
+    }
+    let archetypes = archetypes(&archive, keys);
+
+    // Entity counts of the maps seen, by name, to check the parent indices of their children.
+    let counts: HashMap<u32, usize> = maps.iter().map(|(_, m)| (m.name, m.entities.len())).collect();
+    let records: Vec<MapRecord> = maps.iter().map(|(path, map)| record(path, map, &archetypes, &counts, names)).collect();
+
+    match format {
+        Format::Text => print_text(&records),
+        Format::Csv  => {
+            let rows: Vec<EntityRow> = records.iter()
+                .flat_map(|m| m.entities.iter().map(move |e| EntityRow { map: &m.path, stale: m.stale, entity: e }))
+                .collect();
+            output::print_records(format, &rows)?;
+        }
+        _ => output::print_records(format, &records)?,
+    }
+
+    if errors > 0 {
+        bail!("{} file(s) could not be read", errors);
+    }
+    Ok(())
+}
+
+fn read(data: &[u8]) -> Result<MapData> {
+    ymap::read(&meta::parse(data)?)
+}
+
+/// The archetypes of every .ytyp in `archive` and the archives nested in it, by name.
+/// Unreadable files are reported and skipped; their archetypes count as unknown.
+fn archetypes(archive: &Archive, keys: Option<&GtaKeys>) -> HashMap<u32, Archetype> {
+    let mut archetypes = HashMap::new();
+    walk(archive, "", ".ytyp", true, 0, keys, &mut |full, data| {
+        match data.and_then(|data| ymap::read_archetypes(&meta::parse(&data)?)) {
+            Ok(list) => archetypes.extend(list.into_iter().map(|a| (a.name, a))),
+            Err(e) => eprintln!("Skipping {}: {:#}", full, e),
+        }
+    });
+    archetypes
+}
+
+/// Extract every file ending in `ext` in `archive` and, when `recursive`, in the archives
+/// nested in it, and hand it to `visit` with its full virtual path. Nested archives that
+/// cannot be opened are handed over as errors.
+fn walk(
+    archive: &Archive,
+    prefix: &str,
+    ext: &str,
+    recursive: bool,
+    depth: usize,
+    keys: Option<&GtaKeys>,
+    visit: &mut dyn FnMut(String, Result<Vec<u8>>),
+) {
+    if depth > MAX_DEPTH {
+        eprintln!("[RPF] max nesting depth reached at {}", prefix);
+        return;
+    }
+
+    let files: Vec<FileRef> = archive.list_files().into_iter().cloned().collect();
+    for file in &files {
+        let full = if prefix.is_empty() { file.path.clone() } else { format!("{}/{}", prefix, file.path) };
+        let lower = file.name.to_lowercase();
+
+        if lower.ends_with(".rpf") {
+            if !recursive { continue; }
+            match archive.open_nested(file, keys) {
+                Ok(nested) => walk(&nested, &full, ext, recursive, depth + 1, keys, visit),
+                Err(e) => visit(full, Err(e.context("cannot open nested archive"))),
+            }
+            continue;
+        }
+        if lower.ends_with(ext) {
+            visit(full, archive.extract(file, keys));
+        }
+    }
+}
+
+fn pair(e: &Extents) -> [[f32; 3]; 2] {
+    [e.0, e.1]
+}
+
+/// Whether `outer` covers `inner`, allowing `slack` metres.
+fn contains(outer: &Extents, inner: &Extents, slack: f32) -> bool {
+    (0..3).all(|i| outer.0[i] <= inner.0[i] + slack && outer.1[i] >= inner.1[i] - slack)
+}
+
+fn record(path: &str, map: &MapData, archetypes: &HashMap<u32, Archetype>, counts: &HashMap<u32, usize>, names: &Names) -> MapRecord {
+    let computed = map.computed_extents(archetypes);
+    let mut issues = Vec::new();
+    if let Some(c) = &computed {
+        if !contains(&map.streaming_extents, &c.streaming, TOLERANCE) {
+            issues.push("streaming extents do not cover every entity's LOD distance".to_string());
+        }
+        if !contains(&map.entities_extents, &c.entities, TOLERANCE) {
+            issues.push("entity extents do not cover every entity".to_string());
+        }
+        // Points stand in for unknown archetypes, so the computed extents are only a lower
+        // bound unless every archetype was found.
+        if c.unknown == 0 {
+            if !contains(&c.streaming, &map.streaming_extents, SLACK) {
+                issues.push(format!("streaming extents reach more than {} m past every entity's LOD distance", SLACK));
+            }
+            if !contains(&c.entities, &map.entities_extents, SLACK) {
+                issues.push(format!("entity extents reach more than {} m past every entity", SLACK));
+            }
+        }
+    }
+    let stale = !issues.is_empty();
+
+    let parent_count = (map.parent != 0).then(|| counts.get(&map.parent)).flatten();
+    for (i, e) in map.entities.iter().enumerate().filter(|(_, e)| e.parent_index >= 0) {
+        if map.parent == 0 {
+            issues.push(format!("entity {} has parent index {} but the map has no parent", i, e.parent_index));
+        } else if let Some(&count) = parent_count && e.parent_index as usize >= count {
+            issues.push(format!("entity {} has parent index {} but {} has {} entit{}",
+                i, e.parent_index, names.display(map.parent), count, if count == 1 { "y" } else { "ies" }));
+        }
+    }
+
+    MapRecord {
+        path              : path.to_string(),
+        name              : names.display(map.name),
+        parent            : (map.parent != 0).then(|| names.display(map.parent)),
+        flags             : map.flags,
+        content_flags     : map.content_flags,
+        streaming_extents : pair(&map.streaming_extents),
+        entities_extents  : pair(&map.entities_extents),
+        computed_streaming: computed.as_ref().map(|c| pair(&c.streaming)),
+        computed_entities : computed.as_ref().map(|c| pair(&c.entities)),
+        unknown_archetypes: computed.as_ref().map_or(0, |c| c.unknown),
+        stale,
+        issues,
+        entities          : map.entities.iter().enumerate().map(|(index, e)| EntityRecord {
+            index,
+            kind          : names.display(e.kind),
+            archetype     : names.display(e.archetype),
+            guid          : e.guid,
+            position      : e.position,
+            rotation      : e.rotation,
+            lod_level     : match e.lod_level {
+                (_, Some(name)) => names.display(name),
+                (value, None)   => value.to_string(),
+            },
+            lod_dist      : e.lod_dist,
+            child_lod_dist: e.child_lod_dist,
+            parent_index  : e.parent_index,
+            num_children  : e.num_children,
+        }).collect(),
+    }
+}
+
+fn print_text(records: &[MapRecord]) {
+    let v = |p: [f32; 3]| format!("({:.2}, {:.2}, {:.2})", p[0], p[1], p[2]);
+    let extents = |e: [[f32; 3]; 2]| format!("{} .. {}", v(e[0]), v(e[1]));
+    for r in records {
+        let parent = r.parent.as_ref().map(|p| format!(", parent {}", p)).unwrap_or_default();
+        println!("{}: {}{}, {} entit{}, flags 0x{:X}, content flags 0x{:X}", r.path, r.name, parent,
+            r.entities.len(), if r.entities.len() == 1 { "y" } else { "ies" }, r.flags, r.content_flags);
+        println!("  Streaming extents: {}", extents(r.streaming_extents));
+        if let Some(c) = r.computed_streaming { println!("           computed: {}", extents(c)); }
+        println!("  Entity extents:    {}", extents(r.entities_extents));
+        if let Some(c) = r.computed_entities { println!("           computed: {}", extents(c)); }
+        if r.unknown_archetypes > 0 {
+            println!("  {} entit{} with no archetype in the archive's .ytyp files, counted as points",
+                r.unknown_archetypes, if r.unknown_archetypes == 1 { "y" } else { "ies" });
+        }
+        for issue in &r.issues { println!("  ! {}", issue); }
+
+        if !r.entities.is_empty() {
+            println!("  {:>5}  {:<32} {:<32} {:<34} {:<24} {:>8} {:>6}",
+                "#", "Archetype", "Position", "Rotation", "LOD level", "LOD dist", "Parent");
+            for e in &r.entities {
+                let q = e.rotation;
+                println!("  {:>5}  {:<32} {:<32} {:<34} {:<24} {:>8.1} {:>6}",
+                    e.index, e.archetype, v(e.position), format!("({:.3}, {:.3}, {:.3}, {:.3})", q[0], q[1], q[2], q[3]),
+                    e.lod_level, e.lod_dist, e.parent_index);
+            }
+        }
+        println!();
+    }
+
+    let entities: usize = records.iter().map(|r| r.entities.len()).sum();
+    let stale = records.iter().filter(|r| r.stale).count();
+    let issues = records.iter().filter(|r| !r.issues.is_empty()).count();
+    println!("{} map(s), {} entities; {} with stale extents, {} with issues", records.len(), entities, stale, issues);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ymap::tests::entity;
+
+    /// One 2 m archetype with a 10 m LOD distance, and a map of two of them 10 m apart.
+    fn sample(streaming: Extents, entities: Extents) -> (MapData, HashMap<u32, Archetype>) {
+        let archetypes = HashMap::from([(1, Archetype { name: 1, bounds: ([-1.0; 3], [1.0; 3]), lod_dist: 10.0 })]);
+        let map = MapData {
+            name: 0, parent: 0, flags: 0, content_flags: 0,
+            streaming_extents: streaming,
+            entities_extents : entities,
+            entities         : vec![
+                entity(1, [0.0; 3], [0.0, 0.0, 0.0, 1.0], 0.0),
+                entity(1, [10.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], 0.0),
+            ],
+        };
+        (map, archetypes)
+    }
+
+    fn issues(map: &MapData, archetypes: &HashMap<u32, Archetype>) -> Vec<String> {
+        let r = record("x.ymap", map, archetypes, &HashMap::new(), &Names::load(&[]).unwrap());
+        assert_eq!(r.stale, r.issues.iter().any(|i| i.contains("extents")));
+        r.issues
+    }
+
+    #[test]
+    fn accepts_matching_extents() {
+        let (map, archetypes) = sample(([-11.0, -11.0, -11.0], [21.0, 11.0, 11.0]), ([-1.0; 3], [11.0, 1.0, 1.0]));
+        assert!(issues(&map, &archetypes).is_empty());
+    }
+
+    #[test]
+    fn flags_stale_and_oversized_extents() {
+        let (map, archetypes) = sample(([-11.0, -11.0, -11.0], [15.0, 11.0, 11.0]), ([-1.0; 3], [5.0, 1.0, 1.0]));
+        assert_eq!(issues(&map, &archetypes), [
+            "streaming extents do not cover every entity's LOD distance",
+            "entity extents do not cover every entity",
+        ]);
+
+        let (map, archetypes) = sample(([-11.0, -11.0, -11.0], [50.0, 11.0, 11.0]), ([-1.0, -1.0, -9.0], [11.0, 1.0, 1.0]));
+        assert_eq!(issues(&map, &archetypes), [
+            "streaming extents reach more than 1 m past every entity's LOD distance",
+            "entity extents reach more than 1 m past every entity",
+        ]);
+
+        // Unknown archetypes only give a lower bound, so oversized extents pass.
+        let (map, _) = sample(([-11.0, -11.0, -11.0], [50.0, 11.0, 11.0]), ([-1.0, -1.0, -9.0], [11.0, 1.0, 1.0]));
+        assert!(issues(&map, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn flags_parent_indices_past_the_parent() {
+        let (mut map, archetypes) = sample(([-11.0, -11.0, -11.0], [21.0, 11.0, 11.0]), ([-1.0; 3], [11.0, 1.0, 1.0]));
+        map.entities[1].parent_index = 3;
+        assert_eq!(issues(&map, &archetypes), ["entity 1 has parent index 3 but the map has no parent"]);
+
+        map.parent = 7;
+        let r = record("x.ymap", &map, &archetypes, &HashMap::from([(7, 3)]), &Names::load(&[]).unwrap());
+        assert_eq!(r.issues, ["entity 1 has parent index 3 but hash_00000007 has 3 entities"]);
+        assert!(!r.stale);
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use crate::editor;
+use crate::names::{self, Names};
+use crate::nodes::{self, Junction, Link, Node, NodeDictionary};
+use crate::obj::Obj;
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys};
+
+/// The JSON form of a .ynd, as written by `export` and read by `import`.
+#[derive(Serialize, Deserialize)]
+struct Document {
+    #[serde(default)]
+    vft          : u32,
+    /// Leading entries of `nodes` that are vehicle nodes; the rest are ped nodes.
+    vehicle_nodes: usize,
+    nodes        : Vec<NodeEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeEntry {
+    area_id    : u16,
+    node_id    : u16,
+    /// Street name label, `hash_XXXXXXXX` when unknown.
+    street_name: String,
+    position   : [f32; 3],
+    /// `Flags0` to `Flags4`.
+    flags      : [u8; 5],
+    #[serde(default)]
+    link_flags : u8,
+    #[serde(default)]
+    links      : Vec<Link>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    junction   : Option<Junction>,
+}
+
+fn is_obj(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("obj"))
+}
+
+/// Write the path nodes of a .ynd inside an archive (nested archives allowed in `path`) to
+/// `output` (default `<name>.json`): OBJ when it ends in `.obj`, with a point per node and a
+/// line per link inside the file, else JSON that `import` reads back.
+pub fn export(archive_path: &Path, path: &str, output: Option<&Path>, names: &Names, keys: Option<&GtaKeys>) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let (full, data) = archive.read_path(path, keys)?;
+    let dict = Resource::parse(&data)
+        .and_then(|res| nodes::parse(&res))
+        .with_context(|| format!("failed to read {}", full))?;
+
+    let name = full.rsplit('/').next().unwrap_or(&full);
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.json", name)));
+    let summary = summary(&dict);
+    let text = if is_obj(&output) { to_obj(&dict) } else {
+        let doc = Document {
+            vft          : dict.vft,
+            vehicle_nodes: dict.vehicle_nodes,
+            nodes        : dict.nodes.into_iter().map(|n| NodeEntry {
+                area_id    : n.area_id,
+                node_id    : n.node_id,
+                street_name: names.display(n.street_name),
+                position   : n.position,
+                flags      : n.flags,
+                link_flags : n.link_flags,
+                links      : n.links,
+                junction   : n.junction,
+            }).collect(),
+        };
+        serde_json::to_string_pretty(&doc)? + "\n"
+    };
+    fs::write(&output, text).with_context(|| format!("failed to write {}", output.display()))?;
+    println!("Exported {} from {} to {}", summary, full, output.display());
+    Ok(())
+}
+
+fn summary(dict: &NodeDictionary) -> String {
+    let (nodes, vehicle) = (dict.nodes.len(), dict.vehicle_nodes);
+    let links: usize = dict.nodes.iter().map(|n| n.links.len()).sum();
+    let junctions = dict.nodes.iter().filter(|n| n.junction.is_some()).count();
+    format!("{} nodes ({} vehicle, {} ped), {} links, {} junctions", nodes, vehicle, nodes - vehicle, links, junctions)
+}
+
+fn to_obj(dict: &NodeDictionary) -> String {
+    let mut obj = Obj::new();
+    obj.object("nodes");
+    let index: HashMap<(u16, u16), usize> = dict.nodes.iter()
+        .map(|n| ((n.area_id, n.node_id), obj.vertex(n.position)))
+        .collect();
+    for (label, range) in [("vehicle_links", 0..dict.vehicle_nodes), ("ped_links", dict.vehicle_nodes..dict.nodes.len())] {
+        obj.object(label);
+        for n in &dict.nodes[range] {
+            let from = index[&(n.area_id, n.node_id)];
+            for l in &n.links {
+                // Links into neighbouring areas have no vertex here.
+                if let Some(&to) = index.get(&(l.area_id, l.node_id)) { obj.line(&[from, to]); }
+            }
+        }
+    }
+    obj.to_obj("")
+}
+
+/// Build a .ynd from JSON written by `export` and add or replace it inside the archive.
+pub fn import(archive_path: &Path, path: &str, input: &Path, keys: Option<&GtaKeys>) -> Result<()> {
+    let text = fs::read_to_string(input).with_context(|| format!("cannot read {}", input.display()))?;
+    let doc: Document = serde_json::from_str(&text).with_context(|| format!("invalid path node JSON in {}", input.display()))?;
+    let dict = NodeDictionary {
+        vft          : doc.vft,
+        vehicle_nodes: doc.vehicle_nodes,
+        nodes        : doc.nodes.into_iter().map(|n| Node {
+            area_id    : n.area_id,
+            node_id    : n.node_id,
+            street_name: names::hash_of(&n.street_name),
+            position   : n.position,
+            flags      : n.flags,
+            link_flags : n.link_flags,
+            links      : n.links,
+            junction   : n.junction,
+        }).collect(),
+    };
+    let data = nodes::build(&dict).with_context(|| format!("failed to build from {}", input.display()))?;
+    let summary = summary(&dict);
+
+    let target = {
+        let archive = Archive::open(archive_path, keys)?;
+        archive.find_file(path).map(|f| f.path.clone()).unwrap_or_else(|| path.to_string())
+    };
+    let len = data.len();
+    let count = editor::edit_file(archive_path, keys, |ed| {
+        if ed.exists(&target) { ed.replace_file(&target, data) } else { ed.add_file(&target, data) }
+    })?;
+    println!("Wrote {} ({}, {} bytes)", target, summary, len);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::navmesh::{self, NavMesh};
+use crate::obj::Obj;
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys};
+
+#[derive(Serialize)]
+struct Document {
+    area_id      : u32,
+    content_flags: u32,
+    bounds_min   : [f32; 3],
+    bounds_max   : [f32; 3],
+    vertices     : Vec<[f32; 3]>,
+    polygons     : Vec<PolygonEntry>,
+    portals      : Vec<PortalEntry>,
+}
+
+#[derive(Serialize)]
+struct PolygonEntry {
+    vertices    : Vec<usize>,
+    area_id     : u16,
+    flags0      : u16,
+    index_flags : u8,
+    flags1      : u32,
+    flags2      : u32,
+    part_flags  : u32,
+    /// The polygon across each edge (the edge from vertex `i` to `i + 1`), or `null`.
+    adjacent    : Vec<Option<Adjacent>>,
+    portal_links: Vec<u16>,
+}
+
+#[derive(Serialize)]
+struct Adjacent {
+    area_id: u32,
+    polygon: u32,
+}
+
+#[derive(Serialize)]
+struct PortalEntry {
+    #[serde(rename = "type")]
+    kind      : u8,
+    angle     : u8,
+    flags     : u16,
+    from      : [f32; 3],
+    to        : [f32; 3],
+    area_from : u16,
+    poly_from : [u16; 2],
+    area_to   : u16,
+    poly_to   : [u16; 2],
+    area_flags: u8,
+}
+
+/// Write the navmesh of a .ynv inside an archive (nested archives allowed in `path`) to
+/// `output` (default `<name>.json`): OBJ when it ends in `.obj`, with a face per polygon,
+/// else JSON with vertices, polygons, their adjacency and the portals.
+pub fn export(archive_path: &Path, path: &str, output: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let (full, data) = archive.read_path(path, keys)?;
+    let mesh = Resource::parse(&data)
+        .and_then(|res| navmesh::parse(&res))
+        .with_context(|| format!("failed to read {}", full))?;
+
+    let name = full.rsplit('/').next().unwrap_or(&full);
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.json", name)));
+    let summary = format!("{} vertices, {} polygons, {} portals", mesh.vertices.len(), mesh.polygons.len(), mesh.portals.len());
+    let is_obj = output.extension().is_some_and(|e| e.eq_ignore_ascii_case("obj"));
+    let text = if is_obj { to_obj(&mesh) } else { serde_json::to_string_pretty(&document(mesh))? + "\n" };
+    fs::write(&output, text).with_context(|| format!("failed to write {}", output.display()))?;
+    println!("Exported {} from {} to {}", summary, full, output.display());
+    Ok(())
+}
+
+fn to_obj(mesh: &NavMesh) -> String {
+    let mut obj = Obj::new();
+    obj.object(&format!("navmesh_{}", mesh.area_id));
+    for &v in &mesh.vertices { obj.vertex(v); }
+    for p in &mesh.polygons { obj.face(&p.vertices, None); }
+    obj.to_obj("")
+}
+
+fn document(mesh: NavMesh) -> Document {
+    Document {
+        area_id      : mesh.area_id,
+        content_flags: mesh.content_flags,
+        bounds_min   : mesh.bounds.0,
+        bounds_max   : mesh.bounds.1,
+        vertices     : mesh.vertices,
+        polygons     : mesh.polygons.into_iter().map(|p| PolygonEntry {
+            vertices    : p.vertices,
+            area_id     : p.area_id,
+            flags0      : p.flags.0,
+            index_flags : p.flags.1,
+            flags1      : p.flags.2,
+            flags2      : p.flags.3,
+            part_flags  : p.flags.4,
+            adjacent    : p.adjacent.into_iter()
+                .map(|a| a.map(|(area_id, polygon)| Adjacent { area_id, polygon }))
+                .collect(),
+            portal_links: p.portal_links,
+        }).collect(),
+        portals      : mesh.portals.into_iter().map(|p| PortalEntry {
+            kind      : p.kind,
+            angle     : p.angle,
+            flags     : p.flags,
+            from      : p.from,
+            to        : p.to,
+            area_from : p.area_from,
+            poly_from : p.poly_from,
+            area_to   : p.area_to,
+            poly_to   : p.poly_to,
+            area_flags: p.area_flags,
+        }).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+
+    #[test]
+    fn exports_json_and_obj() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = {
+            let mut builder = RpfBuilder::new(RpfEncryption::Open);
+            builder.add_file("navmeshes/navmesh[0][0].ynv", navmesh::tests::sample());
+            builder.build(None).unwrap()
+        };
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("dlc.rpf", inner);
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, builder.build(None).unwrap()).unwrap();
+
+        let json = dir.path().join("mesh.json");
+        export(&path, "dlc.rpf/navmeshes/navmesh[0][0].ynv", Some(&json), None).unwrap();
+        let doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(doc["area_id"], 42);
+        assert_eq!(doc["vertices"].as_array().unwrap().len(), 4);
+        assert_eq!(doc["polygons"][0]["adjacent"], serde_json::json!([null, null, { "area_id": 42, "polygon": 1 }]));
+        assert_eq!(doc["portals"][0]["type"], 1);
+
+        let obj = dir.path().join("mesh.obj");
+        export(&path, "dlc.rpf/navmeshes/navmesh[0][0].ynv", Some(&obj), None).unwrap();
+        assert_eq!(fs::read_to_string(&obj).unwrap(),
+            "o navmesh_42\nv 1000 2000 0\nv 1100 2000 0\nv 1100 2100 0\nv 1000 2100 10\nf 1 2 3\nf 1 3 4\n");
+    }
+}
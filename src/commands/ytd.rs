@@ -1,15 +1,52 @@
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
 
-use rpf_archive::{parse_ytd, RpfEntryKind};
+use rpf_archive::RpfEntryKind;
 
+use crate::resource::Resource;
 use crate::rpf::{Archive, GtaKeys};
+use crate::texture::{self, codec, Texture};
 
-/// Extract all textures from a .ytd file inside an RPF archive as DDS files.
+/// File format for exported textures.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImageFormat {
+    /// The stored pixel data, unconverted
+    Dds,
+    /// Decoded to 8-bit RGBA
+    Png,
+    /// Decoded to 8-bit RGBA
+    Tga,
+}
+
+/// Export options shared by `ytd` and `ytd-all`.
+#[derive(clap::Args, Clone, Copy)]
+pub struct ExportArgs {
+    /// File format for the extracted textures. Named --image-format because the global
+    /// --format picks the report format
+    #[arg(long, value_enum, default_value_t = ImageFormat::Dds)]
+    pub image_format: ImageFormat,
+
+    /// Export only this mip level (0 = full size)
+    #[arg(long, value_name = "LEVEL")]
+    pub mip: Option<u8>,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Dds => "dds",
+            Self::Png => "png",
+            Self::Tga => "tga",
+        }
+    }
+}
+
+/// Extract all textures from a .ytd file inside an RPF archive as DDS, PNG or TGA files.
 pub fn run(
     archive_path: &Path,
     ytd_name: &str,
     output_dir: Option<&Path>,
+    export_args: &ExportArgs,
     keys: Option<&GtaKeys>,
 ) -> Result<()> {
     let archive = Archive::open(archive_path, keys)?;
@@ -27,7 +64,8 @@ pub fn run(
         .extract(file_ref, keys)
         .with_context(|| format!("failed to extract '{}'", ytd_name))?;
 
-    let textures = parse_ytd(&rsc7_data)
+    let textures = Resource::parse(&rsc7_data)
+        .and_then(|res| texture::read_dictionary(&res))
         .with_context(|| format!("failed to parse YTD '{}'", ytd_name))?;
 
     if textures.is_empty() {
@@ -42,17 +80,14 @@ pub fn run(
 
     std::fs::create_dir_all(&out_dir)?;
 
+    let mut failed = 0;
     for tex in &textures {
-        let tex_name = if tex.name.is_empty() {
-            format!("0x{:08X}", tex.name_hash)
-        } else {
-            tex.name.clone()
-        };
-
-        let dds_path = out_dir.join(format!("{}.dds", tex_name));
-        let dds_data = tex.to_dds();
-        std::fs::write(&dds_path, &dds_data)
-            .with_context(|| format!("failed to write {}", dds_path.display()))?;
+        let tex_name = tex.display_name();
+        if let Err(e) = export(tex, &out_dir, export_args) {
+            eprintln!("Failed to export {}: {:#}", tex_name, e);
+            failed += 1;
+            continue;
+        }
 
         println!(
             "  {} — {}x{}x{} {} {} mip(s) ({} bytes)",
@@ -60,10 +95,99 @@ pub fn run(
             tex.width, tex.height, tex.depth,
             tex.format,
             tex.levels,
-            tex.pixel_data.len(),
+            tex.data.len(),
         );
     }
 
-    println!("Extracted {} texture(s) to {}", textures.len(), out_dir.display());
+    println!("Extracted {} texture(s) to {}", textures.len() - failed, out_dir.display());
+    if failed > 0 {
+        anyhow::bail!("{} texture(s) could not be exported", failed);
+    }
     Ok(())
 }
+
+/// Write `tex` into `dir` as `<name>.<ext>`. PNG and TGA hold a single image, so those
+/// export one mip level (the top one unless `--mip` is given, clamped to the smallest) and
+/// textures with several slices become `<name>_<slice>.<ext>`. DDS keeps the whole mip
+/// chain unless `--mip` is given. Returns the number of files written.
+pub fn export(tex: &Texture, dir: &Path, args: &ExportArgs) -> Result<usize> {
+    let (format, mip) = (args.image_format, args.mip);
+    let name = tex.display_name();
+    let level = mip.unwrap_or(0).min(tex.levels.saturating_sub(1));
+    let write = |file: String, data: Vec<u8>| {
+        let path = dir.join(format!("{}.{}", file, format.extension()));
+        std::fs::write(&path, data).with_context(|| format!("failed to write {}", path.display()))
+    };
+
+    if format == ImageFormat::Dds {
+        let dds = match mip {
+            Some(_) => tex.mip(level).context("texture has no pixel data")?.to_dds(),
+            None    => tex.to_dds(),
+        };
+        write(name, dds)?;
+        return Ok(1);
+    }
+
+    let (width, height) = (tex.width as u32 >> level, tex.height as u32 >> level);
+    let slices = tex.slices();
+    for slice in 0..slices {
+        let data = tex.image(slice, level)
+            .with_context(|| format!("mip {} of slice {} is out of bounds", level, slice))?;
+        let rgba = codec::decode(tex.format, width, height, data)?;
+        let image = match format {
+            ImageFormat::Png => codec::to_png(width, height, &rgba)?,
+            _                => codec::to_tga(width, height, &rgba)?,
+        };
+        write(if slices > 1 { format!("{}_{}", name, slice) } else { name.clone() }, image)?;
+    }
+    Ok(slices as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::tests::dds;
+
+    fn args(image_format: ImageFormat, mip: Option<u8>) -> ExportArgs {
+        ExportArgs { image_format, mip }
+    }
+
+    /// Width and height from a PNG's IHDR chunk.
+    fn png_size(data: &[u8]) -> (u32, u32) {
+        (u32::from_be_bytes(data[16..20].try_into().unwrap()), u32::from_be_bytes(data[20..24].try_into().unwrap()))
+    }
+
+    #[test]
+    fn exports_one_mip_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let tex = Texture::from_dds("wall", &dds(64, 32, 3, 5)).unwrap();
+
+        assert_eq!(export(&tex, dir.path(), &args(ImageFormat::Png, None)).unwrap(), 1);
+        assert_eq!(png_size(&std::fs::read(dir.path().join("wall.png")).unwrap()), (64, 32));
+        export(&tex, dir.path(), &args(ImageFormat::Png, Some(1))).unwrap();
+        assert_eq!(png_size(&std::fs::read(dir.path().join("wall.png")).unwrap()), (32, 16));
+        export(&tex, dir.path(), &args(ImageFormat::Png, Some(9))).unwrap();
+        assert_eq!(png_size(&std::fs::read(dir.path().join("wall.png")).unwrap()), (16, 8), "clamped to the last level");
+
+        export(&tex, dir.path(), &args(ImageFormat::Tga, Some(2))).unwrap();
+        let tga = std::fs::read(dir.path().join("wall.tga")).unwrap();
+        assert_eq!(tga.len(), 18 + 16 * 8 * 4);
+
+        export(&tex, dir.path(), &args(ImageFormat::Dds, None)).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("wall.dds")).unwrap(), tex.to_dds());
+        export(&tex, dir.path(), &args(ImageFormat::Dds, Some(1))).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("wall.dds")).unwrap(), tex.mip(1).unwrap().to_dds());
+    }
+
+    #[test]
+    fn exports_every_slice() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tex = Texture::from_dds("cube", &dds(8, 8, 1, 0)).unwrap();
+        tex.depth = 3;
+        tex.data = tex.data.repeat(3);
+        assert_eq!(export(&tex, dir.path(), &args(ImageFormat::Png, None)).unwrap(), 3);
+        for slice in 0..3 {
+            assert!(dir.path().join(format!("cube_{}.png", slice)).is_file());
+        }
+    }
+}
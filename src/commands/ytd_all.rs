@@ -0,0 +1,235 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::commands::ytd::{self, ExportArgs};
+use crate::output::{self, Format};
+use crate::resource::Resource;
+use crate::rpf::{Archive, FileRef, GtaKeys, MAX_DEPTH};
+use crate::texture::{self, Texture};
+use crate::utils::{FilterArgs, PathFilter};
+
+/// Resource types that can hold texture dictionaries.
+const EXTENSIONS: [&str; 4] = ["ytd", "ydr", "yft", "ydd"];
+
+/// One texture dictionary, as summarised by `ytd-all`.
+#[derive(Serialize)]
+struct DictionaryRecord {
+    /// Virtual path of the file, plus `:<drawable hash>` for .ydd entries.
+    path     : String,
+    textures : usize,
+    /// Pixel data of every texture, in bytes.
+    data_size: usize,
+    /// Texture count and pixel data size per format, e.g. `DXT5 x3 (1398144)`.
+    formats  : Vec<String>,
+    /// Width x height of the largest texture.
+    largest  : String,
+    /// Textures that could not be written.
+    failed   : usize,
+}
+
+/// State shared by the whole walk.
+struct Walk<'a> {
+    output : &'a Path,
+    filter : PathFilter,
+    export : &'a ExportArgs,
+    text   : bool,
+    keys   : Option<&'a GtaKeys>,
+    records: Vec<DictionaryRecord>,
+    errors : usize,
+}
+
+/// Export every texture dictionary in an archive (optionally including nested archives)
+/// to `output_dir`, one directory per dictionary at its virtual path.
+pub fn run(
+    archive_path: &Path,
+    output_dir: Option<&Path>,
+    recursive: bool,
+    filter_args: &FilterArgs,
+    export: &ExportArgs,
+    format: Format,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let archive = Archive::open(archive_path, keys)?;
+    let output = output_dir.map(Path::to_path_buf).unwrap_or_else(|| {
+        PathBuf::from(archive_path.file_stem().and_then(|s| s.to_str()).unwrap_or("textures"))
+    });
+
+    let mut walk = Walk {
+        output: &output,
+        filter: PathFilter::new(None, filter_args)?,
+        export,
+        text: format == Format::Text,
+        keys,
+        records: Vec::new(),
+        errors: 0,
+    };
+    walk.archive(&archive, "", recursive, 0);
+
+    if !walk.text {
+        output::print_records(format, &walk.records)?;
+    } else {
+        let textures: usize = walk.records.iter().map(|r| r.textures).sum();
+        let bytes: usize = walk.records.iter().map(|r| r.data_size).sum();
+        println!("\n{} dictionar{}, {} texture(s), {} of pixel data, written to {}",
+            walk.records.len(), if walk.records.len() == 1 { "y" } else { "ies" },
+            textures, human_size(bytes), output.display());
+    }
+
+    let failed = walk.errors + walk.records.iter().map(|r| r.failed).sum::<usize>();
+    if failed > 0 {
+        anyhow::bail!("{} file(s) or texture(s) could not be exported", failed);
+    }
+    Ok(())
+}
+
+impl Walk<'_> {
+    fn archive(&mut self, archive: &Archive, prefix: &str, recursive: bool, depth: usize) {
+        if depth > MAX_DEPTH {
+            eprintln!("[RPF] max nesting depth reached at {}", prefix);
+            return;
+        }
+
+        let files: Vec<FileRef> = archive.list_files().into_iter().cloned().collect();
+        for file in &files {
+            let full = if prefix.is_empty() { file.path.clone() } else { format!("{}/{}", prefix, file.path) };
+            let lower = file.name.to_lowercase();
+
+            if lower.ends_with(".rpf") {
+                if !recursive { continue; }
+                match archive.open_nested(file, self.keys) {
+                    Ok(nested) => self.archive(&nested, &full, recursive, depth + 1),
+                    Err(e) => {
+                        eprintln!("Failed to parse nested {}: {}", full, e);
+                        self.errors += 1;
+                    }
+                }
+                continue;
+            }
+
+            let Some(ext) = EXTENSIONS.iter().find(|e| lower.ends_with(&format!(".{}", e))) else { continue };
+            if !file.is_resource || !self.filter.matches(&full) { continue; }
+
+            if let Err(e) = self.file(archive, file, &full, ext) {
+                eprintln!("Failed to read {}: {:#}", full, e);
+                self.errors += 1;
+            }
+        }
+    }
+
+    /// Export the dictionaries of one resource file.
+    fn file(&mut self, archive: &Archive, file: &FileRef, full: &str, ext: &str) -> Result<()> {
+        let data = archive.extract(file, self.keys)?;
+        let res = Resource::parse(&data)?;
+        for (label, textures) in texture::read_all_dictionaries(&res, ext)? {
+            let (path, dir) = if label.is_empty() {
+                (full.to_string(), self.output.join(full))
+            } else {
+                (format!("{}:{}", full, label), self.output.join(full).join(&label))
+            };
+            self.dictionary(path, &dir, &textures)?;
+        }
+        Ok(())
+    }
+
+    fn dictionary(&mut self, path: String, dir: &Path, textures: &[Texture]) -> Result<()> {
+        if textures.is_empty() { return Ok(()); }
+        fs::create_dir_all(dir)?;
+
+        let mut failed = 0;
+        for tex in textures {
+            if let Err(e) = ytd::export(tex, dir, self.export) {
+                eprintln!("Failed to export {} from {}: {:#}", tex.display_name(), path, e);
+                failed += 1;
+            }
+        }
+
+        let mut formats: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for tex in textures {
+            let entry = formats.entry(tex.format.to_string()).or_default();
+            entry.0 += 1;
+            entry.1 += tex.data.len();
+        }
+        let largest = textures.iter().max_by_key(|t| t.width as u32 * t.height as u32).unwrap();
+
+        let record = DictionaryRecord {
+            path,
+            textures : textures.len(),
+            data_size: textures.iter().map(|t| t.data.len()).sum(),
+            formats  : formats.iter().map(|(f, (n, size))| format!("{} x{} ({})", f, n, size)).collect(),
+            largest  : format!("{}x{}", largest.width, largest.height),
+            failed,
+        };
+        if self.text {
+            println!("  {} — {} texture(s), {}, largest {}: {}",
+                record.path, record.textures, human_size(record.data_size), record.largest,
+                formats.iter().map(|(f, (n, size))| format!("{} x{} ({})", f, n, human_size(*size))).collect::<Vec<_>>().join(", "));
+        }
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+fn human_size(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b                 => format!("{} B", b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption};
+    use crate::commands::ytd::ImageFormat;
+    use crate::texture::tests::dds;
+
+    fn archive(files: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        for (path, data) in files {
+            builder.add_file(path, data);
+        }
+        builder.build(None).unwrap()
+    }
+
+    fn ytd(names: &[&str]) -> Vec<u8> {
+        let textures: Vec<Texture> = names.iter().map(|n| Texture::from_dds(n, &dds(16, 16, 2, 3)).unwrap()).collect();
+        texture::build_dictionary(&textures).unwrap()
+    }
+
+    #[test]
+    fn exports_every_dictionary() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = archive(vec![("stream/deep.ytd", ytd(&["c"]))]);
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, archive(vec![
+            ("a.ytd", ytd(&["a", "b"])),
+            ("skip/other.ytd", ytd(&["d"])),
+            ("readme.txt", b"not a texture".to_vec()),
+            ("dlc.rpf", inner),
+        ])).unwrap();
+
+        let out = dir.path().join("out");
+        let export = ExportArgs { image_format: ImageFormat::Png, mip: None };
+        let filter = FilterArgs { exclude: vec!["skip/**".into()], ..Default::default() };
+        run(&path, Some(&out), false, &filter, &export, Format::Text, None).unwrap();
+        assert!(out.join("a.ytd/a.png").is_file() && out.join("a.ytd/b.png").is_file());
+        assert!(!out.join("skip").exists());
+        assert!(!out.join("dlc.rpf").exists(), "nested archives need --recursive");
+
+        run(&path, Some(&out), true, &filter, &export, Format::Text, None).unwrap();
+        assert!(out.join("dlc.rpf/stream/deep.ytd/c.png").is_file());
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(3 << 20), "3.0 MB");
+    }
+}
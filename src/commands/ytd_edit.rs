@@ -0,0 +1,187 @@
+use anyhow::{bail, Context, Result};
+use std::{fs, path::Path};
+
+use rpf_archive::rage_joaat;
+
+use crate::editor;
+use crate::resource::Resource;
+use crate::rpf::{Archive, GtaKeys};
+use crate::texture::{self, Texture};
+
+/// Replace one texture of a .ytd inside an archive with a DDS file. Every other texture
+/// keeps its fields and pixel data.
+pub fn replace(
+    archive_path: &Path,
+    ytd_name: &str,
+    texture_name: &str,
+    dds_path: &Path,
+    reencode: bool,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let dds = fs::read(dds_path).with_context(|| format!("cannot read {}", dds_path.display()))?;
+
+    edit(archive_path, ytd_name, keys, |textures| {
+        let hash = rage_joaat(&texture_name.to_lowercase());
+        let old = textures.iter_mut()
+            .find(|t| t.name_hash == hash || t.name.eq_ignore_ascii_case(texture_name))
+            .with_context(|| format!("no texture '{}' in {} (use `ytd add` to add one)", texture_name, ytd_name))?;
+
+        let mut new = Texture::from_dds(&old.name, &dds)
+            .with_context(|| format!("failed to read {}", dds_path.display()))?;
+        if (new.format, new.width, new.height, new.levels) != (old.format, old.width, old.height, old.levels) {
+            if !reencode {
+                bail!("{} is {}x{} {} with {} mip(s), but '{}' is {}x{} {} with {} (use --reencode to convert)",
+                    dds_path.display(), new.width, new.height, new.format, new.levels,
+                    old.display_name(), old.width, old.height, old.format, old.levels);
+            }
+            new = new.reencode(old.format, old.width, old.height, old.levels)
+                .with_context(|| format!("failed to re-encode {}", dds_path.display()))?;
+        }
+
+        println!("  {} — {}x{} {} {} mip(s) ({} bytes)",
+            old.display_name(), new.width, new.height, new.format, new.levels, new.data.len());
+        old.set_image(new);
+        Ok(())
+    })
+}
+
+/// Add a texture to a .ytd inside an archive.
+pub fn add(
+    archive_path: &Path,
+    ytd_name: &str,
+    dds_path: &Path,
+    name: Option<&str>,
+    keys: Option<&GtaKeys>,
+) -> Result<()> {
+    let dds = fs::read(dds_path).with_context(|| format!("cannot read {}", dds_path.display()))?;
+    let name = match name {
+        Some(n) => n,
+        None    => dds_path.file_stem().and_then(|s| s.to_str()).context("non-UTF-8 file name")?,
+    };
+
+    edit(archive_path, ytd_name, keys, |textures| {
+        let tex = Texture::from_dds(name, &dds)
+            .with_context(|| format!("failed to read {}", dds_path.display()))?;
+        if textures.iter().any(|t| t.name_hash == tex.name_hash) {
+            bail!("'{}' already exists in {} (use `ytd replace`)", name, ytd_name);
+        }
+
+        println!("  {} — {}x{} {} {} mip(s) ({} bytes)",
+            tex.name, tex.width, tex.height, tex.format, tex.levels, tex.data.len());
+        textures.push(tex);
+        Ok(())
+    })
+}
+
+/// Load the dictionary `ytd_name` from the archive, apply `change` and write it back.
+fn edit(
+    archive_path: &Path,
+    ytd_name: &str,
+    keys: Option<&GtaKeys>,
+    change: impl FnOnce(&mut Vec<Texture>) -> Result<()>,
+) -> Result<()> {
+    let (path, rsc7) = {
+        let archive = Archive::open(archive_path, keys)?;
+        let file = archive.find_file(ytd_name)
+            .with_context(|| format!("'{}' not found in archive", ytd_name))?;
+        let data = archive.extract(file, keys)
+            .with_context(|| format!("failed to extract '{}'", ytd_name))?;
+        (file.path.clone(), data)
+    };
+
+    let mut textures = Resource::parse(&rsc7)
+        .and_then(|res| texture::read_dictionary(&res))
+        .with_context(|| format!("failed to parse YTD '{}'", ytd_name))?;
+    change(&mut textures)?;
+    let data = texture::build_dictionary(&textures)?;
+
+    let len = data.len();
+    let count = editor::edit_file(archive_path, keys, |ed| ed.replace_file(&path, data))?;
+    println!("Wrote {} ({} texture(s), {} bytes)", path, textures.len(), len);
+    println!("Updated {} ({} entries)", archive_path.display(), count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::{RpfBuilder, RpfEncryption, TextureFormat};
+    use crate::texture::tests::dds;
+
+    /// An archive holding `stream/props.ytd` with textures `a` (64x64, 4 mips) and `b`.
+    fn sample(dir: &Path) -> std::path::PathBuf {
+        let ytd = texture::build_dictionary(&[
+            Texture::from_dds("a", &dds(64, 64, 4, 1)).unwrap(),
+            Texture::from_dds("b", &dds(16, 16, 1, 2)).unwrap(),
+        ]).unwrap();
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("stream/props.ytd", ytd);
+        let path = dir.join("test.rpf");
+        fs::write(&path, builder.build(None).unwrap()).unwrap();
+        path
+    }
+
+    fn textures(path: &Path) -> Vec<Texture> {
+        let archive = Archive::open(path, None).unwrap();
+        let data = archive.extract(archive.find_file("stream/props.ytd").unwrap(), None).unwrap();
+        texture::read_dictionary(&Resource::parse(&data).unwrap()).unwrap()
+    }
+
+    fn write_dds(dir: &Path, name: &str, data: Vec<u8>) -> std::path::PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn replaces_and_adds_textures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample(dir.path());
+        let new_a = write_dds(dir.path(), "new_a.dds", dds(64, 64, 4, 50));
+        replace(&path, "stream/props.ytd", "A", &new_a, false, None).unwrap();
+        let extra = write_dds(dir.path(), "Extra.dds", dds(8, 8, 1, 60));
+        add(&path, "stream/props.ytd", &extra, None, None).unwrap();
+
+        let textures = textures(&path);
+        assert_eq!(textures.len(), 3);
+        let find = |name: &str| textures.iter().find(|t| t.name == name).unwrap();
+        assert_eq!(find("a").data, Texture::from_dds("a", &dds(64, 64, 4, 50)).unwrap().data);
+        assert_eq!(find("b").data, Texture::from_dds("b", &dds(16, 16, 1, 2)).unwrap().data);
+        assert_eq!(find("Extra").width, 8);
+
+        assert!(add(&path, "stream/props.ytd", &extra, Some("a"), None).is_err(), "duplicate name");
+        assert!(replace(&path, "stream/props.ytd", "missing", &new_a, false, None).is_err());
+    }
+
+    #[test]
+    fn rejects_or_reencodes_mismatched_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample(dir.path());
+        let small = write_dds(dir.path(), "small.dds", dds(32, 32, 1, 70));
+        assert!(replace(&path, "stream/props.ytd", "a", &small, false, None).is_err());
+        let original = Texture::from_dds("a", &dds(64, 64, 4, 1)).unwrap().data;
+        assert!(textures(&path).iter().any(|t| t.name == "a" && t.data == original), "left unchanged");
+
+        replace(&path, "stream/props.ytd", "a", &small, true, None).unwrap();
+        let textures = textures(&path);
+        let a = textures.iter().find(|t| t.name == "a").unwrap();
+        assert_eq!((a.width, a.height, a.levels, a.format), (64, 64, 4, TextureFormat::DXT1));
+        let chain: usize = (0..4).map(|l| texture::level_size(TextureFormat::DXT1, 64 >> l, 64 >> l)).sum();
+        assert_eq!(a.data.len(), chain);
+    }
+
+    #[test]
+    fn rejects_or_reencodes_a_different_mip_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample(dir.path());
+        let flat = write_dds(dir.path(), "flat.dds", dds(64, 64, 1, 80));
+        let err = replace(&path, "stream/props.ytd", "a", &flat, false, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("with 1 mip(s)"), "{:#}", err);
+
+        replace(&path, "stream/props.ytd", "a", &flat, true, None).unwrap();
+        let a = textures(&path).into_iter().find(|t| t.name == "a").unwrap();
+        assert_eq!((a.width, a.levels), (64, 4));
+        let chain: usize = (0..4).map(|l| texture::level_size(TextureFormat::DXT1, 64 >> l, 64 >> l)).sum();
+        assert_eq!(a.data.len(), chain);
+    }
+}
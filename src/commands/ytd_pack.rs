@@ -0,0 +1,46 @@
+use anyhow::{bail, Context, Result};
+use std::{fs, path::Path};
+
+use crate::editor;
+use crate::rpf::GtaKeys;
+use crate::texture::{self, Texture};
+
+/// Build a texture dictionary from every .dds file in a directory, writing it to `output`
+/// or, with `archive`, adding or replacing the entry `output` inside that archive.
+pub fn run(input: &Path, output: &str, archive: Option<&Path>, keys: Option<&GtaKeys>) -> Result<()> {
+    let mut paths: Vec<_> = fs::read_dir(input)
+        .with_context(|| format!("cannot read {}", input.display()))?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("dds")));
+    paths.sort();
+    if paths.is_empty() { bail!("no .dds files in {}", input.display()); }
+
+    let mut textures = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let name = path.file_stem().and_then(|s| s.to_str()).context("non-UTF-8 file name")?;
+        let tex = Texture::from_dds(name, &fs::read(path)?)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        println!("  {} — {}x{} {} {} mip(s) ({} bytes)",
+            tex.name, tex.width, tex.height, tex.format, tex.levels, tex.data.len());
+        textures.push(tex);
+    }
+
+    let data = texture::build_dictionary(&textures)?;
+    let len = data.len();
+
+    match archive {
+        None => {
+            fs::write(output, &data).with_context(|| format!("failed to write {}", output))?;
+            println!("Packed {} texture(s) into {} ({} bytes)", textures.len(), output, len);
+        }
+        Some(archive_path) => {
+            let count = editor::edit_file(archive_path, keys, |ed| {
+                if ed.exists(output) { ed.replace_file(output, data) } else { ed.add_file(output, data) }
+            })?;
+            println!("Packed {} texture(s) into {} ({} bytes)", textures.len(), output, len);
+            println!("Updated {} ({} entries)", archive_path.display(), count);
+        }
+    }
+    Ok(())
+}
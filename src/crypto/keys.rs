@@ -1,79 +1,171 @@
-use anyhow::{Context, Result, bail};
-use sha1::{Digest, Sha1};
-use std::{fs, path::Path};
-
-/// GTA V cryptographic keys needed for RPF decryption.
-pub struct GtaKeys {
-    pub aes_key: [u8; 32],
-    pub ng_keys: Vec<Vec<u8>>,
-    pub ng_decrypt_tables: Box<[[[u32; 256]; 16]; 17]>,
-}
+// Key discovery, loading, caching and extraction from GTA5.exe.
+//
+// Keys are the crate's `GtaKeys`, so they can be handed straight to rpf_archive. They are
+// stored as three flat files (AES key, 101 NG keys, 17x16x256 NG decrypt tables) under the
+// same file names CodeWalker uses.
 
-impl GtaKeys {
-    /// Load pre-extracted keys from a directory.
-    /// Expected files: gtav_aes_key.dat, gtav_ng_key.dat, gtav_ng_decrypt_tables.dat
-    pub fn load_from_path(path: &Path) -> Result<Self> {
-        let aes_bytes = fs::read(path.join("gtav_aes_key.dat"))
-            .context("Missing gtav_aes_key.dat")?;
-        if aes_bytes.len() != 32 {
-            bail!("gtav_aes_key.dat has wrong size (expected 32 bytes)");
-        }
-        let aes_key: [u8; 32] = aes_bytes.try_into().unwrap();
+use anyhow::{anyhow, bail, Context, Result};
+use log::debug;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::search;
+use crate::rpf::GtaKeys;
+
+/// Environment variable naming a key directory, used when `--keys` is not given.
+pub const ENV_VAR: &str = "RPF_KEYS_DIR";
 
-        let ng_key_bytes = fs::read(path.join("gtav_ng_key.dat"))
-            .context("Missing gtav_ng_key.dat")?;
-        let ng_keys = read_ng_keys(&ng_key_bytes)?;
+/// The OS executable whose data sections hold the keys.
+const EXE_NAME: &str = "GTA5.exe";
 
-        let ng_table_bytes = fs::read(path.join("gtav_ng_decrypt_tables.dat"))
-            .context("Missing gtav_ng_decrypt_tables.dat")?;
-        let ng_decrypt_tables = read_ng_tables(&ng_table_bytes)?;
+const AES_FILE  : &str = "gtav_aes_key.dat";
+const KEYS_FILE : &str = "gtav_ng_key.dat";
+const TABLE_FILE: &str = "gtav_ng_decrypt_tables.dat";
 
-        Ok(Self { aes_key, ng_keys, ng_decrypt_tables })
+// ─── Resolution ──────────────────────────────────────────────────────────────
+
+/// Find keys for a command, trying in order:
+///
+/// 1. `explicit` (the `--keys` directory),
+/// 2. the directory in `$RPF_KEYS_DIR`,
+/// 3. the per-user cache written by `extract-keys` (see [`cache_dir`]),
+/// 4. a `GTA5.exe` in the directory of `archive` or any parent, extracting and caching
+///    its keys on first use.
+///
+/// An explicit or environment directory that cannot be loaded is an error; the later
+/// sources are best effort. Returns `None` when nothing was found, which is fine for
+/// unencrypted archives.
+pub fn resolve(explicit: Option<&Path>, archive: Option<&Path>) -> Result<Option<GtaKeys>> {
+    if let Some(dir) = explicit {
+        return load(dir).map(Some);
     }
 
-    /// Extract keys from a GTA5.exe and optionally save them to a directory.
-    pub fn extract_from_exe(exe_path: &Path, save_to: Option<&Path>) -> Result<Self> {
-        eprintln!("[Keys] Reading GTA5.exe ({} MB)...", fs::metadata(exe_path)?.len() / 1024 / 1024);
-        let exe_data = fs::read(exe_path).context("Failed to read GTA5.exe")?;
-
-        eprintln!("[Keys] Searching for AES key...");
-        let aes_bytes = search_hash(&exe_data, &PC_AES_KEY_HASH, 32)
-            .context("AES key not found in GTA5.exe")?;
-        let aes_key: [u8; 32] = aes_bytes.try_into().unwrap();
-
-        eprintln!("[Keys] Searching for 101 NG keys...");
-        let ng_key_bufs = search_hashes(&exe_data, &PC_NG_KEY_HASHES, 0x110)?;
-        let mut ng_keys_flat = Vec::with_capacity(101 * 272);
-        for buf in &ng_key_bufs {
-            ng_keys_flat.extend_from_slice(buf);
-        }
-        let ng_keys = read_ng_keys(&ng_keys_flat)?;
+    if let Some(dir) = env::var_os(ENV_VAR).filter(|v| !v.is_empty()) {
+        let dir = PathBuf::from(dir);
+        debug!("loading keys from ${} ({})", ENV_VAR, dir.display());
+        return load(&dir).map(Some)
+            .with_context(|| format!("${} points at unusable keys", ENV_VAR));
+    }
 
-        eprintln!("[Keys] Searching for 272 NG decrypt tables...");
-        let table_bufs = search_hashes(&exe_data, &PC_NG_DECRYPT_TABLE_HASHES, 0x400)?;
-        let mut tables_flat = Vec::with_capacity(272 * 0x400);
-        for buf in &table_bufs {
-            tables_flat.extend_from_slice(buf);
+    if let Some(dir) = cache_dir().filter(|d| has_keys(d)) {
+        debug!("loading cached keys from {}", dir.display());
+        match load(&dir) {
+            Ok(keys) => return Ok(Some(keys)),
+            Err(e)   => eprintln!("[Keys] Ignoring cached keys in {}: {:#}", dir.display(), e),
         }
-        let ng_decrypt_tables = read_ng_tables(&tables_flat)?;
-
-        let keys = Self { aes_key, ng_keys, ng_decrypt_tables };
+    }
 
-        if let Some(out_path) = save_to {
-            keys.save_to_path(out_path)?;
+    let Some(exe) = archive.and_then(find_exe) else {
+        debug!("no keys found");
+        return Ok(None);
+    };
+    eprintln!("[Keys] No keys configured; using {}", exe.display());
+    match extract_and_cache(&exe, None) {
+        Ok(keys) => Ok(Some(keys)),
+        Err(e)   => {
+            eprintln!("[Keys] Key extraction failed: {:#}", e);
+            Ok(None)
         }
+    }
+}
 
-        Ok(keys)
+/// Extract keys from `exe`, save them to `save_to` if given, and store them in the per-user
+/// cache so later commands find them without `--keys`. Caching is only an error when it is
+/// the sole destination.
+pub fn extract_and_cache(exe: &Path, save_to: Option<&Path>) -> Result<GtaKeys> {
+    let keys = extract_from_exe(exe)?;
+    if let Some(dir) = save_to {
+        save(&keys, dir)?;
     }
 
-    pub fn save_to_path(&self, path: &Path) -> Result<()> {
-        fs::create_dir_all(path)?;
-        fs::write(path.join("gtav_aes_key.dat"), &self.aes_key)?;
-        fs::write(path.join("gtav_ng_key.dat"), write_ng_keys(&self.ng_keys))?;
-        fs::write(path.join("gtav_ng_decrypt_tables.dat"), write_ng_tables(&self.ng_decrypt_tables))?;
-        eprintln!("[Keys] Saved to {}", path.display());
-        Ok(())
+    match cache_dir() {
+        Some(dir) if save_to == Some(dir.as_path()) => {}
+        Some(dir) => match save(&keys, &dir) {
+            Ok(())                       => {}
+            Err(e) if save_to.is_some()  => eprintln!("[Keys] Could not cache keys: {:#}", e),
+            Err(e)                       => return Err(e),
+        },
+        None if save_to.is_none() => bail!("No per-user config directory to cache keys in; pass --output"),
+        None => {}
     }
+    Ok(keys)
+}
+
+/// Per-user key cache: `%APPDATA%\rpf-cli\keys` on Windows,
+/// `~/Library/Application Support/rpf-cli/keys` on macOS and
+/// `$XDG_CONFIG_HOME/rpf-cli/keys` (default `~/.config`) elsewhere.
+pub fn cache_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).filter(|p| p.is_absolute())
+            .or_else(|| home().map(|h| h.join(".config")))
+    };
+    base.map(|b| b.join("rpf-cli").join("keys"))
+}
+
+/// Look for `GTA5.exe` next to `archive` and in every parent directory, so archives deep
+/// in an install (`update/x64/dlcpacks/...`) still find it.
+fn find_exe(archive: &Path) -> Option<PathBuf> {
+    let archive = fs::canonicalize(archive).ok()?;
+    archive.ancestors().skip(1)
+        .map(|dir| dir.join(EXE_NAME))
+        .find(|exe| exe.is_file())
+}
+
+fn has_keys(dir: &Path) -> bool {
+    [AES_FILE, KEYS_FILE, TABLE_FILE].iter().all(|f| dir.join(f).is_file())
+}
+
+// ─── Loading and saving ──────────────────────────────────────────────────────
+
+/// Load pre-extracted keys from a directory holding
+/// gtav_aes_key.dat, gtav_ng_key.dat and gtav_ng_decrypt_tables.dat.
+pub fn load(path: &Path) -> Result<GtaKeys> {
+    let read = |file: &str| fs::read(path.join(file))
+        .with_context(|| format!("Missing {} in {}", file, path.display()));
+
+    let aes_key: [u8; 32] = read(AES_FILE)?.try_into()
+        .map_err(|_| anyhow!("{} has wrong size (expected 32 bytes)", AES_FILE))?;
+    let ng_keys = read_ng_keys(&read(KEYS_FILE)?)?;
+    let ng_decrypt_tables = read_ng_tables(&read(TABLE_FILE)?)?;
+
+    Ok(GtaKeys { aes_key, ng_keys, ng_decrypt_tables })
+}
+
+pub fn save(keys: &GtaKeys, path: &Path) -> Result<()> {
+    fs::create_dir_all(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    fs::write(path.join(AES_FILE), keys.aes_key)?;
+    fs::write(path.join(KEYS_FILE), write_ng_keys(&keys.ng_keys))?;
+    fs::write(path.join(TABLE_FILE), write_ng_tables(&keys.ng_decrypt_tables))?;
+    eprintln!("[Keys] Saved to {}", path.display());
+    Ok(())
+}
+
+// ─── Extraction ──────────────────────────────────────────────────────────────
+
+/// Extract keys from a GTA5.exe by searching for data with known SHA-1 hashes
+/// (see `search` for how the search is kept fast).
+pub fn extract_from_exe(exe_path: &Path) -> Result<GtaKeys> {
+    eprintln!("[Keys] Reading GTA5.exe ({} MB)...", fs::metadata(exe_path)?.len() / 1024 / 1024);
+    let exe_data = fs::read(exe_path).context("Failed to read GTA5.exe")?;
+
+    let aes_key: [u8; 32] = search::find_all(&exe_data, &[PC_AES_KEY_HASH], 32, "AES key")?
+        .concat().try_into().unwrap();
+
+    let ng_key_bufs = search::find_all(&exe_data, &PC_NG_KEY_HASHES, 0x110, "NG keys")?;
+    let ng_keys = read_ng_keys(&ng_key_bufs.concat())?;
+
+    let table_bufs = search::find_all(&exe_data, &PC_NG_DECRYPT_TABLE_HASHES, 0x400, "NG decrypt tables")?;
+    let ng_decrypt_tables = read_ng_tables(&table_bufs.concat())?;
+
+    Ok(GtaKeys { aes_key, ng_keys, ng_decrypt_tables })
 }
 
 fn read_ng_keys(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -96,83 +188,16 @@ fn read_ng_tables(data: &[u8]) -> Result<Box<[[[u32; 256]; 16]; 17]>> {
     if data.len() < EXPECTED {
         bail!("NG table data too small: {} bytes (expected {})", data.len(), EXPECTED);
     }
-    let mut tables = vec![[[0u32; 256]; 16]; 17];
-    let mut offset = 0;
-    for i in 0..17 {
-        for j in 0..16 {
-            for k in 0..256 {
-                tables[i][j][k] = u32::from_le_bytes([
-                    data[offset],
-                    data[offset + 1],
-                    data[offset + 2],
-                    data[offset + 3],
-                ]);
-                offset += 4;
-            }
-        }
+    let mut tables = Box::new([[[0u32; 256]; 16]; 17]);
+    let words = data.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+    for (slot, word) in tables.iter_mut().flatten().flatten().zip(words) {
+        *slot = word;
     }
-    let arr: [[[u32; 256]; 16]; 17] = tables
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("Failed to convert ng tables to fixed array"))?;
-    Ok(Box::new(arr))
+    Ok(tables)
 }
 
 fn write_ng_tables(tables: &[[[u32; 256]; 16]; 17]) -> Vec<u8> {
-    let mut out = Vec::with_capacity(17 * 16 * 1024);
-    for i in 0..17 {
-        for j in 0..16 {
-            for k in 0..256 {
-                out.extend_from_slice(&tables[i][j][k].to_le_bytes());
-            }
-        }
-    }
-    out
-}
-
-fn search_hash(data: &[u8], expected_sha1: &[u8; 20], length: usize) -> Option<Vec<u8>> {
-    if data.len() < length {
-        return None;
-    }
-    for i in 0..=(data.len() - length) {
-        let candidate = &data[i..i + length];
-        let mut hasher = Sha1::new();
-        hasher.update(candidate);
-        let hash: [u8; 20] = hasher.finalize().into();
-        if &hash == expected_sha1 {
-            return Some(candidate.to_vec());
-        }
-    }
-    None
-}
-
-fn search_hashes(data: &[u8], hashes: &[[u8; 20]], length: usize) -> Result<Vec<Vec<u8>>> {
-    let mut results: Vec<Option<Vec<u8>>> = vec![None; hashes.len()];
-    let mut found = 0usize;
-
-    if data.len() >= length {
-        for i in 0..=(data.len() - length) {
-            if found == hashes.len() {
-                break;
-            }
-            let candidate = &data[i..i + length];
-            let mut hasher = Sha1::new();
-            hasher.update(candidate);
-            let hash: [u8; 20] = hasher.finalize().into();
-            for (j, expected) in hashes.iter().enumerate() {
-                if results[j].is_none() && &hash == expected {
-                    results[j] = Some(candidate.to_vec());
-                    found += 1;
-                    break;
-                }
-            }
-        }
-    }
-
-    results
-        .into_iter()
-        .enumerate()
-        .map(|(i, r)| r.ok_or_else(|| anyhow::anyhow!("Hash {} not found in GTA5.exe", i)))
-        .collect()
+    tables.iter().flatten().flatten().flat_map(|w| w.to_le_bytes()).collect()
 }
 
 static PC_AES_KEY_HASH: [u8; 20] = [
@@ -185,3 +210,55 @@ static PC_NG_KEY_HASHES: [[u8; 20]; 101] =
 
 static PC_NG_DECRYPT_TABLE_HASHES: [[u8; 20]; 272] =
     include!("ng_table_hashes.rs");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> GtaKeys {
+        let ng_keys = (0..101).map(|i| vec![i as u8; 272]).collect();
+        let mut ng_decrypt_tables = Box::new([[[0u32; 256]; 16]; 17]);
+        for (i, w) in ng_decrypt_tables.iter_mut().flatten().flatten().enumerate() {
+            *w = (i as u32).wrapping_mul(0x9E37_79B9);
+        }
+        GtaKeys { aes_key: [7; 32], ng_keys, ng_decrypt_tables }
+    }
+
+    #[test]
+    fn save_load_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = sample();
+        save(&keys, dir.path()).unwrap();
+        assert!(has_keys(dir.path()));
+
+        let loaded = resolve(Some(dir.path()), None).unwrap().unwrap();
+        assert_eq!(loaded.aes_key, keys.aes_key);
+        assert_eq!(loaded.ng_keys, keys.ng_keys);
+        assert_eq!(loaded.ng_decrypt_tables, keys.ng_decrypt_tables);
+    }
+
+    #[test]
+    fn rejects_unusable_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(resolve(Some(dir.path()), None).is_err(), "an explicit directory must load");
+
+        save(&sample(), dir.path()).unwrap();
+        fs::write(dir.path().join(AES_FILE), [0u8; 16]).unwrap();
+        assert!(load(dir.path()).is_err());
+        assert!(read_ng_keys(&[0; 272 * 100]).is_err());
+        assert!(read_ng_tables(&[0; 1024]).is_err());
+    }
+
+    #[test]
+    fn finds_the_exe_in_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("update/x64/dlcpacks/pack");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("dlc.rpf"), b"").unwrap();
+        assert_eq!(find_exe(&deep.join("dlc.rpf")), None);
+
+        fs::write(dir.path().join(EXE_NAME), b"").unwrap();
+        let found = find_exe(&deep.join("dlc.rpf")).unwrap();
+        assert_eq!(found, fs::canonicalize(dir.path()).unwrap().join(EXE_NAME));
+    }
+}
@@ -1,5 +1,10 @@
-pub mod cipher;
+// GTA V key handling and RPF encryption.
+//
+// Decryption itself lives in rpf_archive; this module owns everything around it: finding
+// and caching keys (`keys`) and the inverse NG cipher used when writing archives (`ng`).
+
 pub mod keys;
+pub mod ng;
+mod search;
 
-pub use cipher::{decrypt_aes, decrypt_ng};
-pub use keys::GtaKeys;
+pub use rpf_archive::crypto::{decrypt_aes, decrypt_ng, encrypt_aes};
@@ -0,0 +1,210 @@
+// NG encryption: the inverse of rpf_archive's `decrypt_ng`.
+//
+// Every NG decryption round computes each output word as the XOR of four table lookups
+// (one per input byte) and a subkey word. The tables of one word are affine maps onto
+// independent 8-bit subspaces of GF(2)^32, so a word can be inverted by projecting it onto
+// those subspaces (one 32x32 GF(2) matrix) and looking each byte back up. The inverse tables
+// are derived from the decrypt tables once per process run; nothing extra is read from disk.
+
+use anyhow::{bail, Result};
+use rpf_archive::{GtaKeys, crypto::cipher::get_ng_key_idx};
+
+/// Input byte positions feeding each output word, per round kind (see `decrypt_ng_block`).
+const ROUND_A: [[usize; 4]; 4] = [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]];
+const ROUND_B: [[usize; 4]; 4] = [[0, 7, 10, 13], [1, 4, 11, 14], [2, 5, 8, 15], [3, 6, 9, 12]];
+
+/// Inverse of one output word of one decryption round.
+struct WordInverse {
+    positions: [usize; 4],
+    constant : u32,
+    /// Byte `m` of the (constant-adjusted) word -> its contribution to the subspace coordinates.
+    project  : Box<[[u32; 256]; 4]>,
+    /// Coordinate byte `k` -> the input byte at `positions[k]`.
+    bytes    : Box<[[u8; 256]; 4]>,
+}
+
+pub struct NgEncryptor<'k> {
+    keys  : &'k GtaKeys,
+    rounds: Vec<[WordInverse; 4]>,
+}
+
+impl<'k> NgEncryptor<'k> {
+    pub fn new(keys: &'k GtaKeys) -> Result<Self> {
+        let mut rounds = Vec::with_capacity(17);
+        for (round, tables) in keys.ng_decrypt_tables.iter().enumerate() {
+            let groups = if (2..=15).contains(&round) { &ROUND_B } else { &ROUND_A };
+            let mut words = Vec::with_capacity(4);
+            for (word, positions) in groups.iter().enumerate() {
+                words.push(invert_word(tables, *positions)
+                    .ok_or_else(|| anyhow::anyhow!("NG decrypt table {} word {} is not invertible", round, word))?);
+            }
+            rounds.push(words.try_into().unwrap_or_else(|_| unreachable!()));
+        }
+        Ok(Self { keys, rounds })
+    }
+
+    /// Encrypt `data` so that `decrypt_ng(data, keys, name, length)` yields the input.
+    /// A trailing partial block is left as-is, exactly as decryption does.
+    pub fn encrypt(&self, data: &[u8], name: &str, length: u32) -> Vec<u8> {
+        let key = &self.keys.ng_keys[get_ng_key_idx(name, length)];
+        let key: Vec<u32> = key.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+
+        let mut out = data.to_vec();
+        for block in out.chunks_exact_mut(16) {
+            let mut buf: [u8; 16] = (&*block).try_into().unwrap();
+            for (round, words) in self.rounds.iter().enumerate().rev() {
+                buf = invert_round(&buf, &key[round * 4..round * 4 + 4], words);
+            }
+            block.copy_from_slice(&buf);
+        }
+        out
+    }
+}
+
+fn invert_round(data: &[u8; 16], key: &[u32], words: &[WordInverse; 4]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    for (w, inv) in words.iter().enumerate() {
+        let x = u32::from_le_bytes(data[w * 4..w * 4 + 4].try_into().unwrap()) ^ key[w] ^ inv.constant;
+        let coords = (0..4).fold(0u32, |acc, m| acc ^ inv.project[m][((x >> (8 * m)) & 0xFF) as usize]);
+        for k in 0..4 {
+            out[inv.positions[k]] = inv.bytes[k][((coords >> (8 * k)) & 0xFF) as usize];
+        }
+    }
+    out
+}
+
+fn invert_word(tables: &[[u32; 256]; 16], positions: [usize; 4]) -> Option<WordInverse> {
+    let constant = positions.iter().fold(0u32, |acc, &p| acc ^ tables[p][0]);
+
+    // Columns 8k..8k+8 of the basis matrix span the subspace hit by table `positions[k]`.
+    let mut columns = [0u32; 32];
+    for (k, &p) in positions.iter().enumerate() {
+        let basis = subspace_basis(tables[p].iter().map(|&v| v ^ tables[p][0]))?;
+        columns[k * 8..k * 8 + 8].copy_from_slice(&basis);
+    }
+    let inverse = invert_matrix(&columns)?;
+
+    let mut project = Box::new([[0u32; 256]; 4]);
+    for (m, table) in project.iter_mut().enumerate() {
+        for (v, slot) in table.iter_mut().enumerate() {
+            *slot = apply_matrix(&inverse, (v as u32) << (8 * m));
+        }
+    }
+
+    let mut bytes = Box::new([[0u8; 256]; 4]);
+    for (k, &p) in positions.iter().enumerate() {
+        let mut seen = [false; 256];
+        for b in 0..256usize {
+            let coords = apply_matrix(&inverse, tables[p][b] ^ tables[p][0]);
+            let coord = (coords >> (8 * k)) & 0xFF;
+            if coords & !(0xFF << (8 * k)) != 0 || seen[coord as usize] { return None; }
+            seen[coord as usize] = true;
+            bytes[k][coord as usize] = b as u8;
+        }
+    }
+
+    Some(WordInverse { positions, constant, project, bytes })
+}
+
+/// Pick 8 linearly independent vectors from `values`; `None` unless they span exactly 8 dimensions.
+fn subspace_basis(values: impl Iterator<Item = u32>) -> Option<[u32; 8]> {
+    let mut reduced: Vec<u32> = Vec::with_capacity(8);
+    let mut basis = Vec::with_capacity(8);
+    for v in values {
+        let r = reduced.iter().fold(v, |acc, &b| acc.min(acc ^ b));
+        if r != 0 {
+            if basis.len() == 8 { return None; }
+            reduced.push(r);
+            reduced.sort_unstable_by(|a, b| b.cmp(a));
+            basis.push(v);
+        }
+    }
+    basis.try_into().ok()
+}
+
+/// Invert a 32x32 GF(2) matrix given as columns; the result is returned as rows.
+fn invert_matrix(columns: &[u32; 32]) -> Option<[u32; 32]> {
+    // Row i of the matrix has bit c set when bit i of column c is set.
+    let mut rows = [0u32; 32];
+    for (c, col) in columns.iter().enumerate() {
+        for (i, row) in rows.iter_mut().enumerate() {
+            if col >> i & 1 == 1 { *row |= 1 << c; }
+        }
+    }
+    let mut inv: [u32; 32] = std::array::from_fn(|i| 1 << i);
+
+    for c in 0..32 {
+        let pivot = (c..32).find(|&r| rows[r] >> c & 1 == 1)?;
+        rows.swap(c, pivot);
+        inv.swap(c, pivot);
+        for r in 0..32 {
+            if r != c && rows[r] >> c & 1 == 1 {
+                rows[r] ^= rows[c];
+                inv[r] ^= inv[c];
+            }
+        }
+    }
+    Some(inv)
+}
+
+fn apply_matrix(rows: &[u32; 32], v: u32) -> u32 {
+    rows.iter().enumerate().fold(0, |acc, (i, row)| acc | (((row & v).count_ones() & 1) << i))
+}
+
+/// Check that freshly derived inverse tables round-trip through the real decryptor.
+pub fn self_test(enc: &NgEncryptor<'_>) -> Result<()> {
+    let sample: Vec<u8> = (0..64u32).map(|i| (i.wrapping_mul(0x9E37_79B9) >> 24) as u8).collect();
+    let encrypted = enc.encrypt(&sample, "self_test", sample.len() as u32);
+    if super::decrypt_ng(&encrypted, enc.keys, "self_test", sample.len() as u32) != sample {
+        bail!("NG encryption tables failed to round-trip; the loaded keys look corrupt");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rpf_archive::crypto::decrypt_ng;
+
+    /// Keys with the structure of the real ones: every decrypt table maps its byte affinely
+    /// onto its own 8-bit slice of the output word, scrambled by an invertible linear map.
+    pub(crate) fn keys() -> GtaKeys {
+        let mut seed = 0x1234_5678u32;
+        let mut next = move || { seed ^= seed << 13; seed ^= seed >> 17; seed ^= seed << 5; seed };
+        let mut tables = Box::new([[[0u32; 256]; 16]; 17]);
+        for (round, table) in tables.iter_mut().enumerate() {
+            let groups = if (2..=15).contains(&round) { &ROUND_B } else { &ROUND_A };
+            for positions in groups {
+                for (k, &p) in positions.iter().enumerate() {
+                    let constant = next();
+                    for b in 0..256u32 {
+                        let v = (b ^ (b << 1) & 0xFF) << (8 * k);
+                        table[p][b as usize] = v ^ (v << 3) ^ constant;
+                    }
+                }
+            }
+        }
+        let ng_keys = (0..101).map(|_| (0..272 / 4).flat_map(|_| next().to_le_bytes()).collect()).collect();
+        GtaKeys { aes_key: [7; 32], ng_keys, ng_decrypt_tables: tables }
+    }
+
+    #[test]
+    fn encrypt_round_trips() {
+        let keys = keys();
+        let enc = NgEncryptor::new(&keys).unwrap();
+        self_test(&enc).unwrap();
+
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 31 + 7) as u8).collect();
+        let encrypted = enc.encrypt(&data, "x64/data/file.meta", data.len() as u32);
+        assert_ne!(encrypted[..992], data[..992]);
+        assert_eq!(encrypted[992..], data[992..], "a trailing partial block stays as-is");
+        assert_eq!(decrypt_ng(&encrypted, &keys, "x64/data/file.meta", data.len() as u32), data);
+    }
+
+    #[test]
+    fn rejects_singular_tables() {
+        let mut keys = keys();
+        keys.ng_decrypt_tables[3][5] = [0; 256];
+        assert!(NgEncryptor::new(&keys).is_err());
+    }
+}
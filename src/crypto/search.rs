@@ -0,0 +1,235 @@
+// Locating key material in GTA5.exe by SHA-1.
+//
+// The keys are stored verbatim in the executable, and all we know about them is their
+// SHA-1, so every candidate window has to be hashed. A first pass covers only the PE
+// initialized-data sections at 4-byte aligned offsets (the keys and tables are u32 arrays)
+// and skips windows that start with padding; any hash still missing after that is
+// searched for at every offset of the whole file. Both passes run in parallel chunks and
+// stop as soon as every hash has been found.
+
+use anyhow::{bail, Result};
+use rayon::prelude::*;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Alignment of the fast pass.
+const ALIGN: usize = 4;
+/// Window start offsets per parallel work item.
+const CHUNK: usize = 256 * 1024;
+
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+
+/// Find the `length`-byte window of `data` matching each of `hashes`, in the same order.
+/// `what` names the hashes in progress output and errors ("NG keys").
+pub fn find_all(data: &[u8], hashes: &[[u8; 20]], length: usize, what: &str) -> Result<Vec<Vec<u8>>> {
+    let mut found: Vec<Option<usize>> = vec![None; hashes.len()];
+
+    let whole = 0..data.len();
+    let passes = [
+        (pe_data_sections(data), ALIGN, true),
+        (vec![whole], 1, false),
+    ];
+    for (regions, step, filtered) in passes {
+        let mut targets: HashMap<[u8; 20], Vec<usize>> = HashMap::new();
+        for (i, hash) in hashes.iter().enumerate().filter(|(i, _)| found[*i].is_none()) {
+            targets.entry(*hash).or_default().push(i);
+        }
+        if targets.is_empty() { break; }
+
+        let scan = Scan { data, length, step, filtered, targets: &targets, what, total: hashes.len() };
+        for (i, offset) in scan.run(&regions, hashes.len() - targets.values().map(Vec::len).sum::<usize>()) {
+            if found[i].is_none_or(|o| offset < o) {
+                found[i] = Some(offset);
+            }
+        }
+    }
+
+    let missing: Vec<String> = found.iter().enumerate()
+        .filter(|(_, f)| f.is_none())
+        .map(|(i, _)| format!("#{} (sha1 {})", i, hex(&hashes[i])))
+        .collect();
+    if !missing.is_empty() {
+        bail!("{} of {} {} not found in GTA5.exe: {}", missing.len(), hashes.len(), what, missing.join(", "));
+    }
+
+    Ok(found.into_iter().flatten().map(|o| data[o..o + length].to_vec()).collect())
+}
+
+/// One pass over a set of regions for a set of target hashes.
+struct Scan<'a> {
+    data    : &'a [u8],
+    length  : usize,
+    step    : usize,
+    filtered: bool,
+    targets : &'a HashMap<[u8; 20], Vec<usize>>,
+    what    : &'a str,
+    total   : usize,
+}
+
+impl Scan<'_> {
+    /// Returns `(hash index, offset)` for every match. `already` is the number of hashes
+    /// found by earlier passes, for progress output.
+    fn run(&self, regions: &[Range<usize>], already: usize) -> Vec<(usize, usize)> {
+        let chunks = self.chunks(regions);
+        let seen: HashMap<usize, AtomicBool> = self.targets.values().flatten().map(|&i| (i, AtomicBool::new(false))).collect();
+        let remaining = AtomicUsize::new(seen.len());
+        let done = AtomicUsize::new(0);
+        let shown = AtomicUsize::new(usize::MAX);
+
+        let hits: Vec<(usize, usize)> = chunks.par_iter().flat_map_iter(|chunk| {
+            let mut hits = Vec::new();
+            if remaining.load(Ordering::Relaxed) == 0 { return hits; }
+
+            for offset in chunk.clone().step_by(self.step) {
+                let window = &self.data[offset..offset + self.length];
+                if self.filtered && is_padding(window) { continue; }
+
+                let hash: [u8; 20] = Sha1::digest(window).into();
+                if let Some(ids) = self.targets.get(&hash) {
+                    for &i in ids {
+                        if !seen[&i].swap(true, Ordering::Relaxed) {
+                            remaining.fetch_sub(1, Ordering::Relaxed);
+                        }
+                        hits.push((i, offset));
+                    }
+                }
+            }
+
+            let pct = (done.fetch_add(1, Ordering::Relaxed) + 1) * 100 / chunks.len();
+            if shown.swap(pct, Ordering::Relaxed) != pct {
+                let found = already + seen.len() - remaining.load(Ordering::Relaxed);
+                eprint!("\r[Keys] {}: {}/{} found, {:>3}% scanned", self.what, found, self.total, pct);
+                io::stderr().flush().ok();
+            }
+            hits
+        }).collect();
+
+        let found = already + seen.len() - remaining.into_inner();
+        eprintln!("\r[Keys] {}: {}/{} found{:20}", self.what, found, self.total, "");
+        hits
+    }
+
+    /// Split the window start offsets of `regions` into aligned work items.
+    fn chunks(&self, regions: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut chunks = Vec::new();
+        for region in regions {
+            if region.end < region.start + self.length { continue; }
+            let last = region.end - self.length;
+            let mut start = region.start.next_multiple_of(self.step);
+            while start <= last {
+                let end = (start + CHUNK).min(last + 1);
+                chunks.push(start..end);
+                start = end.next_multiple_of(self.step);
+            }
+        }
+        chunks
+    }
+}
+
+/// Cheap pre-filter: key material is high-entropy, so a window whose first words are
+/// zero or all ones is padding and not worth hashing.
+fn is_padding(window: &[u8]) -> bool {
+    window.chunks_exact(4).take(4).any(|w| w == [0; 4] || w == [0xFF; 4])
+}
+
+/// File ranges of the initialized-data sections of a PE image, or the whole file if
+/// `data` is not a PE.
+fn pe_data_sections(data: &[u8]) -> Vec<Range<usize>> {
+    let u16_at = |o: usize| data.get(o..o + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let u32_at = |o: usize| data.get(o..o + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    let sections = || -> Option<Vec<Range<usize>>> {
+        if data.get(0..2)? != b"MZ" { return None; }
+        let pe = u32_at(0x3C)? as usize;
+        if data.get(pe..pe + 4)? != b"PE\0\0" { return None; }
+        let count = u16_at(pe + 6)?;
+        let table = pe + 24 + u16_at(pe + 20)?;
+
+        let mut ranges = Vec::new();
+        for s in (0..count).map(|i| table + i * 40) {
+            let (size, start, flags) = (u32_at(s + 16)? as usize, u32_at(s + 20)? as usize, u32_at(s + 36)?);
+            if flags & IMAGE_SCN_CNT_INITIALIZED_DATA != 0 && start < data.len() {
+                ranges.push(start..(start + size).min(data.len()));
+            }
+        }
+        Some(ranges)
+    };
+
+    let whole = 0..data.len();
+    sections().filter(|r| !r.is_empty()).unwrap_or_else(|| vec![whole])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal PE with a code section at 0x200 and an initialized-data section at 0x400.
+    fn pe() -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+        data[0..2].copy_from_slice(b"MZ");
+        data[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+        data[0x86..0x88].copy_from_slice(&2u16.to_le_bytes());
+        let table = 0x80 + 24;
+        for (i, (start, size, flags)) in [(0x200u32, 0x200u32, 0x20u32), (0x400, 0x400, 0x40)].into_iter().enumerate() {
+            let s = table + i * 40;
+            data[s + 16..s + 20].copy_from_slice(&size.to_le_bytes());
+            data[s + 20..s + 24].copy_from_slice(&start.to_le_bytes());
+            data[s + 36..s + 40].copy_from_slice(&flags.to_le_bytes());
+        }
+        data
+    }
+
+    fn key(seed: u8) -> Vec<u8> {
+        (0..32u8).map(|i| i.wrapping_mul(37).wrapping_add(seed) | 1).collect()
+    }
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        Sha1::digest(data).into()
+    }
+
+    fn sections(data: &[u8]) -> Vec<(usize, usize)> {
+        pe_data_sections(data).into_iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn finds_initialized_data_sections() {
+        assert_eq!(sections(&pe()), [(0x400, 0x800)]);
+        assert_eq!(sections(&[1; 100]), [(0, 100)], "not a PE");
+
+        let mut truncated = pe();
+        truncated.truncate(0x600);
+        assert_eq!(sections(&truncated), [(0x400, 0x600)]);
+    }
+
+    #[test]
+    fn skips_padding() {
+        assert!(is_padding(&[0; 32]));
+        assert!(is_padding(&[[1, 2, 3, 4], [0xFF; 4], [5, 6, 7, 8], [9; 4]].concat()));
+        assert!(!is_padding(&key(1)));
+        assert!(!is_padding(&[[1, 2, 3, 4], [5; 4], [6; 4], [7; 4], [0; 4]].concat()), "only the first words count");
+    }
+
+    #[test]
+    fn finds_keys_in_data_sections_and_elsewhere() {
+        let mut data = pe();
+        data[0x410..0x430].copy_from_slice(&key(1));
+        // Unaligned and outside the data section: only the full scan finds it.
+        data[0x233..0x253].copy_from_slice(&key(2));
+
+        let found = find_all(&data, &[sha1(&key(2)), sha1(&key(1))], 32, "test keys").unwrap();
+        assert_eq!(found, [key(2), key(1)]);
+
+        let err = find_all(&data, &[sha1(&key(1)), sha1(&key(3))], 32, "test keys").unwrap_err();
+        assert!(err.to_string().starts_with("1 of 2 test keys not found"), "{}", err);
+    }
+}
@@ -0,0 +1,377 @@
+// Drawables (.ydr), drawable dictionaries (.ydd) and fragments (.yft): models, skeletons and
+// shaders.
+//
+// A drawable points at up to four lists of models, one per LOD (high, medium, low, very low).
+// A model is a set of geometries, each drawn with one shader of the drawable's shader group,
+// and is either skinned to the skeleton or rigidly attached to one of its bones. A geometry
+// has a vertex buffer, whose declaration gives a mask of the components present and a 4-bit
+// type per component, and a list of 16-bit triangle indices. Offsets follow CodeWalker's
+// `Drawable`, `DrawableModel`, `DrawableGeometry`, `VertexBuffer`, `ShaderFX` and `Skeleton`.
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::{f32_at, u16_at, u32_at, u64_at, Resource, SYSTEM_BASE};
+use crate::texture::{self, Texture};
+
+pub const LOD_NAMES: [&str; 4] = ["high", "medium", "low", "verylow"];
+
+const DRAWABLE_SIZE: usize = 0xB0;
+const MODEL_SIZE: usize = 0x30;
+const GEOMETRY_SIZE: usize = 0x98;
+const VERTEX_BUFFER_SIZE: usize = 0x38;
+const SHADER_SIZE: usize = 0x30;
+const SKELETON_SIZE: usize = 0x70;
+const BONE_SIZE: usize = 0x50;
+
+/// Vertex components, by bit of the declaration mask.
+mod component {
+    pub const POSITION: usize = 0;
+    pub const BLEND_WEIGHTS: usize = 1;
+    pub const BLEND_INDICES: usize = 2;
+    pub const NORMAL: usize = 3;
+    pub const COLOUR0: usize = 4;
+    pub const TEXCOORD0: usize = 6;
+    pub const TEXCOORD1: usize = 7;
+}
+
+pub struct Drawable {
+    /// The stored name, or a label given by the caller when there is none.
+    pub name    : String,
+    /// Models per LOD, in `LOD_NAMES` order.
+    pub lods    : [Vec<Model>; 4],
+    pub shaders : Vec<Shader>,
+    pub bones   : Vec<Bone>,
+    /// The dictionary embedded in the shader group, if any.
+    pub textures: Vec<Texture>,
+}
+
+pub struct Model {
+    pub skinned   : bool,
+    /// Bone the model moves with when it is not skinned.
+    pub bone      : u8,
+    pub geometries: Vec<Geometry>,
+}
+
+pub struct Geometry {
+    /// Index into the drawable's shaders.
+    pub shader  : u16,
+    pub vertices: Vertices,
+    pub indices : Vec<u16>,
+    /// Skeleton bone of each blend index, when the geometry remaps them.
+    pub bone_ids: Vec<u16>,
+}
+
+/// The decoded components of a vertex buffer; absent components are empty.
+#[derive(Default)]
+pub struct Vertices {
+    pub positions: Vec<[f32; 3]>,
+    pub normals  : Vec<[f32; 3]>,
+    pub colours  : Vec<[u8; 4]>,
+    /// First and second UV sets.
+    pub uvs      : [Vec<[f32; 2]>; 2],
+    pub weights  : Vec<[u8; 4]>,
+    pub indices  : Vec<[u8; 4]>,
+}
+
+pub struct Shader {
+    pub name    : u32,
+    /// `(parameter hash, texture name)` of every texture parameter.
+    pub textures: Vec<(u32, String)>,
+}
+
+pub struct Bone {
+    pub name       : String,
+    pub tag        : u16,
+    pub parent     : Option<usize>,
+    /// Quaternion, x y z w.
+    pub rotation   : [f32; 4],
+    pub translation: [f32; 3],
+    pub scale      : [f32; 3],
+}
+
+impl Drawable {
+    pub fn triangle_count(&self, lod: usize) -> usize {
+        self.lods[lod].iter().flat_map(|m| &m.geometries).map(|g| g.indices.len() / 3).sum()
+    }
+}
+
+/// Every drawable in a resource, by file extension: the resource itself for a .ydr, the
+/// main drawable of a .yft, and each entry of a .ydd.
+pub fn read_all(res: &Resource, extension: &str) -> Result<Vec<Drawable>> {
+    match extension {
+        "ydr" => Ok(vec![read_drawable(res, SYSTEM_BASE, "")?]),
+        "yft" => {
+            // FragType: the main drawable at 0x30.
+            let root = res.slice(SYSTEM_BASE, 0x38).context("fragment root out of bounds")?;
+            match u64_at(root, 0x30) {
+                0  => bail!("fragment has no drawable"),
+                va => Ok(vec![read_drawable(res, va, "")?]),
+            }
+        }
+        "ydd" => {
+            // DrawableDictionary: hash and drawable pointer lists laid out as in a .ytd.
+            let root = res.slice(SYSTEM_BASE, 0x40).context("drawable dictionary root out of bounds")?;
+            let (hash_ptr, hash_count) = (u64_at(root, 0x20), u16_at(root, 0x28) as usize);
+            let (list_ptr, count) = (u64_at(root, 0x30), u16_at(root, 0x38) as usize);
+            let hashes = res.slice(hash_ptr, hash_count * 4);
+            let pointers = res.slice(list_ptr, count * 8)
+                .with_context(|| format!("drawable pointer list out of bounds (0x{:X})", list_ptr))?;
+            (0..count).map(|i| {
+                let hash = hashes.filter(|_| i < hash_count).map(|h| u32_at(h, i * 4)).unwrap_or(0);
+                let label = format!("0x{:08X}", hash);
+                read_drawable(res, u64_at(pointers, i * 8), &label).with_context(|| label.clone())
+            }).collect()
+        }
+        other => bail!("'.{}' files do not hold drawables", other),
+    }
+}
+
+fn read_drawable(res: &Resource, va: u64, label: &str) -> Result<Drawable> {
+    let d = res.slice(va, DRAWABLE_SIZE)
+        .with_context(|| format!("drawable out of bounds (0x{:X})", va))?;
+
+    let name = match u64_at(d, 0xA8) {
+        0   => None,
+        ptr => res.c_str(ptr).filter(|n| !n.is_empty()),
+    };
+    let mut lods: [Vec<Model>; 4] = Default::default();
+    for (i, lod) in lods.iter_mut().enumerate() {
+        let ptr = u64_at(d, 0x50 + i * 8);
+        if ptr != 0 {
+            *lod = read_models(res, ptr).with_context(|| format!("{} LOD", LOD_NAMES[i]))?;
+        }
+    }
+
+    let (shaders, textures) = match u64_at(d, 0x10) {
+        0   => (Vec::new(), Vec::new()),
+        ptr => read_shader_group(res, ptr).context("shader group")?,
+    };
+    let bones = match u64_at(d, 0x18) {
+        0   => Vec::new(),
+        ptr => read_skeleton(res, ptr).context("skeleton")?,
+    };
+
+    Ok(Drawable { name: name.unwrap_or_else(|| label.to_string()), lods, shaders, bones, textures })
+}
+
+/// The blocks listed by a `ResourcePointerList64` at `va`: the array pointer, then its count.
+fn pointer_list(res: &Resource, va: u64) -> Result<Vec<u64>> {
+    let list = res.slice(va, 0x10).with_context(|| format!("pointer list out of bounds (0x{:X})", va))?;
+    let (ptr, count) = (u64_at(list, 0), u16_at(list, 8) as usize);
+    let array = res.slice(ptr, count * 8)
+        .with_context(|| format!("pointer array out of bounds (0x{:X}, {} entries)", ptr, count))?;
+    Ok((0..count).map(|i| u64_at(array, i * 8)).collect())
+}
+
+fn read_models(res: &Resource, va: u64) -> Result<Vec<Model>> {
+    pointer_list(res, va)?.into_iter().enumerate().map(|(i, ptr)| {
+        read_model(res, ptr).with_context(|| format!("model {}", i))
+    }).collect()
+}
+
+fn read_model(res: &Resource, va: u64) -> Result<Model> {
+    let m = res.slice(va, MODEL_SIZE).with_context(|| format!("model out of bounds (0x{:X})", va))?;
+    let (geoms_ptr, count) = (u64_at(m, 0x08), u16_at(m, 0x10) as usize);
+    let geoms = res.slice(geoms_ptr, count * 8)
+        .with_context(|| format!("geometry list out of bounds (0x{:X})", geoms_ptr))?;
+    let mapping = res.slice(u64_at(m, 0x20), count * 2);
+    let binding = u32_at(m, 0x28);
+
+    let geometries = (0..count).map(|i| {
+        let shader = mapping.map(|s| u16_at(s, i * 2)).unwrap_or(0);
+        read_geometry(res, u64_at(geoms, i * 8), shader).with_context(|| format!("geometry {}", i))
+    }).collect::<Result<_>>()?;
+    Ok(Model { skinned: binding >> 8 & 0xFF != 0, bone: (binding >> 24) as u8, geometries })
+}
+
+fn read_geometry(res: &Resource, va: u64, shader: u16) -> Result<Geometry> {
+    let g = res.slice(va, GEOMETRY_SIZE).with_context(|| format!("geometry out of bounds (0x{:X})", va))?;
+    let vertices = read_vertices(res, u64_at(g, 0x18))?;
+
+    // IndexBuffer: count at 0x08, 16-bit indices at 0x10.
+    let ib_ptr = u64_at(g, 0x38);
+    let ib = res.slice(ib_ptr, 0x18).with_context(|| format!("index buffer out of bounds (0x{:X})", ib_ptr))?;
+    let (count, data_ptr) = (u32_at(ib, 0x08) as usize, u64_at(ib, 0x10));
+    let data = res.slice(data_ptr, count * 2)
+        .with_context(|| format!("indices out of bounds (0x{:X}, {} indices)", data_ptr, count))?;
+    let indices: Vec<u16> = data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+    if let Some(&i) = indices.iter().find(|&&i| i as usize >= vertices.positions.len()) {
+        bail!("index {} is past the {} vertices", i, vertices.positions.len());
+    }
+
+    let bone_count = u16_at(g, 0x7A) as usize;
+    let bone_ids = match res.slice(u64_at(g, 0x70), bone_count * 2) {
+        Some(ids) => ids.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect(),
+        None      => Vec::new(),
+    };
+    Ok(Geometry { shader, vertices, indices, bone_ids })
+}
+
+// ─── Vertices ────────────────────────────────────────────────────────────────
+
+/// Bytes per component type: half x1..x4, float x1..x4, ubyte4, colour, signed ubyte4.
+const COMPONENT_SIZES: [usize; 11] = [2, 4, 6, 8, 4, 8, 12, 16, 4, 4, 4];
+
+fn read_vertices(res: &Resource, va: u64) -> Result<Vertices> {
+    let vb = res.slice(va, VERTEX_BUFFER_SIZE)
+        .with_context(|| format!("vertex buffer out of bounds (0x{:X})", va))?;
+    let (stride, count, data_ptr, decl_ptr) = (u16_at(vb, 0x08) as usize, u32_at(vb, 0x18) as usize, u64_at(vb, 0x10), u64_at(vb, 0x30));
+    let decl = res.slice(decl_ptr, 0x10)
+        .with_context(|| format!("vertex declaration out of bounds (0x{:X})", decl_ptr))?;
+    let (mask, types) = (u32_at(decl, 0), u64_at(decl, 0x08));
+    let data = res.slice(data_ptr, stride * count)
+        .with_context(|| format!("vertex data out of bounds (0x{:X}, {} x {} bytes)", data_ptr, count, stride))?;
+
+    // Offset and type of every component present.
+    let mut layout = [None; 16];
+    let mut offset = 0;
+    for (i, slot) in layout.iter_mut().enumerate() {
+        if mask >> i & 1 == 0 { continue; }
+        let kind = (types >> (i * 4) & 0xF) as usize;
+        let size = *COMPONENT_SIZES.get(kind)
+            .with_context(|| format!("vertex component {} has unsupported type {}", i, kind))?;
+        *slot = Some((offset, kind));
+        offset += size;
+    }
+    if offset > stride {
+        bail!("vertex declaration needs {} bytes, the buffer stride is {}", offset, stride);
+    }
+    if layout[component::POSITION].is_none_or(|(_, kind)| kind < 2) {
+        bail!("vertices have no 3D position");
+    }
+
+    let read = |c: usize| -> Option<Vec<[f32; 4]>> {
+        let (off, kind) = layout[c]?;
+        Some(data.chunks_exact(stride).map(|v| component(&v[off..], kind)).collect())
+    };
+    let bytes = |c: usize| -> Option<Vec<[u8; 4]>> {
+        let (off, _) = layout[c]?;
+        Some(data.chunks_exact(stride).map(|v| v[off..off + 4].try_into().unwrap()).collect())
+    };
+    let xyz = |v: Vec<[f32; 4]>| v.into_iter().map(|c| [c[0], c[1], c[2]]).collect();
+    let uv = |v: Vec<[f32; 4]>| v.into_iter().map(|c| [c[0], c[1]]).collect();
+
+    let colours = match layout[component::COLOUR0] {
+        Some((_, 9)) => bytes(component::COLOUR0).unwrap_or_default(),
+        Some(_) => read(component::COLOUR0).unwrap_or_default().into_iter()
+            .map(|c| c.map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8)).collect(),
+        None => Vec::new(),
+    };
+    let skinned = layout[component::BLEND_WEIGHTS].zip(layout[component::BLEND_INDICES])
+        .is_some_and(|(w, i)| w.1 >= 8 && i.1 >= 8);
+
+    Ok(Vertices {
+        positions: xyz(read(component::POSITION).unwrap_or_default()),
+        normals  : read(component::NORMAL).map(xyz).unwrap_or_default(),
+        colours,
+        uvs      : [read(component::TEXCOORD0).map(uv).unwrap_or_default(), read(component::TEXCOORD1).map(uv).unwrap_or_default()],
+        weights  : if skinned { bytes(component::BLEND_WEIGHTS).unwrap_or_default() } else { Vec::new() },
+        indices  : if skinned { bytes(component::BLEND_INDICES).unwrap_or_default() } else { Vec::new() },
+    })
+}
+
+/// One vertex component as up to four floats; bytes are normalised except for `ubyte4`.
+fn component(data: &[u8], kind: usize) -> [f32; 4] {
+    std::array::from_fn(|i| match kind {
+        0..=3 if i <= kind     => half(u16_at(data, i * 2)),
+        4..=7 if i + 4 <= kind => f32_at(data, i * 4),
+        0..=7 => 0.0,
+        8     => data[i] as f32,
+        9     => data[i] as f32 / 255.0,
+        _     => (data[i] as i8 as f32 / 127.0).max(-1.0),
+    })
+}
+
+fn half(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let (exp, mant) = ((bits >> 10 & 0x1F) as i32, (bits & 0x3FF) as f32);
+    sign * match exp {
+        0  => mant * 2f32.powi(-24),
+        31 => if mant == 0.0 { f32::INFINITY } else { f32::NAN },
+        _  => (1.0 + mant / 1024.0) * 2f32.powi(exp - 15),
+    }
+}
+
+// ─── Shaders and skeleton ────────────────────────────────────────────────────
+
+/// Shaders and the embedded texture dictionary of a shader group: the dictionary at 0x08,
+/// the shader pointer list at 0x10.
+fn read_shader_group(res: &Resource, va: u64) -> Result<(Vec<Shader>, Vec<Texture>)> {
+    let group = res.slice(va, 0x20).with_context(|| format!("shader group out of bounds (0x{:X})", va))?;
+    let textures = match u64_at(group, 0x08) {
+        0   => Vec::new(),
+        ptr => texture::read_dictionary_at(res, ptr).context("embedded texture dictionary")?,
+    };
+    let shaders = pointer_list(res, va + 0x10)?.into_iter().enumerate().map(|(i, ptr)| {
+        read_shader(res, ptr).with_context(|| format!("shader {}", i))
+    }).collect::<Result<_>>()?;
+    Ok((shaders, textures))
+}
+
+/// A shader's parameters are 16-byte records (type, register, data pointer); type 0 is a
+/// texture, n > 0 an array of n vectors stored right after the records. The parameter
+/// name hashes follow the vector data.
+fn read_shader(res: &Resource, va: u64) -> Result<Shader> {
+    let s = res.slice(va, SHADER_SIZE).with_context(|| format!("shader out of bounds (0x{:X})", va))?;
+    let (params_ptr, name, count) = (u64_at(s, 0x00), u32_at(s, 0x08), s[0x10] as usize);
+    let params = res.slice(params_ptr, count * 16)
+        .with_context(|| format!("shader parameters out of bounds (0x{:X})", params_ptr))?;
+    let vectors: usize = params.chunks_exact(16).map(|p| p[0] as usize * 16).sum();
+    let hashes = res.slice(params_ptr + (count * 16 + vectors) as u64, count * 4)
+        .context("shader parameter names out of bounds")?;
+
+    let textures = params.chunks_exact(16).enumerate()
+        .filter(|(_, p)| p[0] == 0 && u64_at(p, 8) != 0)
+        .filter_map(|(i, p)| {
+            let tex = res.slice(u64_at(p, 8), 0x30)?;
+            Some((u32_at(hashes, i * 4), res.c_str(u64_at(tex, 0x28))?))
+        })
+        .collect();
+    Ok(Shader { name, textures })
+}
+
+fn read_skeleton(res: &Resource, va: u64) -> Result<Vec<Bone>> {
+    let s = res.slice(va, SKELETON_SIZE).with_context(|| format!("skeleton out of bounds (0x{:X})", va))?;
+    let (bones_ptr, count) = (u64_at(s, 0x20), u16_at(s, 0x5E) as usize);
+    let data = res.slice(bones_ptr, count * BONE_SIZE)
+        .with_context(|| format!("bones out of bounds (0x{:X}, {} bones)", bones_ptr, count))?;
+
+    Ok(data.chunks_exact(BONE_SIZE).enumerate().map(|(i, b)| {
+        let f = |off: usize| f32_at(b, off);
+        let parent = u16_at(b, 0x32) as i16;
+        Bone {
+            name       : res.c_str(u64_at(b, 0x38)).filter(|n| !n.is_empty()).unwrap_or_else(|| format!("bone_{}", i)),
+            tag        : u16_at(b, 0x44),
+            parent     : usize::try_from(parent).ok().filter(|&p| p < i),
+            rotation   : [f(0x00), f(0x04), f(0x08), f(0x0C)],
+            translation: [f(0x10), f(0x14), f(0x18)],
+            scale      : [f(0x20), f(0x24), f(0x28)],
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_half_floats() {
+        assert_eq!(half(0x3C00), 1.0);
+        assert_eq!(half(0xC000), -2.0);
+        assert_eq!(half(0x3800), 0.5);
+        assert_eq!(half(0x0001), 2f32.powi(-24));
+        assert_eq!(half(0x7C00), f32::INFINITY);
+        assert!(half(0x7E00).is_nan());
+    }
+
+    #[test]
+    fn decodes_vertex_components() {
+        let halves: Vec<u8> = [0x3C00u16, 0xC000].iter().flat_map(|h| h.to_le_bytes()).collect();
+        assert_eq!(component(&halves, 1), [1.0, -2.0, 0.0, 0.0]);
+        let floats: Vec<u8> = [1.5f32, 2.0, -3.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+        assert_eq!(component(&floats, 6), [1.5, 2.0, -3.0, 0.0]);
+        assert_eq!(component(&[0, 1, 2, 255], 8), [0.0, 1.0, 2.0, 255.0]);
+        assert_eq!(component(&[0, 51, 255, 255], 9), [0.0, 0.2, 1.0, 1.0]);
+        assert_eq!(component(&[127, 0, 0x81, 0x80], 10), [1.0, 0.0, -1.0, -1.0]);
+    }
+}
@@ -0,0 +1,962 @@
+// In-place editing of RPF7 archives.
+//
+// The editor loads the TOC of an existing archive into a mutable tree, applies add /
+// replace / remove / rename operations, and then produces an `EditPlan`: the new data
+// chunks and a freshly encoded (and re-encrypted) TOC. Untouched entries keep their
+// bytes and offsets, new data goes into free gaps or is appended, and the header is
+// always written last so an interrupted edit leaves the old TOC pointing at intact data.
+//
+// Paths may run through nested `.rpf` files (`x64/dlc.rpf/x64/data/foo.meta`). Such edits
+// are queued per nested archive and replayed on a child editor at commit time; the rebuilt
+// inner archive then replaces the nested file, so every level keeps its own encryption and
+// is re-encrypted under its own name and new length.
+
+use anyhow::{bail, Context, Result};
+use flate2::{write::DeflateEncoder, Compression};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    io::{Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    rc::Rc,
+};
+
+use crate::crypto::{decrypt_aes, decrypt_ng, encrypt_aes, ng::{self, NgEncryptor}};
+use crate::rpf::{Archive, GtaKeys, RpfEncryption, RpfEntryKind, RpfVersion};
+
+const BLOCK: u64 = 512;
+const RPF7_MAGIC: u32 = 0x52504637;
+const RSC7_MAGIC: u32 = 0x37435352;
+
+// ─── Tree model ──────────────────────────────────────────────────────────────
+
+struct Node {
+    name: String,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    Dir(Vec<Node>),
+    File(FileNode),
+}
+
+struct FileNode {
+    entry: FileEntry,
+    data : Data,
+}
+
+#[derive(Clone, Copy)]
+enum FileEntry {
+    Binary   { file_size: u32, uncompressed_size: u32, is_encrypted: bool },
+    /// `file_size` is the real size; sizes above 0xFFFFFF are encoded in the on-disk header.
+    Resource { file_size: u32, system_flags: u32, graphics_flags: u32 },
+}
+
+enum Data {
+    /// Still lives at this 512-byte block in the source archive.
+    Original(u32),
+    /// New on-disk bytes (already compressed and encrypted) waiting for a slot.
+    Pending(Vec<u8>),
+}
+
+impl FileEntry {
+    fn disk_size(self) -> u64 {
+        match self {
+            Self::Binary { file_size, uncompressed_size, .. } =>
+                if file_size > 0 { file_size as u64 } else { uncompressed_size as u64 },
+            Self::Resource { file_size, .. } => file_size as u64,
+        }
+    }
+}
+
+fn blocks(len: u64) -> u64 {
+    len.div_ceil(BLOCK)
+}
+
+fn lower(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// An edit queued for a nested archive, replayed when the parent commits.
+enum Op {
+    Add(String, Vec<u8>),
+    Replace(String, Vec<u8>),
+    Remove(String),
+    Rename(String, String),
+}
+
+fn split_path(path: &str) -> Result<Vec<&str>> {
+    let parts: Vec<&str> = path.split(['/', '\\']).filter(|s| !s.is_empty()).collect();
+    if parts.is_empty() { bail!("empty archive path"); }
+    Ok(parts)
+}
+
+// ─── Editor ──────────────────────────────────────────────────────────────────
+
+pub struct RpfEditor<'a> {
+    name        : String,
+    archive     : &'a Archive,
+    source      : &'a [u8],
+    tag         : u32,
+    encryption  : RpfEncryption,
+    keys        : Option<&'a GtaKeys>,
+    ng          : Option<Rc<NgEncryptor<'a>>>,
+    root_name   : String,
+    root        : Vec<Node>,
+    /// Block ranges of data that the new TOC no longer references. They stay reserved
+    /// until the header is rewritten so the old TOC remains valid during the write.
+    retired     : Vec<Range<u64>>,
+    header_end  : u64,
+    /// Edits for nested archives, keyed by the lower-cased path of the `.rpf` file.
+    nested      : BTreeMap<String, Vec<Op>>,
+}
+
+impl<'a> RpfEditor<'a> {
+    pub fn new(archive: &'a Archive, keys: Option<&'a GtaKeys>) -> Result<Self> {
+        Self::with_ng(archive, keys, None)
+    }
+
+    /// Nested editors share their parent's NG tables instead of deriving them again.
+    fn with_ng(archive: &'a Archive, keys: Option<&'a GtaKeys>, ng: Option<Rc<NgEncryptor<'a>>>) -> Result<Self> {
+        if archive.version() != RpfVersion::V7 {
+            bail!("in-place editing is only supported for RPF7 archives");
+        }
+
+        let source = archive.bytes();
+        let tag = u32::from_le_bytes(source[12..16].try_into().unwrap());
+        let entry_count = u32::from_le_bytes(source[4..8].try_into().unwrap()) as u64;
+        let names_length = u32::from_le_bytes(source[8..12].try_into().unwrap()) as u64;
+
+        let encryption = archive.encryption;
+        let ng = match (encryption, ng) {
+            (RpfEncryption::Ng, Some(ng)) => Some(ng),
+            (RpfEncryption::Ng, None) => {
+                let k = keys.context("NG-encrypted archive requires --keys to edit")?;
+                let enc = NgEncryptor::new(k)?;
+                ng::self_test(&enc)?;
+                Some(Rc::new(enc))
+            }
+            (RpfEncryption::Aes, _) => { keys.context("AES-encrypted archive requires --keys to edit")?; None }
+            (_, ng) => ng,
+        };
+
+        let entries = archive.entries();
+        let (root_name, root) = match entries.first().map(|e| &e.kind) {
+            Some(RpfEntryKind::Directory { entries_index, entries_count }) => {
+                (entries[0].name.clone(), load_dir(entries, *entries_index, *entries_count, 0)?)
+            }
+            _ => bail!("archive has no root directory"),
+        };
+
+        Ok(Self {
+            name: archive.path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string(),
+            archive, source, tag, encryption, keys, ng, root_name, root,
+            retired: Vec::new(),
+            header_end: blocks(16 + entry_count * 16 + names_length),
+            nested: BTreeMap::new(),
+        })
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        let Ok(parts) = split_path(path) else { return false };
+        match self.route(&parts) {
+            Some((file, inner)) => self.archive.find_file(&file)
+                .and_then(|f| self.archive.open_nested(f, self.keys).ok())
+                .and_then(|a| RpfEditor::with_ng(&a, self.keys, self.ng.clone()).ok().map(|ed| ed.exists(&inner)))
+                .unwrap_or(false),
+            None => find(&self.root, &parts).is_some(),
+        }
+    }
+
+    /// Add a new file; RSC7 data becomes a resource entry, anything else a binary entry.
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        let parts = split_path(path)?;
+        if let Some((file, inner)) = self.route(&parts) {
+            return self.queue(file, Op::Add(inner, data));
+        }
+        let (name, dirs) = parts.split_last().unwrap();
+        if find(&self.root, &parts).is_some() { bail!("'{}' already exists", path); }
+
+        let is_resource = data.len() >= 16 && u32::from_le_bytes(data[..4].try_into().unwrap()) == RSC7_MAGIC;
+        let node = if is_resource {
+            self.encode_resource(name, data)?
+        } else {
+            let encrypt = self.encryption.is_encrypted() && !lower(name).ends_with(".rpf");
+            self.encode_binary(name, data, encrypt)?
+        };
+
+        let children = dir_mut(&mut self.root, dirs, true)?;
+        insert_sorted(children, Node { name: name.to_string(), kind: NodeKind::File(node) });
+        Ok(())
+    }
+
+    /// Replace the contents of an existing file, keeping its entry kind and encryption flag.
+    pub fn replace_file(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        let parts = split_path(path)?;
+        if let Some((file, inner)) = self.route(&parts) {
+            return self.queue(file, Op::Replace(inner, data));
+        }
+        let (name, dirs) = parts.split_last().unwrap();
+
+        let old = match find(&self.root, &parts).map(|n| &n.kind) {
+            Some(NodeKind::File(f)) => f.entry,
+            Some(NodeKind::Dir(_))  => bail!("'{}' is a directory", path),
+            None                    => bail!("'{}' not found in archive", path),
+        };
+        let stored_name = find(&self.root, &parts).unwrap().name.clone();
+
+        let node = match old {
+            FileEntry::Resource { .. } => {
+                if data.len() < 16 || u32::from_le_bytes(data[..4].try_into().unwrap()) != RSC7_MAGIC {
+                    bail!("'{}' is a resource entry; the replacement must be an RSC7 file", path);
+                }
+                self.encode_resource(&stored_name, data)?
+            }
+            FileEntry::Binary { is_encrypted, .. } => self.encode_binary(&stored_name, data, is_encrypted)?,
+        };
+
+        let children = dir_mut(&mut self.root, dirs, false)?;
+        let idx = position(children, name).unwrap();
+        if let NodeKind::File(f) = &mut children[idx].kind {
+            let previous = std::mem::replace(f, node);
+            self.retire(&previous);
+        }
+        Ok(())
+    }
+
+    /// Remove a file or a whole directory.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let parts = split_path(path)?;
+        if let Some((file, inner)) = self.route(&parts) {
+            return self.queue(file, Op::Remove(inner));
+        }
+        let (name, dirs) = parts.split_last().unwrap();
+        let children = dir_mut(&mut self.root, dirs, false)
+            .with_context(|| format!("'{}' not found in archive", path))?;
+        let idx = position(children, name).with_context(|| format!("'{}' not found in archive", path))?;
+        let node = children.remove(idx);
+        self.retire_node(&node);
+        Ok(())
+    }
+
+    /// Move or rename a file or directory. Encrypted data whose key depends on the
+    /// file name (NG binaries, `.ysc` scripts) is re-encrypted under the new name.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        let src = split_path(from)?;
+        let dst = split_path(to)?;
+        match (self.route(&src), self.route(&dst)) {
+            (Some((a, from)), Some((b, to))) if a == b => return self.queue(a, Op::Rename(from, to)),
+            (None, None) => {}
+            _ => bail!("cannot move '{}' to '{}': both paths must be in the same archive", from, to),
+        }
+        let (src_name, src_dirs) = src.split_last().unwrap();
+        let (dst_name, dst_dirs) = dst.split_last().unwrap();
+
+        if find(&self.root, &src).is_none() { bail!("'{}' not found in archive", from); }
+        if find(&self.root, &dst).is_some() { bail!("'{}' already exists", to); }
+        if dst.len() > src.len() && dst[..src.len()].iter().zip(&src).all(|(a, b)| lower(a) == lower(b)) {
+            bail!("cannot move '{}' into itself", from);
+        }
+
+        let children = dir_mut(&mut self.root, src_dirs, false)?;
+        let idx = position(children, src_name).unwrap();
+        let mut node = children.remove(idx);
+
+        if let NodeKind::File(f) = &mut node.kind
+            && let Some(data) = self.transcode_for_rename(f, &node.name, dst_name)? {
+            let previous = std::mem::replace(&mut f.data, Data::Pending(data));
+            self.retire(&FileNode { entry: f.entry, data: previous });
+        }
+        node.name = dst_name.to_string();
+
+        let children = dir_mut(&mut self.root, dst_dirs, true)?;
+        insert_sorted(children, node);
+        Ok(())
+    }
+
+    /// Lay out the edited archive and encode its TOC.
+    pub fn commit(mut self) -> Result<EditPlan> {
+        // Nested archives are rebuilt first (recursively); each becomes a replacement of its `.rpf` file.
+        for (file, ops) in std::mem::take(&mut self.nested) {
+            let bytes = self.rebuild_nested(&file, ops).with_context(|| format!("while editing {}", file))?;
+            self.replace_file(&file, bytes)?;
+        }
+
+        // Flatten in the same order `RpfBuilder` uses: a directory's children are
+        // contiguous, and sub-directories are expanded after their siblings.
+        let mut flat: Vec<FlatEntry> = vec![FlatEntry { name: self.root_name.clone(), kind: FlatKind::Dir { index: 0, count: 0 } }];
+        let root = std::mem::take(&mut self.root);
+        let mut files: Vec<FileNode> = Vec::new();
+        flatten(root, 0, &mut flat, &mut files);
+
+        // Names table, deduplicated and padded to the AES block size.
+        let mut names = Vec::<u8>::new();
+        let mut name_offsets = HashMap::<String, u32>::new();
+        let mut offsets = Vec::with_capacity(flat.len());
+        for e in &flat {
+            let off = *name_offsets.entry(e.name.clone()).or_insert_with(|| {
+                let o = names.len() as u32;
+                names.extend_from_slice(e.name.as_bytes());
+                names.push(0);
+                o
+            });
+            if off > 0xFFFF && !matches!(e.kind, FlatKind::Dir { .. }) {
+                bail!("names table exceeds 64 KB; too many entries for one RPF7 archive");
+            }
+            offsets.push(off);
+        }
+        names.resize(names.len().next_multiple_of(16), 0);
+
+        let header_blocks = blocks(16 + flat.len() as u64 * 16 + names.len() as u64);
+
+        // Data the new header would overlap has to move before anything else.
+        for f in files.iter_mut() {
+            if let Data::Original(off) = f.data
+                && (off as u64) < header_blocks && f.entry.disk_size() > 0 {
+                let bytes = self.original_bytes(off, f.entry)?.to_vec();
+                self.retired.push(off as u64..off as u64 + blocks(f.entry.disk_size()));
+                f.data = Data::Pending(bytes);
+            }
+        }
+
+        let mut used: Vec<Range<u64>> = self.retired.clone();
+        used.push(0..header_blocks.max(self.header_end));
+        for f in &files {
+            if let Data::Original(off) = f.data {
+                used.push(off as u64..off as u64 + blocks(f.entry.disk_size()));
+            }
+        }
+        used.sort_by_key(|r| r.start);
+
+        // Place pending data first-fit into free gaps, appending when nothing fits.
+        let mut placed: Vec<u64> = Vec::with_capacity(files.len());
+        for f in &files {
+            let off = match &f.data {
+                Data::Original(off) => *off as u64,
+                Data::Pending(bytes) if bytes.is_empty() => 0,
+                Data::Pending(bytes) => allocate(&mut used, blocks(bytes.len() as u64)),
+            };
+            let limit = if matches!(f.entry, FileEntry::Resource { .. }) { 0x7FFFFF } else { 0xFFFFFF };
+            if off > limit { bail!("archive too large: data offset exceeds the RPF7 limit"); }
+            placed.push(off);
+        }
+
+        let end_blocks = files.iter().zip(&placed)
+            .map(|(f, off)| off + blocks(f.entry.disk_size()))
+            .fold(header_blocks, u64::max);
+        let len = end_blocks * BLOCK;
+
+        // Encode the entries table.
+        let mut entries = Vec::<u8>::with_capacity(flat.len() * 16);
+        let mut file_idx = 0usize;
+        for (e, name_off) in flat.iter().zip(&offsets) {
+            match e.kind {
+                FlatKind::Dir { index, count } => {
+                    entries.extend_from_slice(&name_off.to_le_bytes());
+                    entries.extend_from_slice(&0x7FFFFF00u32.to_le_bytes());
+                    entries.extend_from_slice(&index.to_le_bytes());
+                    entries.extend_from_slice(&count.to_le_bytes());
+                }
+                FlatKind::File => {
+                    let off = placed[file_idx] as u32;
+                    entries.extend_from_slice(&(*name_off as u16).to_le_bytes());
+                    match files[file_idx].entry {
+                        FileEntry::Binary { file_size, uncompressed_size, is_encrypted } => {
+                            entries.extend_from_slice(&file_size.to_le_bytes()[..3]);
+                            entries.extend_from_slice(&off.to_le_bytes()[..3]);
+                            entries.extend_from_slice(&uncompressed_size.to_le_bytes());
+                            entries.extend_from_slice(&(is_encrypted as u32).to_le_bytes());
+                        }
+                        FileEntry::Resource { file_size, system_flags, graphics_flags } => {
+                            entries.extend_from_slice(&file_size.min(0xFFFFFF).to_le_bytes()[..3]);
+                            entries.extend_from_slice(&(off | 0x800000).to_le_bytes()[..3]);
+                            entries.extend_from_slice(&system_flags.to_le_bytes());
+                            entries.extend_from_slice(&graphics_flags.to_le_bytes());
+                        }
+                    }
+                    file_idx += 1;
+                }
+            }
+        }
+
+        let (entries, names) = match self.encryption {
+            RpfEncryption::Aes => {
+                let k = &self.keys.unwrap().aes_key;
+                (encrypt_aes(&entries, k), encrypt_aes(&names, k))
+            }
+            RpfEncryption::Ng => {
+                let ng = self.ng.as_ref().unwrap();
+                (ng.encrypt(&entries, &self.name, len as u32), ng.encrypt(&names, &self.name, len as u32))
+            }
+            RpfEncryption::None | RpfEncryption::Open => (entries, names),
+            RpfEncryption::Tfit => bail!("TFIT-encrypted archives cannot be edited"),
+        };
+
+        let mut header = Vec::with_capacity((header_blocks * BLOCK) as usize);
+        header.extend_from_slice(&RPF7_MAGIC.to_le_bytes());
+        header.extend_from_slice(&(flat.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        header.extend_from_slice(&self.tag.to_le_bytes());
+        header.extend_from_slice(&entries);
+        header.extend_from_slice(&names);
+        header.resize((header_blocks * BLOCK) as usize, 0);
+
+        let data = files.into_iter().zip(placed)
+            .filter_map(|(f, off)| match f.data {
+                Data::Pending(mut bytes) if !bytes.is_empty() => {
+                    bytes.resize(bytes.len().next_multiple_of(BLOCK as usize), 0);
+                    Some((off * BLOCK, bytes))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Ok(EditPlan { data, header, len })
+    }
+
+    // ─── Nested archives ─────────────────────────────────────────────────────
+
+    /// Split `parts` at the first `.rpf` file: `(path of the nested archive, path inside it)`.
+    fn route(&self, parts: &[&str]) -> Option<(String, String)> {
+        for i in 0..parts.len().saturating_sub(1) {
+            match find(&self.root, &parts[..=i]).map(|n| &n.kind) {
+                Some(NodeKind::Dir(_)) => continue,
+                Some(NodeKind::File(_)) if lower(parts[i]).ends_with(".rpf") =>
+                    return Some((lower(&parts[..=i].join("/")), parts[i + 1..].join("/"))),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn queue(&mut self, file: String, op: Op) -> Result<()> {
+        self.nested.entry(file).or_default().push(op);
+        Ok(())
+    }
+
+    /// Replay the queued edits on the nested archive at `file` and return its new bytes.
+    fn rebuild_nested(&self, file: &str, ops: Vec<Op>) -> Result<Vec<u8>> {
+        let parts = split_path(file)?;
+        let untouched = matches!(find(&self.root, &parts).map(|n| &n.kind),
+            Some(NodeKind::File(FileNode { data: Data::Original(_), .. })));
+        let entry = self.archive.find_file(file).filter(|_| untouched)
+            .context("the nested archive was replaced, moved or removed in the same edit")?;
+
+        let nested = self.archive.open_nested(entry, self.keys)?;
+        let mut editor = RpfEditor::with_ng(&nested, self.keys, self.ng.clone())?;
+        for op in ops {
+            match op {
+                Op::Add(path, data)     => editor.add_file(&path, data)?,
+                Op::Replace(path, data) => editor.replace_file(&path, data)?,
+                Op::Remove(path)        => editor.remove(&path)?,
+                Op::Rename(from, to)    => editor.rename(&from, &to)?,
+            }
+        }
+        Ok(editor.commit()?.apply_to_vec(nested.bytes()))
+    }
+
+    // ─── Encoding helpers ────────────────────────────────────────────────────
+
+    fn encode_binary(&self, name: &str, data: Vec<u8>, encrypt: bool) -> Result<FileNode> {
+        let uncompressed_size = u32::try_from(data.len()).context("file larger than 4 GB")?;
+
+        // Nested archives must stay stored so they can be opened in place.
+        let compressed = if lower(name).ends_with(".rpf") { None } else { deflate(&data).ok() };
+        let (file_size, mut bytes) = match compressed {
+            Some(c) if c.len() < data.len() && c.len() <= 0xFFFFFF => (c.len() as u32, c),
+            _ => (0, data),
+        };
+
+        if encrypt {
+            bytes = self.encrypt(&bytes, name, uncompressed_size)?;
+        }
+
+        Ok(FileNode {
+            entry: FileEntry::Binary { file_size, uncompressed_size, is_encrypted: encrypt },
+            data : Data::Pending(bytes),
+        })
+    }
+
+    fn encode_resource(&self, name: &str, mut data: Vec<u8>) -> Result<FileNode> {
+        let file_size = u32::try_from(data.len()).context("resource larger than 4 GB")?;
+        let system_flags = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        let graphics_flags = u32::from_le_bytes(data[12..16].try_into().unwrap());
+
+        if self.resource_encrypted(name) {
+            let body = self.encrypt(&data[16..], name, file_size)?;
+            data[16..].copy_from_slice(&body);
+        }
+        if file_size > 0xFFFFFF {
+            // The TOC field is only 24 bits wide; the real size goes into the stored header.
+            data[2] = (file_size >> 24) as u8;
+            data[5] = (file_size >> 16) as u8;
+            data[14] = (file_size >> 8) as u8;
+            data[7] = file_size as u8;
+        }
+
+        Ok(FileNode {
+            entry: FileEntry::Resource { file_size, system_flags, graphics_flags },
+            data : Data::Pending(data),
+        })
+    }
+
+    /// Resources have no per-entry encryption flag; `.ysc` scripts are always encrypted.
+    fn resource_encrypted(&self, name: &str) -> bool {
+        self.encryption.is_encrypted() && lower(name).ends_with(".ysc")
+    }
+
+    fn encrypt(&self, data: &[u8], name: &str, length: u32) -> Result<Vec<u8>> {
+        Ok(match self.encryption {
+            RpfEncryption::Aes => encrypt_aes(data, &self.keys.unwrap().aes_key),
+            RpfEncryption::Ng  => self.ng.as_ref().unwrap().encrypt(data, name, length),
+            _                  => data.to_vec(),
+        })
+    }
+
+    fn decrypt(&self, data: &[u8], name: &str, length: u32) -> Vec<u8> {
+        match (self.encryption, self.keys) {
+            (RpfEncryption::Aes, Some(k)) => decrypt_aes(data, &k.aes_key),
+            (RpfEncryption::Ng, Some(k))  => decrypt_ng(data, k, name, length),
+            _                             => data.to_vec(),
+        }
+    }
+
+    /// New on-disk bytes for a file being renamed, or `None` when they do not change.
+    fn transcode_for_rename(&self, f: &FileNode, old: &str, new: &str) -> Result<Option<Vec<u8>>> {
+        let raw = match &f.data {
+            Data::Original(off) => self.original_bytes(*off, f.entry)?,
+            Data::Pending(bytes) => bytes.as_slice(),
+        };
+        match f.entry {
+            FileEntry::Binary { uncompressed_size, is_encrypted: true, .. } if self.encryption == RpfEncryption::Ng => {
+                let plain = self.decrypt(raw, old, uncompressed_size);
+                Ok(Some(self.encrypt(&plain, new, uncompressed_size)?))
+            }
+            FileEntry::Resource { file_size, .. } => {
+                let (was, will) = (self.resource_encrypted(old), self.resource_encrypted(new));
+                if !was && !will { return Ok(None); }
+                if was && will && self.encryption != RpfEncryption::Ng { return Ok(None); }
+                let mut out = raw.to_vec();
+                let mut body = out[16..].to_vec();
+                if was { body = self.decrypt(&body, old, file_size); }
+                if will { body = self.encrypt(&body, new, file_size)?; }
+                out[16..].copy_from_slice(&body);
+                Ok(Some(out))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn original_bytes(&self, block: u32, entry: FileEntry) -> Result<&'a [u8]> {
+        let start = block as usize * BLOCK as usize;
+        self.source.get(start..start + entry.disk_size() as usize)
+            .context("entry data lies outside the archive")
+    }
+
+    fn retire(&mut self, f: &FileNode) {
+        if let Data::Original(off) = f.data {
+            self.retired.push(off as u64..off as u64 + blocks(f.entry.disk_size()));
+        }
+    }
+
+    fn retire_node(&mut self, node: &Node) {
+        match &node.kind {
+            NodeKind::File(f)   => self.retire(f),
+            NodeKind::Dir(kids) => for k in kids { self.retire_node(k); },
+        }
+    }
+}
+
+// ─── Edit plan ───────────────────────────────────────────────────────────────
+
+/// The bytes an edit has to write: new data chunks, then the header at offset 0.
+pub struct EditPlan {
+    data  : Vec<(u64, Vec<u8>)>,
+    header: Vec<u8>,
+    len   : u64,
+}
+
+impl EditPlan {
+    /// Write the plan into an archive on disk. Data goes first and is synced before the
+    /// header, so the old TOC stays valid until the new one is in place.
+    pub fn apply_to_file(&self, path: &Path) -> Result<()> {
+        let mut file = OpenOptions::new().write(true).open(path)
+            .with_context(|| format!("cannot open {} for writing", path.display()))?;
+        let current = file.metadata()?.len();
+
+        if self.len > current { file.set_len(self.len)?; }
+        for (off, bytes) in &self.data {
+            file.seek(SeekFrom::Start(*off))?;
+            file.write_all(bytes)?;
+        }
+        file.sync_data()?;
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&self.header)?;
+        if self.len < current { file.set_len(self.len)?; }
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Apply the plan to a copy of `source`; used to rebuild nested archives in memory.
+    pub fn apply_to_vec(&self, source: &[u8]) -> Vec<u8> {
+        let mut out = source.to_vec();
+        out.resize(self.len as usize, 0);
+        for (off, bytes) in &self.data {
+            out[*off as usize..*off as usize + bytes.len()].copy_from_slice(bytes);
+        }
+        out[..self.header.len()].copy_from_slice(&self.header);
+        out
+    }
+}
+
+/// Open `path`, let `edit` modify it, and write the result back in place.
+/// Returns the entry count of the re-opened archive.
+pub fn edit_file(
+    path: &Path,
+    keys: Option<&GtaKeys>,
+    edit: impl FnOnce(&mut RpfEditor<'_>) -> Result<()>,
+) -> Result<usize> {
+    let plan = {
+        let archive = Archive::open(path, keys)?;
+        let mut editor = RpfEditor::new(&archive, keys)?;
+        edit(&mut editor)?;
+        editor.commit()?
+    };
+    plan.apply_to_file(path)?;
+
+    let reopened = Archive::open(path, keys)
+        .with_context(|| format!("{} did not re-open after the edit", path.display()))?;
+    Ok(reopened.entry_count)
+}
+
+// ─── Tree helpers ────────────────────────────────────────────────────────────
+
+fn load_dir(entries: &[crate::rpf::RpfEntry], index: u32, count: u32, depth: usize) -> Result<Vec<Node>> {
+    if depth > 64 { bail!("directory nesting too deep (corrupt TOC?)"); }
+    let range = index as usize..index as usize + count as usize;
+    let slice = entries.get(range).context("directory entry range out of bounds")?;
+
+    let mut nodes = Vec::with_capacity(slice.len());
+    for e in slice {
+        let kind = match e.kind {
+            RpfEntryKind::Directory { entries_index, entries_count } =>
+                NodeKind::Dir(load_dir(entries, entries_index, entries_count, depth + 1)?),
+            RpfEntryKind::BinaryFile { file_offset, file_size, uncompressed_size, is_encrypted } =>
+                NodeKind::File(FileNode {
+                    entry: FileEntry::Binary { file_size, uncompressed_size, is_encrypted },
+                    data : Data::Original(file_offset),
+                }),
+            RpfEntryKind::ResourceFile { file_offset, file_size, system_flags, graphics_flags, .. } =>
+                NodeKind::File(FileNode {
+                    entry: FileEntry::Resource { file_size, system_flags, graphics_flags },
+                    data : Data::Original(file_offset),
+                }),
+        };
+        nodes.push(Node { name: e.name.clone(), kind });
+    }
+    Ok(nodes)
+}
+
+fn position(children: &[Node], name: &str) -> Option<usize> {
+    let name = lower(name);
+    children.iter().position(|n| lower(&n.name) == name)
+}
+
+fn find<'n>(children: &'n [Node], parts: &[&str]) -> Option<&'n Node> {
+    let (first, rest) = parts.split_first()?;
+    let node = &children[position(children, first)?];
+    match (&node.kind, rest.is_empty()) {
+        (_, true)              => Some(node),
+        (NodeKind::Dir(k), _)  => find(k, rest),
+        (NodeKind::File(_), _) => None,
+    }
+}
+
+fn dir_mut<'n>(children: &'n mut Vec<Node>, parts: &[&str], create: bool) -> Result<&'n mut Vec<Node>> {
+    let Some((first, rest)) = parts.split_first() else { return Ok(children) };
+    let idx = match position(children, first) {
+        Some(i) => i,
+        None if create => insert_sorted(children, Node { name: first.to_string(), kind: NodeKind::Dir(Vec::new()) }),
+        None => bail!("directory '{}' not found", first),
+    };
+    match &mut children[idx].kind {
+        NodeKind::Dir(kids) => dir_mut(kids, rest, create),
+        NodeKind::File(_)   => bail!("'{}' is a file, not a directory", first),
+    }
+}
+
+/// Insert before the first sibling that sorts after `node`, keeping existing order intact.
+fn insert_sorted(children: &mut Vec<Node>, node: Node) -> usize {
+    let name = lower(&node.name);
+    let idx = children.iter().position(|n| lower(&n.name) > name).unwrap_or(children.len());
+    children.insert(idx, node);
+    idx
+}
+
+struct FlatEntry {
+    name: String,
+    kind: FlatKind,
+}
+
+enum FlatKind {
+    Dir { index: u32, count: u32 },
+    File,
+}
+
+fn flatten(children: Vec<Node>, self_idx: usize, flat: &mut Vec<FlatEntry>, files: &mut Vec<FileNode>) {
+    let first = flat.len();
+    flat[self_idx].kind = FlatKind::Dir { index: first as u32, count: children.len() as u32 };
+
+    let mut subdirs = Vec::new();
+    for (i, node) in children.into_iter().enumerate() {
+        match node.kind {
+            NodeKind::Dir(kids) => {
+                flat.push(FlatEntry { name: node.name, kind: FlatKind::Dir { index: 0, count: 0 } });
+                subdirs.push((first + i, kids));
+            }
+            NodeKind::File(f) => {
+                flat.push(FlatEntry { name: node.name, kind: FlatKind::File });
+                files.push(f);
+            }
+        }
+    }
+    for (idx, kids) in subdirs {
+        flatten(kids, idx, flat, files);
+    }
+}
+
+/// First-fit allocation of `count` blocks in the gaps of the sorted `used` list.
+fn allocate(used: &mut Vec<Range<u64>>, count: u64) -> u64 {
+    let mut cursor = 0u64;
+    let mut at = used.len();
+    for (i, r) in used.iter().enumerate() {
+        if r.start >= cursor + count { at = i; break; }
+        cursor = cursor.max(r.end);
+    }
+    used.insert(at, cursor..cursor + count);
+    cursor
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut enc = DeflateEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    enc.write_all(data)?;
+    Ok(enc.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::RpfBuilder;
+    use std::fs;
+
+    fn text(seed: u32, len: usize) -> Vec<u8> {
+        (0..len as u32).map(|i| b'a' + ((i * 7 + seed) % 26) as u8).collect()
+    }
+
+    /// A resource header and a payload; the editor only looks at the header.
+    fn resource() -> Vec<u8> {
+        let mut data = RSC7_MAGIC.to_le_bytes().to_vec();
+        // Version 2, split across the top nibbles of the page flags.
+        for v in [2u32, 0x0000_0001, 0x2000_0001] { data.extend(v.to_le_bytes()); }
+        data.extend((0..0x400u32).map(|i| (i * 13) as u8));
+        data
+    }
+
+    /// An unencrypted archive holding plain files and a resource.
+    fn sample() -> Vec<u8> {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("a.txt", text(2, 5000));
+        builder.add_file("dir/b.bin", (0..9000u32).map(|i| ((i * i) >> 3) as u8).collect());
+        builder.add_file("dir/c.ydr", resource());
+        builder.build(None).unwrap()
+    }
+
+    fn write(data: &[u8]) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.rpf");
+        fs::write(&path, data).unwrap();
+        (dir, path)
+    }
+
+    fn read(archive: &Archive, path: &str, keys: Option<&GtaKeys>) -> Vec<u8> {
+        archive.extract(archive.find_file(path).unwrap(), keys).unwrap()
+    }
+
+    fn block_of(archive: &Archive, path: &str) -> u32 {
+        match *archive.entry_kind(archive.find_file(path).unwrap()) {
+            RpfEntryKind::BinaryFile { file_offset, .. } | RpfEntryKind::ResourceFile { file_offset, .. } => file_offset,
+            RpfEntryKind::Directory { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn edits_round_trip() {
+        let (_dir, path) = write(&sample());
+        let count = edit_file(&path, None, |ed| {
+            ed.add_file("dir/new/d.txt", text(3, 2000))?;
+            ed.replace_file("a.txt", text(4, 7000))?;
+            ed.remove("dir/b.bin")?;
+            ed.rename("dir/c.ydr", "e.ydr")
+        }).unwrap();
+
+        let archive = Archive::open(&path, None).unwrap();
+        assert_eq!(count, archive.entry_count);
+        assert_eq!(read(&archive, "a.txt", None), text(4, 7000));
+        assert_eq!(read(&archive, "dir/new/d.txt", None), text(3, 2000));
+        assert_eq!(read(&archive, "e.ydr", None), resource());
+        assert!(archive.find_file("dir/b.bin").is_none());
+        assert!(archive.find_file("dir/c.ydr").is_none());
+        assert_eq!(archive.list_files().len(), 3);
+    }
+
+    #[test]
+    fn rejects_conflicting_edits() {
+        let archive = Archive::from_bytes(sample(), "test.rpf", None).unwrap();
+        let mut ed = RpfEditor::new(&archive, None).unwrap();
+        assert!(ed.add_file("A.TXT", vec![1]).is_err(), "names compare case-insensitively");
+        assert!(ed.replace_file("missing.txt", vec![1]).is_err());
+        assert!(ed.remove("dir/nope").is_err());
+        assert!(ed.rename("a.txt", "dir/c.ydr").is_err());
+        assert!(ed.add_file("a.txt/child", vec![1]).is_err());
+    }
+
+    #[test]
+    fn reuses_freed_blocks() {
+        let (_dir, path) = write(&sample());
+        let before = Archive::open(&path, None).unwrap();
+        let (freed, len) = (block_of(&before, "dir/b.bin"), fs::metadata(&path).unwrap().len());
+        drop(before);
+
+        edit_file(&path, None, |ed| ed.remove("dir/b.bin")).unwrap();
+        edit_file(&path, None, |ed| ed.add_file("b2.bin", vec![0xC3; 300])).unwrap();
+
+        let archive = Archive::open(&path, None).unwrap();
+        assert_eq!(block_of(&archive, "b2.bin"), freed);
+        assert!(fs::metadata(&path).unwrap().len() <= len);
+        assert_eq!(read(&archive, "b2.bin", None), vec![0xC3; 300]);
+        assert_eq!(read(&archive, "a.txt", None), text(2, 5000));
+    }
+
+    /// A crash after the data is written but before the header leaves the old archive intact.
+    #[test]
+    fn writes_the_header_last() {
+        let source = sample();
+        let archive = Archive::from_bytes(source.clone(), "test.rpf", None).unwrap();
+        let mut ed = RpfEditor::new(&archive, None).unwrap();
+        ed.replace_file("a.txt", text(4, 7000)).unwrap();
+        ed.remove("dir/b.bin").unwrap();
+        ed.add_file("dir/new.txt", text(5, 3000)).unwrap();
+        let plan = ed.commit().unwrap();
+
+        let mut partial = source.clone();
+        partial.resize(partial.len().max(plan.len as usize), 0);
+        for (off, bytes) in &plan.data {
+            assert!(*off >= plan.header.len() as u64, "data chunk at 0x{:X} overlaps the header", off);
+            partial[*off as usize..*off as usize + bytes.len()].copy_from_slice(bytes);
+        }
+        let old = Archive::from_bytes(partial.clone(), "test.rpf", None).unwrap();
+        assert_eq!(read(&old, "a.txt", None), text(2, 5000));
+        assert_eq!(read(&old, "dir/b.bin", None), read(&archive, "dir/b.bin", None));
+        assert!(old.find_file("dir/new.txt").is_none());
+
+        partial[..plan.header.len()].copy_from_slice(&plan.header);
+        partial.truncate(plan.len as usize);
+        let new = Archive::from_bytes(partial, "test.rpf", None).unwrap();
+        assert_eq!(read(&new, "a.txt", None), text(4, 7000));
+        assert_eq!(read(&new, "dir/new.txt", None), text(5, 3000));
+    }
+
+    /// `outer.rpf` with `inner.rpf`, which in turn holds `deep.rpf`.
+    fn nested_sample() -> Vec<u8> {
+        let archive = |files: Vec<(&str, Vec<u8>)>| {
+            let mut builder = RpfBuilder::new(RpfEncryption::Open);
+            for (path, data) in files { builder.add_file(path, data); }
+            builder.build(None).unwrap()
+        };
+        let deep = archive(vec![("z.txt", text(8, 700))]);
+        let inner = archive(vec![("x/y.txt", text(1, 300)), ("x/old.txt", text(9, 50)), ("deep.rpf", deep)]);
+        archive(vec![("a.txt", text(2, 500)), ("inner.rpf", inner)])
+    }
+
+    /// Read `path` through the nested archives named before it.
+    fn read_nested(archive: &Archive, path: &str) -> Option<Vec<u8>> {
+        let (nested, rest) = path.split_once(".rpf/").map(|(a, b)| (format!("{}.rpf", a), b))?;
+        let inner = archive.open_nested(archive.find_file(&nested)?, None).unwrap();
+        match rest.contains(".rpf/") {
+            true  => read_nested(&inner, rest),
+            false => inner.find_file(rest).map(|f| inner.extract(f, None).unwrap()),
+        }
+    }
+
+    #[test]
+    fn nested_edits_round_trip() {
+        let (_dir, path) = write(&nested_sample());
+        edit_file(&path, None, |ed| {
+            assert!(ed.exists("inner.rpf/x/y.txt") && ed.exists("inner.rpf/deep.rpf/z.txt"));
+            assert!(!ed.exists("inner.rpf/x/nope.txt"));
+            ed.add_file("inner.rpf/x/new.txt", text(3, 2000))?;
+            ed.replace_file("inner.rpf/x/y.txt", text(4, 900))?;
+            ed.remove("inner.rpf/x/old.txt")?;
+            ed.rename("inner.rpf/deep.rpf/z.txt", "inner.rpf/deep.rpf/w/z2.txt")?;
+            ed.add_file("inner.rpf/deep.rpf/q.txt", text(5, 100))
+        }).unwrap();
+
+        let archive = Archive::open(&path, None).unwrap();
+        assert_eq!(read(&archive, "a.txt", None), text(2, 500));
+        assert_eq!(read_nested(&archive, "inner.rpf/x/new.txt"), Some(text(3, 2000)));
+        assert_eq!(read_nested(&archive, "inner.rpf/x/y.txt"), Some(text(4, 900)));
+        assert_eq!(read_nested(&archive, "inner.rpf/x/old.txt"), None);
+        assert_eq!(read_nested(&archive, "inner.rpf/deep.rpf/w/z2.txt"), Some(text(8, 700)));
+        assert_eq!(read_nested(&archive, "inner.rpf/deep.rpf/z.txt"), None);
+        assert_eq!(read_nested(&archive, "inner.rpf/deep.rpf/q.txt"), Some(text(5, 100)));
+    }
+
+    #[test]
+    fn rejects_conflicting_nested_edits() {
+        let archive = Archive::from_bytes(nested_sample(), "outer.rpf", None).unwrap();
+        let mut ed = RpfEditor::new(&archive, None).unwrap();
+        assert!(ed.rename("inner.rpf/x/y.txt", "y.txt").is_err(), "moves cannot cross archives");
+        ed.add_file("inner.rpf/x/new.txt", vec![1]).unwrap();
+        ed.remove("inner.rpf").unwrap();
+        assert!(ed.commit().is_err(), "the nested archive was removed in the same edit");
+    }
+
+    /// `RpfBuilder` cannot write NG archives, so encrypt the TOC of an unencrypted one.
+    fn encrypt_toc(mut data: Vec<u8>, name: &str, enc: &NgEncryptor<'_>) -> Vec<u8> {
+        let word = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+        let (entries, names) = (16 + word(4) * 16, word(8));
+        let length = data.len() as u32;
+        data[12..16].copy_from_slice(&RpfEncryption::Ng.as_u32().to_le_bytes());
+        let toc = enc.encrypt(&data[16..entries], name, length);
+        data[16..entries].copy_from_slice(&toc);
+        let toc = enc.encrypt(&data[entries..entries + names], name, length);
+        data[entries..entries + names].copy_from_slice(&toc);
+        data
+    }
+
+    #[test]
+    fn ng_edits_round_trip() {
+        let keys = ng::tests::keys();
+        let enc = NgEncryptor::new(&keys).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ng.rpf");
+        fs::write(&path, encrypt_toc(sample(), "ng.rpf", &enc)).unwrap();
+
+        let k = Some(&keys);
+        assert_eq!(Archive::open(&path, k).unwrap().encryption, RpfEncryption::Ng);
+        edit_file(&path, k, |ed| {
+            ed.add_file("dir/d.meta", text(6, 4000))?;
+            ed.replace_file("a.txt", text(7, 3000))?;
+            ed.rename("dir/d.meta", "d2.meta")
+        }).unwrap();
+        edit_file(&path, k, |ed| ed.rename("d2.meta", "dir/d3.meta")).unwrap();
+
+        let archive = Archive::open(&path, k).unwrap();
+        assert_eq!(archive.encryption, RpfEncryption::Ng);
+        assert_eq!(read(&archive, "dir/d3.meta", k), text(6, 4000));
+        assert_eq!(read(&archive, "a.txt", k), text(7, 3000));
+        assert_eq!(read(&archive, "dir/c.ydr", k), resource());
+    }
+}
@@ -0,0 +1,228 @@
+// glTF 2.0 writer: accessors, meshes, nodes and embedded PNG images over a single binary
+// buffer, saved as a .glb or as a .gltf with a sibling .bin.
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const FLOAT: u32 = 5126;
+
+/// A glTF document under construction. Every `add_*` returns the new item's index.
+#[derive(Default)]
+pub struct Gltf {
+    bin         : Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors   : Vec<Value>,
+    images      : Vec<Value>,
+    textures    : Vec<Value>,
+    materials   : Vec<Value>,
+    meshes      : Vec<Value>,
+    skins       : Vec<Value>,
+    nodes       : Vec<Value>,
+    roots       : Vec<usize>,
+}
+
+impl Gltf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while !self.bin.len().is_multiple_of(4) { self.bin.push(0); }
+        let mut view = json!({ "buffer": 0, "byteOffset": self.bin.len(), "byteLength": data.len() });
+        if let Some(target) = target { view["target"] = json!(target); }
+        self.bin.extend_from_slice(data);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    /// A float accessor of `N`-component items: VEC2, VEC3, VEC4 or MAT4. `bounds` adds the
+    /// min and max glTF requires for positions.
+    pub fn add_floats<const N: usize>(&mut self, items: &[[f32; N]], bounds: bool) -> usize {
+        let data: Vec<u8> = items.iter().flatten().flat_map(|f| f.to_le_bytes()).collect();
+        let target = (N <= 4).then_some(ARRAY_BUFFER);
+        let view = self.add_view(&data, target);
+        let mut accessor = json!({
+            "bufferView": view, "componentType": FLOAT, "count": items.len(), "type": accessor_type(N),
+        });
+        if bounds && !items.is_empty() {
+            let fold = |f: fn(f32, f32) -> f32, init: f32| (0..N).map(|i| items.iter().map(|v| v[i]).fold(init, f)).collect::<Vec<_>>();
+            accessor["min"] = json!(fold(f32::min, f32::INFINITY));
+            accessor["max"] = json!(fold(f32::max, f32::NEG_INFINITY));
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// A VEC4 accessor of bytes, read as 0..1 when `normalized`.
+    pub fn add_bytes(&mut self, items: &[[u8; 4]], normalized: bool) -> usize {
+        let view = self.add_view(items.as_flattened(), Some(ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view, "componentType": UNSIGNED_BYTE, "normalized": normalized, "count": items.len(), "type": "VEC4",
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// A VEC4 accessor of 16-bit joint indices.
+    pub fn add_joints(&mut self, items: &[[u16; 4]]) -> usize {
+        let data: Vec<u8> = items.iter().flatten().flat_map(|j| j.to_le_bytes()).collect();
+        let view = self.add_view(&data, Some(ARRAY_BUFFER));
+        self.accessors.push(json!({ "bufferView": view, "componentType": UNSIGNED_SHORT, "count": items.len(), "type": "VEC4" }));
+        self.accessors.len() - 1
+    }
+
+    pub fn add_indices(&mut self, indices: &[u16]) -> usize {
+        let data: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.add_view(&data, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({ "bufferView": view, "componentType": UNSIGNED_SHORT, "count": indices.len(), "type": "SCALAR" }));
+        self.accessors.len() - 1
+    }
+
+    /// An embedded PNG and a texture sampling it.
+    pub fn add_texture(&mut self, name: &str, png: &[u8]) -> usize {
+        let view = self.add_view(png, None);
+        self.images.push(json!({ "name": name, "bufferView": view, "mimeType": "image/png" }));
+        self.textures.push(json!({ "source": self.images.len() - 1 }));
+        self.textures.len() - 1
+    }
+
+    pub fn add_material(&mut self, material: Value) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    pub fn add_mesh(&mut self, mesh: Value) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    pub fn add_skin(&mut self, skin: Value) -> usize {
+        self.skins.push(skin);
+        self.skins.len() - 1
+    }
+
+    /// Add a node, as a child of `parent` or else as a root of the scene.
+    pub fn add_node(&mut self, node: Value, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(node);
+        match parent {
+            Some(p) => match self.nodes[p].get_mut("children").and_then(Value::as_array_mut) {
+                Some(children) => children.push(json!(index)),
+                None           => self.nodes[p]["children"] = json!([index]),
+            },
+            None => self.roots.push(index),
+        }
+        index
+    }
+
+    fn document(&self, uri: Option<&str>) -> Value {
+        let mut buffer = json!({ "byteLength": self.bin.len() });
+        if let Some(uri) = uri { buffer["uri"] = json!(uri); }
+        let mut doc = Map::new();
+        doc.insert("asset".into(), json!({ "version": "2.0", "generator": concat!("rpf ", env!("CARGO_PKG_VERSION")) }));
+        doc.insert("scene".into(), json!(0));
+        doc.insert("scenes".into(), json!([{ "nodes": self.roots }]));
+        for (key, items) in [
+            ("nodes", &self.nodes), ("meshes", &self.meshes), ("skins", &self.skins), ("materials", &self.materials),
+            ("textures", &self.textures), ("images", &self.images), ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+        ] {
+            if !items.is_empty() { doc.insert(key.into(), Value::Array(items.clone())); }
+        }
+        if !self.bin.is_empty() { doc.insert("buffers".into(), json!([buffer])); }
+        Value::Object(doc)
+    }
+
+    /// A binary glTF: the JSON chunk, then the buffer.
+    pub fn to_glb(&self) -> Result<Vec<u8>> {
+        let mut json = serde_json::to_vec(&self.document(None))?;
+        while !json.len().is_multiple_of(4) { json.push(b' '); }
+        let mut bin = self.bin.clone();
+        while !bin.len().is_multiple_of(4) { bin.push(0); }
+
+        let total = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin.len() };
+        let mut out = Vec::with_capacity(total);
+        out.extend(b"glTF");
+        out.extend(2u32.to_le_bytes());
+        out.extend(u32::try_from(total)?.to_le_bytes());
+        out.extend(u32::try_from(json.len())?.to_le_bytes());
+        out.extend(b"JSON");
+        out.extend(json);
+        if !bin.is_empty() {
+            out.extend(u32::try_from(bin.len())?.to_le_bytes());
+            out.extend(b"BIN\0");
+            out.extend(bin);
+        }
+        Ok(out)
+    }
+
+    /// A .gltf document referring to its buffer as `bin_uri`, and that buffer.
+    pub fn to_gltf(&self, bin_uri: &str) -> Result<(String, &[u8])> {
+        Ok((serde_json::to_string_pretty(&self.document(Some(bin_uri)))? + "\n", &self.bin))
+    }
+}
+
+fn accessor_type(components: usize) -> &'static str {
+    match components {
+        1  => "SCALAR",
+        2  => "VEC2",
+        3  => "VEC3",
+        4  => "VEC4",
+        16 => "MAT4",
+        _  => unreachable!("no accessor type has {} components", components),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_accessors_and_node_tree() {
+        let mut gltf = Gltf::new();
+        let indices = gltf.add_indices(&[0, 1, 2]);
+        let positions = gltf.add_floats(&[[0.0, 1.0, -2.0], [3.0, -1.0, 0.5], [1.0, 0.0, 0.0]], true);
+        let mesh = gltf.add_mesh(json!({ "primitives": [{ "attributes": { "POSITION": positions }, "indices": indices }] }));
+        let root = gltf.add_node(json!({ "name": "root" }), None);
+        gltf.add_node(json!({ "name": "a", "mesh": mesh }), Some(root));
+        gltf.add_node(json!({ "name": "b" }), Some(root));
+
+        let (text, bin) = gltf.to_gltf("model.bin").unwrap();
+        let doc: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(doc["scenes"][0]["nodes"], json!([0]));
+        assert_eq!(doc["nodes"][0]["children"], json!([1, 2]));
+        assert_eq!(doc["buffers"][0], json!({ "byteLength": bin.len(), "uri": "model.bin" }));
+        assert_eq!(doc["accessors"][1]["min"], json!([0.0, -1.0, -2.0]));
+        assert_eq!(doc["accessors"][1]["max"], json!([3.0, 1.0, 0.5]));
+        // Six bytes of indices, padded to four before the positions.
+        assert_eq!(doc["bufferViews"][1]["byteOffset"], json!(8));
+        assert_eq!(bin.len(), 8 + 36);
+        assert!(doc.get("skins").is_none());
+    }
+
+    #[test]
+    fn writes_aligned_glb_chunks() {
+        let mut gltf = Gltf::new();
+        gltf.add_indices(&[0, 1, 2]);
+        let glb = gltf.to_glb().unwrap();
+
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+        let json_len = u32_at(&glb, 12) as usize;
+        assert_eq!((&glb[16..20], json_len % 4), (&b"JSON"[..], 0));
+        let doc: Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert!(doc["buffers"][0].get("uri").is_none());
+
+        let bin = 20 + json_len;
+        assert_eq!((u32_at(&glb, bin), &glb[bin + 4..bin + 8]), (8, &b"BIN\0"[..]));
+        assert_eq!(&glb[bin + 8..], &[0, 0, 1, 0, 2, 0, 0, 0]);
+    }
+}
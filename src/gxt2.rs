@@ -0,0 +1,90 @@
+// GXT2 text tables (.gxt2): the localised strings of a language, keyed by label hash.
+//
+// Layout: "2TXG", entry count, then (hash, offset) pairs sorted by hash, a second "2TXG"
+// and the file size, followed by the NUL-terminated UTF-8 strings. Offsets are from the
+// start of the file.
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::u32_at;
+
+const MAGIC: &[u8; 4] = b"2TXG";
+
+/// Entries in file order: `(label hash, text)`.
+pub fn parse(data: &[u8]) -> Result<Vec<(u32, String)>> {
+    if !data.starts_with(MAGIC) {
+        bail!("not a GXT2 file (no 2TXG magic)");
+    }
+    let count = data.get(4..8).map(|b| u32_at(b, 0) as usize).context("truncated header")?;
+    let table = 8 + count * 8;
+    if data.get(table..table + 4) != Some(MAGIC) {
+        bail!("{} entries do not fit the file or the second 2TXG marker is missing", count);
+    }
+
+    (0..count).map(|i| {
+        let (hash, offset) = (u32_at(data, 8 + i * 8), u32_at(data, 12 + i * 8) as usize);
+        let bytes = data.get(offset..).with_context(|| format!("string of 0x{:08X} at 0x{:X} is outside the file", hash, offset))?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let text = String::from_utf8(bytes[..end].to_vec())
+            .with_context(|| format!("string of 0x{:08X} is not UTF-8", hash))?;
+        Ok((hash, text))
+    }).collect()
+}
+
+/// Build a GXT2 file; entries are sorted by hash, as the game looks them up by bisection.
+pub fn build(entries: &[(u32, String)]) -> Result<Vec<u8>> {
+    let mut sorted: Vec<&(u32, String)> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.0);
+    if let Some(w) = sorted.windows(2).find(|w| w[0].0 == w[1].0) {
+        bail!("label 0x{:08X} appears more than once", w[0].0);
+    }
+
+    let mut out = MAGIC.to_vec();
+    out.extend(u32::try_from(sorted.len())?.to_le_bytes());
+    let mut offset = 8 + sorted.len() * 8 + 8;
+    for (hash, text) in &sorted {
+        out.extend(hash.to_le_bytes());
+        out.extend(u32::try_from(offset)?.to_le_bytes());
+        offset += text.len() + 1;
+    }
+    out.extend(MAGIC);
+    out.extend(u32::try_from(offset)?.to_le_bytes());
+    for (_, text) in &sorted {
+        if text.contains('\0') {
+            bail!("text '{}' contains a NUL character", text);
+        }
+        out.extend(text.as_bytes());
+        out.push(0);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_round_trips() {
+        let entries = vec![
+            (0xF000_0001, "Last".to_string()),
+            (0x0000_0002, "~r~Wasted~s~".to_string()),
+            (0x1234_5678, "Café ünïcode — ok".to_string()),
+            (0x0000_0010, String::new()),
+        ];
+        let built = build(&entries).unwrap();
+        assert_eq!(u32_at(&built, 8 + entries.len() * 8 + 4) as usize, built.len());
+
+        let parsed = parse(&built).unwrap();
+        let mut sorted = entries.clone();
+        sorted.sort_by_key(|e| e.0);
+        assert_eq!(parsed, sorted);
+        assert_eq!(build(&parsed).unwrap(), built);
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        assert!(build(&[(1, "a".into()), (1, "b".into())]).is_err());
+        assert!(build(&[(1, "a\0b".into())]).is_err());
+        assert!(parse(b"2TXG\x05\0\0\0").is_err());
+    }
+}
@@ -3,25 +3,51 @@ use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
 
 mod rpf;
+mod awc;
+mod bounds;
+mod clips;
 mod commands;
+mod crypto;
+mod drawable;
+mod editor;
+mod gltf;
+mod gxt2;
+mod meta;
+mod names;
+mod navmesh;
+mod nodes;
+mod obj;
+mod output;
+mod rbf;
+mod resource;
+mod texture;
 mod utils;
+mod ymap;
 
-use commands::{info, list, extract, verify, tree, ytd, create};
-use rpf::GtaKeys;
+use commands::{info, list, extract, verify, tree, ytd, ytd_all, ytd_edit, ytd_pack, resinfo, resource_pack, meta as meta_cmd, gxt2 as gxt2_cmd, awc as awc_cmd, model, ybn, ymap as ymap_cmd, ynd, ynv, ycd, create, add, replace, rm, mv};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(name = "rpf")]
 #[command(about = "A CLI tool for working with RAGE Package Files (RPF)", long_about = None)]
 struct Cli {
-    /// Enable verbose output
-    #[arg(short, long, global = true)]
+    /// Enable verbose output (before the subcommand; `create -v` is the RPF version)
+    #[arg(short, long)]
     verbose: bool,
 
-    /// Directory with extracted GTA V keys (gtav_aes_key.dat, gtav_ng_key.dat, gtav_ng_decrypt_tables.dat)
+    /// Directory with extracted GTA V keys (gtav_aes_key.dat, gtav_ng_key.dat, gtav_ng_decrypt_tables.dat).
+    /// Defaults to $RPF_KEYS_DIR, then the per-user key cache, then a GTA5.exe next to the archive
     #[arg(long, global = true, value_name = "DIR")]
     keys: Option<PathBuf>,
 
+    /// Output format for info, list, tree, verify, resinfo, ytd-all, awc list, ybn info, ymap inspect and ycd list
+    #[arg(long, global = true, value_enum, default_value_t = output::Format::Text)]
+    format: output::Format,
+
+    /// Extra names for hashes in meta, .gxt2, .awc and model files, one per line (repeatable)
+    #[arg(long = "names", global = true, value_name = "FILE")]
+    names: Vec<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -39,12 +65,15 @@ enum Commands {
         /// Path to the RPF archive
         archive: PathBuf,
 
-        /// Pattern to filter files (e.g., "*.xml")
+        /// Glob to filter files (e.g., "*.xml", "**/*.{ydr,yft}")
         pattern: Option<String>,
 
         /// Show detailed information
         #[arg(short, long)]
         detailed: bool,
+
+        #[command(flatten)]
+        filter: utils::FilterArgs,
     },
 
     /// Extract files from an RPF archive
@@ -56,19 +85,35 @@ enum Commands {
         #[arg(short, long, value_name = "DIR")]
         output: Option<PathBuf>,
 
-        /// Specific file or pattern to extract
+        /// Specific file or glob to extract
         pattern: Option<String>,
 
-        /// Recurse into nested RPF archives, extracting them to loose files
-        /// (resource files get a valid RSC7 header, like CodeWalker)
-        #[arg(short, long)]
-        recursive: bool,
+        #[command(flatten)]
+        args: extract::ExtractArgs,
+
+        #[command(flatten)]
+        filter: utils::FilterArgs,
     },
 
     /// Verify integrity of an RPF archive
     Verify {
         /// Path to the RPF archive
         archive: PathBuf,
+
+        /// Also verify every nested RPF archive
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Write a machine-readable report to this file
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
+        /// Report format (default: junit for .xml files, json otherwise)
+        #[arg(long, value_enum, requires = "report")]
+        report_format: Option<verify::ReportFormat>,
+
+        #[command(flatten)]
+        filter: utils::FilterArgs,
     },
 
     /// Display archive contents in tree format
@@ -79,19 +124,145 @@ enum Commands {
         /// Maximum depth to display
         #[arg(short, long)]
         depth: Option<usize>,
+
+        #[command(flatten)]
+        filter: utils::FilterArgs,
     },
 
-    /// Extract textures from a .ytd file inside an RPF archive as DDS files
-    Ytd {
+    /// Decode the RSC7 header and page flags of a resource inside an RPF archive
+    Resinfo {
         /// Path to the RPF archive
         archive: PathBuf,
 
+        /// Path or name of the resource inside the archive
+        path: String,
+    },
+
+    /// Extract textures from a .ytd file inside an RPF archive as DDS, PNG or TGA files,
+    /// or edit one with `ytd replace` / `ytd add`
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Ytd {
+        #[command(subcommand)]
+        action: Option<YtdAction>,
+
+        /// Path to the RPF archive
+        #[arg(required = true)]
+        archive: Option<PathBuf>,
+
         /// Name of the .ytd file inside the archive (e.g. "vehicles.ytd")
-        ytd: String,
+        #[arg(required = true)]
+        ytd: Option<String>,
 
         /// Output directory (default: ytd stem)
         #[arg(short, long, value_name = "DIR")]
         output: Option<PathBuf>,
+
+        #[command(flatten)]
+        export: ytd::ExportArgs,
+    },
+
+    /// Extract every texture dictionary in an RPF archive, including those embedded in
+    /// .ydr, .yft and .ydd files, and summarise formats and sizes per dictionary
+    YtdAll {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Output directory (defaults to archive name without extension); each dictionary
+        /// goes in a folder at its path inside the archive
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// Also search nested RPF archives
+        #[arg(short, long)]
+        recursive: bool,
+
+        #[command(flatten)]
+        export: ytd::ExportArgs,
+
+        #[command(flatten)]
+        filter: utils::FilterArgs,
+    },
+
+    /// Build a .ytd texture dictionary from a directory of DDS files
+    YtdPack {
+        /// Directory of .dds files (file names become texture names)
+        input: PathBuf,
+
+        /// Output .ytd file, or the entry path inside --archive
+        #[arg(short, long, value_name = "PATH")]
+        output: String,
+
+        /// Add or replace the dictionary inside this RPF archive instead of writing a file
+        #[arg(long, value_name = "FILE")]
+        archive: Option<PathBuf>,
+    },
+
+    /// Rebuild an RSC7 resource from the sections written by `extract --raw-resource`
+    ResourcePack {
+        /// The resource's .json sidecar (e.g. "out/stream/car.ydr.json")
+        sidecar: PathBuf,
+
+        /// Output resource file, or the entry path inside --archive
+        #[arg(short, long, value_name = "PATH")]
+        output: String,
+
+        /// Add or replace the resource inside this RPF archive instead of writing a file
+        #[arg(long, value_name = "FILE")]
+        archive: Option<PathBuf>,
+    },
+
+    /// Convert meta files (.ymap, .ytyp, .ymt) to CodeWalker XML and back
+    Meta {
+        #[command(subcommand)]
+        action: MetaAction,
+    },
+
+    /// Convert .gxt2 text tables to text or JSON and back
+    Gxt2 {
+        #[command(subcommand)]
+        action: Gxt2Action,
+    },
+
+    /// Export drawables and fragments (.ydr, .ydd, .yft) to glTF
+    Model {
+        #[command(subcommand)]
+        action: ModelAction,
+    },
+
+    /// Inspect collision bounds (.ybn) and export them to OBJ
+    Ybn {
+        #[command(subcommand)]
+        action: YbnAction,
+    },
+
+    /// Inspect map placements (.ymap): entities, extents and LOD links
+    Ymap {
+        #[command(subcommand)]
+        action: YmapAction,
+    },
+
+    /// Export path nodes (.ynd) to JSON or OBJ and import them back
+    Ynd {
+        #[command(subcommand)]
+        action: YndAction,
+    },
+
+    /// Export navmeshes (.ynv) to JSON or OBJ
+    Ynv {
+        #[command(subcommand)]
+        action: YnvAction,
+    },
+
+    /// List the clips of clip dictionaries (.ycd) and export their keyframes
+    Ycd {
+        #[command(subcommand)]
+        action: YcdAction,
+    },
+
+    /// List, extract and build .awc audio containers
+    Awc {
+        #[command(subcommand)]
+        action: AwcAction,
     },
 
     /// Create an RPF archive from a directory
@@ -110,24 +281,384 @@ enum Commands {
         /// Encryption mode (none, open, ng)
         #[arg(short, long, default_value = "none")]
         encryption: String,
+
+        /// Store text XML .meta and .xml files as RBF (binary XML)
+        #[arg(long)]
+        xml_to_rbf: bool,
     },
 
-    /// Extract AES/NG keys from a GTA5.exe binary
+    /// Add a file or directory to an existing RPF archive in place
+    Add {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// File or directory to add
+        source: PathBuf,
+
+        /// Destination path inside the archive (a trailing '/' keeps the source file name)
+        dest: Option<String>,
+
+        /// Replace entries that already exist
+        #[arg(short, long)]
+        force: bool,
+
+        /// Store text XML .meta and .xml files as RBF (binary XML)
+        #[arg(long)]
+        xml_to_rbf: bool,
+    },
+
+    /// Replace the contents of a file inside an RPF archive in place
+    Replace {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Path of the entry inside the archive
+        path: String,
+
+        /// File with the new contents
+        source: PathBuf,
+    },
+
+    /// Delete files or directories from an RPF archive in place
+    Rm {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Paths of the entries to delete
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Move or rename a file or directory inside an RPF archive in place
+    Mv {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Current path inside the archive
+        from: String,
+
+        /// New path inside the archive
+        to: String,
+    },
+
+    /// Extract AES/NG keys from a GTA5.exe binary and cache them for later commands
     ExtractKeys {
         /// Path to GTA5.exe
         #[arg(long, value_name = "FILE")]
         exe: PathBuf,
 
-        /// Directory to save extracted keys into
+        /// Also save the extracted keys into this directory
         #[arg(short, long, value_name = "DIR")]
-        output: PathBuf,
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum YtdAction {
+    /// Replace one texture of a .ytd inside an RPF archive with a DDS file
+    Replace {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ytd file inside the archive
+        ytd: String,
+
+        /// Name of the texture to replace
+        texture: String,
+
+        /// DDS file with the new texture
+        dds: PathBuf,
+
+        /// Convert the DDS to the existing texture's format, size and mip count
+        /// instead of requiring them to match
+        #[arg(long)]
+        reencode: bool,
+    },
+
+    /// Add a texture from a DDS file to a .ytd inside an RPF archive
+    Add {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ytd file inside the archive
+        ytd: String,
+
+        /// DDS file with the new texture
+        dds: PathBuf,
+
+        /// Texture name (default: the DDS file name without extension)
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetaAction {
+    /// Convert a meta file inside an RPF archive to XML
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the meta file inside the archive
+        path: String,
+
+        /// Output XML file (default: <file name>.xml)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Convert XML to a meta file and add or replace it inside an RPF archive
+    Import {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Path of the meta file inside the archive
+        path: String,
+
+        /// XML file to convert
+        xml: PathBuf,
+
+        /// Meta file (RSC7 or PSO) whose structure definitions to use (default: the entry being
+        /// replaced, or the built-in .ymap/.ytyp definitions for a new entry)
+        #[arg(long, value_name = "FILE")]
+        schema: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Gxt2Action {
+    /// Write the strings of a .gxt2 inside an RPF archive to a text or JSON file
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .gxt2 file inside the archive
+        path: String,
+
+        /// Output file; JSON if it ends in .json (default: <file name>.txt)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Build a .gxt2 from a text or JSON file and add or replace it inside an RPF archive
+    Import {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Path of the .gxt2 file inside the archive
+        path: String,
+
+        /// Text ("LABEL = text" lines) or JSON file, as written by `gxt2 export`
+        input: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelAction {
+    /// Convert a .ydr, .ydd or .yft inside an RPF archive to glTF 2.0 with its textures
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the model inside the archive
+        path: String,
+
+        /// Output .glb, or .gltf with a sibling .bin (default: <file stem>.glb)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Level of detail to export
+        #[arg(long, value_enum, default_value_t = model::Lod::High)]
+        lod: model::Lod,
+
+        /// Extra texture dictionary inside the archive to take textures from (repeatable)
+        #[arg(long, value_name = "PATH")]
+        ytd: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum YmapAction {
+    /// List the entities of a .ymap with the stored and recomputed extents, flagging stale
+    /// extents and broken LOD parent indices
+    Inspect {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ymap, which may run through nested archives
+        /// (default: every .ymap in the archive)
+        path: Option<String>,
+
+        /// Also scan archives nested inside the archive (only without a path)
+        #[arg(short, long, conflicts_with = "path")]
+        recursive: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum YndAction {
+    /// Write the nodes, links and junctions of a .ynd to JSON, or to OBJ for viewing
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ynd, which may run through nested archives
+        path: String,
+
+        /// Output file; OBJ if it ends in .obj (default: <file name>.json)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Build a .ynd from JSON and add or replace it inside an RPF archive
+    Import {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Path of the .ynd inside the archive
+        path: String,
+
+        /// JSON file, as written by `ynd export`
+        input: PathBuf,
     },
 }
 
-fn load_keys(path: Option<&Path>) -> Result<Option<GtaKeys>> {
-    match path {
-        Some(p) => Ok(Some(GtaKeys::load_from_path(p)?)),
-        None    => Ok(None),
+#[derive(Subcommand)]
+enum YnvAction {
+    /// Write the polygons, adjacency and portals of a .ynv to JSON, or to OBJ for viewing
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ynv, which may run through nested archives
+        path: String,
+
+        /// Output file; OBJ if it ends in .obj (default: <file name>.json)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum YcdAction {
+    /// Show the clips of a .ycd: names, hashes, durations, frame counts and bone tracks
+    List {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ycd, which may run through nested archives
+        path: String,
+    },
+
+    /// Decode the keyframes of one clip, per track and frame, to JSON or CSV
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ycd, which may run through nested archives
+        path: String,
+
+        /// Clip name or hash (may be omitted when the dictionary holds one clip)
+        clip: Option<String>,
+
+        /// Output file; CSV if it ends in .csv (default: <clip>.json)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum YbnAction {
+    /// Show the bounds of a .ybn: types, extents, polygon counts, materials and flags
+    Info {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ybn, which may run through nested archives
+        /// (e.g. "x64/levels/gta5/_citye/downtown_01/dt1_01.rpf/dt1_01_0.ybn")
+        path: String,
+    },
+
+    /// Write the collision geometry of a .ybn to OBJ, grouped by material
+    Export {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .ybn, which may run through nested archives
+        path: String,
+
+        /// Output .obj file; a .mtl is written next to it (default: <file stem>.obj)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AwcAction {
+    /// List the streams of an .awc inside an RPF archive
+    List {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .awc file inside the archive
+        path: String,
+    },
+
+    /// Write the streams of an .awc inside an RPF archive to WAV files (raw data for other codecs)
+    Extract {
+        /// Path to the RPF archive
+        archive: PathBuf,
+
+        /// Name or path of the .awc file inside the archive
+        path: String,
+
+        /// Output directory (default: <file stem>)
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+
+    /// Build an .awc from a directory of mono 16-bit WAV files, one stream per file
+    Build {
+        /// Directory of .wav files; each file name becomes a stream name
+        input: PathBuf,
+
+        /// Output .awc file, or the entry path inside --archive
+        #[arg(short, long, value_name = "PATH")]
+        output: String,
+
+        /// Add or replace the .awc inside this RPF archive instead of writing a file
+        #[arg(long, value_name = "FILE")]
+        archive: Option<PathBuf>,
+
+        /// Encode streams as IMA ADPCM (about 4:1) instead of 16-bit PCM
+        #[arg(long)]
+        adpcm: bool,
+    },
+}
+
+impl Commands {
+    /// The archive a command operates on, used to look for a GTA5.exe nearby.
+    fn archive(&self) -> Option<&Path> {
+        match self {
+            Self::Info { archive } | Self::List { archive, .. } | Self::Extract { archive, .. }
+            | Self::Verify { archive, .. } | Self::Tree { archive, .. } | Self::YtdAll { archive, .. }
+            | Self::Resinfo { archive, .. }
+            | Self::Add { archive, .. } | Self::Replace { archive, .. } | Self::Rm { archive, .. }
+            | Self::Mv { archive, .. } => Some(archive),
+            Self::Ytd { action: Some(YtdAction::Replace { archive, .. } | YtdAction::Add { archive, .. }), .. } => Some(archive),
+            Self::Meta { action: MetaAction::Export { archive, .. } | MetaAction::Import { archive, .. } } => Some(archive),
+            Self::Gxt2 { action: Gxt2Action::Export { archive, .. } | Gxt2Action::Import { archive, .. } } => Some(archive),
+            Self::Model { action: ModelAction::Export { archive, .. } } => Some(archive),
+            Self::Ybn { action: YbnAction::Info { archive, .. } | YbnAction::Export { archive, .. } } => Some(archive),
+            Self::Ymap { action: YmapAction::Inspect { archive, .. } } => Some(archive),
+            Self::Ynd { action: YndAction::Export { archive, .. } | YndAction::Import { archive, .. } } => Some(archive),
+            Self::Ynv { action: YnvAction::Export { archive, .. } } => Some(archive),
+            Self::Ycd { action: YcdAction::List { archive, .. } | YcdAction::Export { archive, .. } } => Some(archive),
+            Self::Awc { action: AwcAction::List { archive, .. } | AwcAction::Extract { archive, .. } } => Some(archive),
+            Self::Awc { action: AwcAction::Build { archive, .. } } => archive.as_deref(),
+            Self::Ytd { archive, .. } | Self::YtdPack { archive, .. } | Self::ResourcePack { archive, .. } => archive.as_deref(),
+            Self::Create { .. } | Self::ExtractKeys { .. } => None,
+        }
     }
 }
 
@@ -138,23 +669,122 @@ fn main() -> Result<()> {
         env_logger::Env::default().default_filter_or(if cli.verbose { "debug" } else { "info" })
     ).init();
 
-    let keys = load_keys(cli.keys.as_deref())?;
+    let keys = match cli.command {
+        Commands::ExtractKeys { .. } => None,
+        ref command => crypto::keys::resolve(cli.keys.as_deref(), command.archive())?,
+    };
 
     match cli.command {
-        Commands::Info        { archive }                    => info::run(&archive, keys.as_ref()),
-        Commands::List        { archive, pattern, detailed } => list::run(&archive, pattern.as_deref(), detailed, keys.as_ref()),
-        Commands::Extract     { archive, output, pattern, recursive } => extract::run(&archive, output.as_deref(), pattern.as_deref(), recursive, keys.as_ref()),
-        Commands::Verify      { archive }                    => verify::run(&archive, keys.as_ref()),
-        Commands::Tree        { archive, depth }             => tree::run(&archive, depth, keys.as_ref()),
-        Commands::Ytd         { archive, ytd: ytd_name, output } => {
-            ytd::run(&archive, &ytd_name, output.as_deref(), keys.as_ref())
+        Commands::Info        { archive }                    => info::run(&archive, cli.format, keys.as_ref()),
+        Commands::List        { archive, pattern, detailed, filter } => {
+            list::run(&archive, pattern.as_deref(), &filter, detailed, cli.format, keys.as_ref())
+        }
+        Commands::Extract     { archive, output, pattern, args, filter } => {
+            extract::run(&archive, output.as_deref(), pattern.as_deref(), &filter, &args, keys.as_ref())
+        }
+        Commands::Verify      { archive, recursive, report, report_format, filter } => {
+            verify::run(&archive, recursive, &filter, report.as_deref(), report_format, cli.format, keys.as_ref())
+        }
+        Commands::Tree        { archive, depth, filter }     => tree::run(&archive, depth, &filter, cli.format, keys.as_ref()),
+        Commands::Resinfo     { archive, path }              => resinfo::run(&archive, &path, cli.format, keys.as_ref()),
+        Commands::Ytd { action: Some(action), .. } => match action {
+            YtdAction::Replace { archive, ytd, texture, dds, reencode } => {
+                ytd_edit::replace(&archive, &ytd, &texture, &dds, reencode, keys.as_ref())
+            }
+            YtdAction::Add { archive, ytd, dds, name } => {
+                ytd_edit::add(&archive, &ytd, &dds, name.as_deref(), keys.as_ref())
+            }
+        },
+        Commands::Ytd { archive: Some(archive), ytd: Some(ytd_name), output, export, .. } => {
+            ytd::run(&archive, &ytd_name, output.as_deref(), &export, keys.as_ref())
+        }
+        Commands::Ytd { .. } => unreachable!("clap requires an archive and a ytd"),
+        Commands::YtdAll { archive, output, recursive, export, filter } => {
+            ytd_all::run(&archive, output.as_deref(), recursive, &filter, &export, cli.format, keys.as_ref())
+        }
+        Commands::YtdPack { input, output, archive } => {
+            ytd_pack::run(&input, &output, archive.as_deref(), keys.as_ref())
+        }
+        Commands::ResourcePack { sidecar, output, archive } => {
+            resource_pack::run(&sidecar, &output, archive.as_deref(), keys.as_ref())
+        }
+        Commands::Meta { action: MetaAction::Export { archive, path, output } } => {
+            let names = names::Names::load(&cli.names)?;
+            meta_cmd::export(&archive, &path, output.as_deref(), &names, keys.as_ref())
+        }
+        Commands::Meta { action: MetaAction::Import { archive, path, xml, schema } } => {
+            meta_cmd::import(&archive, &path, &xml, schema.as_deref(), keys.as_ref())
         }
-        Commands::Create { input, output, version, encryption } => {
-            create::run(&input, &output, version, &encryption, keys.as_ref())
+        Commands::Gxt2 { action: Gxt2Action::Export { archive, path, output } } => {
+            let names = names::Names::load(&cli.names)?;
+            gxt2_cmd::export(&archive, &path, output.as_deref(), &names, keys.as_ref())
         }
+        Commands::Gxt2 { action: Gxt2Action::Import { archive, path, input } } => {
+            gxt2_cmd::import(&archive, &path, &input, keys.as_ref())
+        }
+        Commands::Model { action: ModelAction::Export { archive, path, output, lod, ytd } } => {
+            let names = names::Names::load(&cli.names)?;
+            model::export(&archive, &path, output.as_deref(), lod, &ytd, &names, keys.as_ref())
+        }
+        Commands::Ybn { action: YbnAction::Info { archive, path } } => ybn::info(&archive, &path, cli.format, keys.as_ref()),
+        Commands::Ybn { action: YbnAction::Export { archive, path, output } } => {
+            ybn::export(&archive, &path, output.as_deref(), keys.as_ref())
+        }
+        Commands::Ymap { action: YmapAction::Inspect { archive, path, recursive } } => {
+            let names = names::Names::load(&cli.names)?;
+            ymap_cmd::inspect(&archive, path.as_deref(), recursive, cli.format, &names, keys.as_ref())
+        }
+        Commands::Ynd { action: YndAction::Export { archive, path, output } } => {
+            let names = names::Names::load(&cli.names)?;
+            ynd::export(&archive, &path, output.as_deref(), &names, keys.as_ref())
+        }
+        Commands::Ynd { action: YndAction::Import { archive, path, input } } => {
+            ynd::import(&archive, &path, &input, keys.as_ref())
+        }
+        Commands::Ynv { action: YnvAction::Export { archive, path, output } } => {
+            ynv::export(&archive, &path, output.as_deref(), keys.as_ref())
+        }
+        Commands::Ycd { action: YcdAction::List { archive, path } } => ycd::list(&archive, &path, cli.format, keys.as_ref()),
+        Commands::Ycd { action: YcdAction::Export { archive, path, clip, output } } => {
+            ycd::export(&archive, &path, clip.as_deref(), output.as_deref(), keys.as_ref())
+        }
+        Commands::Awc { action: AwcAction::List { archive, path } } => {
+            let names = names::Names::load(&cli.names)?;
+            awc_cmd::list(&archive, &path, cli.format, &names, keys.as_ref())
+        }
+        Commands::Awc { action: AwcAction::Extract { archive, path, output } } => {
+            let names = names::Names::load(&cli.names)?;
+            awc_cmd::extract(&archive, &path, output.as_deref(), &names, keys.as_ref())
+        }
+        Commands::Awc { action: AwcAction::Build { input, output, archive, adpcm } } => {
+            awc_cmd::build(&input, &output, archive.as_deref(), adpcm, keys.as_ref())
+        }
+        Commands::Create { input, output, version, encryption, xml_to_rbf } => {
+            create::run(&input, &output, version, &encryption, xml_to_rbf, keys.as_ref())
+        }
+        Commands::Add     { archive, source, dest, force, xml_to_rbf } => {
+            add::run(&archive, &source, dest.as_deref(), force, xml_to_rbf, keys.as_ref())
+        }
+        Commands::Replace { archive, path, source }        => replace::run(&archive, &path, &source, keys.as_ref()),
+        Commands::Rm      { archive, paths }               => rm::run(&archive, &paths, keys.as_ref()),
+        Commands::Mv      { archive, from, to }            => mv::run(&archive, &from, &to, keys.as_ref()),
         Commands::ExtractKeys { exe, output }                => {
-            GtaKeys::extract_from_exe(&exe, Some(&output))?;
+            crypto::keys::extract_and_cache(&exe, output.as_deref())?;
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from(["rpf", "-v", "create", "dir", "-o", "out.rpf", "-v", "2"]).unwrap();
+        assert!(cli.verbose);
+        assert!(matches!(cli.command, Commands::Create { version: 2, .. }));
+    }
+}
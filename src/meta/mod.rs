@@ -0,0 +1,326 @@
+// RSC7 meta resources: .ymap, .ytyp and the RSC7 flavour of .ymt.
+//
+// A meta resource is a set of typed data blocks plus the structure and enum definitions
+// that describe them, all stored in the file itself. The 0x70-byte root holds pointers to
+// the structure infos, enum infos and data block table, their counts, and the 1-based
+// index of the block whose first structure is the document root. Inside data blocks,
+// pointers are not virtual addresses but `block index + 1 | offset << 12`, so blocks can
+// be moved freely. Layouts follow CodeWalker's `Meta`, `MetaStructureInfo` and
+// `MetaEnumInfo`. PSO files are read into the same model (see `pso`).
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::{u16_at, u32_at, u64_at, Resource, ResourceBuilder, Section, SYSTEM_BASE};
+
+pub mod pso;
+pub mod schema;
+pub mod xml;
+
+/// Resource version of meta files.
+pub const META_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 0x70;
+const STRUCT_INFO_SIZE: usize = 0x20;
+const ENTRY_INFO_SIZE: usize = 0x10;
+const ENUM_INFO_SIZE: usize = 0x18;
+const BLOCK_INFO_SIZE: usize = 0x10;
+
+/// Entry name of the pseudo-entries that describe array elements.
+pub const ARRAY_INFO: u32 = 0x100;
+
+/// Field types of structure entries (`MetaStructureEntryDataType`).
+pub mod data_type {
+    pub const BOOLEAN: u8 = 0x01;
+    pub const STRUCTURE: u8 = 0x05;
+    pub const STRUCTURE_POINTER: u8 = 0x07;
+    pub const SIGNED_BYTE: u8 = 0x10;
+    pub const UNSIGNED_BYTE: u8 = 0x11;
+    pub const SIGNED_SHORT: u8 = 0x12;
+    pub const UNSIGNED_SHORT: u8 = 0x13;
+    pub const SIGNED_INT: u8 = 0x14;
+    pub const UNSIGNED_INT: u8 = 0x15;
+    pub const FLOAT: u8 = 0x21;
+    pub const FLOAT_XY: u8 = 0x32;
+    pub const FLOAT_XYZ: u8 = 0x33;
+    pub const FLOAT_XYZW: u8 = 0x34;
+    pub const ARRAY_OF_CHARS: u8 = 0x40;
+    pub const CHAR_POINTER: u8 = 0x44;
+    pub const HASH: u8 = 0x4A;
+    pub const ARRAY_OF_BYTES: u8 = 0x50;
+    pub const ARRAY: u8 = 0x52;
+    pub const DATA_BLOCK_POINTER: u8 = 0x59;
+    pub const BYTE_ENUM: u8 = 0x60;
+    pub const INT_ENUM: u8 = 0x62;
+    pub const INT_FLAGS1: u8 = 0x63;
+    pub const SHORT_FLAGS: u8 = 0x64;
+    pub const INT_FLAGS2: u8 = 0x65;
+}
+
+/// Name of the data blocks holding strings referenced by char pointers.
+pub const STRING_BLOCK: u32 = 0x10;
+
+pub struct StructInfo {
+    pub name     : u32,
+    pub key      : u32,
+    pub unknown_8: u32,
+    pub size     : u32,
+    pub entries  : Vec<EntryInfo>,
+}
+
+#[derive(Clone, Copy)]
+pub struct EntryInfo {
+    pub name     : u32,
+    pub offset   : u32,
+    pub kind     : u8,
+    pub unknown_9: u8,
+    /// For arrays, the index of the entry describing the elements.
+    pub ref_index: i16,
+    /// Structure or enum name, or the length of inline arrays.
+    pub ref_key  : u32,
+}
+
+impl StructInfo {
+    /// The entry named `name` (a field name hash).
+    pub fn entry(&self, name: u32) -> Option<&EntryInfo> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// The entry describing the elements of array entry `e`.
+    pub fn array_element(&self, e: &EntryInfo) -> Result<&EntryInfo> {
+        usize::try_from(e.ref_index).ok()
+            .and_then(|i| self.entries.get(i))
+            .context("array has no element definition")
+    }
+}
+
+pub struct EnumInfo {
+    pub name   : u32,
+    pub key    : u32,
+    pub entries: Vec<(u32, i32)>,
+}
+
+pub struct DataBlock {
+    pub name: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct Meta {
+    /// The root struct as stored; pointers and counts are rewritten on build.
+    header     : [u8; HEADER_SIZE],
+    pub name   : Option<String>,
+    pub structs: Vec<StructInfo>,
+    pub enums  : Vec<EnumInfo>,
+    pub blocks : Vec<DataBlock>,
+    /// 0-based index of the root block.
+    pub root   : usize,
+    /// Set for documents read from a PSO file, which build back to one.
+    pub pso    : Option<pso::Pso>,
+}
+
+impl Meta {
+    pub fn read(res: &Resource) -> Result<Self> {
+        if res.version != META_VERSION {
+            bail!("not a meta resource (resource version {}, expected {})", res.version, META_VERSION);
+        }
+        let header: [u8; HEADER_SIZE] = res.slice(SYSTEM_BASE, HEADER_SIZE)
+            .context("system section too small for a meta header")?
+            .try_into().unwrap();
+
+        let root = u32_at(&header, 0x1C) as usize;
+        let (struct_count, enum_count, block_count) =
+            (u16_at(&header, 0x48) as usize, u16_at(&header, 0x4A) as usize, u16_at(&header, 0x4C) as usize);
+        let list = |ptr_at: usize, count: usize, size: usize, what: &str| -> Result<&[u8]> {
+            let ptr = u64_at(&header, ptr_at);
+            if count == 0 { return Ok(&[]); }
+            res.slice(ptr, count * size).with_context(|| format!("{} out of bounds (0x{:X})", what, ptr))
+        };
+
+        let mut structs = Vec::with_capacity(struct_count);
+        for info in list(0x20, struct_count, STRUCT_INFO_SIZE, "structure infos")?.chunks_exact(STRUCT_INFO_SIZE) {
+            let count = u16_at(info, 0x1E) as usize;
+            let entries = if count == 0 { &[][..] } else {
+                res.slice(u64_at(info, 0x10), count * ENTRY_INFO_SIZE).context("structure entries out of bounds")?
+            };
+            structs.push(StructInfo {
+                name     : u32_at(info, 0x00),
+                key      : u32_at(info, 0x04),
+                unknown_8: u32_at(info, 0x08),
+                size     : u32_at(info, 0x18),
+                entries  : entries.chunks_exact(ENTRY_INFO_SIZE).map(|e| EntryInfo {
+                    name     : u32_at(e, 0x00),
+                    offset   : u32_at(e, 0x04),
+                    kind     : e[0x08],
+                    unknown_9: e[0x09],
+                    ref_index: u16_at(e, 0x0A) as i16,
+                    ref_key  : u32_at(e, 0x0C),
+                }).collect(),
+            });
+        }
+
+        let mut enums = Vec::with_capacity(enum_count);
+        for info in list(0x28, enum_count, ENUM_INFO_SIZE, "enum infos")?.chunks_exact(ENUM_INFO_SIZE) {
+            let count = u32_at(info, 0x10) as usize;
+            let entries = if count == 0 { &[][..] } else {
+                res.slice(u64_at(info, 0x08), count * 8).context("enum entries out of bounds")?
+            };
+            enums.push(EnumInfo {
+                name   : u32_at(info, 0x00),
+                key    : u32_at(info, 0x04),
+                entries: entries.chunks_exact(8).map(|e| (u32_at(e, 0), u32_at(e, 4) as i32)).collect(),
+            });
+        }
+
+        let mut blocks = Vec::with_capacity(block_count);
+        for (i, info) in list(0x30, block_count, BLOCK_INFO_SIZE, "data blocks")?.chunks_exact(BLOCK_INFO_SIZE).enumerate() {
+            let (len, ptr) = (u32_at(info, 0x04) as usize, u64_at(info, 0x08));
+            let data = res.slice(ptr, len).with_context(|| format!("data block {} out of bounds (0x{:X})", i + 1, ptr))?;
+            blocks.push(DataBlock { name: u32_at(info, 0x00), data: data.to_vec() });
+        }
+
+        if root == 0 || root > blocks.len() {
+            bail!("root block index {} is out of range (1..{})", root, blocks.len());
+        }
+        let name = match u64_at(&header, 0x38) {
+            0   => None,
+            ptr => res.c_str(ptr),
+        };
+        Ok(Self { header, name, structs, enums, blocks, root: root - 1, pso: None })
+    }
+
+    /// An empty document using the header and definitions of `schema`.
+    pub fn with_schema(schema: Meta) -> Self {
+        Self { blocks: Vec::new(), root: 0, ..schema }
+    }
+
+    pub fn struct_info(&self, name: u32) -> Option<&StructInfo> {
+        self.structs.iter().find(|s| s.name == name)
+    }
+
+    pub fn enum_info(&self, name: u32) -> Option<&EnumInfo> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+
+    /// `len` bytes at a data block pointer. Long arrays may continue into the next blocks.
+    pub fn data(&self, ptr: u32, len: usize) -> Result<Vec<u8>> {
+        let (mut block, mut offset) = decode_pointer(ptr).context("null pointer")?;
+        let mut out = Vec::with_capacity(len);
+        while out.len() < len {
+            let b = self.blocks.get(block)
+                .with_context(|| format!("pointer 0x{:08X} runs past the last data block", ptr))?;
+            if offset > b.data.len() {
+                bail!("pointer 0x{:08X} is outside data block {}", ptr, block + 1);
+            }
+            let take = (len - out.len()).min(b.data.len() - offset);
+            out.extend_from_slice(&b.data[offset..offset + take]);
+            (block, offset) = (block + 1, 0);
+        }
+        Ok(out)
+    }
+
+    /// Build the meta file: a complete RSC7 file, or a PSO file for documents read from one.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        if let Some(pso) = &self.pso {
+            return pso::build(self, pso);
+        }
+        let mut b = ResourceBuilder::new();
+        let mut header = self.header;
+        header[0x08..0x10].fill(0);
+        header[0x16] = 0; // no "useless" data
+        header[0x1C..0x20].copy_from_slice(&(self.root as u32 + 1).to_le_bytes());
+        header[0x20..0x48].fill(0);
+        header[0x48..0x4A].copy_from_slice(&u16::try_from(self.structs.len())?.to_le_bytes());
+        header[0x4A..0x4C].copy_from_slice(&u16::try_from(self.enums.len())?.to_le_bytes());
+        header[0x4C..0x4E].copy_from_slice(&u16::try_from(self.blocks.len())?.to_le_bytes());
+        let root = b.add(Section::System, header.to_vec());
+        let pages = b.add_pages_info();
+        b.pointer(root, 0x08, pages);
+
+        if !self.structs.is_empty() {
+            let mut table = Vec::with_capacity(self.structs.len() * STRUCT_INFO_SIZE);
+            for s in &self.structs {
+                let mut info = [0u8; STRUCT_INFO_SIZE];
+                info[0x00..0x04].copy_from_slice(&s.name.to_le_bytes());
+                info[0x04..0x08].copy_from_slice(&s.key.to_le_bytes());
+                info[0x08..0x0C].copy_from_slice(&s.unknown_8.to_le_bytes());
+                info[0x18..0x1C].copy_from_slice(&s.size.to_le_bytes());
+                info[0x1E..0x20].copy_from_slice(&u16::try_from(s.entries.len())?.to_le_bytes());
+                table.extend_from_slice(&info);
+            }
+            let infos = b.add(Section::System, table);
+            b.pointer(root, 0x20, infos);
+
+            for (i, s) in self.structs.iter().enumerate().filter(|(_, s)| !s.entries.is_empty()) {
+                let entries = s.entries.iter().flat_map(|e| {
+                    let mut raw = [0u8; ENTRY_INFO_SIZE];
+                    raw[0x00..0x04].copy_from_slice(&e.name.to_le_bytes());
+                    raw[0x04..0x08].copy_from_slice(&e.offset.to_le_bytes());
+                    raw[0x08] = e.kind;
+                    raw[0x09] = e.unknown_9;
+                    raw[0x0A..0x0C].copy_from_slice(&e.ref_index.to_le_bytes());
+                    raw[0x0C..0x10].copy_from_slice(&e.ref_key.to_le_bytes());
+                    raw
+                }).collect();
+                let entries = b.add(Section::System, entries);
+                b.pointer(infos, i * STRUCT_INFO_SIZE + 0x10, entries);
+            }
+        }
+
+        if !self.enums.is_empty() {
+            let infos = b.add(Section::System, self.enums.iter().flat_map(|e| {
+                let mut info = [0u8; ENUM_INFO_SIZE];
+                info[0x00..0x04].copy_from_slice(&e.name.to_le_bytes());
+                info[0x04..0x08].copy_from_slice(&e.key.to_le_bytes());
+                info[0x10..0x14].copy_from_slice(&(e.entries.len() as u32).to_le_bytes());
+                info
+            }).collect());
+            b.pointer(root, 0x28, infos);
+
+            for (i, e) in self.enums.iter().enumerate().filter(|(_, e)| !e.entries.is_empty()) {
+                let entries = e.entries.iter()
+                    .flat_map(|&(name, value)| [name.to_le_bytes(), value.to_le_bytes()].concat())
+                    .collect();
+                let entries = b.add(Section::System, entries);
+                b.pointer(infos, i * ENUM_INFO_SIZE + 0x08, entries);
+            }
+        }
+
+        if !self.blocks.is_empty() {
+            let table = b.add(Section::System, self.blocks.iter().flat_map(|block| {
+                let mut info = [0u8; BLOCK_INFO_SIZE];
+                info[0x00..0x04].copy_from_slice(&block.name.to_le_bytes());
+                info[0x04..0x08].copy_from_slice(&(block.data.len() as u32).to_le_bytes());
+                info
+            }).collect());
+            b.pointer(root, 0x30, table);
+            for (i, block) in self.blocks.iter().enumerate() {
+                let data = b.add(Section::System, block.data.clone());
+                b.pointer(table, i * BLOCK_INFO_SIZE + 0x08, data);
+            }
+        }
+
+        if let Some(name) = &self.name {
+            let mut bytes = name.clone().into_bytes();
+            bytes.push(0);
+            let name = b.add(Section::System, bytes);
+            b.pointer(root, 0x38, name);
+        }
+
+        b.build(META_VERSION)
+    }
+}
+
+/// Encode a pointer into a data block.
+pub fn encode_pointer(block: usize, offset: usize) -> Result<u32> {
+    if block >= 0xFFF || offset > 0xF_FFFF {
+        bail!("data block {} offset 0x{:X} cannot be addressed", block + 1, offset);
+    }
+    Ok((block as u32 + 1) | (offset as u32) << 12)
+}
+
+/// Decode a data block pointer: `(0-based block, offset)`, or `None` for null.
+pub fn decode_pointer(ptr: u32) -> Option<(usize, usize)> {
+    match ptr & 0xFFF {
+        0     => None,
+        block => Some((block as usize - 1, (ptr >> 12) as usize)),
+    }
+}
@@ -0,0 +1,621 @@
+// PSO meta files: most .ymt and some .meta files, starting with `PSIN`.
+//
+// A PSO file is a run of big-endian sections, each an ident and a length that includes
+// the 8-byte header. `PSIN` holds the data blocks; `PMAP` maps them (type name, offset
+// from the start of `PSIN`, length) and names the 1-based root block; `PSCH` holds the
+// structure and enum definitions behind an index of `(name, offset)` pairs. `STRF`, `STRS`,
+// `STRE`, `PSIG` and `CHKS` (names, signature, checksum) are kept as read. Layouts follow
+// CodeWalker's `PsoFile`, `PsoStructureInfo` and `PsoEnumInfo`.
+//
+// Reading converts a PSO file into the same `Meta` model as RSC7 files: the definitions are
+// translated to meta field types and the data is byte-swapped by walking it from the root,
+// so the XML conversion is shared. Building walks it back and reuses the file's own
+// definitions. Pointers and array headers mean the same in both formats; blocks written
+// from XML are named and laid out as in RSC7 files.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+
+use super::{data_type as dt, decode_pointer, DataBlock, EntryInfo, EnumInfo, Meta, StructInfo, ARRAY_INFO, HEADER_SIZE};
+
+pub const PSIN: u32 = u32::from_be_bytes(*b"PSIN");
+const PMAP: u32 = u32::from_be_bytes(*b"PMAP");
+const PSCH: u32 = u32::from_be_bytes(*b"PSCH");
+const CHKS: u32 = u32::from_be_bytes(*b"CHKS");
+
+const SECTION_HEADER_SIZE: usize = 8;
+const MAP_ENTRY_SIZE: usize = 16;
+const STRUCT_ENTRY_SIZE: usize = 12;
+/// Alignment of data blocks inside `PSIN`.
+const BLOCK_ALIGN: usize = 16;
+
+/// Field types of PSO structure entries (`PsoDataType`).
+pub mod pso_type {
+    pub const BOOL: u8 = 0x00;
+    pub const SBYTE: u8 = 0x01;
+    pub const UBYTE: u8 = 0x02;
+    pub const SSHORT: u8 = 0x03;
+    pub const USHORT: u8 = 0x04;
+    pub const SINT: u8 = 0x05;
+    pub const UINT: u8 = 0x06;
+    pub const FLOAT: u8 = 0x07;
+    pub const FLOAT2: u8 = 0x08;
+    pub const FLOAT3: u8 = 0x09;
+    pub const FLOAT4: u8 = 0x0A;
+    pub const STRING: u8 = 0x0B;
+    pub const STRUCTURE: u8 = 0x0C;
+    pub const ARRAY: u8 = 0x0D;
+    pub const ENUM: u8 = 0x0E;
+    pub const FLAGS: u8 = 0x0F;
+    pub const FLOAT3A: u8 = 0x14;
+    pub const FLOAT4A: u8 = 0x15;
+}
+use pso_type as pt;
+
+/// The parts of a PSO file that are written back as read.
+pub struct Pso {
+    /// Structure and enum definitions in schema index order.
+    defs    : Vec<Def>,
+    /// Sections other than `PSIN`, `PMAP` and `PSCH`, header included, in file order.
+    sections: Vec<Vec<u8>>,
+}
+
+enum Def {
+    Struct(StructDef),
+    Enum(u32, Vec<(u32, i32)>),
+}
+
+struct StructDef {
+    name     : u32,
+    unknown_2: u8,
+    size     : u32,
+    unknown_c: u32,
+    entries  : Vec<EntryDef>,
+}
+
+struct EntryDef {
+    name   : u32,
+    kind   : u8,
+    subtype: u8,
+    offset : u16,
+    ref_key: u32,
+}
+
+fn be16(data: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes(data[off..off + 2].try_into().unwrap())
+}
+
+fn be32(data: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+fn ident(id: u32) -> String {
+    String::from_utf8_lossy(&id.to_be_bytes()).into_owned()
+}
+
+/// Whether `data` starts like a PSO file.
+pub fn is_pso(data: &[u8]) -> bool {
+    data.len() >= 4 && be32(data, 0) == PSIN
+}
+
+// ─── Read ────────────────────────────────────────────────────────────────────
+
+/// Read a PSO file into a meta document.
+pub fn read(data: &[u8]) -> Result<Meta> {
+    let (mut psin, mut pmap, mut psch, mut sections) = (None, None, None, Vec::new());
+    let mut pos = 0;
+    while pos + SECTION_HEADER_SIZE <= data.len() {
+        let (id, len) = (be32(data, pos), be32(data, pos + 4) as usize);
+        if len < SECTION_HEADER_SIZE || pos + len > data.len() {
+            bail!("{} section at 0x{:X} runs past the end of the file", ident(id), pos);
+        }
+        let section = &data[pos..pos + len];
+        match id {
+            PSIN => psin = Some(section),
+            PMAP => pmap = Some(section),
+            PSCH => psch = Some(section),
+            _    => sections.push(section.to_vec()),
+        }
+        pos += len;
+    }
+    let psin = psin.context("PSO file has no PSIN section")?;
+    let pmap = pmap.context("PSO file has no PMAP section")?;
+    let psch = psch.context("PSO file has no PSCH section")?;
+
+    let defs = read_schema(psch)?;
+    let (structs, enums) = convert(&defs);
+
+    if pmap.len() < 16 {
+        bail!("PMAP section is too small");
+    }
+    let (root, count) = (be32(pmap, 8) as usize, be16(pmap, 12) as usize);
+    let entries = pmap.get(16..16 + count * MAP_ENTRY_SIZE).context("PMAP entries run past the section")?;
+    let mut blocks = Vec::with_capacity(count);
+    for (i, e) in entries.chunks_exact(MAP_ENTRY_SIZE).enumerate() {
+        let (offset, len) = (be32(e, 4) as usize, be32(e, 12) as usize);
+        let bytes = psin.get(offset..offset + len)
+            .with_context(|| format!("data block {} (0x{:X}, {} bytes) is outside PSIN", i + 1, offset, len))?;
+        blocks.push(DataBlock { name: be32(e, 0), data: bytes.to_vec() });
+    }
+    if root == 0 || root > blocks.len() {
+        bail!("root block index {} is out of range (1..{})", root, blocks.len());
+    }
+
+    swap(&structs, &mut blocks, root - 1, true)?;
+    Ok(Meta {
+        header : [0; HEADER_SIZE],
+        name   : None,
+        structs,
+        enums,
+        blocks,
+        root   : root - 1,
+        pso    : Some(Pso { defs, sections }),
+    })
+}
+
+fn read_schema(psch: &[u8]) -> Result<Vec<Def>> {
+    let count = be32(psch.get(..12).context("PSCH section is too small")?, 8) as usize;
+    let index = psch.get(12..12 + count * 8).context("PSCH index runs past the section")?;
+    let mut defs = Vec::with_capacity(count);
+    for item in index.chunks_exact(8) {
+        let (name, offset) = (be32(item, 0), be32(item, 4) as usize);
+        let header = psch.get(offset..offset + 4)
+            .with_context(|| format!("definition of 0x{:08X} is outside PSCH", name))?;
+        let x = be32(header, 0);
+        defs.push(match x >> 24 {
+            0 => {
+                let count = (x & 0xFFFF) as usize;
+                let raw = psch.get(offset..offset + 12 + count * STRUCT_ENTRY_SIZE)
+                    .with_context(|| format!("structure 0x{:08X} runs past PSCH", name))?;
+                Def::Struct(StructDef {
+                    name,
+                    unknown_2: (x >> 16) as u8,
+                    size     : be32(raw, 4),
+                    unknown_c: be32(raw, 8),
+                    entries  : raw[12..].chunks_exact(STRUCT_ENTRY_SIZE).map(|e| EntryDef {
+                        name   : be32(e, 0),
+                        kind   : e[4],
+                        subtype: e[5],
+                        offset : be16(e, 6),
+                        ref_key: be32(e, 8),
+                    }).collect(),
+                })
+            }
+            1 => {
+                let count = (x & 0xFF_FFFF) as usize;
+                let raw = psch.get(offset + 4..offset + 4 + count * 8)
+                    .with_context(|| format!("enum 0x{:08X} runs past PSCH", name))?;
+                Def::Enum(name, raw.chunks_exact(8).map(|e| (be32(e, 0), be32(e, 4) as i32)).collect())
+            }
+            kind => bail!("definition of 0x{:08X} has unknown type {}", name, kind),
+        });
+    }
+    Ok(defs)
+}
+
+/// Meta definitions equivalent to the PSO ones.
+fn convert(defs: &[Def]) -> (Vec<StructInfo>, Vec<EnumInfo>) {
+    let (mut structs, mut enums) = (Vec::new(), Vec::new());
+    for def in defs {
+        match def {
+            Def::Struct(s) => structs.push(StructInfo {
+                name     : s.name,
+                key      : 0,
+                unknown_8: 0,
+                size     : s.size,
+                entries  : s.entries.iter().map(|e| convert_entry(e, &s.entries)).collect(),
+            }),
+            Def::Enum(name, entries) => enums.push(EnumInfo { name: *name, key: 0, entries: entries.clone() }),
+        }
+    }
+    (structs, enums)
+}
+
+/// Kind given to fields with no meta equivalent; reading data through them fails.
+const UNSUPPORTED: u8 = 0;
+
+fn convert_entry(e: &EntryDef, siblings: &[EntryDef]) -> EntryInfo {
+    let index = (e.ref_key & 0xFFFF) as i16;
+    let (kind, ref_index, ref_key) = match (e.kind, e.subtype) {
+        (pt::BOOL, _)                     => (dt::BOOLEAN, 0, 0),
+        (pt::SBYTE, _)                    => (dt::SIGNED_BYTE, 0, 0),
+        (pt::UBYTE, _)                    => (dt::UNSIGNED_BYTE, 0, 0),
+        (pt::SSHORT, _)                   => (dt::SIGNED_SHORT, 0, 0),
+        (pt::USHORT, _)                   => (dt::UNSIGNED_SHORT, 0, 0),
+        (pt::SINT, _)                     => (dt::SIGNED_INT, 0, 0),
+        (pt::UINT, _)                     => (dt::UNSIGNED_INT, 0, 0),
+        (pt::FLOAT, _)                    => (dt::FLOAT, 0, 0),
+        (pt::FLOAT2, _)                   => (dt::FLOAT_XY, 0, 0),
+        (pt::FLOAT3 | pt::FLOAT3A, _)     => (dt::FLOAT_XYZ, 0, 0),
+        (pt::FLOAT4 | pt::FLOAT4A, _)     => (dt::FLOAT_XYZW, 0, 0),
+        (pt::STRING, 0)                   => (dt::ARRAY_OF_CHARS, 0, e.ref_key),
+        (pt::STRING, 3)                   => (dt::CHAR_POINTER, 0, 0),
+        (pt::STRING, 7 | 8)               => (dt::HASH, 0, 0),
+        (pt::STRUCTURE, 0)                => (dt::STRUCTURE, 0, e.ref_key),
+        (pt::STRUCTURE, 3)                => (dt::STRUCTURE_POINTER, 0, 0),
+        (pt::ARRAY, 0 | 4)                => (dt::ARRAY, index, 0),
+        (pt::ARRAY, 1 | 2 | 129)          => (dt::ARRAY_OF_BYTES, index, e.ref_key >> 16),
+        (pt::ENUM, 0)                     => (dt::INT_ENUM, 0, e.ref_key),
+        (pt::ENUM, 2)                     => (dt::BYTE_ENUM, 0, e.ref_key),
+        (pt::FLAGS, 0 | 1) => {
+            // The enum is named by an array-info entry, or else by the field itself.
+            let named = siblings.get((e.ref_key & 0xFFF) as usize).filter(|s| s.name == ARRAY_INFO);
+            let kind = if e.subtype == 0 { dt::INT_FLAGS1 } else { dt::SHORT_FLAGS };
+            (kind, 0, named.map_or(e.name, |s| s.ref_key))
+        }
+        _ => (UNSUPPORTED, 0, (e.kind as u32) << 8 | e.subtype as u32),
+    };
+    EntryInfo { name: e.name, offset: e.offset as u32, kind, unknown_9: 0, ref_index, ref_key }
+}
+
+// ─── Byte order ──────────────────────────────────────────────────────────────
+
+/// Swap the data blocks between big-endian PSO order and the little-endian order of meta
+/// documents, following every field from the root structure.
+fn swap(structs: &[StructInfo], blocks: &mut [DataBlock], root: usize, to_native: bool) -> Result<()> {
+    let name = blocks[root].name;
+    let info = structs.iter().find(|s| s.name == name)
+        .with_context(|| format!("root block type 0x{:08X} has no structure definition", name))?;
+    Swapper { structs, blocks, to_native, seen: HashSet::new() }.structure(info, (root, 0))
+}
+
+struct Swapper<'a> {
+    structs  : &'a [StructInfo],
+    blocks   : &'a mut [DataBlock],
+    to_native: bool,
+    /// Structures and arrays already swapped, by block and offset.
+    seen     : HashSet<(usize, usize)>,
+}
+
+impl<'a> Swapper<'a> {
+    fn struct_info(&self, name: u32) -> Result<&'a StructInfo> {
+        self.structs.iter().find(|s| s.name == name)
+            .with_context(|| format!("no structure definition for 0x{:08X}", name))
+    }
+
+    /// Reverse the bytes of `count` values of `size` bytes at `at`.
+    fn values(&mut self, (block, offset): (usize, usize), size: usize, count: usize) -> Result<()> {
+        let data = &mut self.blocks.get_mut(block).context("pointer to a missing data block")?.data;
+        let bytes = data.get_mut(offset..offset + size * count)
+            .with_context(|| format!("{} bytes at 0x{:X} run past data block {}", size * count, offset, block + 1))?;
+        bytes.chunks_exact_mut(size).for_each(<[u8]>::reverse);
+        Ok(())
+    }
+
+    /// Swap the `u32` at `at` and return it in native order.
+    fn word(&mut self, at: (usize, usize)) -> Result<u32> {
+        self.values(at, 4, 1)?;
+        let data = &self.blocks[at.0].data;
+        let value = u32::from_le_bytes(data[at.1..at.1 + 4].try_into().unwrap());
+        Ok(if self.to_native { value } else { value.swap_bytes() })
+    }
+
+    /// Swap a 16-byte array or string header; returns the pointer and count.
+    fn header(&mut self, at: (usize, usize)) -> Result<(u32, usize)> {
+        let ptr = self.word(at)?;
+        self.values((at.0, at.1 + 4), 4, 1)?;
+        self.values((at.0, at.1 + 8), 2, 2)?;
+        self.values((at.0, at.1 + 12), 4, 1)?;
+        let data = &self.blocks[at.0].data;
+        let count = u16::from_le_bytes(data[at.1 + 8..at.1 + 10].try_into().unwrap());
+        Ok((ptr, if self.to_native { count } else { count.swap_bytes() } as usize))
+    }
+
+    fn structure(&mut self, info: &StructInfo, at: (usize, usize)) -> Result<()> {
+        for e in info.entries.iter().filter(|e| e.name != ARRAY_INFO) {
+            self.field(info, e, (at.0, at.1 + e.offset as usize))
+                .with_context(|| format!("in field 0x{:08X} of structure 0x{:08X}", e.name, info.name))?;
+        }
+        Ok(())
+    }
+
+    fn field(&mut self, info: &StructInfo, e: &EntryInfo, at: (usize, usize)) -> Result<()> {
+        match e.kind {
+            dt::STRUCTURE => {
+                let inner = self.struct_info(e.ref_key)?;
+                self.structure(inner, at)
+            }
+            dt::STRUCTURE_POINTER => {
+                let ptr = self.word(at)?;
+                self.values((at.0, at.1 + 4), 4, 1)?;
+                self.pointer(ptr)
+            }
+            dt::ARRAY => {
+                let (ptr, count) = self.header(at)?;
+                self.array(info.array_element(e)?, ptr, count)
+            }
+            dt::ARRAY_OF_BYTES => {
+                let elem = info.array_element(e)?;
+                self.scalars(elem.kind, at, e.ref_key as usize)
+            }
+            dt::CHAR_POINTER => self.header(at).map(drop),
+            dt::ARRAY_OF_CHARS => Ok(()),
+            UNSUPPORTED => bail!("unsupported PSO field type 0x{:02X} (subtype {})", e.ref_key >> 8, e.ref_key & 0xFF),
+            kind => self.scalars(kind, at, 1),
+        }
+    }
+
+    /// A structure pointer; its type is the name of the block it points into.
+    fn pointer(&mut self, ptr: u32) -> Result<()> {
+        let Some(at) = decode_pointer(ptr) else { return Ok(()) };
+        if !self.seen.insert(at) { return Ok(()); }
+        let name = self.blocks.get(at.0).with_context(|| format!("pointer 0x{:08X} to missing data block", ptr))?.name;
+        let info = self.struct_info(name)?;
+        self.structure(info, at)
+    }
+
+    fn array(&mut self, elem: &EntryInfo, ptr: u32, count: usize) -> Result<()> {
+        let Some(at) = decode_pointer(ptr) else { return Ok(()) };
+        if count == 0 || !self.seen.insert(at) { return Ok(()); }
+        match elem.kind {
+            dt::STRUCTURE => {
+                let info = self.struct_info(elem.ref_key)?;
+                for i in 0..count {
+                    let item = (at.0, at.1 + i * info.size as usize);
+                    if i == 0 || self.seen.insert(item) {
+                        self.structure(info, item)?;
+                    }
+                }
+                Ok(())
+            }
+            dt::STRUCTURE_POINTER => {
+                for i in 0..count {
+                    let item = (at.0, at.1 + i * 8);
+                    let target = self.word(item)?;
+                    self.values((item.0, item.1 + 4), 4, 1)?;
+                    self.pointer(target)?;
+                }
+                Ok(())
+            }
+            // Vectors in arrays are padded to 16 bytes.
+            dt::FLOAT_XYZ => self.values(at, 4, count * 4),
+            kind => self.scalars(kind, at, count),
+        }
+    }
+
+    fn scalars(&mut self, kind: u8, at: (usize, usize), count: usize) -> Result<()> {
+        match kind {
+            dt::BOOLEAN | dt::SIGNED_BYTE | dt::UNSIGNED_BYTE | dt::BYTE_ENUM => Ok(()),
+            dt::SIGNED_SHORT | dt::UNSIGNED_SHORT | dt::SHORT_FLAGS => self.values(at, 2, count),
+            dt::SIGNED_INT | dt::UNSIGNED_INT | dt::FLOAT | dt::HASH
+                | dt::INT_ENUM | dt::INT_FLAGS1 | dt::INT_FLAGS2 => self.values(at, 4, count),
+            dt::FLOAT_XY   => self.values(at, 4, count * 2),
+            dt::FLOAT_XYZ  => self.values(at, 4, count * 3),
+            dt::FLOAT_XYZW => self.values(at, 4, count * 4),
+            kind => bail!("unsupported field type 0x{:02X}", kind),
+        }
+    }
+}
+
+// ─── Build ───────────────────────────────────────────────────────────────────
+
+/// Build a PSO file from a document read from one (or converted with its definitions).
+pub fn build(meta: &Meta, pso: &Pso) -> Result<Vec<u8>> {
+    let mut blocks: Vec<DataBlock> = meta.blocks.iter().map(|b| DataBlock { name: b.name, data: b.data.clone() }).collect();
+    swap(&meta.structs, &mut blocks, meta.root, false)?;
+
+    let mut psin = vec![0u8; SECTION_HEADER_SIZE];
+    let mut map = Vec::with_capacity(blocks.len() * MAP_ENTRY_SIZE);
+    for block in &blocks {
+        psin.resize(psin.len().next_multiple_of(BLOCK_ALIGN), 0);
+        map.extend(block.name.to_be_bytes());
+        map.extend(u32::try_from(psin.len())?.to_be_bytes());
+        map.extend(0u32.to_be_bytes());
+        map.extend(u32::try_from(block.data.len())?.to_be_bytes());
+        psin.extend_from_slice(&block.data);
+    }
+    let mut pmap = vec![0u8; SECTION_HEADER_SIZE];
+    pmap.extend((meta.root as u32 + 1).to_be_bytes());
+    pmap.extend(u16::try_from(blocks.len())?.to_be_bytes());
+    pmap.extend(0x7070u16.to_be_bytes());
+    pmap.extend(map);
+
+    let mut out = Vec::new();
+    for (id, mut section) in [(PSIN, psin), (PMAP, pmap), (PSCH, build_schema(&pso.defs)?)] {
+        let len = u32::try_from(section.len())?;
+        section[0..4].copy_from_slice(&id.to_be_bytes());
+        section[4..8].copy_from_slice(&len.to_be_bytes());
+        out.extend(section);
+    }
+    let size = out.len() + pso.sections.iter().map(Vec::len).sum::<usize>();
+    for section in &pso.sections {
+        let start = out.len();
+        out.extend_from_slice(section);
+        // The checksum section records the file size; its checksum is kept as read.
+        if be32(section, 0) == CHKS && section.len() >= 12 {
+            out[start + 8..start + 12].copy_from_slice(&u32::try_from(size)?.to_be_bytes());
+        }
+    }
+    Ok(out)
+}
+
+fn build_schema(defs: &[Def]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut index = Vec::with_capacity(defs.len() * 8);
+    let start = 12 + defs.len() * 8;
+    for def in defs {
+        let (name, bytes) = match def {
+            Def::Struct(s) => {
+                let mut bytes = (u32::from(s.unknown_2) << 16 | u32::from(u16::try_from(s.entries.len())?)).to_be_bytes().to_vec();
+                bytes.extend(s.size.to_be_bytes());
+                bytes.extend(s.unknown_c.to_be_bytes());
+                for e in &s.entries {
+                    bytes.extend(e.name.to_be_bytes());
+                    bytes.extend([e.kind, e.subtype]);
+                    bytes.extend(e.offset.to_be_bytes());
+                    bytes.extend(e.ref_key.to_be_bytes());
+                }
+                (s.name, bytes)
+            }
+            Def::Enum(name, entries) => {
+                let count = u32::try_from(entries.len()).ok().filter(|&n| n <= 0xFF_FFFF).context("enum has too many values")?;
+                let mut bytes = (1 << 24 | count).to_be_bytes().to_vec();
+                for &(value_name, value) in entries {
+                    bytes.extend(value_name.to_be_bytes());
+                    bytes.extend(value.to_be_bytes());
+                }
+                (*name, bytes)
+            }
+        };
+        index.extend(name.to_be_bytes());
+        index.extend(u32::try_from(start + body.len())?.to_be_bytes());
+        body.extend(bytes);
+    }
+    let mut out = vec![0u8; SECTION_HEADER_SIZE];
+    out.extend(u32::try_from(defs.len())?.to_be_bytes());
+    out.extend(index);
+    out.extend(body);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{encode_pointer, xml};
+    use crate::names::{hash, Names};
+
+    const NAMES: &[&str] = &[
+        "CTestRoot", "CTestItem", "eTestKind", "eTestFlags", "KIND_A", "KIND_B", "FLAG_X", "FLAG_Y", "FLAG_Z",
+        "count", "value", "flag", "small", "pos", "v2", "kind", "rot", "child", "items", "label", "ints",
+        "bytes", "id", "w", "h", "alpha", "beta", "testroot",
+    ];
+
+    fn field(name: u32, kind: u8, subtype: u8, offset: u16, ref_key: u32) -> Vec<u8> {
+        let mut e = name.to_be_bytes().to_vec();
+        e.extend([kind, subtype]);
+        e.extend(offset.to_be_bytes());
+        e.extend(ref_key.to_be_bytes());
+        e
+    }
+
+    fn section(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut s = id.to_vec();
+        s.extend((body.len() as u32 + 8).to_be_bytes());
+        s.extend(body);
+        s
+    }
+
+    fn put(buf: &mut [u8], at: usize, values: &[[u8; 4]]) {
+        for (i, v) in values.iter().enumerate() {
+            buf[at + i * 4..at + i * 4 + 4].copy_from_slice(v);
+        }
+    }
+
+    /// Array header: pointer, count and capacity.
+    fn array(buf: &mut [u8], at: usize, block: usize, offset: usize, count: u16, capacity: u16) {
+        put(buf, at, &[encode_pointer(block, offset).unwrap().to_be_bytes()]);
+        buf[at + 8..at + 10].copy_from_slice(&count.to_be_bytes());
+        buf[at + 10..at + 12].copy_from_slice(&capacity.to_be_bytes());
+    }
+
+    /// A PSO file covering every supported field type, assembled by hand.
+    fn sample() -> Vec<u8> {
+        let h = hash;
+        let root_fields = [
+            field(h("name"), pt::STRING, 7, 0, 0), field(h("count"), pt::UINT, 0, 4, 0),
+            field(h("value"), pt::FLOAT, 0, 8, 0), field(h("flag"), pt::BOOL, 0, 12, 0),
+            field(h("small"), pt::SSHORT, 0, 14, 0), field(h("pos"), pt::FLOAT3, 0, 16, 0),
+            field(h("v2"), pt::FLOAT2, 0, 32, 0), field(h("kind"), pt::ENUM, 0, 40, h("eTestKind")),
+            field(ARRAY_INFO, pt::ENUM, 0, 0, h("eTestFlags")), field(h("flags"), pt::FLAGS, 0, 44, 8),
+            field(h("rot"), pt::FLOAT4A, 0, 48, 0), field(h("child"), pt::STRUCTURE, 3, 96, 0),
+            field(ARRAY_INFO, pt::STRUCTURE, 0, 0, h("CTestItem")), field(h("items"), pt::ARRAY, 0, 64, 12),
+            field(h("label"), pt::STRING, 3, 80, 0),
+            field(ARRAY_INFO, pt::UINT, 0, 0, 0), field(h("ints"), pt::ARRAY, 0, 104, 15),
+            field(ARRAY_INFO, pt::UBYTE, 0, 0, 0), field(h("bytes"), pt::ARRAY, 1, 120, 4 << 16 | 17),
+        ];
+        let item_fields = [field(h("id"), pt::UINT, 0, 0, 0), field(h("w"), pt::FLOAT, 0, 4, 0), field(h("h"), pt::STRING, 8, 8, 0)];
+        let structure = |fields: &[Vec<u8>], size: u32| {
+            let mut d = (fields.len() as u32).to_be_bytes().to_vec();
+            d.extend(size.to_be_bytes());
+            d.extend(0u32.to_be_bytes());
+            d.extend(fields.concat());
+            d
+        };
+        let enumeration = |values: &[&str]| {
+            let mut d = (1 << 24 | values.len() as u32).to_be_bytes().to_vec();
+            for (i, v) in values.iter().enumerate() {
+                d.extend(h(v).to_be_bytes());
+                d.extend((i as i32).to_be_bytes());
+            }
+            d
+        };
+        let defs = [
+            ("CTestRoot", structure(&root_fields, 128)), ("eTestKind", enumeration(&["KIND_A", "KIND_B"])),
+            ("CTestItem", structure(&item_fields, 16)), ("eTestFlags", enumeration(&["FLAG_X", "FLAG_Y", "FLAG_Z"])),
+        ];
+        let mut schema = (defs.len() as u32).to_be_bytes().to_vec();
+        let mut at = 12 + defs.len() * 8;
+        for (name, d) in &defs {
+            schema.extend(h(name).to_be_bytes());
+            schema.extend((at as u32).to_be_bytes());
+            at += d.len();
+        }
+        for (_, d) in &defs { schema.extend(d); }
+
+        let mut root = [0u8; 128];
+        put(&mut root, 0, &[h("testroot").to_be_bytes(), 2u32.to_be_bytes(), 3.25f32.to_be_bytes()]);
+        root[12] = 1;
+        root[14..16].copy_from_slice(&(-5i16).to_be_bytes());
+        put(&mut root, 16, &[1f32.to_be_bytes(), 2f32.to_be_bytes(), 3f32.to_be_bytes()]);
+        put(&mut root, 32, &[4f32.to_be_bytes(), 5f32.to_be_bytes(), 1i32.to_be_bytes(), 0b101u32.to_be_bytes()]);
+        put(&mut root, 48, &[0f32.to_be_bytes(), 0f32.to_be_bytes(), 0.5f32.to_be_bytes(), 0.75f32.to_be_bytes()]);
+        array(&mut root, 64, 1, 0, 2, 2);
+        array(&mut root, 80, 2, 0, 9, 10);
+        put(&mut root, 96, &[encode_pointer(1, 16).unwrap().to_be_bytes()]);
+        array(&mut root, 104, 3, 0, 3, 3);
+        root[120..124].copy_from_slice(&[1, 2, 3, 4]);
+
+        let mut items = [0u8; 32];
+        put(&mut items, 0, &[7u32.to_be_bytes(), 0.5f32.to_be_bytes(), h("alpha").to_be_bytes()]);
+        put(&mut items, 16, &[8u32.to_be_bytes(), 1.5f32.to_be_bytes(), h("beta").to_be_bytes()]);
+        let ints: Vec<u8> = [10u32, 20, 30].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let blocks: [(u32, &[u8]); 4] = [(h("CTestRoot"), &root), (h("CTestItem"), &items), (0x10, b"hello pso\0"), (6, &ints)];
+
+        let (mut psin, mut map) = (Vec::new(), vec![0, 0, 0, 1, 0, blocks.len() as u8, 0x70, 0x70]);
+        for (name, data) in blocks {
+            psin.resize((psin.len() + 8).next_multiple_of(16) - 8, 0);
+            for v in [name, psin.len() as u32 + 8, 0, data.len() as u32] { map.extend(v.to_be_bytes()); }
+            psin.extend(data);
+        }
+        let mut out = [section(b"PSIN", &psin), section(b"PMAP", &map), section(b"PSCH", &schema), section(b"STRF", b"CTestRoot\0CTestItem\0")].concat();
+        let size = out.len() as u32 + 20;
+        out.extend(section(b"CHKS", &[size.to_be_bytes(), 0xDEAD_BEEFu32.to_be_bytes(), 0x7970_7070u32.to_be_bytes()].concat()));
+        out
+    }
+
+    fn names() -> Names {
+        let mut names = Names::load(&[]).unwrap();
+        for name in NAMES { names.add(name); }
+        names
+    }
+
+    #[test]
+    fn read_build_round_trips() {
+        let data = sample();
+        let meta = read(&data).unwrap();
+        let text = xml::to_xml(&meta, &names()).unwrap();
+        for line in ["<name>testroot</name>", r#"<pos x="1" y="2" z="3" />"#, r#"<v2 x="4" y="5" />"#,
+                     "<kind>KIND_B</kind>", "<flags>FLAG_X FLAG_Z</flags>", "<label>hello pso</label>", "<h>beta</h>"] {
+            assert!(text.contains(line), "{} missing from\n{}", line, text);
+        }
+        assert_eq!(meta.build().unwrap(), data);
+    }
+
+    #[test]
+    fn xml_round_trips() {
+        let names = names();
+        let text = xml::to_xml(&read(&sample()).unwrap(), &names).unwrap();
+        let edited = text.replace("hello pso", "a longer label than before");
+        let built = xml::from_xml(&edited, read(&sample()).unwrap()).unwrap().build().unwrap();
+
+        assert!(is_pso(&built));
+        assert_eq!(be32(&built, built.len() - 12), built.len() as u32, "CHKS records the new file size");
+        assert_eq!(xml::to_xml(&read(&built).unwrap(), &names).unwrap(), edited);
+    }
+
+    #[test]
+    fn rejects_truncated_sections() {
+        let data = sample();
+        assert!(read(&data[..data.len() - 4]).is_err());
+    }
+}
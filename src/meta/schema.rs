@@ -0,0 +1,471 @@
+// Built-in structure and enum definitions for .ymap and .ytyp files, so XML can be
+// imported as a new entry without a file of the same kind to take them from.
+//
+// Layouts follow CodeWalker's `MetaTypes`: `CMapData` with its entities, container LODs,
+// occluders, instanced grass, time cycle modifiers, car generators, LOD lights and block
+// description, and `CMapTypes` with base, time and MLO archetypes. Extension definitions
+// (`CExtensionDef*`) and composite entity types are not included; files that use them need
+// `--schema`. Structure and enum keys are left zero.
+
+use super::{data_type as dt, EntryInfo, EnumInfo, Meta, StructInfo, ARRAY_INFO, HEADER_SIZE};
+use crate::names::hash;
+
+/// Value of a structure info's unknown field in game files.
+const STRUCT_UNKNOWN_8: u32 = 0x400;
+
+/// Entries of a structure definition, each array preceded by the entry describing its
+/// elements.
+struct Fields(Vec<EntryInfo>);
+
+impl Fields {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn push(mut self, name: u32, offset: u32, kind: u8, ref_index: i16, ref_key: u32) -> Self {
+        self.0.push(EntryInfo { name, offset, kind, unknown_9: 0, ref_index, ref_key });
+        self
+    }
+
+    fn add(self, name: &str, offset: u32, kind: u8) -> Self {
+        self.push(hash(name), offset, kind, 0, 0)
+    }
+
+    /// A structure, enum or flags field of type `ty`.
+    fn typed(self, name: &str, offset: u32, kind: u8, ty: &str) -> Self {
+        self.push(hash(name), offset, kind, 0, hash(ty))
+    }
+
+    /// An array (or data block pointer) of `elem` values; `ty` names structure elements.
+    fn array_of(self, name: &str, offset: u32, kind: u8, elem: u8, ty: u32) -> Self {
+        let index = self.0.len() as i16;
+        self.push(ARRAY_INFO, 0, elem, 0, ty).push(hash(name), offset, kind, index, 0)
+    }
+
+    fn array(self, name: &str, offset: u32, elem: u8) -> Self {
+        self.array_of(name, offset, dt::ARRAY, elem, 0)
+    }
+
+    fn structs(self, name: &str, offset: u32, ty: &str) -> Self {
+        self.array_of(name, offset, dt::ARRAY, dt::STRUCTURE, hash(ty))
+    }
+
+    /// An inline array of `count` `elem` values.
+    fn inline(self, name: &str, offset: u32, elem: u8, count: u32) -> Self {
+        let index = self.0.len() as i16;
+        self.push(ARRAY_INFO, 0, elem, 0, 0).push(hash(name), offset, dt::ARRAY_OF_BYTES, index, count)
+    }
+}
+
+fn structure(name: &str, size: u32, fields: Fields) -> StructInfo {
+    StructInfo { name: hash(name), key: 0, unknown_8: STRUCT_UNKNOWN_8, size, entries: fields.0 }
+}
+
+fn enumeration(name: &str, values: &[&str]) -> EnumInfo {
+    EnumInfo { name: hash(name), key: 0, entries: values.iter().zip(0..).map(|(v, i)| (hash(v), i)).collect() }
+}
+
+/// The `CEntityDef` fields, which `CMloInstanceDef` starts with.
+fn entity() -> Fields {
+    Fields::new()
+        .add("archetypeName", 8, dt::HASH)
+        .add("flags", 12, dt::UNSIGNED_INT)
+        .add("guid", 16, dt::UNSIGNED_INT)
+        .add("position", 32, dt::FLOAT_XYZ)
+        .add("rotation", 48, dt::FLOAT_XYZW)
+        .add("scaleXY", 64, dt::FLOAT)
+        .add("scaleZ", 68, dt::FLOAT)
+        .add("parentIndex", 72, dt::SIGNED_INT)
+        .add("lodDist", 76, dt::FLOAT)
+        .add("childLodDist", 80, dt::FLOAT)
+        .typed("lodLevel", 84, dt::INT_ENUM, "rage__eLodType")
+        .add("numChildren", 88, dt::UNSIGNED_INT)
+        .typed("priorityLevel", 92, dt::INT_ENUM, "rage__ePriorityLevel")
+        .array("extensions", 96, dt::STRUCTURE_POINTER)
+        .add("ambientOcclusionMultiplier", 112, dt::SIGNED_INT)
+        .add("artificialAmbientOcclusion", 116, dt::SIGNED_INT)
+        .add("tintValue", 120, dt::UNSIGNED_INT)
+}
+
+/// The `CBaseArchetypeDef` fields, which the other archetype definitions start with.
+fn archetype() -> Fields {
+    Fields::new()
+        .add("lodDist", 8, dt::FLOAT)
+        .add("flags", 12, dt::UNSIGNED_INT)
+        .add("specialAttribute", 16, dt::UNSIGNED_INT)
+        .add("bbMin", 32, dt::FLOAT_XYZ)
+        .add("bbMax", 48, dt::FLOAT_XYZ)
+        .add("bsCentre", 64, dt::FLOAT_XYZ)
+        .add("bsRadius", 80, dt::FLOAT)
+        .add("hdTextureDist", 84, dt::FLOAT)
+        .add("name", 88, dt::HASH)
+        .add("textureDictionary", 92, dt::HASH)
+        .add("clipDictionary", 96, dt::HASH)
+        .add("drawableDictionary", 100, dt::HASH)
+        .add("physicsDictionary", 104, dt::HASH)
+        .typed("assetType", 108, dt::INT_ENUM, "rage__fwArchetypeDef__eAssetType")
+        .add("assetName", 112, dt::HASH)
+        .array("extensions", 120, dt::STRUCTURE_POINTER)
+}
+
+/// An empty document holding every built-in definition.
+pub fn builtin() -> Meta {
+    let mut header = [0u8; HEADER_SIZE];
+    header[0x00..0x04].copy_from_slice(&0x405B_C808u32.to_le_bytes());
+    header[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+    header[0x10..0x14].copy_from_slice(&0x5052_4430u32.to_le_bytes());
+    header[0x14..0x16].copy_from_slice(&0x0079u16.to_le_bytes());
+
+    let structs = vec![
+        structure("CMapData", 512, Fields::new()
+            .add("name", 8, dt::HASH)
+            .add("parent", 12, dt::HASH)
+            .add("flags", 16, dt::UNSIGNED_INT)
+            .add("contentFlags", 20, dt::UNSIGNED_INT)
+            .add("streamingExtentsMin", 32, dt::FLOAT_XYZ)
+            .add("streamingExtentsMax", 48, dt::FLOAT_XYZ)
+            .add("entitiesExtentsMin", 64, dt::FLOAT_XYZ)
+            .add("entitiesExtentsMax", 80, dt::FLOAT_XYZ)
+            .array("entities", 96, dt::STRUCTURE_POINTER)
+            .structs("containerLods", 112, "rage__fwContainerLodDef")
+            .structs("boxOccluders", 128, "BoxOccluder")
+            .structs("occludeModels", 144, "OccludeModel")
+            .array("physicsDictionaries", 160, dt::HASH)
+            .typed("instancedData", 176, dt::STRUCTURE, "rage__fwInstancedMapData")
+            .structs("timeCycleModifiers", 224, "CTimeCycleModifier")
+            .structs("carGenerators", 240, "CCarGen")
+            .typed("LODLightsSOA", 256, dt::STRUCTURE, "CLODLight")
+            .typed("DistantLODLightsSOA", 392, dt::STRUCTURE, "CDistantLODLight")
+            .typed("block", 440, dt::STRUCTURE, "CBlockDesc")),
+        structure("CEntityDef", 128, entity()),
+        structure("CMloInstanceDef", 160, entity()
+            .add("groupId", 128, dt::UNSIGNED_INT)
+            .add("floorId", 132, dt::UNSIGNED_INT)
+            .array("defaultEntitySets", 136, dt::HASH)
+            .add("numExitPortals", 152, dt::UNSIGNED_INT)
+            .add("MLOInstflags", 156, dt::UNSIGNED_INT)),
+        structure("rage__fwContainerLodDef", 8, Fields::new()
+            .add("name", 0, dt::HASH)
+            .add("parentIndex", 4, dt::UNSIGNED_INT)),
+        structure("BoxOccluder", 16, Fields::new()
+            .add("iCenterX", 0, dt::SIGNED_SHORT)
+            .add("iCenterY", 2, dt::SIGNED_SHORT)
+            .add("iCenterZ", 4, dt::SIGNED_SHORT)
+            .add("iCosZ", 6, dt::SIGNED_SHORT)
+            .add("iLength", 8, dt::SIGNED_SHORT)
+            .add("iWidth", 10, dt::SIGNED_SHORT)
+            .add("iHeight", 12, dt::SIGNED_SHORT)
+            .add("iSinZ", 14, dt::SIGNED_SHORT)),
+        structure("OccludeModel", 64, Fields::new()
+            .add("bmin", 0, dt::FLOAT_XYZ)
+            .add("bmax", 16, dt::FLOAT_XYZ)
+            .add("dataSize", 32, dt::UNSIGNED_INT)
+            .array_of("verts", 40, dt::DATA_BLOCK_POINTER, dt::UNSIGNED_BYTE, 0)
+            .add("numVertsInBytes", 48, dt::UNSIGNED_SHORT)
+            .add("numTris", 50, dt::UNSIGNED_SHORT)
+            .add("flags", 52, dt::UNSIGNED_INT)),
+        structure("rage__fwInstancedMapData", 48, Fields::new()
+            .add("ImapLink", 8, dt::HASH)
+            .structs("PropInstanceList", 16, "rage__fwPropInstanceListDef")
+            .structs("GrassInstanceList", 32, "rage__fwGrassInstanceListDef")),
+        structure("rage__fwGrassInstanceListDef", 96, Fields::new()
+            .typed("BatchAABB", 0, dt::STRUCTURE, "rage__spdAABB")
+            .add("ScaleRange", 32, dt::FLOAT_XYZ)
+            .add("archetypeName", 48, dt::HASH)
+            .add("lodDist", 52, dt::UNSIGNED_INT)
+            .add("LodFadeStartDist", 56, dt::FLOAT)
+            .add("LodInstFadeRange", 60, dt::FLOAT)
+            .add("OrientToTerrain", 64, dt::FLOAT)
+            .structs("InstanceList", 72, "rage__fwGrassInstanceListDef__InstanceData")),
+        structure("rage__fwGrassInstanceListDef__InstanceData", 16, Fields::new()
+            .inline("Position", 0, dt::UNSIGNED_SHORT, 3)
+            .add("NormalX", 6, dt::UNSIGNED_BYTE)
+            .add("NormalY", 7, dt::UNSIGNED_BYTE)
+            .inline("Color", 8, dt::UNSIGNED_BYTE, 3)
+            .add("Scale", 11, dt::UNSIGNED_BYTE)
+            .add("Ao", 12, dt::UNSIGNED_BYTE)
+            .inline("Pad", 13, dt::UNSIGNED_BYTE, 3)),
+        structure("rage__spdAABB", 32, Fields::new()
+            .add("min", 0, dt::FLOAT_XYZW)
+            .add("max", 16, dt::FLOAT_XYZW)),
+        structure("CTimeCycleModifier", 64, Fields::new()
+            .add("name", 8, dt::HASH)
+            .add("minExtents", 16, dt::FLOAT_XYZ)
+            .add("maxExtents", 32, dt::FLOAT_XYZ)
+            .add("percentage", 48, dt::FLOAT)
+            .add("range", 52, dt::FLOAT)
+            .add("startHour", 56, dt::UNSIGNED_INT)
+            .add("endHour", 60, dt::UNSIGNED_INT)),
+        structure("CCarGen", 80, Fields::new()
+            .add("position", 16, dt::FLOAT_XYZ)
+            .add("orientX", 32, dt::FLOAT)
+            .add("orientY", 36, dt::FLOAT)
+            .add("perpendicularLength", 40, dt::FLOAT)
+            .add("carModel", 44, dt::HASH)
+            .add("flags", 48, dt::UNSIGNED_INT)
+            .add("bodyColorRemap1", 52, dt::SIGNED_INT)
+            .add("bodyColorRemap2", 56, dt::SIGNED_INT)
+            .add("bodyColorRemap3", 60, dt::SIGNED_INT)
+            .add("bodyColorRemap4", 64, dt::SIGNED_INT)
+            .add("popGroup", 68, dt::HASH)
+            .add("livery", 72, dt::SIGNED_BYTE)),
+        structure("CLODLight", 136, Fields::new()
+            .array("direction", 8, dt::FLOAT_XYZ)
+            .array("falloff", 24, dt::FLOAT)
+            .array("falloffExponent", 40, dt::FLOAT)
+            .array("timeAndStateFlags", 56, dt::UNSIGNED_INT)
+            .array("hash", 72, dt::UNSIGNED_INT)
+            .array("coneInnerAngle", 88, dt::UNSIGNED_BYTE)
+            .array("coneOuterAngleOrCapExt", 104, dt::UNSIGNED_BYTE)
+            .array("coronaIntensity", 120, dt::UNSIGNED_BYTE)),
+        structure("CDistantLODLight", 48, Fields::new()
+            .array("position", 8, dt::FLOAT_XYZ)
+            .array("RGBI", 24, dt::UNSIGNED_INT)
+            .add("numStreetLights", 40, dt::UNSIGNED_SHORT)
+            .add("category", 42, dt::UNSIGNED_SHORT)),
+        structure("CBlockDesc", 72, Fields::new()
+            .add("version", 0, dt::UNSIGNED_INT)
+            .add("flags", 4, dt::UNSIGNED_INT)
+            .add("name", 8, dt::CHAR_POINTER)
+            .add("exportedBy", 24, dt::CHAR_POINTER)
+            .add("owner", 40, dt::CHAR_POINTER)
+            .add("time", 56, dt::CHAR_POINTER)),
+        structure("CMapTypes", 80, Fields::new()
+            .array("extensions", 0, dt::STRUCTURE_POINTER)
+            .array("archetypes", 16, dt::STRUCTURE_POINTER)
+            .add("name", 32, dt::HASH)
+            .array("dependencies", 40, dt::HASH)
+            .structs("compositeEntityTypes", 56, "CCompositeEntityType")),
+        structure("CBaseArchetypeDef", 144, archetype()),
+        structure("CTimeArchetypeDef", 160, archetype()
+            .add("timeFlags", 144, dt::UNSIGNED_INT)),
+        structure("CMloArchetypeDef", 240, archetype()
+            .add("mloFlags", 144, dt::UNSIGNED_INT)
+            .array("entities", 152, dt::STRUCTURE_POINTER)
+            .structs("rooms", 168, "CMloRoomDef")
+            .structs("portals", 184, "CMloPortalDef")
+            .structs("entitySets", 200, "CMloEntitySet")
+            .structs("timeCycleModifiers", 216, "CMloTimeCycleModifier")),
+        structure("CMloRoomDef", 112, Fields::new()
+            .add("name", 8, dt::CHAR_POINTER)
+            .add("bbMin", 32, dt::FLOAT_XYZ)
+            .add("bbMax", 48, dt::FLOAT_XYZ)
+            .add("blend", 64, dt::FLOAT)
+            .add("timecycleName", 68, dt::HASH)
+            .add("secondaryTimecycleName", 72, dt::HASH)
+            .add("flags", 76, dt::UNSIGNED_INT)
+            .add("portalCount", 80, dt::UNSIGNED_INT)
+            .add("floorId", 84, dt::SIGNED_INT)
+            .add("exteriorVisibiltyDepth", 88, dt::SIGNED_INT)
+            .array("attachedObjects", 96, dt::UNSIGNED_INT)),
+        structure("CMloPortalDef", 64, Fields::new()
+            .add("roomFrom", 8, dt::UNSIGNED_INT)
+            .add("roomTo", 12, dt::UNSIGNED_INT)
+            .add("flags", 16, dt::UNSIGNED_INT)
+            .add("mirrorPriority", 20, dt::UNSIGNED_INT)
+            .add("opacity", 24, dt::UNSIGNED_INT)
+            .add("audioOcclusion", 28, dt::UNSIGNED_INT)
+            .array("corners", 32, dt::FLOAT_XYZ)
+            .array("attachedObjects", 48, dt::UNSIGNED_INT)),
+        structure("CMloEntitySet", 48, Fields::new()
+            .add("name", 8, dt::HASH)
+            .array("locations", 16, dt::UNSIGNED_INT)
+            .array("entities", 32, dt::STRUCTURE_POINTER)),
+        structure("CMloTimeCycleModifier", 48, Fields::new()
+            .add("name", 8, dt::HASH)
+            .add("sphere", 16, dt::FLOAT_XYZW)
+            .add("percentage", 32, dt::FLOAT)
+            .add("range", 36, dt::FLOAT)
+            .add("startHour", 40, dt::UNSIGNED_INT)
+            .add("endHour", 44, dt::UNSIGNED_INT)),
+    ];
+    let enums = vec![
+        enumeration("rage__eLodType", &[
+            "LODTYPES_DEPTH_HD", "LODTYPES_DEPTH_LOD", "LODTYPES_DEPTH_SLOD1", "LODTYPES_DEPTH_SLOD2",
+            "LODTYPES_DEPTH_SLOD3", "LODTYPES_DEPTH_ORPHANHD", "LODTYPES_DEPTH_SLOD4",
+        ]),
+        enumeration("rage__ePriorityLevel", &[
+            "PRI_REQUIRED", "PRI_OPTIONAL_HIGH", "PRI_OPTIONAL_MEDIUM", "PRI_OPTIONAL_LOW",
+        ]),
+        enumeration("rage__fwArchetypeDef__eAssetType", &[
+            "ASSET_TYPE_UNINITIALIZED", "ASSET_TYPE_FRAGMENT", "ASSET_TYPE_DRAWABLE",
+            "ASSET_TYPE_DRAWABLEDICTIONARY", "ASSET_TYPE_ASSETLESS",
+        ]),
+    ];
+    Meta { header, name: None, structs, enums, blocks: Vec::new(), root: 0, pso: None }
+}
+
+/// Drop the definitions `meta` does not use: those of no data block and not inline in a
+/// used structure. Game files only carry the ones they use.
+pub fn prune(meta: &mut Meta) {
+    let mut used: Vec<u32> = meta.blocks.iter().map(|b| b.name).collect();
+    let mut i = 0;
+    while i < used.len() {
+        let inline: Vec<u32> = meta.struct_info(used[i]).into_iter()
+            .flat_map(|info| &info.entries)
+            .filter(|e| e.kind == dt::STRUCTURE && e.name != ARRAY_INFO)
+            .map(|e| e.ref_key)
+            .collect();
+        for name in inline {
+            if !used.contains(&name) { used.push(name); }
+        }
+        i += 1;
+    }
+    meta.structs.retain(|s| used.contains(&s.name));
+    let enums: Vec<u32> = meta.structs.iter()
+        .flat_map(|s| &s.entries)
+        .filter(|e| matches!(e.kind, dt::BYTE_ENUM | dt::INT_ENUM | dt::SHORT_FLAGS | dt::INT_FLAGS1 | dt::INT_FLAGS2))
+        .map(|e| e.ref_key)
+        .collect();
+    meta.enums.retain(|e| enums.contains(&e.name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::xml;
+    use crate::names::Names;
+    use crate::resource::Resource;
+
+    const YMAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CMapData>
+ <name>new</name>
+ <parent />
+ <flags value="0" />
+ <contentFlags value="1" />
+ <streamingExtentsMin x="-160" y="-160" z="-160" />
+ <streamingExtentsMax x="160" y="160" z="160" />
+ <entitiesExtentsMin x="-10" y="-10" z="-10" />
+ <entitiesExtentsMax x="10" y="10" z="10" />
+ <entities>
+  <Item type="CEntityDef">
+   <archetypeName>prop_rock</archetypeName>
+   <flags value="32" />
+   <guid value="123" />
+   <position x="1" y="2" z="3" />
+   <rotation x="0" y="0" z="0" w="1" />
+   <scaleXY value="1" />
+   <scaleZ value="1" />
+   <parentIndex value="-1" />
+   <lodDist value="150" />
+   <childLodDist value="0" />
+   <lodLevel>LODTYPES_DEPTH_HD</lodLevel>
+   <numChildren value="0" />
+   <priorityLevel>PRI_REQUIRED</priorityLevel>
+   <extensions />
+   <ambientOcclusionMultiplier value="255" />
+   <artificialAmbientOcclusion value="255" />
+   <tintValue value="0" />
+  </Item>
+ </entities>
+ <containerLods />
+ <boxOccluders />
+ <occludeModels />
+ <physicsDictionaries />
+ <instancedData>
+  <ImapLink />
+  <PropInstanceList />
+  <GrassInstanceList />
+ </instancedData>
+ <timeCycleModifiers />
+ <carGenerators>
+  <Item>
+   <position x="5" y="6" z="7" />
+   <orientX value="1" />
+   <orientY value="0" />
+   <perpendicularLength value="2.5" />
+   <carModel>adder</carModel>
+   <flags value="3" />
+   <bodyColorRemap1 value="-1" />
+   <bodyColorRemap2 value="-1" />
+   <bodyColorRemap3 value="-1" />
+   <bodyColorRemap4 value="-1" />
+   <popGroup />
+   <livery value="-1" />
+  </Item>
+ </carGenerators>
+ <LODLightsSOA>
+  <direction />
+  <falloff />
+  <falloffExponent />
+  <timeAndStateFlags />
+  <hash />
+  <coneInnerAngle />
+  <coneOuterAngleOrCapExt />
+  <coronaIntensity />
+ </LODLightsSOA>
+ <DistantLODLightsSOA>
+  <position />
+  <RGBI />
+  <numStreetLights value="0" />
+  <category value="0" />
+ </DistantLODLightsSOA>
+ <block>
+  <version value="0" />
+  <flags value="0" />
+  <name>new</name>
+  <exportedBy>me</exportedBy>
+  <owner />
+  <time>today</time>
+ </block>
+</CMapData>"#;
+
+    const YTYP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CMapTypes>
+ <archetypes>
+  <Item type="CBaseArchetypeDef">
+   <lodDist value="100" />
+   <bbMin x="-1" y="-1" z="0" />
+   <bsRadius value="1.7" />
+   <name>prop_rock</name>
+   <assetType>ASSET_TYPE_DRAWABLE</assetType>
+  </Item>
+  <Item type="CTimeArchetypeDef">
+   <name>prop_lamp</name>
+   <timeFlags value="8" />
+  </Item>
+ </archetypes>
+ <name>props</name>
+</CMapTypes>"#;
+
+    fn names() -> Names {
+        let mut names = Names::load(&[]).unwrap();
+        for name in ["new", "prop_rock", "prop_lamp", "adder", "me", "today", "props"] { names.add(name); }
+        names
+    }
+
+    /// Convert with the built-in definitions, build, and read the result back.
+    fn round_trip(text: &str) -> Meta {
+        let mut meta = xml::from_xml(text, builtin()).unwrap();
+        prune(&mut meta);
+        Meta::read(&Resource::parse(&meta.build().unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn ymap_round_trips() {
+        let meta = round_trip(YMAP);
+        assert_eq!(xml::to_xml(&meta, &names()).unwrap().trim_end(), YMAP);
+    }
+
+    #[test]
+    fn ytyp_round_trips() {
+        let meta = round_trip(YTYP);
+        let text = xml::to_xml(&meta, &names()).unwrap();
+        for line in [r#"<Item type="CTimeArchetypeDef">"#, "<name>prop_lamp</name>", r#"<timeFlags value="8" />"#,
+                     r#"<bbMin x="-1" y="-1" z="0" />"#, "<assetType>ASSET_TYPE_DRAWABLE</assetType>", "<name>props</name>"] {
+            assert!(text.contains(line), "{} missing from\n{}", line, text);
+        }
+        assert_eq!(xml::to_xml(&round_trip(&text), &names()).unwrap(), text);
+    }
+
+    #[test]
+    fn prune_keeps_used_definitions() {
+        let meta = round_trip(YTYP);
+        let mut structs: Vec<u32> = meta.structs.iter().map(|s| s.name).collect();
+        structs.sort();
+        let mut expected: Vec<u32> = ["CMapTypes", "CBaseArchetypeDef", "CTimeArchetypeDef"].map(hash).to_vec();
+        expected.sort();
+        assert_eq!(structs, expected);
+        assert_eq!(meta.enums.iter().map(|e| e.name).collect::<Vec<_>>(), [hash("rage__fwArchetypeDef__eAssetType")]);
+    }
+}
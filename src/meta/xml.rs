@@ -0,0 +1,622 @@
+// Meta <-> CodeWalker XML.
+//
+// The element tree mirrors the structure definitions: the document element is named after
+// the root structure and every field becomes a child element named after its entry.
+// Scalars are written as `value="..."`, vectors as `x`/`y`/`z`/`w` attributes, hashes,
+// strings and enums as text (flags as space-separated names). Arrays of structures hold
+// `<Item>` elements, pointer arrays `<Item type="...">`, and primitive arrays are text with
+// a `content` attribute (`int_array`, `float_array`, `vector3_array`, ...).
+//
+// Import walks the same definitions, so it needs the schema of the file being written.
+// Every structure type gets its own data block, the root structure first, which is how
+// CodeWalker's MetaBuilder lays files out.
+
+use anyhow::{bail, Context, Result};
+use roxmltree::{Document, Node};
+use std::fmt::Write;
+
+use super::{data_type as dt, decode_pointer, encode_pointer, DataBlock, EntryInfo, Meta, StructInfo, ARRAY_INFO, STRING_BLOCK};
+use crate::names::{self, Names};
+use crate::resource::{u16_at, u32_at};
+use crate::utils::xml_escape;
+
+/// Size of `Array_*` and `CharPointer` fields.
+const ARRAY_SIZE: usize = 16;
+/// Size of a structure pointer.
+const POINTER_SIZE: usize = 8;
+
+/// Size of one value of a primitive type.
+fn primitive_size(kind: u8) -> Option<usize> {
+    Some(match kind {
+        dt::BOOLEAN | dt::SIGNED_BYTE | dt::UNSIGNED_BYTE | dt::BYTE_ENUM => 1,
+        dt::SIGNED_SHORT | dt::UNSIGNED_SHORT | dt::SHORT_FLAGS          => 2,
+        dt::SIGNED_INT | dt::UNSIGNED_INT | dt::FLOAT | dt::HASH
+            | dt::INT_ENUM | dt::INT_FLAGS1 | dt::INT_FLAGS2             => 4,
+        dt::FLOAT_XY                                                     => 8,
+        dt::FLOAT_XYZ | dt::FLOAT_XYZW                                   => 16,
+        _ => return None,
+    })
+}
+
+/// Size of a primitive field. The padding after a 3-component vector is not part of it
+/// (PSO files pack them).
+fn scalar_size(kind: u8) -> Option<usize> {
+    if kind == dt::FLOAT_XYZ { Some(12) } else { primitive_size(kind) }
+}
+
+/// Components of a vector type.
+fn axes(kind: u8) -> Option<&'static [&'static str]> {
+    Some(match kind {
+        dt::FLOAT_XY   => &["x", "y"],
+        dt::FLOAT_XYZ  => &["x", "y", "z"],
+        dt::FLOAT_XYZW => &["x", "y", "z", "w"],
+        _ => return None,
+    })
+}
+
+/// `content` attribute of a primitive array.
+fn array_content(kind: u8) -> Option<&'static str> {
+    Some(match kind {
+        dt::SIGNED_BYTE | dt::UNSIGNED_BYTE   => "char_array",
+        dt::SIGNED_SHORT | dt::UNSIGNED_SHORT => "short_array",
+        dt::SIGNED_INT | dt::UNSIGNED_INT     => "int_array",
+        dt::FLOAT                             => "float_array",
+        dt::FLOAT_XY                          => "vector2_array",
+        dt::FLOAT_XYZ                         => "vector3_array",
+        dt::FLOAT_XYZW                        => "vector4_array",
+        _ => return None,
+    })
+}
+
+fn f32_at(data: &[u8], off: usize) -> f32 {
+    f32::from_bits(u32_at(data, off))
+}
+
+/// `len` bytes at `off`, or an error naming the field.
+fn field<'d>(data: &'d [u8], off: usize, len: usize, what: &str) -> Result<&'d [u8]> {
+    data.get(off..off + len).with_context(|| format!("{} at 0x{:X} runs past its structure", what, off))
+}
+
+// ─── Export ──────────────────────────────────────────────────────────────────
+
+/// Convert a meta document to CodeWalker XML.
+pub fn to_xml(meta: &Meta, names: &Names) -> Result<String> {
+    let block = &meta.blocks[meta.root];
+    let info = meta.struct_info(block.name)
+        .with_context(|| format!("root block type {} has no structure definition", names.display(block.name)))?;
+    let data = field(&block.data, 0, info.size as usize, "root structure")?;
+
+    let mut w = XmlWriter { meta, names, out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n") };
+    let tag = names.display(info.name);
+    w.structure(&tag, "", info, data, 0)?;
+    Ok(w.out)
+}
+
+struct XmlWriter<'a> {
+    meta : &'a Meta,
+    names: &'a Names,
+    out  : String,
+}
+
+impl<'a> XmlWriter<'a> {
+    fn line(&mut self, depth: usize, text: &str) {
+        let _ = writeln!(self.out, "{:width$}{}", "", text, width = depth);
+    }
+
+    fn empty(&mut self, depth: usize, tag: &str, attrs: &str) {
+        self.line(depth, &format!("<{}{} />", tag, attrs));
+    }
+
+    fn text(&mut self, depth: usize, tag: &str, text: &str) {
+        if text.is_empty() {
+            self.empty(depth, tag, "");
+        } else {
+            self.line(depth, &format!("<{}>{}</{}>", tag, xml_escape(text), tag));
+        }
+    }
+
+    fn data(&self, ptr: u32, len: usize) -> Result<Vec<u8>> {
+        self.meta.data(ptr, len)
+    }
+
+    /// A structure as an element holding one child per field.
+    fn structure(&mut self, tag: &str, attrs: &str, info: &StructInfo, data: &[u8], depth: usize) -> Result<()> {
+        if info.entries.iter().all(|e| e.name == ARRAY_INFO) {
+            self.empty(depth, tag, attrs);
+            return Ok(());
+        }
+        self.line(depth, &format!("<{}{}>", tag, attrs));
+        for entry in info.entries.iter().filter(|e| e.name != ARRAY_INFO) {
+            let name = self.names.display(entry.name);
+            self.entry(info, entry, &name, data, depth + 1)
+                .with_context(|| format!("in {}.{}", self.names.display(info.name), name))?;
+        }
+        self.line(depth, &format!("</{}>", tag));
+        Ok(())
+    }
+
+    fn entry(&mut self, info: &StructInfo, e: &EntryInfo, tag: &str, data: &[u8], depth: usize) -> Result<()> {
+        let at = e.offset as usize;
+        match e.kind {
+            dt::STRUCTURE => {
+                let inner = self.struct_info(e.ref_key)?;
+                let bytes = field(data, at, inner.size as usize, "structure")?;
+                self.structure(tag, "", inner, bytes, depth)
+            }
+            dt::STRUCTURE_POINTER => {
+                let ptr = u32_at(field(data, at, POINTER_SIZE, "pointer")?, 0);
+                self.pointer(tag, ptr, depth)
+            }
+            dt::ARRAY => {
+                let header = field(data, at, ARRAY_SIZE, "array")?;
+                let elem = info.array_element(e)?;
+                self.array(tag, elem, u32_at(header, 0), u16_at(header, 8) as usize, depth)
+            }
+            dt::ARRAY_OF_BYTES => {
+                let elem = info.array_element(e)?;
+                let size = primitive_size(elem.kind).context("unsupported inline array element")?;
+                let bytes = field(data, at, size * e.ref_key as usize, "inline array")?.to_vec();
+                self.primitives(tag, elem.kind, &bytes, depth)
+            }
+            dt::ARRAY_OF_CHARS => {
+                let bytes = field(data, at, e.ref_key as usize, "inline string")?;
+                let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                self.text(depth, tag, &String::from_utf8_lossy(&bytes[..end]));
+                Ok(())
+            }
+            dt::CHAR_POINTER => {
+                let header = field(data, at, ARRAY_SIZE, "string")?;
+                let (ptr, len) = (u32_at(header, 0), u16_at(header, 8) as usize);
+                let text = if ptr == 0 || len == 0 { String::new() } else {
+                    let bytes = self.data(ptr, len)?;
+                    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                    String::from_utf8_lossy(&bytes[..end]).into_owned()
+                };
+                self.text(depth, tag, &text);
+                Ok(())
+            }
+            dt::DATA_BLOCK_POINTER => {
+                self.empty(depth, tag, "");
+                Ok(())
+            }
+            kind => {
+                let size = scalar_size(kind).with_context(|| format!("unsupported field type 0x{:02X}", kind))?;
+                let bytes = field(data, at, size, "value")?;
+                self.scalar(tag, e, bytes, depth)
+            }
+        }
+    }
+
+    fn struct_info(&self, name: u32) -> Result<&'a StructInfo> {
+        self.meta.struct_info(name)
+            .with_context(|| format!("no structure definition for {}", self.names.display(name)))
+    }
+
+    fn scalar(&mut self, tag: &str, e: &EntryInfo, b: &[u8], depth: usize) -> Result<()> {
+        let value = match e.kind {
+            dt::BOOLEAN        => (b[0] != 0).to_string(),
+            dt::SIGNED_BYTE    => (b[0] as i8).to_string(),
+            dt::UNSIGNED_BYTE  => b[0].to_string(),
+            dt::SIGNED_SHORT   => (u16_at(b, 0) as i16).to_string(),
+            dt::UNSIGNED_SHORT => u16_at(b, 0).to_string(),
+            dt::SIGNED_INT     => (u32_at(b, 0) as i32).to_string(),
+            dt::UNSIGNED_INT   => u32_at(b, 0).to_string(),
+            dt::FLOAT          => f32_at(b, 0).to_string(),
+            dt::FLOAT_XY | dt::FLOAT_XYZ | dt::FLOAT_XYZW => {
+                let attrs: String = axes(e.kind).unwrap().iter().enumerate()
+                    .map(|(i, axis)| format!(" {}=\"{}\"", axis, f32_at(b, i * 4)))
+                    .collect();
+                self.empty(depth, tag, &attrs);
+                return Ok(());
+            }
+            dt::HASH => {
+                let hash = u32_at(b, 0);
+                let text = if hash == 0 { String::new() } else { self.names.display(hash) };
+                self.text(depth, tag, &text);
+                return Ok(());
+            }
+            dt::BYTE_ENUM | dt::INT_ENUM => {
+                let value = if e.kind == dt::BYTE_ENUM { b[0] as i32 } else { u32_at(b, 0) as i32 };
+                match self.meta.enum_info(e.ref_key).and_then(|en| en.entries.iter().find(|v| v.1 == value)) {
+                    Some(&(name, _)) => self.text(depth, tag, &self.names.display(name)),
+                    None             => self.empty(depth, tag, &format!(" value=\"{}\"", value)),
+                }
+                return Ok(());
+            }
+            _ => {
+                let value = if e.kind == dt::SHORT_FLAGS { u16_at(b, 0) as u32 } else { u32_at(b, 0) };
+                match self.meta.enum_info(e.ref_key) {
+                    Some(en) => {
+                        let mut names = Vec::new();
+                        let mut rest = value;
+                        for bit in 0..32 {
+                            if value & 1 << bit == 0 { continue; }
+                            if let Some(&(name, _)) = en.entries.iter().find(|v| v.1 == bit) {
+                                names.push(self.names.display(name));
+                                rest &= !(1 << bit);
+                            }
+                        }
+                        if rest != 0 { names.push(rest.to_string()); }
+                        self.text(depth, tag, &names.join(" "));
+                    }
+                    None => self.empty(depth, tag, &format!(" value=\"{}\"", value)),
+                }
+                return Ok(());
+            }
+        };
+        self.empty(depth, tag, &format!(" value=\"{}\"", value));
+        Ok(())
+    }
+
+    /// A structure pointer; its type is the name of the block it points into.
+    fn pointer(&mut self, tag: &str, ptr: u32, depth: usize) -> Result<()> {
+        let Some((block, _)) = decode_pointer(ptr) else {
+            self.empty(depth, tag, " type=\"NULL\"");
+            return Ok(());
+        };
+        let name = self.meta.blocks.get(block)
+            .with_context(|| format!("pointer 0x{:08X} to missing data block", ptr))?.name;
+        let info = self.struct_info(name)?;
+        let data = self.data(ptr, info.size as usize)?;
+        let attrs = format!(" type=\"{}\"", self.names.display(name));
+        self.structure(tag, &attrs, info, &data, depth)
+    }
+
+    fn array(&mut self, tag: &str, elem: &EntryInfo, ptr: u32, count: usize, depth: usize) -> Result<()> {
+        if count == 0 || ptr == 0 {
+            self.empty(depth, tag, "");
+            return Ok(());
+        }
+        match elem.kind {
+            dt::STRUCTURE => {
+                let info = self.struct_info(elem.ref_key)?;
+                let size = info.size as usize;
+                let data = self.data(ptr, size * count)?;
+                self.line(depth, &format!("<{}>", tag));
+                for item in data.chunks_exact(size) {
+                    self.structure("Item", "", info, item, depth + 1)?;
+                }
+            }
+            dt::STRUCTURE_POINTER => {
+                let data = self.data(ptr, POINTER_SIZE * count)?;
+                self.line(depth, &format!("<{}>", tag));
+                for item in data.chunks_exact(POINTER_SIZE) {
+                    self.pointer("Item", u32_at(item, 0), depth + 1)?;
+                }
+            }
+            dt::HASH => {
+                let data = self.data(ptr, 4 * count)?;
+                self.line(depth, &format!("<{}>", tag));
+                for item in data.chunks_exact(4) {
+                    let hash = u32_at(item, 0);
+                    let text = if hash == 0 { String::new() } else { self.names.display(hash) };
+                    self.text(depth + 1, "Item", &text);
+                }
+            }
+            kind => {
+                let size = primitive_size(kind).context("unsupported array element")?;
+                let data = self.data(ptr, size * count)?;
+                return self.primitives(tag, kind, &data, depth);
+            }
+        }
+        self.line(depth, &format!("</{}>", tag));
+        Ok(())
+    }
+
+    /// A primitive array as text, one value (or vector) per line.
+    fn primitives(&mut self, tag: &str, kind: u8, data: &[u8], depth: usize) -> Result<()> {
+        let content = array_content(kind)
+            .with_context(|| format!("unsupported array element type 0x{:02X}", kind))?;
+        let size = primitive_size(kind).unwrap();
+        self.line(depth, &format!("<{} content=\"{}\">", tag, content));
+        for v in data.chunks_exact(size) {
+            let value = match kind {
+                dt::FLOAT_XY       => format!("{}, {}", f32_at(v, 0), f32_at(v, 4)),
+                dt::SIGNED_BYTE    => (v[0] as i8).to_string(),
+                dt::UNSIGNED_BYTE  => v[0].to_string(),
+                dt::SIGNED_SHORT   => (u16_at(v, 0) as i16).to_string(),
+                dt::UNSIGNED_SHORT => u16_at(v, 0).to_string(),
+                dt::SIGNED_INT     => (u32_at(v, 0) as i32).to_string(),
+                dt::UNSIGNED_INT   => u32_at(v, 0).to_string(),
+                dt::FLOAT          => f32_at(v, 0).to_string(),
+                dt::FLOAT_XYZ      => format!("{}, {}, {}", f32_at(v, 0), f32_at(v, 4), f32_at(v, 8)),
+                _                  => format!("{}, {}, {}, {}", f32_at(v, 0), f32_at(v, 4), f32_at(v, 8), f32_at(v, 12)),
+            };
+            self.line(depth + 1, &value);
+        }
+        self.line(depth, &format!("</{}>", tag));
+        Ok(())
+    }
+}
+
+// ─── Import ──────────────────────────────────────────────────────────────────
+
+/// Convert CodeWalker XML back to a meta document, using the header and definitions of
+/// `schema`.
+pub fn from_xml(text: &str, schema: Meta) -> Result<Meta> {
+    let doc = Document::parse(text).context("invalid XML")?;
+    let root = doc.root_element();
+    let mut meta = Meta::with_schema(schema);
+
+    let mut r = XmlReader { meta: &meta, blocks: Vec::new() };
+    let info = r.struct_info(names::hash_of(root.tag_name().name()), root)?;
+    let at = r.alloc(info.name, info.size as usize);
+    r.structure(info, root, at)?;
+
+    meta.blocks = r.blocks;
+    Ok(meta)
+}
+
+struct XmlReader<'a> {
+    meta  : &'a Meta,
+    blocks: Vec<DataBlock>,
+}
+
+/// `<tag>` and its line, for error messages.
+fn describe(node: Node) -> String {
+    let pos = node.document().text_pos_at(node.range().start);
+    format!("<{}> at line {}", node.tag_name().name(), pos.row)
+}
+
+fn attr<'n>(node: Node<'n, '_>, name: &str) -> Result<&'n str> {
+    node.attribute(name).with_context(|| format!("{} has no '{}' attribute", describe(node), name))
+}
+
+fn text<'n>(node: Node<'n, '_>) -> &'n str {
+    node.text().unwrap_or("").trim()
+}
+
+/// An integer that fits in `size` bytes, signed or unsigned.
+fn int(text: &str, size: usize) -> Result<u32> {
+    let v: i64 = text.trim().parse().with_context(|| format!("'{}' is not an integer", text))?;
+    let bits = size as u32 * 8;
+    if v < -(1i64 << (bits - 1)) || v >= 1i64 << bits {
+        bail!("{} does not fit in {} bits", v, bits);
+    }
+    Ok(v as u32)
+}
+
+fn float(text: &str) -> Result<f32> {
+    text.trim().parse().with_context(|| format!("'{}' is not a number", text))
+}
+
+impl<'a> XmlReader<'a> {
+    fn struct_info(&self, name: u32, node: Node) -> Result<&'a StructInfo> {
+        self.meta.struct_info(name)
+            .with_context(|| format!("{}: the schema has no structure with this name", describe(node)))
+    }
+
+    /// Reserve `len` zeroed bytes in the block of type `name`, 16-byte aligned.
+    fn alloc(&mut self, name: u32, len: usize) -> (usize, usize) {
+        let block = match self.blocks.iter().position(|b| b.name == name) {
+            Some(b) => b,
+            None    => { self.blocks.push(DataBlock { name, data: Vec::new() }); self.blocks.len() - 1 }
+        };
+        let data = &mut self.blocks[block].data;
+        let offset = data.len().next_multiple_of(16);
+        data.resize(offset + len, 0);
+        (block, offset)
+    }
+
+    fn put(&mut self, (block, offset): (usize, usize), bytes: &[u8]) {
+        self.blocks[block].data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Store `bytes` in the block of type `name` and return a pointer to them.
+    fn store(&mut self, name: u32, bytes: &[u8]) -> Result<u32> {
+        let at = self.alloc(name, bytes.len());
+        self.put(at, bytes);
+        encode_pointer(at.0, at.1)
+    }
+
+    /// Write the fields of `node` as structure `info` at `at`.
+    fn structure(&mut self, info: &StructInfo, node: Node, at: (usize, usize)) -> Result<()> {
+        for e in info.entries.iter().filter(|e| e.name != ARRAY_INFO) {
+            let Some(child) = node.children().find(|c| c.is_element() && names::hash_of(c.tag_name().name()) == e.name) else {
+                continue;
+            };
+            self.entry(info, e, child, (at.0, at.1 + e.offset as usize))
+                .with_context(|| describe(child))?;
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, info: &StructInfo, e: &EntryInfo, node: Node, at: (usize, usize)) -> Result<()> {
+        match e.kind {
+            dt::STRUCTURE => {
+                let inner = self.struct_info(e.ref_key, node)?;
+                self.structure(inner, node, at)
+            }
+            dt::STRUCTURE_POINTER => {
+                let ptr = self.pointer(node)?;
+                self.put(at, &ptr.to_le_bytes());
+                Ok(())
+            }
+            dt::ARRAY => {
+                let elem = info.array_element(e)?;
+                let (ptr, count) = self.array(elem, node)?;
+                let count = u16::try_from(count).context("array has more than 65535 items")?;
+                let mut header = [0u8; ARRAY_SIZE];
+                header[0..4].copy_from_slice(&ptr.to_le_bytes());
+                header[8..10].copy_from_slice(&count.to_le_bytes());
+                header[10..12].copy_from_slice(&count.to_le_bytes());
+                self.put(at, &header);
+                Ok(())
+            }
+            dt::ARRAY_OF_BYTES => {
+                let elem = info.array_element(e)?;
+                let bytes = primitives(elem.kind, text(node))?;
+                let capacity = primitive_size(elem.kind).unwrap() * e.ref_key as usize;
+                if bytes.len() > capacity {
+                    bail!("inline array holds at most {} values", e.ref_key);
+                }
+                self.put(at, &bytes);
+                Ok(())
+            }
+            dt::ARRAY_OF_CHARS => {
+                let s = text(node).as_bytes();
+                if s.len() >= e.ref_key as usize {
+                    bail!("string is longer than {} characters", e.ref_key.saturating_sub(1));
+                }
+                self.put(at, s);
+                Ok(())
+            }
+            dt::CHAR_POINTER => {
+                let s = text(node);
+                if s.is_empty() { return Ok(()); }
+                let len = u16::try_from(s.len()).context("string is too long")?;
+                let ptr = self.store(STRING_BLOCK, &[s.as_bytes(), &[0]].concat())?;
+                let mut header = [0u8; ARRAY_SIZE];
+                header[0..4].copy_from_slice(&ptr.to_le_bytes());
+                header[8..10].copy_from_slice(&len.to_le_bytes());
+                header[10..12].copy_from_slice(&(len + 1).to_le_bytes());
+                self.put(at, &header);
+                Ok(())
+            }
+            dt::DATA_BLOCK_POINTER => Ok(()),
+            _ => {
+                let bytes = self.scalar(e, node)?;
+                self.put(at, &bytes);
+                Ok(())
+            }
+        }
+    }
+
+    fn scalar(&self, e: &EntryInfo, node: Node) -> Result<Vec<u8>> {
+        let size = scalar_size(e.kind).context("unsupported field type")?;
+        Ok(match e.kind {
+            dt::BOOLEAN => vec![match attr(node, "value")? {
+                "true" | "1"  => 1,
+                "false" | "0" => 0,
+                v => bail!("'{}' is not a boolean", v),
+            }],
+            dt::FLOAT => float(attr(node, "value")?)?.to_le_bytes().to_vec(),
+            dt::FLOAT_XY | dt::FLOAT_XYZ | dt::FLOAT_XYZW => {
+                let mut out = Vec::with_capacity(size);
+                for axis in axes(e.kind).unwrap() {
+                    out.extend(float(attr(node, axis)?)?.to_le_bytes());
+                }
+                out
+            }
+            dt::HASH => {
+                let t = text(node);
+                let hash = if t.is_empty() { 0 } else { names::hash_of(t) };
+                hash.to_le_bytes().to_vec()
+            }
+            dt::BYTE_ENUM | dt::INT_ENUM => {
+                let value = match node.attribute("value") {
+                    Some(v) => int(v, size)?,
+                    None    => {
+                        let name = text(node);
+                        let en = self.meta.enum_info(e.ref_key).context("the schema has no definition for this enum")?;
+                        let hash = names::hash_of(name);
+                        match en.entries.iter().find(|v| v.0 == hash) {
+                            Some(&(_, value)) => value as u32,
+                            None => int(name, size).with_context(|| format!("'{}' is not a value of this enum", name))?,
+                        }
+                    }
+                };
+                value.to_le_bytes()[..size].to_vec()
+            }
+            dt::SHORT_FLAGS | dt::INT_FLAGS1 | dt::INT_FLAGS2 => {
+                let value = match node.attribute("value") {
+                    Some(v) => int(v, size)?,
+                    None    => {
+                        let en = self.meta.enum_info(e.ref_key).context("the schema has no definition for these flags")?;
+                        let mut value = 0u32;
+                        for name in text(node).split_whitespace() {
+                            let hash = names::hash_of(name);
+                            value |= match en.entries.iter().find(|v| v.0 == hash) {
+                                Some(&(_, bit)) => 1u32.checked_shl(bit as u32).context("flag bit out of range")?,
+                                None => int(name, size).with_context(|| format!("'{}' is not one of these flags", name))?,
+                            };
+                        }
+                        value
+                    }
+                };
+                value.to_le_bytes()[..size].to_vec()
+            }
+            _ => int(attr(node, "value")?, size)?.to_le_bytes()[..size].to_vec(),
+        })
+    }
+
+    /// Write the structure a pointer element describes; returns the encoded pointer.
+    fn pointer(&mut self, node: Node) -> Result<u32> {
+        let kind = attr(node, "type")?;
+        if kind == "NULL" { return Ok(0); }
+        let info = self.struct_info(names::hash_of(kind), node)?;
+        let at = self.alloc(info.name, info.size as usize);
+        self.structure(info, node, at)?;
+        encode_pointer(at.0, at.1)
+    }
+
+    /// Write the items of an array element; returns the pointer and item count.
+    fn array(&mut self, elem: &EntryInfo, node: Node) -> Result<(u32, usize)> {
+        let items: Vec<Node> = node.children().filter(|c| c.is_element()).collect();
+        match elem.kind {
+            dt::STRUCTURE => {
+                if items.is_empty() { return Ok((0, 0)); }
+                let info = self.struct_info(elem.ref_key, node)?;
+                let size = info.size as usize;
+                let (block, start) = self.alloc(info.name, size * items.len());
+                for (i, item) in items.iter().enumerate() {
+                    self.structure(info, *item, (block, start + i * size))
+                        .with_context(|| format!("item {}", i))?;
+                }
+                Ok((encode_pointer(block, start)?, items.len()))
+            }
+            dt::STRUCTURE_POINTER => {
+                if items.is_empty() { return Ok((0, 0)); }
+                let (block, start) = self.alloc(dt::STRUCTURE_POINTER as u32, POINTER_SIZE * items.len());
+                for (i, item) in items.iter().enumerate() {
+                    let ptr = self.pointer(*item).with_context(|| format!("item {}", i))?;
+                    self.put((block, start + i * POINTER_SIZE), &ptr.to_le_bytes());
+                }
+                Ok((encode_pointer(block, start)?, items.len()))
+            }
+            dt::HASH => {
+                if items.is_empty() { return Ok((0, 0)); }
+                let bytes: Vec<u8> = items.iter().flat_map(|item| {
+                    let t = text(*item);
+                    if t.is_empty() { 0 } else { names::hash_of(t) }.to_le_bytes()
+                }).collect();
+                Ok((self.store(dt::HASH as u32, &bytes)?, items.len()))
+            }
+            kind => {
+                let bytes = primitives(kind, text(node))?;
+                if bytes.is_empty() { return Ok((0, 0)); }
+                let size = primitive_size(kind).unwrap();
+                // Padded vectors live in the same blocks as 4-component ones.
+                let block = if kind == dt::FLOAT_XYZ { dt::FLOAT_XYZW } else { kind };
+                Ok((self.store(block as u32, &bytes)?, bytes.len() / size))
+            }
+        }
+    }
+}
+
+/// Parse the text of a primitive array.
+fn primitives(kind: u8, text: &str) -> Result<Vec<u8>> {
+    array_content(kind).with_context(|| format!("unsupported array element type 0x{:02X}", kind))?;
+    let size = primitive_size(kind).unwrap();
+    let values: Vec<&str> = text.split(|c: char| c.is_whitespace() || c == ',').filter(|v| !v.is_empty()).collect();
+
+    let mut out = Vec::with_capacity(values.len() * size);
+    match kind {
+        dt::FLOAT => for v in values { out.extend(float(v)?.to_le_bytes()); },
+        dt::FLOAT_XY | dt::FLOAT_XYZ | dt::FLOAT_XYZW => {
+            let axes = axes(kind).unwrap().len();
+            if !values.len().is_multiple_of(axes) {
+                bail!("{} values do not make whole {}-component vectors", values.len(), axes);
+            }
+            for vector in values.chunks(axes) {
+                let mut v = vec![0u8; size];
+                for (i, c) in vector.iter().enumerate() {
+                    v[i * 4..i * 4 + 4].copy_from_slice(&float(c)?.to_le_bytes());
+                }
+                out.extend(v);
+            }
+        }
+        _ => for v in values { out.extend_from_slice(&int(v, size)?.to_le_bytes()[..size]); },
+    }
+    Ok(out)
+}
@@ -0,0 +1,160 @@
+// Names for the JOAAT hashes found in game files.
+//
+// GTA V stores most identifiers (meta field and type names, archetypes, text keys, sound
+// names) only as the JOAAT hash of the lower-case name. A dictionary maps them back: a
+// built-in list of common meta names, plus any name lists given with `--names` (one name
+// per line; `#` starts a comment). Hashes with no known name are written `hash_XXXXXXXX`,
+// which `hash_of` parses back.
+
+use anyhow::{Context, Result};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use rpf_archive::rage_joaat;
+
+/// Names of meta structures, fields and enum values used by .ymap and .ytyp files.
+const BUILTIN: &[&str] = &[
+    // Structures and enums
+    "CMapData", "CEntityDef", "CMloInstanceDef", "CMapTypes", "CBaseArchetypeDef",
+    "CTimeArchetypeDef", "CMloArchetypeDef", "CMloRoomDef", "CMloPortalDef", "CMloEntitySet",
+    "CMloTimeCycleModifier", "CCompositeEntityType", "CBlockDesc", "CContainerLodDef",
+    "CBoxOccluder", "COccludeModel", "CTimeCycleModifier", "CCarGen", "CLODLight",
+    "CDistantLODLight", "CInstancedMapData", "rage__fwGrassInstanceListDef",
+    "rage__fwGrassInstanceListDef__InstanceData", "CExtensionDefParticleEffect",
+    "CExtensionDefLightEffect", "CExtensionDefAudioEmitter", "CExtensionDefSpawnPoint",
+    "CExtensionDefLadder", "CExtensionDefBuoyancy", "CExtensionDefDoor",
+    "CExtensionDefExplosionEffect", "CExtensionDefLightShaft", "CExtensionDefWindDisturbance",
+    "CExtensionDefProcObject", "CExtensionDefExpression", "CExtensionDefAudioCollisionSettings",
+    "CExtensionDefSpawnPointOverride", "CLightAttrDef", "rage__eLodType", "rage__ePriorityLevel",
+    "rage__fwArchetypeDef__eAssetType", "CMapDataContents", "rage__fwContainerLodDef",
+    "BoxOccluder", "OccludeModel", "rage__fwInstancedMapData", "rage__fwPropInstanceListDef",
+    "rage__spdAABB",
+    // CMapData
+    "name", "parent", "flags", "contentFlags", "streamingExtentsMin", "streamingExtentsMax",
+    "entitiesExtentsMin", "entitiesExtentsMax", "entities", "containerLods", "boxOccluders",
+    "occludeModels", "physicsDictionaries", "instancedData", "timeCycleModifiers",
+    "carGenerators", "LODLightsSOA", "DistantLODLightsSOA", "block", "GrassInstanceList",
+    // CEntityDef
+    "archetypeName", "guid", "position", "rotation", "scaleXY", "scaleZ", "parentIndex",
+    "lodDist", "childLodDist", "lodLevel", "numChildren", "priorityLevel", "extensions",
+    "ambientOcclusionMultiplier", "artificialAmbientOcclusion", "tintValue",
+    // CBlockDesc
+    "version", "exportedBy", "owner", "time",
+    // CMapTypes and archetypes
+    "archetypes", "dependencies", "compositeEntityTypes", "specialAttribute", "bbMin", "bbMax",
+    "bsCentre", "bsRadius", "hdTextureDist", "textureDictionary", "clipDictionary",
+    "drawableDictionary", "physicsDictionary", "assetType", "assetName", "timeFlags",
+    "mloFlags", "rooms", "portals", "entitySets", "blend", "timecycleName",
+    "secondaryTimecycleName", "portalCount", "floorId", "exteriorVisibiltyDepth",
+    "attachedObjects", "roomFrom", "roomTo", "mirrorPriority", "opacity", "audioOcclusion",
+    "corners", "locations", "groupId", "defaultEntitySets", "numExitPortals", "MLOInstflags",
+    // Map extras
+    "orientX", "orientY", "perpendicularLength", "carModel", "bodyColorRemap1",
+    "bodyColorRemap2", "bodyColorRemap3", "bodyColorRemap4", "popGroup", "livery",
+    "minExtents", "maxExtents", "percentage", "range", "startHour", "endHour",
+    "iCenterX", "iCenterY", "iCenterZ", "iCosZ", "iLength", "iWidth", "iHeight", "iSinZ",
+    "bmin", "bmax", "dataSize", "verts", "numVertsInBytes", "numTris", "direction", "falloff",
+    "falloffExponent", "timeAndStateFlags", "hash", "coneInnerAngle", "coneOuterAngleOrCapExt",
+    "coronaIntensity", "RGBI", "numStreetLights", "category", "BatchAABB", "ScaleRange",
+    "lodFadeStartDist", "LodInstFadeRange", "OrientToTerrain", "InstanceList",
+    "Position", "NormalX", "NormalY", "Color", "Scale", "Ao", "Pad", "ImapLink",
+    "PropInstanceList", "min", "max", "sphere",
+    // Extensions
+    "offsetPosition", "offsetRotation", "fxName", "fxType", "boneTag", "scale", "probability",
+    "color", "instances",
+    // Enum values
+    "LODTYPES_DEPTH_HD", "LODTYPES_DEPTH_LOD", "LODTYPES_DEPTH_SLOD1", "LODTYPES_DEPTH_SLOD2",
+    "LODTYPES_DEPTH_SLOD3", "LODTYPES_DEPTH_ORPHANHD", "LODTYPES_DEPTH_SLOD4",
+    "PRI_REQUIRED", "PRI_OPTIONAL_HIGH", "PRI_OPTIONAL_MEDIUM", "PRI_OPTIONAL_LOW",
+    "ASSET_TYPE_UNINITIALIZED", "ASSET_TYPE_FRAGMENT", "ASSET_TYPE_DRAWABLE",
+    "ASSET_TYPE_DRAWABLEDICTIONARY", "ASSET_TYPE_ASSETLESS",
+];
+
+/// Hash -> name dictionary.
+pub struct Names {
+    names: HashMap<u32, String>,
+}
+
+impl Names {
+    /// The built-in names plus every name in `files`.
+    pub fn load(files: &[PathBuf]) -> Result<Self> {
+        let mut names = Self { names: HashMap::new() };
+        for name in BUILTIN { names.add(name); }
+        for path in files {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("cannot read name list {}", path.display()))?;
+            for line in text.lines() {
+                let name = line.split('#').next().unwrap_or("").trim();
+                if !name.is_empty() { names.add(name); }
+            }
+        }
+        Ok(names)
+    }
+
+    pub fn add(&mut self, name: &str) {
+        self.names.entry(hash(name)).or_insert_with(|| name.to_string());
+    }
+
+    pub fn get(&self, hash: u32) -> Option<&str> {
+        self.names.get(&hash).map(String::as_str)
+    }
+
+    /// The name of a hash stored with only the bits in `mask` (e.g. 29-bit AWC stream IDs).
+    pub fn get_masked(&self, hash: u32, mask: u32) -> Option<&str> {
+        self.names.iter().find(|(h, _)| *h & mask == hash).map(|(_, n)| n.as_str())
+    }
+
+    /// The name of `hash`, or `hash_XXXXXXXX`.
+    pub fn display(&self, hash: u32) -> String {
+        match self.get(hash) {
+            Some(name) => name.to_string(),
+            None       => format!("hash_{:08X}", hash),
+        }
+    }
+}
+
+/// JOAAT of the lower-case name.
+pub fn hash(name: &str) -> u32 {
+    rage_joaat(&name.to_lowercase())
+}
+
+/// Parse a name written by `Names::display` (or a `0x` hex literal) back to its hash.
+pub fn hash_of(text: &str) -> u32 {
+    let hex = text.strip_prefix("hash_").or_else(|| text.strip_prefix("0x"));
+    match hex.and_then(|h| u32::from_str_radix(h, 16).ok()) {
+        Some(h) => h,
+        None    => hash(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn builtin_names_are_unique() {
+        let mut seen = HashSet::new();
+        for name in BUILTIN {
+            assert!(seen.insert(*name), "'{}' is listed twice", name);
+        }
+    }
+
+    #[test]
+    fn displays_and_parses_hashes() {
+        let names = Names::load(&[]).unwrap();
+        assert_eq!(names.display(hash("CEntityDef")), "CEntityDef");
+        assert_eq!(names.display(hash("archetypename")), "archetypeName");
+        assert_eq!(names.display(0x1234_ABCD), "hash_1234ABCD");
+        assert_eq!(hash_of("hash_1234ABCD"), 0x1234_ABCD);
+        assert_eq!(hash_of("0x1234abcd"), 0x1234_ABCD);
+        assert_eq!(hash_of("CEntityDef"), rage_joaat("centitydef"));
+    }
+
+    #[test]
+    fn finds_masked_hashes() {
+        let names = Names::load(&[]).unwrap();
+        let id = hash("CEntityDef") & 0x1FFF_FFFF;
+        assert_eq!(names.get_masked(id, 0x1FFF_FFFF), Some("CEntityDef"));
+        assert_eq!(names.get_masked(id | 0x8000_0000, 0x1FFF_FFFF), None);
+    }
+}
@@ -0,0 +1,275 @@
+// Navigation meshes (.ynv): the polygons peds walk on, for one cell of the map grid.
+//
+// The 0x170-byte `NavMesh` root holds the mesh size, a table of up to 32 adjacent area IDs
+// and pointers to lists of vertices, vertex indices, edges and polygons, plus a sector
+// tree, portals and portal links. Lists are split into parts, each a pointer and a count.
+// Vertices are three u16s scaled over the mesh size from the root sector's minimum
+// corner. A polygon's vertex indices and edges run from its first index; each edge names
+// the polygon across it as an area table slot and a polygon ID. Offsets follow CodeWalker's
+// `NavMesh`, `NavMeshList`, `NavMeshPoly`, `NavMeshEdge`, `NavMeshPortal` and `NavMeshSector`.
+
+use anyhow::{bail, Context, Result};
+
+use crate::resource::{f32_at, u16_at, u32_at, u64_at, Resource, SYSTEM_BASE};
+
+const NAVMESH_SIZE: usize = 0x170;
+const LIST_SIZE: usize = 0x30;
+const LIST_PART_SIZE: usize = 0x10;
+const POLY_SIZE: usize = 0x30;
+const PORTAL_SIZE: usize = 0x1C;
+const SECTOR_SIZE: usize = 0x60;
+
+/// A portal's `AreaFlags` holds the area IDs it joins in two 14-bit fields.
+const AREA_MASK: u32 = 0x3FFF;
+
+/// Polygon ID of an edge with nothing across it.
+const NO_POLY: u32 = 0x3FFF;
+
+pub struct NavMesh {
+    pub area_id      : u32,
+    pub content_flags: u32,
+    /// Minimum and maximum corner.
+    pub bounds       : ([f32; 3], [f32; 3]),
+    pub vertices     : Vec<[f32; 3]>,
+    pub polygons     : Vec<Polygon>,
+    pub portals      : Vec<Portal>,
+}
+
+pub struct Polygon {
+    pub vertices    : Vec<usize>,
+    pub area_id     : u16,
+    /// `PolyFlags0`, the low 5 bits of the index count word, `PolyFlags1`, `PolyFlags2` and
+    /// `PartFlags` as stored.
+    pub flags       : (u16, u8, u32, u32, u32),
+    /// The polygon across each edge (after vertex `i`), as `(area ID, polygon)`.
+    pub adjacent    : Vec<Option<(u32, u32)>>,
+    /// Entries of the portal link table used by this polygon.
+    pub portal_links: Vec<u16>,
+}
+
+pub struct Portal {
+    pub kind      : u8,
+    pub angle     : u8,
+    pub flags     : u16,
+    pub from      : [f32; 3],
+    pub to        : [f32; 3],
+    /// Two polygon IDs each side.
+    pub poly_from : [u16; 2],
+    pub poly_to   : [u16; 2],
+    pub area_from : u16,
+    pub area_to   : u16,
+    /// The top 4 bits of `AreaFlags`, meaning unknown.
+    pub area_flags: u8,
+}
+
+/// The items of a `NavMeshList`: the concatenated data of its parts.
+fn list<'r>(res: &'r Resource, ptr: u64, size: usize, what: &str) -> Result<Vec<&'r [u8]>> {
+    if ptr == 0 { return Ok(Vec::new()); }
+    let header = res.slice(ptr, LIST_SIZE).with_context(|| format!("{} list out of bounds (0x{:X})", what, ptr))?;
+    let (count, parts) = (u32_at(header, 0x08) as usize, u32_at(header, 0x20) as usize);
+    let table = if parts == 0 { &[][..] } else {
+        res.slice(u64_at(header, 0x10), parts * LIST_PART_SIZE).with_context(|| format!("{} list parts out of bounds", what))?
+    };
+
+    let mut items = Vec::with_capacity(count);
+    for part in table.chunks_exact(LIST_PART_SIZE) {
+        let n = u32_at(part, 0x08) as usize;
+        if n == 0 { continue; }
+        let data = res.slice(u64_at(part, 0), n * size).with_context(|| format!("{} list part out of bounds", what))?;
+        items.extend(data.chunks_exact(size));
+    }
+    if items.len() != count {
+        bail!("{} list holds {} items in its parts, expected {}", what, items.len(), count);
+    }
+    Ok(items)
+}
+
+/// Parse a .ynv resource.
+pub fn parse(res: &Resource) -> Result<NavMesh> {
+    let n = res.slice(SYSTEM_BASE, NAVMESH_SIZE).context("system section too small for a navmesh")?;
+    let size = [f32_at(n, 0x60), f32_at(n, 0x64), f32_at(n, 0x68)];
+    let adjacent_areas: Vec<u32> = (0..(u32_at(n, 0x94) as usize).min(32)).map(|i| u32_at(n, 0x98 + i * 4)).collect();
+
+    let min = match u64_at(n, 0x120) {
+        0   => [0.0; 3],
+        ptr => {
+            let s = res.slice(ptr, SECTOR_SIZE).context("sector tree out of bounds")?;
+            [f32_at(s, 0), f32_at(s, 4), f32_at(s, 8)]
+        }
+    };
+    let position = |v: &[u8]| -> [f32; 3] {
+        std::array::from_fn(|i| min[i] + u16_at(v, i * 2) as f32 / u16::MAX as f32 * size[i])
+    };
+
+    let vertices: Vec<[f32; 3]> = list(res, u64_at(n, 0x70), 6, "vertex")?.into_iter().map(position).collect();
+    let indices: Vec<usize> = list(res, u64_at(n, 0x80), 2, "index")?.into_iter().map(|i| u16_at(i, 0) as usize).collect();
+    let edges: Vec<u32> = list(res, u64_at(n, 0x88), 8, "edge")?.into_iter().map(|e| u32_at(e, 0)).collect();
+    let portal_count = u32_at(n, 0x14C) as usize;
+    let portals = match portal_count {
+        0 => &[][..],
+        c => res.slice(u64_at(n, 0x128), c * PORTAL_SIZE).context("portals out of bounds")?,
+    };
+    let link_count = u32_at(n, 0x150) as usize;
+    let links: Vec<u16> = match link_count {
+        0 => Vec::new(),
+        c => res.slice(u64_at(n, 0x130), c * 2).context("portal links out of bounds")?
+            .chunks_exact(2).map(|l| u16_at(l, 0)).collect(),
+    };
+
+    let polygons = list(res, u64_at(n, 0x118), POLY_SIZE, "polygon")?.into_iter().enumerate().map(|(i, p)| {
+        let (first, count) = (u16_at(p, 4) as usize, (u16_at(p, 2) >> 5) as usize);
+        let corners = indices.get(first..first + count)
+            .with_context(|| format!("polygon {}: indices {}..{} out of range", i, first, first + count))?;
+        if let Some(&v) = corners.iter().find(|&&v| v >= vertices.len()) {
+            bail!("polygon {}: vertex {} out of range", i, v);
+        }
+        let adjacent = edges.get(first..first + count)
+            .with_context(|| format!("polygon {}: edges out of range", i))?
+            .iter().map(|&e| {
+                let poly = (e >> 5) & NO_POLY;
+                (poly != NO_POLY).then(|| (adjacent_areas.get((e & 0x1F) as usize).copied().unwrap_or(u32::MAX), poly))
+            }).collect();
+        let part = u32_at(p, 0x2C);
+        let (link_first, link_count) = ((part >> 15) as usize, ((part >> 12) & 7) as usize);
+        Ok(Polygon {
+            vertices    : corners.to_vec(),
+            area_id     : u16_at(p, 6),
+            flags       : (u16_at(p, 0), (u16_at(p, 2) & 0x1F) as u8, u32_at(p, 0x24), u32_at(p, 0x28), part),
+            adjacent,
+            portal_links: links.get(link_first..link_first + link_count).map(<[u16]>::to_vec).unwrap_or_default(),
+        })
+    }).collect::<Result<_>>()?;
+
+    Ok(NavMesh {
+        area_id      : u32_at(n, 0x140),
+        content_flags: u32_at(n, 0x10),
+        bounds       : (min, std::array::from_fn(|i| min[i] + size[i])),
+        vertices,
+        polygons,
+        portals      : portals.chunks_exact(PORTAL_SIZE).map(|p| Portal {
+            kind      : p[0],
+            angle     : p[1],
+            flags     : u16_at(p, 2),
+            from      : position(&p[4..10]),
+            to        : position(&p[10..16]),
+            poly_from : [u16_at(p, 16), u16_at(p, 18)],
+            poly_to   : [u16_at(p, 20), u16_at(p, 22)],
+            area_from : (u32_at(p, 24) & AREA_MASK) as u16,
+            area_to   : ((u32_at(p, 24) >> 14) & AREA_MASK) as u16,
+            area_flags: (u32_at(p, 24) >> 28) as u8,
+        }).collect(),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::resource::{Block, ResourceBuilder, Section};
+
+    const NO_EDGE: u32 = NO_POLY << 5;
+
+    fn bytes<T: Copy, const N: usize>(items: &[T], to_le: impl Fn(T) -> [u8; N]) -> Vec<u8> {
+        items.iter().flat_map(|&i| to_le(i)).collect()
+    }
+
+    /// A `NavMeshList` of `size`-byte items, one part per entry of `parts`.
+    fn add_list(b: &mut ResourceBuilder, root: Block, at: usize, size: usize, parts: &[Vec<u8>]) {
+        let mut header = vec![0; LIST_SIZE];
+        header[0x08..0x0C].copy_from_slice(&((parts.iter().map(Vec::len).sum::<usize>() / size) as u32).to_le_bytes());
+        header[0x20..0x24].copy_from_slice(&(parts.len() as u32).to_le_bytes());
+        let mut table = vec![0; parts.len() * LIST_PART_SIZE];
+        for (i, part) in parts.iter().enumerate() {
+            table[i * LIST_PART_SIZE + 8..i * LIST_PART_SIZE + 12].copy_from_slice(&((part.len() / size) as u32).to_le_bytes());
+        }
+        let (header_block, table_block) = (b.add(Section::System, header), b.add(Section::System, table));
+        for (i, part) in parts.iter().enumerate() {
+            let data = b.add(Section::System, part.clone());
+            b.pointer(table_block, i * LIST_PART_SIZE, data);
+        }
+        b.pointer(header_block, 0x10, table_block);
+        b.pointer(root, at, header_block);
+    }
+
+    fn polygon(first: u16, count: u16, part_flags: u32) -> Vec<u8> {
+        let mut p = vec![0; POLY_SIZE];
+        p[0..2].copy_from_slice(&0x0102u16.to_le_bytes());
+        p[2..4].copy_from_slice(&(count << 5 | 3).to_le_bytes());
+        p[4..6].copy_from_slice(&first.to_le_bytes());
+        p[6..8].copy_from_slice(&42u16.to_le_bytes());
+        p[0x2C..0x30].copy_from_slice(&part_flags.to_le_bytes());
+        p
+    }
+
+    /// A 100 x 100 x 10 m navmesh at (1000, 2000, 0) for area 42: two triangles sharing an
+    /// edge, the vertex list split over two parts, and one portal to area 43 using links 1
+    /// and 2.
+    pub(crate) fn sample() -> Vec<u8> {
+        let mut b = ResourceBuilder::new();
+        let mut root = vec![0; NAVMESH_SIZE];
+        root[0x10..0x14].copy_from_slice(&7u32.to_le_bytes());
+        for (i, s) in [100.0f32, 100.0, 10.0].into_iter().enumerate() {
+            root[0x60 + i * 4..0x64 + i * 4].copy_from_slice(&s.to_le_bytes());
+        }
+        root[0x94..0x98].copy_from_slice(&1u32.to_le_bytes());
+        root[0x98..0x9C].copy_from_slice(&42u32.to_le_bytes());
+        root[0x140..0x144].copy_from_slice(&42u32.to_le_bytes());
+        root[0x14C..0x150].copy_from_slice(&1u32.to_le_bytes());
+        root[0x150..0x154].copy_from_slice(&3u32.to_le_bytes());
+        let root_block = b.add(Section::System, root);
+
+        let quantised = |v: &[[u16; 3]]| v.iter().flatten().flat_map(|q| q.to_le_bytes()).collect::<Vec<u8>>();
+        add_list(&mut b, root_block, 0x70, 6, &[
+            quantised(&[[0, 0, 0], [0xFFFF, 0, 0], [0xFFFF, 0xFFFF, 0]]),
+            quantised(&[[0, 0xFFFF, 0xFFFF]]),
+        ]);
+        add_list(&mut b, root_block, 0x80, 2, &[bytes(&[0u16, 1, 2, 0, 2, 3], u16::to_le_bytes)]);
+        let edges = [NO_EDGE, NO_EDGE, 1 << 5, 0, NO_EDGE, NO_EDGE];
+        add_list(&mut b, root_block, 0x88, 8, &[bytes(&edges, |e: u32| u64::from(e).to_le_bytes())]);
+        add_list(&mut b, root_block, 0x118, POLY_SIZE, &[[polygon(0, 3, 1 << 15 | 2 << 12), polygon(3, 3, 0)].concat()]);
+
+        let mut sector = vec![0; SECTOR_SIZE];
+        for (i, m) in [1000.0f32, 2000.0, 0.0].into_iter().enumerate() {
+            sector[i * 4..i * 4 + 4].copy_from_slice(&m.to_le_bytes());
+        }
+        let mut portal = vec![1, 2];
+        portal.extend(bytes(&[3u16, 0, 0, 0, 0xFFFF, 0xFFFF, 0xFFFF, 0, 1, 1, 0], u16::to_le_bytes));
+        portal.extend((42u32 | 43 << 14 | 5 << 28).to_le_bytes());
+        for (at, data) in [(0x120, sector), (0x128, portal), (0x130, bytes(&[5u16, 6, 7], u16::to_le_bytes))] {
+            let block = b.add(Section::System, data);
+            b.pointer(root_block, at, block);
+        }
+        b.build(2).unwrap()
+    }
+
+    #[test]
+    fn parses_polygons_edges_and_portals() {
+        let mesh = parse(&Resource::parse(&sample()).unwrap()).unwrap();
+
+        assert_eq!((mesh.area_id, mesh.content_flags), (42, 7));
+        assert_eq!(mesh.bounds, ([1000.0, 2000.0, 0.0], [1100.0, 2100.0, 10.0]));
+        assert_eq!(mesh.vertices, [[1000.0, 2000.0, 0.0], [1100.0, 2000.0, 0.0], [1100.0, 2100.0, 0.0], [1000.0, 2100.0, 10.0]]);
+
+        let [a, b] = &mesh.polygons[..] else { panic!("expected two polygons") };
+        assert_eq!((a.vertices.as_slice(), b.vertices.as_slice()), (&[0, 1, 2][..], &[0, 2, 3][..]));
+        assert_eq!((a.area_id, a.flags), (42, (0x0102, 3, 0, 0, 1 << 15 | 2 << 12)));
+        assert_eq!(a.adjacent, [None, None, Some((42, 1))]);
+        assert_eq!(b.adjacent, [Some((42, 0)), None, None]);
+        assert_eq!((a.portal_links.as_slice(), b.portal_links.as_slice()), (&[6, 7][..], &[][..]));
+
+        let p = &mesh.portals[0];
+        assert_eq!((p.kind, p.angle, p.flags), (1, 2, 3));
+        assert_eq!((p.from, p.to), ([1000.0, 2000.0, 0.0], [1100.0, 2100.0, 10.0]));
+        assert_eq!((p.poly_from, p.poly_to), ([0, 1], [1, 0]));
+        assert_eq!((p.area_from, p.area_to, p.area_flags), (42, 43, 5));
+    }
+
+    #[test]
+    fn rejects_polygons_past_their_lists() {
+        let mut res = Resource::parse(&sample()).unwrap();
+        // Start the first polygon's three indices at 5, past the end of the index list.
+        let poly = res.system.windows(8).position(|w| w == [2, 1, 0x63, 0, 0, 0, 42, 0]).unwrap();
+        res.system[poly + 4] = 5;
+        let err = parse(&res).err().unwrap();
+        assert!(err.to_string().contains("out of range"), "{}", err);
+    }
+}
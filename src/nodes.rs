@@ -0,0 +1,287 @@
+// Path nodes (.ynd): the vehicle and ped road graph of one 512x512 area of the map.
+//
+// The 0x70-byte `NodeDictionary` points at an array of 0x28-byte nodes (vehicle nodes
+// first, then ped nodes), an array of 8-byte links, and the junctions: 12-byte height
+// ranges with a byte heightmap each, tied to their node by 8-byte junction refs. A node's
+// links are the `link count` entries starting at its link index. Positions are quantised:
+// X and Y in quarter metres, Z in 1/32 metres. Offsets follow CodeWalker's
+// `NodeDictionary`, `Node`, `NodeLink`, `NodeJunction` and `NodeJunctionRef`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::resource::{u16_at, u32_at, u64_at, Resource, ResourceBuilder, Section, SYSTEM_BASE};
+
+/// Resource version of .ynd files.
+pub const VERSION: u32 = 1;
+
+const DICTIONARY_SIZE: usize = 0x70;
+const NODE_SIZE: usize = 0x28;
+const LINK_SIZE: usize = 8;
+const JUNCTION_SIZE: usize = 12;
+const JUNCTION_REF_SIZE: usize = 8;
+
+/// Quantisation steps per metre.
+const XY_SCALE: f32 = 4.0;
+const Z_SCALE: f32 = 32.0;
+
+pub struct NodeDictionary {
+    /// Virtual function table address stored in the root, kept for rebuilding.
+    pub vft          : u32,
+    pub nodes        : Vec<Node>,
+    /// How many of `nodes` (from the start) are vehicle nodes; the rest are ped nodes.
+    pub vehicle_nodes: usize,
+}
+
+pub struct Node {
+    pub area_id    : u16,
+    pub node_id    : u16,
+    /// Text hash of the street name.
+    pub street_name: u32,
+    pub position   : [f32; 3],
+    /// `Flags0` to `Flags4` as stored.
+    pub flags      : [u8; 5],
+    /// Low 3 bits of the link count byte.
+    pub link_flags : u8,
+    pub links      : Vec<Link>,
+    pub junction   : Option<Junction>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Link {
+    pub area_id: u16,
+    pub node_id: u16,
+    pub flags  : [u8; 3],
+    pub length : u8,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Junction {
+    /// X and Y.
+    pub position : [f32; 2],
+    pub min_z    : f32,
+    pub max_z    : f32,
+    pub width    : u8,
+    pub height   : u8,
+    /// `width * height` bytes.
+    pub heightmap: Vec<u8>,
+    /// Second field of the junction ref, kept as stored.
+    pub unknown  : u16,
+}
+
+fn i16_at(data: &[u8], off: usize) -> i16 {
+    u16_at(data, off) as i16
+}
+
+/// Parse a .ynd resource.
+pub fn parse(res: &Resource) -> Result<NodeDictionary> {
+    let d = res.slice(SYSTEM_BASE, DICTIONARY_SIZE).context("system section too small for a node dictionary")?;
+    let list = |ptr_at: usize, count: usize, size: usize, what: &str| -> Result<&[u8]> {
+        if count == 0 { return Ok(&[]); }
+        let ptr = u64_at(d, ptr_at);
+        res.slice(ptr, count * size).with_context(|| format!("{} {} out of bounds (0x{:X})", count, what, ptr))
+    };
+
+    let node_count = u32_at(d, 0x18) as usize;
+    let vehicle_nodes = u32_at(d, 0x1C) as usize;
+    if vehicle_nodes > node_count {
+        bail!("{} vehicle nodes but only {} nodes", vehicle_nodes, node_count);
+    }
+    let nodes = list(0x10, node_count, NODE_SIZE, "nodes")?;
+    let links = list(0x28, u32_at(d, 0x30) as usize, LINK_SIZE, "links")?;
+    let junction_count = u32_at(d, 0x60) as usize;
+    let junctions = list(0x38, junction_count, JUNCTION_SIZE, "junctions")?;
+    let heights = list(0x40, u32_at(d, 0x64) as usize, 1, "heightmap bytes")?;
+    let refs = list(0x50, u16_at(d, 0x58) as usize, JUNCTION_REF_SIZE, "junction refs")?;
+
+    let mut out: Vec<Node> = nodes.chunks_exact(NODE_SIZE).enumerate().map(|(i, n)| {
+        let (first, count) = (u16_at(n, 0x1A) as usize, (n[0x25] >> 3) as usize);
+        let links = links.get(first * LINK_SIZE..(first + count) * LINK_SIZE)
+            .with_context(|| format!("node {}: links {}..{} out of range", i, first, first + count))?;
+        Ok(Node {
+            area_id    : u16_at(n, 0x10),
+            node_id    : u16_at(n, 0x12),
+            street_name: u32_at(n, 0x14),
+            position   : [i16_at(n, 0x1C) as f32 / XY_SCALE, i16_at(n, 0x1E) as f32 / XY_SCALE, i16_at(n, 0x22) as f32 / Z_SCALE],
+            flags      : [n[0x20], n[0x21], n[0x24], n[0x26], n[0x27]],
+            link_flags : n[0x25] & 7,
+            links      : links.chunks_exact(LINK_SIZE).map(|l| Link {
+                area_id: u16_at(l, 0),
+                node_id: u16_at(l, 2),
+                flags  : [l[4], l[5], l[6]],
+                length : l[7],
+            }).collect(),
+            junction   : None,
+        })
+    }).collect::<Result<_>>()?;
+
+    for (i, r) in refs.chunks_exact(JUNCTION_REF_SIZE).enumerate() {
+        let (area, node, id) = (u16_at(r, 0), u16_at(r, 2), u16_at(r, 4) as usize);
+        let j = junctions.get(id * JUNCTION_SIZE..(id + 1) * JUNCTION_SIZE)
+            .with_context(|| format!("junction ref {} names junction {} of {}", i, id, junction_count))?;
+        let (start, width, height) = (u16_at(j, 8) as usize, j[10], j[11]);
+        let heightmap = heights.get(start..start + width as usize * height as usize)
+            .with_context(|| format!("junction {}: heightmap out of range", id))?;
+        let target = out.iter_mut().find(|n| n.area_id == area && n.node_id == node)
+            .with_context(|| format!("junction ref {} names missing node {}:{}", i, area, node))?;
+        target.junction = Some(Junction {
+            position : [i16_at(j, 2) as f32 / XY_SCALE, i16_at(j, 4) as f32 / XY_SCALE],
+            max_z    : i16_at(j, 0) as f32 / Z_SCALE,
+            min_z    : i16_at(j, 6) as f32 / Z_SCALE,
+            width, height,
+            heightmap: heightmap.to_vec(),
+            unknown  : u16_at(r, 6),
+        });
+    }
+
+    Ok(NodeDictionary { vft: u32_at(d, 0), nodes: out, vehicle_nodes })
+}
+
+/// Quantise a coordinate, failing if it is out of range.
+fn quantise(value: f32, scale: f32, what: &str) -> Result<[u8; 2]> {
+    let q = (value * scale).round();
+    if !(i16::MIN as f32..=i16::MAX as f32).contains(&q) {
+        bail!("{} {} is out of range (±{})", what, value, i16::MAX as f32 / scale);
+    }
+    Ok((q as i16).to_le_bytes())
+}
+
+/// Build a .ynd resource (a complete RSC7 file).
+pub fn build(dict: &NodeDictionary) -> Result<Vec<u8>> {
+    if dict.vehicle_nodes > dict.nodes.len() {
+        bail!("{} vehicle nodes but only {} nodes", dict.vehicle_nodes, dict.nodes.len());
+    }
+    let (mut nodes, mut links) = (Vec::new(), Vec::new());
+    let (mut junctions, mut heights, mut refs) = (Vec::new(), Vec::new(), Vec::new());
+    let mut link_count = 0usize;
+    for (i, n) in dict.nodes.iter().enumerate() {
+        let what = |field: &str| format!("node {} {}", i, field);
+        if n.links.len() > 0x1F { bail!("node {} has {} links (at most 31)", i, n.links.len()); }
+        let mut raw = [0u8; NODE_SIZE];
+        raw[0x10..0x12].copy_from_slice(&n.area_id.to_le_bytes());
+        raw[0x12..0x14].copy_from_slice(&n.node_id.to_le_bytes());
+        raw[0x14..0x18].copy_from_slice(&n.street_name.to_le_bytes());
+        raw[0x1A..0x1C].copy_from_slice(&u16::try_from(link_count).context("too many links")?.to_le_bytes());
+        raw[0x1C..0x1E].copy_from_slice(&quantise(n.position[0], XY_SCALE, &what("X"))?);
+        raw[0x1E..0x20].copy_from_slice(&quantise(n.position[1], XY_SCALE, &what("Y"))?);
+        raw[0x22..0x24].copy_from_slice(&quantise(n.position[2], Z_SCALE, &what("Z"))?);
+        [raw[0x20], raw[0x21], raw[0x24], raw[0x26], raw[0x27]] = n.flags;
+        raw[0x25] = (n.links.len() as u8) << 3 | (n.link_flags & 7);
+        nodes.extend_from_slice(&raw);
+
+        for l in &n.links {
+            links.extend(l.area_id.to_le_bytes());
+            links.extend(l.node_id.to_le_bytes());
+            links.extend([l.flags[0], l.flags[1], l.flags[2], l.length]);
+        }
+        link_count += n.links.len();
+
+        if let Some(j) = &n.junction {
+            if j.heightmap.len() != j.width as usize * j.height as usize {
+                bail!("node {}: heightmap has {} bytes, expected {} x {}", i, j.heightmap.len(), j.width, j.height);
+            }
+            let id = u16::try_from(junctions.len() / JUNCTION_SIZE).context("too many junctions")?;
+            junctions.extend(quantise(j.max_z, Z_SCALE, &what("junction max Z"))?);
+            junctions.extend(quantise(j.position[0], XY_SCALE, &what("junction X"))?);
+            junctions.extend(quantise(j.position[1], XY_SCALE, &what("junction Y"))?);
+            junctions.extend(quantise(j.min_z, Z_SCALE, &what("junction min Z"))?);
+            junctions.extend(u16::try_from(heights.len()).context("junction heightmaps too large")?.to_le_bytes());
+            junctions.extend([j.width, j.height]);
+            heights.extend_from_slice(&j.heightmap);
+            refs.extend(n.area_id.to_le_bytes());
+            refs.extend(n.node_id.to_le_bytes());
+            refs.extend(id.to_le_bytes());
+            refs.extend(j.unknown.to_le_bytes());
+        }
+    }
+
+    let junction_count = junctions.len() / JUNCTION_SIZE;
+    let mut d = vec![0u8; DICTIONARY_SIZE];
+    d[0x00..0x04].copy_from_slice(&dict.vft.to_le_bytes());
+    d[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+    d[0x18..0x1C].copy_from_slice(&(dict.nodes.len() as u32).to_le_bytes());
+    d[0x1C..0x20].copy_from_slice(&(dict.vehicle_nodes as u32).to_le_bytes());
+    d[0x20..0x24].copy_from_slice(&((dict.nodes.len() - dict.vehicle_nodes) as u32).to_le_bytes());
+    d[0x30..0x34].copy_from_slice(&(link_count as u32).to_le_bytes());
+    d[0x48..0x4C].copy_from_slice(&1u32.to_le_bytes());
+    d[0x58..0x5A].copy_from_slice(&(junction_count as u16).to_le_bytes());
+    d[0x5A..0x5C].copy_from_slice(&(junction_count as u16).to_le_bytes());
+    d[0x60..0x64].copy_from_slice(&(junction_count as u32).to_le_bytes());
+    d[0x64..0x68].copy_from_slice(&(heights.len() as u32).to_le_bytes());
+
+    let mut b = ResourceBuilder::new();
+    let root = b.add(Section::System, d);
+    let pages = b.add_pages_info();
+    b.pointer(root, 0x08, pages);
+    for (data, at) in [(nodes, 0x10), (links, 0x28), (junctions, 0x38), (heights, 0x40), (refs, 0x50)] {
+        if data.is_empty() { continue; }
+        let block = b.add(Section::System, data);
+        b.pointer(root, at, block);
+    }
+    b.build(VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_id: u16, position: [f32; 3], links: Vec<Link>, junction: Option<Junction>) -> Node {
+        Node { area_id: 7, node_id, street_name: 0xDEAD_BEEF, position, flags: [1, 2, 3, 4, 5], link_flags: 5, links, junction }
+    }
+
+    fn link(node_id: u16, length: u8) -> Link {
+        Link { area_id: 7, node_id, flags: [9, 8, 7], length }
+    }
+
+    fn sample() -> NodeDictionary {
+        let junction = Junction {
+            position : [-100.25, 250.5],
+            min_z    : 10.0,
+            max_z    : 12.53125,
+            width    : 3,
+            height   : 2,
+            heightmap: vec![1, 2, 3, 4, 5, 6],
+            unknown  : 0x0F0F,
+        };
+        NodeDictionary {
+            vft          : 0x4061_4C68,
+            nodes        : vec![
+                node(0, [-100.25, 250.5, 11.125], vec![link(1, 20), link(2, 30)], Some(junction)),
+                node(1, [-80.0, 250.5, 11.0], vec![link(0, 20)], None),
+                node(2, [-100.25, 220.75, -3.5], vec![link(0, 30)], None),
+            ],
+            vehicle_nodes: 2,
+        }
+    }
+
+    #[test]
+    fn build_round_trips() {
+        let built = build(&sample()).unwrap();
+        let dict = parse(&Resource::parse(&built).unwrap()).unwrap();
+
+        assert_eq!((dict.vft, dict.vehicle_nodes, dict.nodes.len()), (0x4061_4C68, 2, 3));
+        let first = &dict.nodes[0];
+        assert_eq!((first.area_id, first.node_id, first.street_name), (7, 0, 0xDEAD_BEEF));
+        assert_eq!((first.position, first.flags, first.link_flags), ([-100.25, 250.5, 11.125], [1, 2, 3, 4, 5], 5));
+        let ids: Vec<_> = first.links.iter().map(|l| (l.node_id, l.flags, l.length)).collect();
+        assert_eq!(ids, [(1, [9, 8, 7], 20), (2, [9, 8, 7], 30)]);
+        let j = first.junction.as_ref().unwrap();
+        assert_eq!((j.position, j.min_z, j.max_z, j.unknown), ([-100.25, 250.5], 10.0, 12.53125, 0x0F0F));
+        assert_eq!((j.width, j.height, j.heightmap.as_slice()), (3, 2, &[1, 2, 3, 4, 5, 6][..]));
+        assert!(dict.nodes[1].junction.is_none());
+        assert_eq!(dict.nodes[2].position, [-100.25, 220.75, -3.5]);
+
+        assert_eq!(build(&dict).unwrap(), built);
+    }
+
+    #[test]
+    fn rejects_out_of_range_positions() {
+        let mut dict = sample();
+        dict.nodes[1].position[0] = 9000.0;
+        assert!(build(&dict).is_err());
+
+        let mut dict = sample();
+        dict.nodes[0].junction.as_mut().unwrap().heightmap.pop();
+        assert!(build(&dict).is_err());
+    }
+}
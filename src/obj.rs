@@ -0,0 +1,110 @@
+// Wavefront OBJ writer, with a companion .mtl of flat colours for material groups.
+
+use std::fmt::Write;
+
+#[derive(Default)]
+pub struct Obj {
+    text     : String,
+    vertices : usize,
+    faces    : usize,
+    /// Materials referenced so far, in first-use order.
+    materials: Vec<String>,
+    current  : Option<String>,
+}
+
+impl Obj {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a named object; following faces belong to it.
+    pub fn object(&mut self, name: &str) {
+        writeln!(self.text, "o {}", name).unwrap();
+        self.current = None;
+    }
+
+    /// Add a vertex and return its 0-based index.
+    pub fn vertex(&mut self, v: [f32; 3]) -> usize {
+        writeln!(self.text, "v {} {} {}", v[0], v[1], v[2]).unwrap();
+        self.vertices += 1;
+        self.vertices - 1
+    }
+
+    /// Add a face over 0-based vertex indices, in `material` when given.
+    pub fn face(&mut self, indices: &[usize], material: Option<&str>) {
+        if let Some(m) = material && self.current.as_deref() != Some(m) {
+            writeln!(self.text, "usemtl {}", m).unwrap();
+            if !self.materials.iter().any(|x| x == m) { self.materials.push(m.to_string()); }
+            self.current = Some(m.to_string());
+        }
+        self.text.push('f');
+        for i in indices { write!(self.text, " {}", i + 1).unwrap(); }
+        self.text.push('\n');
+        self.faces += 1;
+    }
+
+    /// Add a polyline over 0-based vertex indices.
+    pub fn line(&mut self, indices: &[usize]) {
+        self.text.push('l');
+        for i in indices { write!(self.text, " {}", i + 1).unwrap(); }
+        self.text.push('\n');
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces
+    }
+
+    /// The .obj text, referring to `mtl_name` when any face has a material.
+    pub fn to_obj(&self, mtl_name: &str) -> String {
+        if self.materials.is_empty() { return self.text.clone(); }
+        format!("mtllib {}\n{}", mtl_name, self.text)
+    }
+
+    /// A .mtl giving every material a distinct flat colour, or `None` if there are none.
+    pub fn to_mtl(&self) -> Option<String> {
+        if self.materials.is_empty() { return None; }
+        let mut out = String::new();
+        for name in &self.materials {
+            let h = name.bytes().fold(0x811C_9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193));
+            let c = |shift: u32| 0.25 + ((h >> shift) & 0xFF) as f32 / 255.0 * 0.75;
+            writeln!(out, "newmtl {}\nKd {:.3} {:.3} {:.3}\n", name, c(0), c(8), c(16)).unwrap();
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_faces_by_material() {
+        let mut obj = Obj::new();
+        obj.object("box");
+        let v: Vec<usize> = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]].into_iter().map(|p| obj.vertex(p)).collect();
+        obj.face(&v, Some("GRASS"));
+        obj.face(&v, Some("GRASS"));
+        obj.face(&v, Some("ROCK"));
+        obj.face(&v, Some("GRASS"));
+
+        assert_eq!((obj.vertex_count(), obj.face_count()), (3, 4));
+        assert_eq!(obj.to_obj("box.mtl"), "mtllib box.mtl\no box\nv 0 0 0\nv 1 0 0\nv 0 1.5 0\n\
+            usemtl GRASS\nf 1 2 3\nf 1 2 3\nusemtl ROCK\nf 1 2 3\nusemtl GRASS\nf 1 2 3\n");
+        let mtl = obj.to_mtl().unwrap();
+        assert_eq!(mtl.matches("newmtl").count(), 2);
+        assert!(mtl.starts_with("newmtl GRASS\nKd "));
+    }
+
+    #[test]
+    fn skips_the_mtl_without_materials() {
+        let mut obj = Obj::new();
+        obj.vertex([0.0; 3]);
+        obj.face(&[0, 0, 0], None);
+        assert_eq!(obj.to_obj("x.mtl"), "v 0 0 0\nf 1 1 1\n");
+        assert!(obj.to_mtl().is_none());
+    }
+}
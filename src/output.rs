@@ -0,0 +1,213 @@
+// Machine-readable output for `--format json|ndjson|csv`.
+//
+// Records are plain serde structs. JSON prints one document, NDJSON one record per line,
+// and CSV one row per record with the struct's field names (in declaration order) as the
+// header. Field names and meanings are part of the CLI's interface; see README.md.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::rpf::{Archive, FileRef, RpfEncryption, RpfEntryKind, RpfVersion};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// One file entry, as emitted by `list`, `tree` and `info`.
+#[derive(Serialize)]
+pub struct EntryRecord {
+    pub path            : String,
+    pub name            : String,
+    /// `binary` or `resource`.
+    #[serde(rename = "type")]
+    pub kind            : &'static str,
+    /// Bytes stored in the archive.
+    pub size            : u32,
+    /// Uncompressed size (binaries) or system page size (resources).
+    pub mem_size        : u32,
+    pub is_resource     : bool,
+    pub compressed      : bool,
+    pub encrypted       : bool,
+    pub system_flags    : Option<u32>,
+    pub graphics_flags  : Option<u32>,
+    pub resource_version: Option<u32>,
+}
+
+impl EntryRecord {
+    pub fn new(archive: &Archive, f: &FileRef) -> Self {
+        let (compressed, encrypted, flags) = match *archive.entry_kind(f) {
+            RpfEntryKind::BinaryFile { file_size, uncompressed_size, is_encrypted, .. } =>
+                (file_size > 0 && file_size < uncompressed_size, is_encrypted, None),
+            RpfEntryKind::ResourceFile { system_flags, graphics_flags, is_encrypted, .. } =>
+                (true, is_encrypted && archive.encryption.is_encrypted(), Some((system_flags, graphics_flags))),
+            RpfEntryKind::Directory { .. } => (false, false, None),
+        };
+
+        Self {
+            path            : f.path.clone(),
+            name            : f.name.clone(),
+            kind            : if f.is_resource { "resource" } else { "binary" },
+            size            : f.size,
+            mem_size        : f.mem_size,
+            is_resource     : f.is_resource,
+            compressed,
+            encrypted,
+            system_flags    : flags.map(|(s, _)| s),
+            graphics_flags  : flags.map(|(_, g)| g),
+            resource_version: flags.map(|(s, g)| rpf_archive::resource_version_from_flags(s, g)),
+        }
+    }
+}
+
+pub fn version_name(version: RpfVersion) -> &'static str {
+    match version {
+        RpfVersion::V0   => "RPF0",
+        RpfVersion::V2   => "RPF2",
+        RpfVersion::V3   => "RPF3",
+        RpfVersion::V4   => "RPF4",
+        RpfVersion::V6   => "RPF6",
+        RpfVersion::V7   => "RPF7",
+        RpfVersion::V8   => "RPF8",
+        RpfVersion::Img3 => "IMG3",
+    }
+}
+
+pub fn encryption_name(encryption: RpfEncryption) -> &'static str {
+    match encryption {
+        RpfEncryption::None => "none",
+        RpfEncryption::Open => "open",
+        RpfEncryption::Aes  => "aes",
+        RpfEncryption::Ng   => "ng",
+        RpfEncryption::Tfit => "tfit",
+    }
+}
+
+/// Print `records` in `format`: a JSON array, NDJSON lines, or CSV rows under one header.
+/// `Text` output is the caller's job.
+pub fn print_records<T: Serialize>(format: Format, records: &[T]) -> Result<()> {
+    if format == Format::Json {
+        println!("{}", serde_json::to_string_pretty(records)?);
+        return Ok(());
+    }
+    for (i, r) in records.iter().enumerate() {
+        print_record_line(format, r, i == 0)?;
+    }
+    Ok(())
+}
+
+/// Print a single record (a JSON object rather than an array).
+pub fn print_record<T: Serialize>(format: Format, record: &T) -> Result<()> {
+    if format == Format::Json {
+        println!("{}", serde_json::to_string_pretty(record)?);
+        return Ok(());
+    }
+    print_record_line(format, record, true)
+}
+
+fn print_record_line<T: Serialize>(format: Format, record: &T, header: bool) -> Result<()> {
+    match format {
+        Format::Csv => print!("{}", csv_row(record, header)?),
+        _ => println!("{}", serde_json::to_string(record)?),
+    }
+    Ok(())
+}
+
+/// `records` as CSV text under one header row, for commands that write CSV files.
+pub fn to_csv<T: Serialize>(records: &[T]) -> Result<String> {
+    let mut out = String::new();
+    for (i, r) in records.iter().enumerate() {
+        out += &csv_row(r, i == 0)?;
+    }
+    Ok(out)
+}
+
+/// One CSV line for `record`, preceded by the header line when `header` is set.
+fn csv_row<T: Serialize>(record: &T, header: bool) -> Result<String> {
+    let Value::Object(map) = serde_json::to_value(record)? else {
+        anyhow::bail!("CSV output needs flat records");
+    };
+    let mut out = String::new();
+    if header {
+        out += &map.keys().map(|k| csv_field(k)).collect::<Vec<_>>().join(",");
+        out.push('\n');
+    }
+    out += &map.values().map(csv_value).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    Ok(out)
+}
+
+fn csv_value(v: &Value) -> String {
+    match v {
+        Value::Null      => String::new(),
+        Value::String(s) => csv_field(s),
+        Value::Array(a)  => csv_field(&a.iter().map(|x| match x {
+            Value::String(s) => s.clone(),
+            other            => other.to_string(),
+        }).collect::<Vec<_>>().join("; ")),
+        Value::Number(n) => match n.as_f64() {
+            // f32 fields reach here widened; print them as written, not as 0.10000000149011612.
+            Some(f) if n.is_f64() && f as f32 as f64 == f => serde_json::to_string(&(f as f32)).unwrap_or_else(|_| n.to_string()),
+            _ => n.to_string(),
+        },
+        other            => other.to_string(),
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::RpfBuilder;
+    use serde_json::json;
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn flattens_csv_values() {
+        assert_eq!(csv_value(&Value::Null), "");
+        assert_eq!(csv_value(&json!(42)), "42");
+        assert_eq!(csv_value(&json!(true)), "true");
+        assert_eq!(csv_value(&json!("x,y")), "\"x,y\"");
+        assert_eq!(csv_value(&json!(["a", 1, "b"])), "a; 1; b");
+    }
+
+    #[test]
+    fn describes_entries() {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        builder.add_file("dir/a.txt", vec![7; 100]);
+        let archive = Archive::from_bytes(builder.build(None).unwrap(), "test.rpf", None).unwrap();
+        let record = EntryRecord::new(&archive, archive.find_file("dir/a.txt").unwrap());
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["path"], "dir/a.txt");
+        assert_eq!(value["name"], "a.txt");
+        assert_eq!(value["type"], "binary");
+        assert_eq!(value["is_resource"], false);
+        assert_eq!(value["encrypted"], false);
+        assert_eq!(value["system_flags"], Value::Null);
+        assert_eq!(value["resource_version"], Value::Null);
+    }
+}
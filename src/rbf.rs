@@ -0,0 +1,426 @@
+// RBF ("RBF0"): the binary XML used by many .meta and .xml files.
+//
+// The file is a stream of records after the magic. Each record starts with a descriptor
+// index (a name; index == descriptor count introduces a new name, stored inline) and a
+// type byte: 0x00 opens an element, whose header gives its attribute count (the next that
+// many values are its attributes), 0x10 is a u32, 0x20/0x30 true/false, 0x40 a float,
+// 0x50 three floats and 0x60 a string. 0xFD 0xFF is raw text content and 0xFF 0xFF closes
+// the current element. Conversion to and from XML follows CodeWalker, so files round-trip
+// between the two tools.
+
+use anyhow::{bail, Context, Result};
+use roxmltree::Document;
+use std::fmt::Write;
+
+use crate::resource::{u16_at, u32_at};
+use crate::utils::xml_escape;
+
+pub const RBF_MAGIC: &[u8; 4] = b"RBF0";
+
+/// Extensions of files that may hold RBF or XML meta data.
+pub const EXTENSIONS: [&str; 2] = ["meta", "xml"];
+
+const ELEMENT: u8 = 0x00;
+const UINT: u8 = 0x10;
+const TRUE: u8 = 0x20;
+const FALSE: u8 = 0x30;
+const FLOAT: u8 = 0x40;
+const VECTOR3: u8 = 0x50;
+const STRING: u8 = 0x60;
+const BYTES: u8 = 0xFD;
+const CLOSE: u8 = 0xFF;
+
+pub fn is_rbf(data: &[u8]) -> bool {
+    data.starts_with(RBF_MAGIC)
+}
+
+/// True for text that looks like an XML document.
+pub fn is_xml(data: &[u8]) -> bool {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'<')
+}
+
+/// True for a text XML file whose extension marks it as a meta file.
+pub fn is_xml_meta(name: &str, data: &[u8]) -> bool {
+    let lower = name.to_lowercase();
+    EXTENSIONS.iter().any(|e| lower.ends_with(&format!(".{}", e))) && is_xml(data)
+}
+
+/// `RBF`, `XML` or `None` for the contents of a meta file.
+pub fn describe(data: &[u8]) -> Option<&'static str> {
+    if is_rbf(data) { Some("RBF") } else if is_xml(data) { Some("XML") } else { None }
+}
+
+enum Value {
+    Element(Element),
+    Bytes(Vec<u8>),
+    Uint(u32),
+    Bool(bool),
+    Float(f32),
+    Vector3([f32; 3]),
+    String(String),
+}
+
+struct Element {
+    attributes: Vec<(String, Value)>,
+    children  : Vec<(String, Value)>,
+}
+
+// ─── Reading ─────────────────────────────────────────────────────────────────
+
+/// Convert an RBF file to XML text.
+pub fn to_xml(data: &[u8]) -> Result<String> {
+    let (name, root) = parse(data)?;
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    write_element(&mut out, &name, &root, 0);
+    Ok(out)
+}
+
+fn parse(data: &[u8]) -> Result<(String, Element)> {
+    if !is_rbf(data) {
+        bail!("not an RBF file (no RBF0 magic)");
+    }
+    let mut r = Reader { data, pos: 4 };
+    let mut names: Vec<String> = Vec::new();
+    // Open elements with their names and the attributes still to come.
+    let mut stack: Vec<(String, Element, usize)> = Vec::new();
+
+    loop {
+        let at = r.pos;
+        let index = r.u8()?;
+        if index == CLOSE || index == BYTES {
+            if r.u8()? != 0xFF {
+                bail!("bad record marker at 0x{:X}", at);
+            }
+            let Some(top) = stack.last_mut() else { bail!("record at 0x{:X} outside the root element", at) };
+            if index == BYTES {
+                let len = r.u32()? as usize;
+                top.1.children.push((String::new(), Value::Bytes(r.bytes(len)?.to_vec())));
+                continue;
+            }
+            let (name, element, _) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some(parent) => add(parent, name, Value::Element(element)),
+                None => {
+                    if r.pos != data.len() {
+                        bail!("{} trailing bytes after the root element", data.len() - r.pos);
+                    }
+                    return Ok((name, element));
+                }
+            }
+            continue;
+        }
+
+        let kind = r.u8()?;
+        let name = match (index as usize).cmp(&names.len()) {
+            std::cmp::Ordering::Less    => names[index as usize].clone(),
+            std::cmp::Ordering::Equal   => {
+                let len = r.u16()? as usize;
+                let name = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+                names.push(name.clone());
+                name
+            }
+            std::cmp::Ordering::Greater => bail!("undefined name index {} at 0x{:X}", index, at),
+        };
+
+        let value = match kind {
+            ELEMENT => {
+                r.bytes(4)?;
+                let attributes = r.u16()? as usize;
+                if stack.is_empty() && at != 4 {
+                    bail!("second root element at 0x{:X}", at);
+                }
+                stack.push((name, Element { attributes: Vec::new(), children: Vec::new() }, attributes));
+                continue;
+            }
+            UINT    => Value::Uint(r.u32()?),
+            TRUE    => Value::Bool(true),
+            FALSE   => Value::Bool(false),
+            FLOAT   => Value::Float(f32::from_bits(r.u32()?)),
+            VECTOR3 => Value::Vector3([
+                f32::from_bits(r.u32()?), f32::from_bits(r.u32()?), f32::from_bits(r.u32()?),
+            ]),
+            STRING  => {
+                let len = r.u16()? as usize;
+                Value::String(String::from_utf8_lossy(r.bytes(len)?).into_owned())
+            }
+            other => bail!("unknown value type 0x{:02X} at 0x{:X}", other, at),
+        };
+        let Some(parent) = stack.last_mut() else { bail!("value at 0x{:X} outside the root element", at) };
+        add(parent, name, value);
+    }
+}
+
+/// Add a value to an open element: as an attribute while some are still expected.
+fn add(parent: &mut (String, Element, usize), name: String, value: Value) {
+    if parent.2 > 0 {
+        parent.2 -= 1;
+        parent.1.attributes.push((name, value));
+    } else {
+        parent.1.children.push((name, value));
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos : usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)
+            .with_context(|| format!("unexpected end of file at 0x{:X}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16_at(self.bytes(2)?, 0))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32_at(self.bytes(4)?, 0))
+    }
+}
+
+/// A scalar value as attribute text.
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Uint(v)   => format!("0x{:08X}", v),
+        Value::Bool(v)   => v.to_string(),
+        Value::Float(v)  => v.to_string(),
+        Value::String(v) => v.clone(),
+        Value::Vector3(v) => format!("{}, {}, {}", v[0], v[1], v[2]),
+        Value::Element(_) | Value::Bytes(_) => String::new(),
+    }
+}
+
+fn write_element(out: &mut String, name: &str, element: &Element, depth: usize) {
+    let indent = " ".repeat(depth);
+    let attrs: String = element.attributes.iter()
+        .map(|(n, v)| format!(" {}=\"{}\"", n, xml_escape(&value_text(v))))
+        .collect();
+    let content = element.attributes.iter().find_map(|(n, v)| match v {
+        Value::String(s) if n == "content" => Some(s.as_str()),
+        _ => None,
+    });
+
+    match element.children.as_slice() {
+        [] => { let _ = writeln!(out, "{}<{}{} />", indent, name, attrs); }
+        [(_, Value::Bytes(bytes))] if content.is_none() => {
+            let _ = writeln!(out, "{}<{}{}>{}</{}>", indent, name, attrs, xml_escape(&text(bytes)), name);
+        }
+        children => {
+            let _ = writeln!(out, "{}<{}{}>", indent, name, attrs);
+            for (child, value) in children {
+                match value {
+                    Value::Element(e) => write_element(out, child, e, depth + 1),
+                    Value::Bytes(bytes) => {
+                        let values: Vec<String> = match content {
+                            Some("char_array")  => bytes.iter().map(u8::to_string).collect(),
+                            Some("short_array") => bytes.chunks_exact(2).map(|c| u16_at(c, 0).to_string()).collect(),
+                            _                   => vec![xml_escape(&text(bytes))],
+                        };
+                        for v in values { let _ = writeln!(out, "{} {}", indent, v); }
+                    }
+                    Value::Vector3(v) => {
+                        let _ = writeln!(out, "{} <{} x=\"{}\" y=\"{}\" z=\"{}\" />", indent, child, v[0], v[1], v[2]);
+                    }
+                    v => { let _ = writeln!(out, "{} <{} value=\"{}\" />", indent, child, xml_escape(&value_text(v))); }
+                }
+            }
+            let _ = writeln!(out, "{}</{}>", indent, name);
+        }
+    }
+}
+
+/// Text content without its NUL terminator.
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+}
+
+// ─── Writing ─────────────────────────────────────────────────────────────────
+
+/// Convert XML text to an RBF file.
+pub fn from_xml(text: &str) -> Result<Vec<u8>> {
+    let doc = Document::parse(text).context("invalid XML")?;
+    let root = doc.root_element();
+    let mut w = Writer { out: RBF_MAGIC.to_vec(), names: Vec::new() };
+    w.value(root.tag_name().name(), &convert(root)?)?;
+    Ok(w.out)
+}
+
+/// The RBF value an XML element stands for.
+fn convert(node: roxmltree::Node) -> Result<Value> {
+    let attrs: Vec<_> = node.attributes().collect();
+    let has_elements = node.children().any(|c| c.is_element());
+
+    if !has_elements {
+        if let [a] = attrs.as_slice()
+            && a.name() == "value" && !a.value().is_empty() {
+            return Ok(scalar(a.value()));
+        }
+        if attrs.len() == 3 {
+            let axis = |name| node.attribute(name).and_then(|v: &str| v.trim().parse::<f32>().ok());
+            if let (Some(x), Some(y), Some(z)) = (axis("x"), axis("y"), axis("z")) {
+                return Ok(Value::Vector3([x, y, z]));
+            }
+        }
+    }
+
+    let content = node.attribute("content");
+    let mut children = Vec::new();
+    for child in node.children() {
+        if child.is_element() {
+            let name = child.tag_name().name().to_string();
+            let value = convert(child).with_context(|| format!("in <{}>", name))?;
+            children.push((name, value));
+        } else if let Some(t) = child.text().filter(|_| child.is_text()).map(str::trim).filter(|t| !t.is_empty()) {
+            let values = t.split_whitespace();
+            let bytes = match content {
+                Some("char_array") => values.map(|v| v.parse::<u8>().with_context(|| format!("'{}' is not a byte", v)))
+                    .collect::<Result<Vec<_>>>()?,
+                Some("short_array") => values
+                    .map(|v| v.parse::<u16>().map(u16::to_le_bytes).with_context(|| format!("'{}' is not a u16", v)))
+                    .collect::<Result<Vec<_>>>()?.concat(),
+                _ => [t.as_bytes(), &[0]].concat(),
+            };
+            children.push((String::new(), Value::Bytes(bytes)));
+        }
+    }
+    Ok(Value::Element(Element {
+        attributes: attrs.iter().map(|a| (a.name().to_string(), scalar(a.value()))).collect(),
+        children,
+    }))
+}
+
+/// An attribute value: a bool, `0x` hex u32, float or string, as CodeWalker reads them.
+fn scalar(text: &str) -> Value {
+    if text.eq_ignore_ascii_case("true") { return Value::Bool(true); }
+    if text.eq_ignore_ascii_case("false") { return Value::Bool(false); }
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"))
+        && let Ok(v) = u32::from_str_radix(hex, 16) {
+        return Value::Uint(v);
+    }
+    match text.parse::<f32>() {
+        Ok(v) if !text.is_empty() && !text.chars().any(char::is_alphabetic) => Value::Float(v),
+        _ => Value::String(text.to_string()),
+    }
+}
+
+struct Writer {
+    out  : Vec<u8>,
+    names: Vec<String>,
+}
+
+impl Writer {
+    /// Descriptor index and type, plus the name the first time it is used.
+    fn header(&mut self, name: &str, kind: u8) -> Result<()> {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => self.out.extend([i as u8, kind]),
+            None => {
+                if self.names.len() >= BYTES as usize {
+                    bail!("more than {} distinct names", BYTES);
+                }
+                self.out.extend([self.names.len() as u8, kind]);
+                self.out.extend(u16::try_from(name.len())?.to_le_bytes());
+                self.out.extend(name.as_bytes());
+                self.names.push(name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, name: &str, value: &Value) -> Result<()> {
+        match value {
+            Value::Element(e) => {
+                self.header(name, ELEMENT)?;
+                self.out.extend([0; 4]);
+                self.out.extend(u16::try_from(e.attributes.len())?.to_le_bytes());
+                for (n, v) in e.attributes.iter().chain(&e.children) {
+                    self.value(n, v)?;
+                }
+                self.out.extend([CLOSE, 0xFF]);
+            }
+            Value::Bytes(bytes) => {
+                self.out.extend([BYTES, 0xFF]);
+                self.out.extend(u32::try_from(bytes.len())?.to_le_bytes());
+                self.out.extend(bytes);
+            }
+            Value::Uint(v) => {
+                self.header(name, UINT)?;
+                self.out.extend(v.to_le_bytes());
+            }
+            Value::Bool(v) => self.header(name, if *v { TRUE } else { FALSE })?,
+            Value::Float(v) => {
+                self.header(name, FLOAT)?;
+                self.out.extend(v.to_le_bytes());
+            }
+            Value::Vector3(v) => {
+                self.header(name, VECTOR3)?;
+                for c in v { self.out.extend(c.to_le_bytes()); }
+            }
+            Value::String(s) => {
+                self.header(name, STRING)?;
+                self.out.extend(u16::try_from(s.len())?.to_le_bytes());
+                self.out.extend(s.as_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CVehicleModelInfo__InitDataList>
+  <residentTxd>vehshare</residentTxd>
+  <InitDatas>
+    <Item>
+      <modelName>adder</modelName>
+      <lodDistances content="float_array">10 25.5 60</lodDistances>
+      <flags value="0x0000FF01" />
+      <wheelScale value="0.25" />
+      <enabled value="true" />
+      <hidden value="false" />
+      <offset x="1" y="-2.5" z="3" />
+      <layout type="LAYOUT_STD" order="2" />
+      <bytes content="char_array">1 2 250</bytes>
+      <shorts content="short_array">7 65535</shorts>
+    </Item>
+    <Item>
+      <modelName>zentorno</modelName>
+      <flags value="0x00000002" />
+    </Item>
+  </InitDatas>
+</CVehicleModelInfo__InitDataList>
+"#;
+
+    #[test]
+    fn xml_round_trips() {
+        let rbf = from_xml(SAMPLE).unwrap();
+        assert!(is_rbf(&rbf) && describe(&rbf) == Some("RBF"));
+        let text = to_xml(&rbf).unwrap();
+        for line in ["<modelName>zentorno</modelName>", r#"<flags value="0x0000FF01" />"#, r#"<wheelScale value="0.25" />"#,
+                     r#"<hidden value="false" />"#, r#"<offset x="1" y="-2.5" z="3" />"#, r#"<layout type="LAYOUT_STD" order="2" />"#,
+                     "    10 25.5 60\n", "    250\n", "    65535\n"] {
+            assert!(text.contains(line), "{} missing from\n{}", line, text);
+        }
+        assert_eq!(from_xml(&text).unwrap(), rbf);
+        assert_eq!(to_xml(&from_xml(&text).unwrap()).unwrap(), text);
+    }
+
+    #[test]
+    fn detects_meta_files() {
+        assert!(is_xml_meta("x64/data/handling.META", b"\xEF\xBB\xBF  <?xml version=\"1.0\"?><a />"));
+        assert!(!is_xml_meta("x64/data/handling.meta", b"RBF0"));
+        assert!(!is_xml_meta("x64/data/readme.txt", b"<a />"));
+        assert_eq!(describe(b"binary"), None);
+    }
+}
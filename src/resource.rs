@@ -0,0 +1,474 @@
+// RSC7 resources: the paged container behind .ytd, .ydr, .yft and the other GTA V assets.
+//
+// A resource has two virtual memory sections, system (CPU, 0x5000_0000) and graphics
+// (GPU, 0x6000_0000). Each section is a run of pages whose sizes are packed into a flags
+// word: a base size of 0x200 << shift, and page counts for nine size classes from 256x
+// down to 1x the base. Pages are laid out largest first and no block may straddle a page.
+// The file is a 16-byte header (magic, version, system flags, graphics flags) followed by
+// both sections, deflated together.
+//
+// For hand editing, a resource can also be kept as its two raw sections plus a JSON sidecar
+// with the header fields (see "Raw segments" below).
+
+use anyhow::{bail, Context, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use rpf_archive::resource_size_from_flags;
+
+pub const RSC7_MAGIC: u32 = 0x37435352;
+pub const SYSTEM_BASE: u64 = 0x5000_0000;
+pub const GRAPHICS_BASE: u64 = 0x6000_0000;
+
+/// Block alignment within a page.
+const ALIGN: usize = 16;
+/// Size of a `ResourcePagesInfo` block, filled in by the game on load.
+const PAGES_INFO_SIZE: usize = 20 + 256 * 16;
+/// Per size class (1x base .. 256x base): the most pages the flags can describe, and the
+/// bit position of that count.
+const PAGE_LIMITS: [u32; 9] = [1, 1, 1, 1, 127, 63, 15, 3, 1];
+const PAGE_SHIFTS: [u32; 9] = [27, 26, 25, 24, 17, 11, 7, 5, 4];
+
+// ─── Reading ─────────────────────────────────────────────────────────────────
+
+/// An inflated resource.
+pub struct Resource {
+    pub version       : u32,
+    pub system_flags  : u32,
+    pub graphics_flags: u32,
+    pub system        : Vec<u8>,
+    pub graphics      : Vec<u8>,
+}
+
+impl Resource {
+    /// Parse an RSC7 file, as stored loose or returned by `Archive::extract`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 16 { bail!("resource too short ({} bytes)", data.len()); }
+        let word = |i: usize| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+        if word(0) != RSC7_MAGIC { bail!("not an RSC7 resource (magic 0x{:08X})", word(0)); }
+
+        let (version, system_flags, graphics_flags) = (word(1), word(2), word(3));
+        let sys_size = resource_size_from_flags(system_flags);
+        let gfx_size = resource_size_from_flags(graphics_flags);
+
+        let mut body = Vec::with_capacity(sys_size + gfx_size);
+        DeflateDecoder::new(&data[16..]).read_to_end(&mut body)
+            .context("resource body does not inflate")?;
+        if body.len() < sys_size + gfx_size {
+            bail!("resource body is {} bytes, flags describe {}", body.len(), sys_size + gfx_size);
+        }
+
+        body.truncate(sys_size + gfx_size);
+        let graphics = body.split_off(sys_size);
+        Ok(Self { version, system_flags, graphics_flags, system: body, graphics })
+    }
+
+    /// Deflate back into an RSC7 file. Each section must be the size its flags describe.
+    pub fn to_rsc7(&self) -> Result<Vec<u8>> {
+        for (name, data, flags) in [("system", &self.system, self.system_flags), ("graphics", &self.graphics, self.graphics_flags)] {
+            if data.len() != resource_size_from_flags(flags) {
+                bail!("{} section is {} bytes, its flags (0x{:08X}) describe {}", name, data.len(), flags, resource_size_from_flags(flags));
+            }
+        }
+
+        let mut out = Vec::with_capacity((self.system.len() + self.graphics.len()) / 2);
+        out.extend_from_slice(&RSC7_MAGIC.to_le_bytes());
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.system_flags.to_le_bytes());
+        out.extend_from_slice(&self.graphics_flags.to_le_bytes());
+        let mut enc = DeflateEncoder::new(out, Compression::default());
+        enc.write_all(&self.system)?;
+        enc.write_all(&self.graphics)?;
+        Ok(enc.finish()?)
+    }
+
+    /// `len` bytes at virtual address `va`, in either section.
+    pub fn slice(&self, va: u64, len: usize) -> Option<&[u8]> {
+        let (section, off) = self.locate(va)?;
+        section.get(off..off.checked_add(len)?)
+    }
+
+    /// The NUL-terminated string at `va`.
+    pub fn c_str(&self, va: u64) -> Option<String> {
+        let (section, off) = self.locate(va)?;
+        let rest = section.get(off..)?;
+        let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+        Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    fn locate(&self, va: u64) -> Option<(&[u8], usize)> {
+        match va & 0xF000_0000 {
+            SYSTEM_BASE   => Some((&self.system, (va - SYSTEM_BASE) as usize)),
+            GRAPHICS_BASE => Some((&self.graphics, (va - GRAPHICS_BASE) as usize)),
+            _             => None,
+        }
+    }
+}
+
+pub fn u16_at(data: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes(data[off..off + 2].try_into().unwrap())
+}
+
+pub fn u32_at(data: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+pub fn u64_at(data: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(data[off..off + 8].try_into().unwrap())
+}
+
+pub fn f32_at(data: &[u8], off: usize) -> f32 {
+    f32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+// ─── Flags and types ─────────────────────────────────────────────────────────
+
+/// Base page size of a section's flags word.
+pub fn page_base(flags: u32) -> usize {
+    0x200 << (flags & 0xF)
+}
+
+/// `(page size, count)` for every size class in use in a section's flags word, largest
+/// first (the order the pages are laid out in).
+pub fn pages(flags: u32) -> Vec<(usize, u32)> {
+    let base = page_base(flags);
+    (0..9).rev()
+        .map(|k| (base << k, (flags >> PAGE_SHIFTS[k]) & PAGE_LIMITS[k]))
+        .filter(|&(_, count)| count > 0)
+        .collect()
+}
+
+/// Known resource types: extension, description and the version GTA V loads.
+const TYPES: [(&str, &str, u32); 15] = [
+    ("ytd",  "texture dictionary",    13),
+    ("ydr",  "drawable",              165),
+    ("ydd",  "drawable dictionary",   165),
+    ("yft",  "fragment",              162),
+    ("ybn",  "static collision",      43),
+    ("ycd",  "clip dictionary",       46),
+    ("ypt",  "particle effects",      68),
+    ("ynd",  "path nodes",            1),
+    ("ynv",  "navigation mesh",       2),
+    ("ymap", "map data",              2),
+    ("ytyp", "archetype definitions", 2),
+    ("ymt",  "metadata",              2),
+    ("yld",  "cloth dictionary",      8),
+    ("yed",  "expression dictionary", 25),
+    ("ywr",  "waypoint recording",    1),
+];
+
+/// `(extension, description, expected version)` for a resource file name.
+pub fn resource_type(name: &str) -> Option<(&'static str, &'static str, u32)> {
+    let ext = name.rsplit_once('.')?.1.to_lowercase();
+    TYPES.iter().find(|t| t.0 == ext).copied()
+}
+
+/// `(extension, description)` of the known types that use `version`.
+pub fn types_with_version(version: u32) -> Vec<(&'static str, &'static str)> {
+    TYPES.iter().filter(|t| t.2 == version).map(|t| (t.0, t.1)).collect()
+}
+
+// ─── Building ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    System,
+    Graphics,
+}
+
+/// Handle to a block added to a `ResourceBuilder`.
+#[derive(Clone, Copy)]
+pub struct Block(usize);
+
+/// Assembles a resource from blocks and the pointers between them.
+///
+/// The first system block is the resource root and always lands at 0x5000_0000. Blocks are
+/// packed into pages at build time, after which every registered pointer is patched with
+/// its target's virtual address.
+#[derive(Default)]
+pub struct ResourceBuilder {
+    blocks    : Vec<(Section, Vec<u8>)>,
+    pointers  : Vec<(Block, usize, Block)>,
+    pages_info: Option<Block>,
+}
+
+impl ResourceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, section: Section, data: Vec<u8>) -> Block {
+        self.blocks.push((section, data));
+        Block(self.blocks.len() - 1)
+    }
+
+    /// Add the `ResourcePagesInfo` block; its page counts are filled in by `build`.
+    pub fn add_pages_info(&mut self) -> Block {
+        let block = self.add(Section::System, vec![0; PAGES_INFO_SIZE]);
+        self.pages_info = Some(block);
+        block
+    }
+
+    /// Make the u64 at `offset` in `from` point at the start of `to`.
+    pub fn pointer(&mut self, from: Block, offset: usize, to: Block) {
+        self.pointers.push((from, offset, to));
+    }
+
+    /// Lay out, link and deflate the resource into a complete RSC7 file.
+    pub fn build(mut self, version: u32) -> Result<Vec<u8>> {
+        let (sys, sys_pages) = Layout::of(&self.blocks, Section::System).context("system section")?;
+        let (gfx, gfx_pages) = Layout::of(&self.blocks, Section::Graphics).context("graphics section")?;
+
+        let address = |b: usize| match self.blocks[b].0 {
+            Section::System   => SYSTEM_BASE + sys.offsets[b] as u64,
+            Section::Graphics => GRAPHICS_BASE + gfx.offsets[b] as u64,
+        };
+        let patches: Vec<(usize, usize, u64)> = self.pointers.iter()
+            .map(|&(from, off, to)| (from.0, off, address(to.0)))
+            .collect();
+        for (from, off, va) in patches {
+            self.blocks[from].1[off..off + 8].copy_from_slice(&va.to_le_bytes());
+        }
+        if let Some(info) = self.pages_info {
+            let info = &mut self.blocks[info.0].1;
+            info[8] = sys_pages as u8;
+            info[9] = gfx_pages as u8;
+        }
+
+        let mut system = vec![0u8; sys.size];
+        let mut graphics = vec![0u8; gfx.size];
+        for (i, (section, data)) in self.blocks.iter().enumerate() {
+            let (target, start) = match section {
+                Section::System   => (&mut system, sys.offsets[i]),
+                Section::Graphics => (&mut graphics, gfx.offsets[i]),
+            };
+            target[start..start + data.len()].copy_from_slice(data);
+        }
+
+        Resource {
+            version,
+            system_flags  : sys.flags | ((version >> 4) & 0xF) << 28,
+            graphics_flags: gfx.flags | (version & 0xF) << 28,
+            system,
+            graphics,
+        }.to_rsc7()
+    }
+}
+
+/// Placement of one section's blocks.
+struct Layout {
+    flags  : u32,
+    size   : usize,
+    /// Offset of every block in the section (entries for other sections are unused).
+    offsets: Vec<usize>,
+}
+
+impl Layout {
+    /// Pack the blocks of `section` into pages, trying the smallest base size first.
+    /// Returns the layout and its page count.
+    fn of(blocks: &[(Section, Vec<u8>)], section: Section) -> Result<(Self, usize)> {
+        let mut order: Vec<usize> = (0..blocks.len()).filter(|&i| blocks[i].0 == section).collect();
+        let mut offsets = vec![0; blocks.len()];
+        let Some(largest) = order.iter().map(|&i| blocks[i].1.len()).max() else {
+            return Ok((Self { flags: 0, size: 0, offsets }, 0));
+        };
+        // The root stays first; everything else goes in largest first.
+        order[1..].sort_by_key(|&i| Reverse(blocks[i].1.len()));
+
+        for shift in 0..16u32 {
+            let base = 0x200usize << shift;
+            let Some(top) = (0..9).find(|&k| base << k >= largest) else { continue };
+            let page = base << top;
+
+            // First fit into pages of the largest class needed.
+            let mut used: Vec<usize> = Vec::new();
+            let mut placed = vec![(0, 0); blocks.len()];
+            for &i in &order {
+                let len = blocks[i].1.len().next_multiple_of(ALIGN);
+                let p = match used.iter().position(|&u| u + len <= page) {
+                    Some(p) => p,
+                    None    => { used.push(0); used.len() - 1 }
+                };
+                placed[i] = (p, used[p]);
+                used[p] += len;
+            }
+
+            // Shrink every page but the root's to the smallest class that holds it.
+            let classes: Vec<usize> = used.iter().enumerate()
+                .map(|(p, &u)| if p == 0 { top } else { (0..=top).find(|&k| base << k >= u).unwrap() })
+                .collect();
+            let mut counts = [0u32; 9];
+            for &k in &classes { counts[k] += 1; }
+            if counts.iter().zip(PAGE_LIMITS).any(|(&c, limit)| c > limit) { continue; }
+
+            let mut page_order: Vec<usize> = (0..used.len()).collect();
+            page_order[1..].sort_by_key(|&p| Reverse(classes[p]));
+            let mut starts = vec![0; used.len()];
+            let mut size = 0;
+            for &p in &page_order {
+                starts[p] = size;
+                size += base << classes[p];
+            }
+
+            for &i in &order {
+                let (p, off) = placed[i];
+                offsets[i] = starts[p] + off;
+            }
+            let flags = counts.iter().zip(PAGE_SHIFTS).fold(shift, |f, (&c, s)| f | c << s);
+            debug_assert_eq!(resource_size_from_flags(flags), size);
+            return Ok((Self { flags, size, offsets }, used.len()));
+        }
+        bail!("{} bytes in blocks of up to {} bytes do not fit in a resource",
+            order.iter().map(|&i| blocks[i].1.len()).sum::<usize>(), largest)
+    }
+}
+
+// ─── Raw segments ────────────────────────────────────────────────────────────
+
+/// JSON sidecar of a resource stored as raw sections: `<name>.json` next to
+/// `<name>.system` and `<name>.graphics`.
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    version       : u32,
+    system_flags  : u32,
+    graphics_flags: u32,
+    /// Section file names, relative to the sidecar.
+    system        : String,
+    graphics      : String,
+}
+
+/// Write `res` as `<path>.system`, `<path>.graphics` and the `<path>.json` sidecar.
+/// Returns the sidecar path.
+pub fn write_raw(path: &Path, res: &Resource) -> Result<PathBuf> {
+    let with_suffix = |suffix: &str| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(suffix);
+        PathBuf::from(p)
+    };
+    let (system, graphics, sidecar) = (with_suffix(".system"), with_suffix(".graphics"), with_suffix(".json"));
+    let file_name = |p: &Path| p.file_name().unwrap().to_string_lossy().into_owned();
+
+    fs::write(&system, &res.system).with_context(|| format!("failed to write {}", system.display()))?;
+    fs::write(&graphics, &res.graphics).with_context(|| format!("failed to write {}", graphics.display()))?;
+    let json = serde_json::to_string_pretty(&Sidecar {
+        version       : res.version,
+        system_flags  : res.system_flags,
+        graphics_flags: res.graphics_flags,
+        system        : file_name(&system),
+        graphics      : file_name(&graphics),
+    })?;
+    fs::write(&sidecar, json + "\n").with_context(|| format!("failed to write {}", sidecar.display()))?;
+    Ok(sidecar)
+}
+
+/// Load a resource written by `write_raw` from its sidecar.
+pub fn read_raw(sidecar: &Path) -> Result<Resource> {
+    let text = fs::read_to_string(sidecar).with_context(|| format!("cannot read {}", sidecar.display()))?;
+    let meta: Sidecar = serde_json::from_str(&text)
+        .with_context(|| format!("{} is not a resource sidecar", sidecar.display()))?;
+    let dir = sidecar.parent().unwrap_or(Path::new(""));
+    let read = |name: &str| {
+        let path = dir.join(name);
+        fs::read(&path).with_context(|| format!("cannot read {}", path.display()))
+    };
+
+    Ok(Resource {
+        version       : meta.version,
+        system_flags  : meta.system_flags,
+        graphics_flags: meta.graphics_flags,
+        system        : read(&meta.system)?,
+        graphics      : read(&meta.graphics)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `RpfResourcePageFlags.Size` from CodeWalker.
+    fn codewalker_size(value: u32) -> usize {
+        let s0 = (value >> 27) & 0x1;
+        let s1 = ((value >> 26) & 0x1) << 1;
+        let s2 = ((value >> 25) & 0x1) << 2;
+        let s3 = ((value >> 24) & 0x1) << 3;
+        let s4 = ((value >> 17) & 0x7F) << 4;
+        let s5 = ((value >> 11) & 0x3F) << 5;
+        let s6 = ((value >> 7) & 0xF) << 6;
+        let s7 = ((value >> 5) & 0x3) << 7;
+        let s8 = ((value >> 4) & 0x1) << 8;
+        let base = 0x200 << (value & 0xF);
+        base * (s0 + s1 + s2 + s3 + s4 + s5 + s6 + s7 + s8) as usize
+    }
+
+    #[test]
+    fn decodes_page_flags() {
+        // One 0x20000 page and one 0x8000 page on a 0x200 base.
+        assert_eq!(pages(0x90), [(0x20000, 1), (0x8000, 1)]);
+        // Base 0x2000: two of the fifth class and one of the smallest.
+        assert_eq!(page_base(0x0804_0004), 0x2000);
+        assert_eq!(pages(0x0804_0004), [(0x20000, 2), (0x2000, 1)]);
+        assert!(pages(0).is_empty());
+
+        for flags in [0x90, 0x0804_0004, 0x2000_0001, 0x0F00_0003, 0x0FFF_FFF0, 0x4000_8A52, 0x0000_0062] {
+            let total: usize = pages(flags).iter().map(|&(size, n)| size * n as usize).sum();
+            assert_eq!(total, codewalker_size(flags), "0x{:08X}", flags);
+            assert_eq!(total, rpf_archive::resource_size_from_flags(flags), "0x{:08X}", flags);
+        }
+    }
+
+    #[test]
+    fn identifies_resource_types() {
+        assert_eq!(resource_type("props/Thing.YDR"), Some(("ydr", "drawable", 165)));
+        assert_eq!(resource_type("a.ynv").map(|t| t.2), Some(2));
+        assert_eq!(resource_type("noext"), None);
+        assert_eq!(resource_type("a.xml"), None);
+        assert_eq!(types_with_version(165), [("ydr", "drawable"), ("ydd", "drawable dictionary")]);
+        assert_eq!(types_with_version(43), [("ybn", "static collision")]);
+        assert!(types_with_version(9999).is_empty());
+    }
+
+    fn sample() -> Resource {
+        let mut b = ResourceBuilder::new();
+        let root = b.add(Section::System, (0..0x40).collect());
+        let data = b.add(Section::Graphics, vec![0xAB; 0x3000]);
+        b.pointer(root, 0x10, data);
+        Resource::parse(&b.build(13).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn raw_sections_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let res = sample();
+        let sidecar = write_raw(&dir.path().join("a.ytd"), &res).unwrap();
+        assert_eq!(sidecar, dir.path().join("a.ytd.json"));
+        assert_eq!(fs::read(dir.path().join("a.ytd.system")).unwrap(), res.system);
+        assert_eq!(fs::read(dir.path().join("a.ytd.graphics")).unwrap(), res.graphics);
+
+        let back = read_raw(&sidecar).unwrap();
+        assert_eq!((back.version, back.system_flags, back.graphics_flags), (13, res.system_flags, res.graphics_flags));
+        assert_eq!((&back.system, &back.graphics), (&res.system, &res.graphics));
+
+        let again = Resource::parse(&back.to_rsc7().unwrap()).unwrap();
+        assert_eq!(u64_at(&again.system, 0x10), GRAPHICS_BASE);
+        assert_eq!((again.system, again.graphics), (res.system, res.graphics));
+    }
+
+    #[test]
+    fn rejects_resized_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let sidecar = write_raw(&dir.path().join("a.ytd"), &sample()).unwrap();
+        let mut res = read_raw(&sidecar).unwrap();
+        res.graphics.push(0);
+        assert!(res.to_rsc7().is_err());
+
+        fs::remove_file(dir.path().join("a.ytd.system")).unwrap();
+        assert!(read_raw(&sidecar).is_err());
+        assert!(read_raw(&dir.path().join("a.ytd.graphics")).is_err(), "not a sidecar");
+    }
+}
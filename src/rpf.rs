@@ -1,42 +1,132 @@
 // Thin adapter over rpf_archive for rpf-cli commands.
 // Re-exports rpf_archive types that commands use directly.
 pub use rpf_archive::{
-    DirNode, FileRef, GtaKeys, RpfArchive, RpfEncryption,
+    DirNode, FileRef, GtaKeys, RpfArchive, RpfEncryption, RpfEntry, RpfEntryKind, RpfVersion,
     build_directory_tree, list_all_files,
 };
 
-use anyhow::Result;
-use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::{fs::File, ops::Range, path::{Path, PathBuf}, sync::Arc};
 
-/// Full archive with parsed metadata, directory tree, and raw data in memory.
+/// How deep recursive commands descend into archives nested inside archives.
+pub const MAX_DEPTH: usize = 16;
+
+enum Backing {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+/// Bytes backing an archive. Top-level archives are memory-mapped, so only the pages
+/// touched by the TOC and by extracted entries are ever read from disk. Nested archives
+/// that are stored uncompressed share their parent's backing through an offset window.
+#[derive(Clone)]
+struct Source {
+    backing: Arc<Backing>,
+    range  : Range<usize>,
+}
+
+impl Source {
+    fn new(backing: Backing) -> Self {
+        let len = match &backing { Backing::Mapped(m) => m.len(), Backing::Owned(v) => v.len() };
+        Self { backing: Arc::new(backing), range: 0..len }
+    }
+
+    fn window(&self, range: Range<usize>) -> Self {
+        let start = self.range.start + range.start;
+        Self { backing: Arc::clone(&self.backing), range: start..start + range.len() }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let all: &[u8] = match &*self.backing {
+            Backing::Mapped(m) => m,
+            Backing::Owned(v)  => v,
+        };
+        &all[self.range.clone()]
+    }
+}
+
+/// Full archive with parsed metadata and directory tree. Entry data is fetched on demand.
 pub struct Archive {
-    #[allow(dead_code)]
     pub path        : std::path::PathBuf,
     pub encryption  : RpfEncryption,
     pub entry_count : usize,
     pub dir_count   : usize,
     pub root        : DirNode,
     archive         : RpfArchive,
-    data            : Vec<u8>,
+    source          : Source,
 }
 
 impl Archive {
     pub fn open(path: &Path, keys: Option<&GtaKeys>) -> Result<Self> {
-        let data = std::fs::read(path)?;
+        let file = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+        if file.metadata()?.len() == 0 { bail!("{} is empty", path.display()); }
+
+        // SAFETY: the map is read-only and we never hand out references that outlive the
+        // `Archive`; modifying the file from another process while it is open is unsupported.
+        let map = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("cannot map {}", path.display()))?;
+
         let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
-        Self::from_bytes(data, &name, keys)
+        let mut archive = Self::parse(Source::new(Backing::Mapped(map)), &name, keys)?;
+        archive.path = path.to_path_buf();
+        Ok(archive)
     }
 
-    /// Parse an archive from in-memory bytes (used to descend into nested RPFs).
+    /// Parse an archive from in-memory bytes (used for nested RPFs that had to be decrypted or inflated).
     pub fn from_bytes(data: Vec<u8>, name: &str, keys: Option<&GtaKeys>) -> Result<Self> {
-        let archive = RpfArchive::parse(&data, name, keys)?;
+        Self::parse(Source::new(Backing::Owned(data)), name, keys)
+    }
+
+    /// Open the nested archive stored in `file`. Stored, unencrypted entries (the usual case
+    /// for `.rpf` inside `.rpf`) are read through a window over this archive's bytes, so
+    /// nothing is copied; anything else is extracted into memory first.
+    pub fn open_nested(&self, file: &FileRef, keys: Option<&GtaKeys>) -> Result<Self> {
+        if let Some(window) = self.stored_range(file) {
+            return Self::parse(self.source.window(window), &file.name, keys);
+        }
+
+        let data = self.extract(file, keys)?;
+        Self::from_bytes(data, &file.name, keys)
+    }
+
+    fn parse(source: Source, name: &str, keys: Option<&GtaKeys>) -> Result<Self> {
+        let archive = RpfArchive::parse(source.bytes(), name, keys)?;
 
         let encryption = archive.encryption;
         let entry_count = archive.entries.len();
         let dir_count = archive.entries.iter().filter(|e| e.is_directory()).count();
         let root = build_directory_tree(&archive.entries);
 
-        Ok(Self { path: PathBuf::from(name), encryption, entry_count, dir_count, root, archive, data })
+        Ok(Self { path: PathBuf::from(name), encryption, entry_count, dir_count, root, archive, source })
+    }
+
+    /// Byte range of a binary entry that is stored as-is (no compression, no encryption),
+    /// relative to the start of this archive.
+    fn stored_range(&self, file: &FileRef) -> Option<Range<usize>> {
+        let RpfEntryKind::BinaryFile { file_size, uncompressed_size, is_encrypted, .. } =
+            self.archive.entries[file.entry_index].kind else { return None };
+        if is_encrypted || (file_size > 0 && file_size < uncompressed_size) { return None; }
+
+        self.entry_range(file).filter(|r| r.end <= self.source.bytes().len())
+    }
+
+    /// Byte range an entry's stored data occupies, relative to the start of this archive.
+    /// `None` for directories and empty entries; the range may run past EOF in a corrupt archive.
+    pub fn entry_range(&self, file: &FileRef) -> Option<Range<usize>> {
+        let (offset, len) = match self.archive.entries[file.entry_index].kind {
+            RpfEntryKind::BinaryFile { file_offset, file_size, uncompressed_size, .. } =>
+                (file_offset, if file_size > 0 { file_size } else { uncompressed_size }),
+            RpfEntryKind::ResourceFile { file_offset, file_size, .. } => (file_offset, file_size),
+            RpfEntryKind::Directory { .. } => return None,
+        };
+
+        let start = self.archive.start_offset + match self.archive.version {
+            RpfVersion::V7 => offset as usize * 512,
+            _              => offset as usize,
+        };
+        let end = start.checked_add(len as usize)?;
+        (len > 0).then_some(start..end)
     }
 
     pub fn list_files(&self) -> Vec<&FileRef> {
@@ -48,14 +138,47 @@ impl Archive {
         find_in_dir(&self.root, &path)
     }
 
+    /// Extract the file at `path`, which may run through nested `.rpf` files
+    /// (`x64/dlc.rpf/x64/levels/foo.ybn`). Returns the file's full path and its data.
+    pub fn read_path(&self, path: &str, keys: Option<&GtaKeys>) -> Result<(String, Vec<u8>)> {
+        let path = path.replace('\\', "/");
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        for i in 0..parts.len().saturating_sub(1) {
+            if !parts[i].to_lowercase().ends_with(".rpf") { continue; }
+            let Some(file) = self.find_file(&parts[..=i].join("/")) else { continue };
+            let nested = self.open_nested(file, keys)
+                .with_context(|| format!("failed to open nested archive {}", file.path))?;
+            let (inner, data) = nested.read_path(&parts[i + 1..].join("/"), keys)
+                .with_context(|| format!("in {}", file.path))?;
+            return Ok((format!("{}/{}", file.path, inner), data));
+        }
+
+        let file = self.find_file(&path).with_context(|| format!("'{}' not found in archive", path))?;
+        let data = self.extract(file, keys).with_context(|| format!("failed to extract '{}'", file.path))?;
+        Ok((file.path.clone(), data))
+    }
+
     pub fn extract(&self, file: &FileRef, keys: Option<&GtaKeys>) -> Result<Vec<u8>> {
         let entry = &self.archive.entries[file.entry_index];
-        self.archive.extract_entry(&self.data, entry, keys)
+        self.archive.extract_entry(self.source.bytes(), entry, keys)
     }
 
-    pub fn entry_kind(&self, file: &FileRef) -> &rpf_archive::RpfEntryKind {
+    pub fn entry_kind(&self, file: &FileRef) -> &RpfEntryKind {
         &self.archive.entries[file.entry_index].kind
     }
+
+    /// Raw archive bytes (a window into the parent's bytes for nested archives).
+    pub fn bytes(&self) -> &[u8] {
+        self.source.bytes()
+    }
+
+    pub fn version(&self) -> RpfVersion {
+        self.archive.version
+    }
+
+    pub fn entries(&self) -> &[RpfEntry] {
+        &self.archive.entries
+    }
 }
 
 fn find_in_dir<'a>(dir: &'a DirNode, path: &str) -> Option<&'a FileRef> {
@@ -67,3 +190,78 @@ fn find_in_dir<'a>(dir: &'a DirNode, path: &str) -> Option<&'a FileRef> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpf_archive::RpfBuilder;
+
+    fn archive(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = RpfBuilder::new(RpfEncryption::Open);
+        for (path, data) in files {
+            builder.add_file(path, data.clone());
+        }
+        builder.build(None).unwrap()
+    }
+
+    /// `outer.rpf` holding `mid.rpf`, which holds `inner.rpf` with `x/y.txt`.
+    fn nested() -> Vec<u8> {
+        let inner = archive(&[("x/y.txt", b"deep".repeat(100))]);
+        let mid = archive(&[("pad.bin", vec![1; 3000]), ("inner.rpf", inner)]);
+        archive(&[("a.txt", b"top".to_vec()), ("dir/mid.rpf", mid)])
+    }
+
+    fn write(data: &[u8]) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outer.rpf");
+        std::fs::write(&path, data).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn opens_nested_archives_through_windows() {
+        let (_dir, path) = write(&nested());
+        let outer = Archive::open(&path, None).unwrap();
+        let mid_file = outer.find_file("dir/mid.rpf").unwrap();
+        let mid = outer.open_nested(mid_file, None).unwrap();
+        let inner = mid.open_nested(mid.find_file("inner.rpf").unwrap(), None).unwrap();
+
+        assert!(Arc::ptr_eq(&inner.source.backing, &outer.source.backing), "no copy was made");
+        let mid_start = outer.stored_range(mid_file).unwrap().start;
+        let inner_start = mid.stored_range(mid.find_file("inner.rpf").unwrap()).unwrap().start;
+        assert_eq!(inner.source.range.start, mid_start + inner_start);
+
+        let file = inner.find_file("x/y.txt").unwrap();
+        assert_eq!(inner.extract(file, None).unwrap(), b"deep".repeat(100));
+        assert_eq!(inner.path, PathBuf::from("inner.rpf"));
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_archive() {
+        let mut data = nested();
+        let (_dir, path) = write(&data);
+        let index = Archive::open(&path, None).unwrap().find_file("dir/mid.rpf").unwrap().entry_index;
+
+        // Point the nested archive's 24-bit block offset past the end of the file.
+        let at = 16 + index * 16 + 5;
+        data[at..at + 3].copy_from_slice(&[0x00, 0x00, 0x10]);
+        let (_dir, path) = write(&data);
+        let outer = Archive::open(&path, None).unwrap();
+        let file = outer.find_file("dir/mid.rpf").unwrap();
+        assert!(outer.stored_range(file).is_none());
+        assert!(outer.open_nested(file, None).is_err());
+        assert!(outer.extract(file, None).is_err());
+    }
+
+    #[test]
+    fn reads_paths_through_nested_archives() {
+        let (_dir, path) = write(&nested());
+        let outer = Archive::open(&path, None).unwrap();
+
+        let (full, data) = outer.read_path("dir\\mid.rpf/inner.rpf/x/y.txt", None).unwrap();
+        assert_eq!(full, "dir/mid.rpf/inner.rpf/x/y.txt");
+        assert_eq!(data, b"deep".repeat(100));
+        assert_eq!(outer.read_path("a.txt", None).unwrap().1, b"top");
+        assert!(outer.read_path("dir/mid.rpf/missing.txt", None).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+// Conversion between texture formats and 8-bit RGBA, for re-encoding and image export.
+//
+// Decoding covers every format a dictionary can hold (BC1-BC5 and BC7 via texture2ddecoder).
+// Encoding covers the uncompressed formats and BC1-BC5 (texpresso); there is no BC7 encoder.
+// Everything runs on the CPU.
+
+use anyhow::{bail, Context, Result};
+use rpf_archive::TextureFormat;
+
+use super::level_size;
+
+/// Decode one mip level to RGBA rows.
+pub fn decode(format: TextureFormat, width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>> {
+    let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+    if data.len() < level_size(format, width, height) {
+        bail!("{}x{} {} level needs {} bytes, have {}", w, h, format, level_size(format, width, height), data.len());
+    }
+
+    if format.is_block_compressed() {
+        let decode = match format {
+            TextureFormat::DXT1 => texture2ddecoder::decode_bc1,
+            TextureFormat::DXT3 => texture2ddecoder::decode_bc2,
+            TextureFormat::DXT5 => texture2ddecoder::decode_bc3,
+            TextureFormat::ATI1 => texture2ddecoder::decode_bc4,
+            TextureFormat::ATI2 => texture2ddecoder::decode_bc5,
+            _                   => texture2ddecoder::decode_bc7,
+        };
+        let mut pixels = vec![0u32; w * h];
+        decode(data, w, h, &mut pixels).map_err(|e| anyhow::anyhow!("{} decode failed: {}", format, e))?;
+
+        // Pixels come out as BGRA; single-channel BC4 is spread to grey.
+        return Ok(pixels.iter().flat_map(|p| {
+            let [b, g, r, a] = p.to_le_bytes();
+            if format == TextureFormat::ATI1 { [r, r, r, 255] } else { [r, g, b, a] }
+        }).collect());
+    }
+
+    let n = w * h;
+    Ok(match format {
+        TextureFormat::A8R8G8B8 => data[..n * 4].chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+        TextureFormat::X8R8G8B8 => data[..n * 4].chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], 255]).collect(),
+        TextureFormat::A8B8G8R8 => data[..n * 4].to_vec(),
+        TextureFormat::A1R5G5B5 => data[..n * 2].chunks_exact(2).flat_map(|p| {
+            let v = u16::from_le_bytes([p[0], p[1]]);
+            let c = |shift: u16| (((v >> shift) & 0x1F) as u32 * 255 / 31) as u8;
+            [c(10), c(5), c(0), if v & 0x8000 != 0 { 255 } else { 0 }]
+        }).collect(),
+        TextureFormat::A8 => data[..n].iter().flat_map(|&a| [255, 255, 255, a]).collect(),
+        TextureFormat::L8 => data[..n].iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        _ => bail!("cannot decode {} textures", format),
+    })
+}
+
+/// Encode RGBA rows as one mip level.
+pub fn encode(format: TextureFormat, width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+    let bc = match format {
+        TextureFormat::DXT1 => Some(texpresso::Format::Bc1),
+        TextureFormat::DXT3 => Some(texpresso::Format::Bc2),
+        TextureFormat::DXT5 => Some(texpresso::Format::Bc3),
+        TextureFormat::ATI1 => Some(texpresso::Format::Bc4),
+        TextureFormat::ATI2 => Some(texpresso::Format::Bc5),
+        _                   => None,
+    };
+    if let Some(bc) = bc {
+        let mut out = vec![0u8; bc.compressed_size(w, h)];
+        bc.compress(rgba, w, h, texpresso::Params::default(), &mut out);
+        return Ok(out);
+    }
+
+    let px = rgba.chunks_exact(4);
+    Ok(match format {
+        TextureFormat::A8R8G8B8 => px.flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+        TextureFormat::X8R8G8B8 => px.flat_map(|p| [p[2], p[1], p[0], 255]).collect(),
+        TextureFormat::A8B8G8R8 => rgba.to_vec(),
+        TextureFormat::A1R5G5B5 => px.flat_map(|p| {
+            let c = |v: u8| (v as u16 * 31 + 127) / 255;
+            let v = ((p[3] >= 128) as u16) << 15 | c(p[0]) << 10 | c(p[1]) << 5 | c(p[2]);
+            v.to_le_bytes()
+        }).collect(),
+        TextureFormat::A8 => px.map(|p| p[3]).collect(),
+        TextureFormat::L8 => px.map(|p| ((p[0] as u32 * 54 + p[1] as u32 * 183 + p[2] as u32 * 19) >> 8) as u8).collect(),
+        _ => bail!("encoding to {} is not supported", format),
+    })
+}
+
+/// Encode `rgba` and `levels - 1` successively halved copies of it.
+pub fn encode_chain(format: TextureFormat, width: u32, height: u32, levels: u8, rgba: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut w, mut h, mut level) = (width, height, rgba.to_vec());
+    for i in 0..levels {
+        out.extend(encode(format, w, h, &level)?);
+        if i + 1 < levels {
+            let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+            level = resize(&level, w, h, nw, nh);
+            (w, h) = (nw, nh);
+        }
+    }
+    Ok(out)
+}
+
+/// Resample RGBA rows to a new size, averaging every source pixel under each target pixel.
+pub fn resize(rgba: &[u8], width: u32, height: u32, new_width: u32, new_height: u32) -> Vec<u8> {
+    if (width, height) == (new_width, new_height) { return rgba.to_vec(); }
+    let (w, h, nw, nh) = (width as usize, height as usize, new_width as usize, new_height as usize);
+
+    let mut out = Vec::with_capacity(nw * nh * 4);
+    for y in 0..nh {
+        let (y0, y1) = (y * h / nh, ((y + 1) * h).div_ceil(nh).max(y * h / nh + 1));
+        for x in 0..nw {
+            let (x0, x1) = (x * w / nw, ((x + 1) * w).div_ceil(nw).max(x * w / nw + 1));
+            let mut sum = [0u32; 4];
+            for sy in y0..y1.min(h) {
+                for sx in x0..x1.min(w) {
+                    let p = &rgba[(sy * w + sx) * 4..][..4];
+                    for c in 0..4 { sum[c] += p[c] as u32; }
+                }
+            }
+            let n = ((y1.min(h) - y0) * (x1.min(w) - x0)) as u32;
+            out.extend(sum.map(|s| ((s + n / 2) / n) as u8));
+        }
+    }
+    out
+}
+
+// ─── Image files ─────────────────────────────────────────────────────────────
+
+/// RGBA rows as a PNG file.
+pub fn to_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width.max(1), height.max(1));
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()
+        .and_then(|mut w| w.write_image_data(rgba))
+        .context("PNG encoding failed")?;
+    Ok(out)
+}
+
+/// RGBA rows as an uncompressed 32-bit TGA file.
+pub fn to_tga(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let (w, h) = (u16::try_from(width.max(1))?, u16::try_from(height.max(1))?);
+    let mut out = Vec::with_capacity(18 + rgba.len());
+    out.extend_from_slice(&[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    out.extend_from_slice(&w.to_le_bytes());
+    out.extend_from_slice(&h.to_le_bytes());
+    // 32 bits per pixel; descriptor: 8 alpha bits, rows stored top to bottom.
+    out.extend_from_slice(&[32, 0x28]);
+    out.extend(rgba.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncompressed_formats_round_trip() {
+        let rgba: Vec<u8> = (0..4 * 4 * 4).map(|i| (i * 17) as u8).collect();
+        for format in [TextureFormat::A8R8G8B8, TextureFormat::A8B8G8R8] {
+            let data = encode(format, 4, 4, &rgba).unwrap();
+            assert_eq!(data.len(), level_size(format, 4, 4));
+            assert_eq!(decode(format, 4, 4, &data).unwrap(), rgba, "{}", format);
+        }
+        assert_eq!(encode(TextureFormat::A8R8G8B8, 1, 1, &[1, 2, 3, 4]).unwrap(), [3, 2, 1, 4], "stored as BGRA");
+        assert_eq!(decode(TextureFormat::L8, 2, 1, &[10, 20]).unwrap(), [10, 10, 10, 255, 20, 20, 20, 255]);
+        assert_eq!(decode(TextureFormat::A1R5G5B5, 1, 1, &0xFC00u16.to_le_bytes()).unwrap(), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc1_keeps_solid_colours() {
+        let rgba: Vec<u8> = [255, 0, 0, 255].repeat(8 * 8);
+        let data = encode(TextureFormat::DXT1, 8, 8, &rgba).unwrap();
+        assert_eq!(data.len(), 4 * 8);
+        assert_eq!(decode(TextureFormat::DXT1, 8, 8, &data).unwrap(), rgba);
+        assert!(decode(TextureFormat::DXT1, 8, 8, &data[..16]).is_err(), "truncated level");
+    }
+
+    #[test]
+    fn resizes_by_averaging() {
+        let rgba = [[0, 0, 0, 255], [100, 0, 0, 255], [0, 100, 0, 255], [0, 0, 100, 255]].concat();
+        assert_eq!(resize(&rgba, 2, 2, 1, 1), [25, 25, 25, 255]);
+        assert_eq!(resize(&rgba, 2, 2, 2, 2), rgba);
+        assert_eq!(resize(&[7, 8, 9, 10], 1, 1, 2, 2), [7, 8, 9, 10].repeat(4));
+
+        let chain = encode_chain(TextureFormat::A8, 4, 4, 3, &[0, 0, 0, 200].repeat(16)).unwrap();
+        assert_eq!(chain, [200; 16 + 4 + 1]);
+    }
+
+    #[test]
+    fn decodes_known_bc1_block() {
+        // Red and blue 565 endpoints; the first row uses indices 0, 1, 2 and 3, the rest 0.
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b11_10_01_00, 0, 0, 0];
+        let rgba = decode(TextureFormat::DXT1, 4, 4, &block).unwrap();
+        assert_eq!(rgba[0..4], [255, 0, 0, 255]);
+        assert_eq!(rgba[4..8], [0, 0, 255, 255]);
+        assert_eq!(rgba[8..12], [170, 0, 85, 255], "two thirds red");
+        assert_eq!(rgba[12..16], [85, 0, 170, 255], "two thirds blue");
+        assert_eq!(rgba[16..20], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_known_bc4_block() {
+        // Endpoints 200 and 100; index 1 selects the second endpoint, index 2 the first blend.
+        let mut block = [200, 100, 0, 0, 0, 0, 0, 0];
+        block[2] = 0b010_001_000;
+        let rgba = decode(TextureFormat::ATI1, 4, 4, &block).unwrap();
+        assert_eq!(rgba[0..4], [200, 200, 200, 255]);
+        assert_eq!(rgba[4..8], [100, 100, 100, 255]);
+        assert_eq!(rgba[8..12], [185, 185, 185, 255], "(6 * 200 + 100) / 7, rounded down");
+        assert_eq!(rgba[60..64], [200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn writes_tga_and_png() {
+        let tga = to_tga(2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(tga[2], 2, "uncompressed true-colour");
+        assert_eq!(tga[12..18], [2, 0, 1, 0, 32, 0x28]);
+        assert_eq!(tga[18..], [3, 2, 1, 4, 7, 6, 5, 8]);
+
+        let png = to_png(2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png[16..24], [0, 0, 0, 2, 0, 0, 0, 1], "IHDR width and height");
+    }
+}
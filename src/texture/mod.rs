@@ -0,0 +1,508 @@
+// Texture dictionaries (.ytd) and DDS conversion.
+//
+// A dictionary's root block (0x40 bytes) holds two parallel lists: the JOAAT hashes of the
+// lower-case texture names, sorted ascending so the game can binary-search them, and
+// pointers to the 0x90-byte texture structs. Each texture struct points at its name in the
+// system section and its pixel data (every mip level, largest first) in the graphics
+// section. Offsets follow CodeWalker's `TextureDictionary` and `Texture`.
+//
+// Drawables (.ydr, .ydd) and fragments (.yft) can embed a dictionary of the same layout in
+// their shader group.
+
+use anyhow::{bail, Context, Result};
+use rpf_archive::{rage_joaat, TextureFormat, YtdTexture};
+
+use crate::resource::{u16_at, u32_at, u64_at, Resource, ResourceBuilder, Section, SYSTEM_BASE};
+
+pub mod codec;
+
+/// Resource version of texture dictionaries.
+pub const YTD_VERSION: u32 = 13;
+/// Resource versions of drawables and drawable dictionaries, and of fragments.
+const DRAWABLE_VERSION: u32 = 165;
+const FRAGMENT_VERSION: u32 = 162;
+
+const DICT_SIZE: usize = 0x40;
+const TEXTURE_SIZE: usize = 0x90;
+
+pub struct Texture {
+    pub name     : String,
+    pub name_hash: u32,
+    pub width    : u16,
+    pub height   : u16,
+    pub depth    : u16,
+    pub stride   : u16,
+    pub format   : TextureFormat,
+    pub levels   : u8,
+    /// The texture struct as stored, so fields this tool does not model survive a rebuild.
+    /// Pointers and the fields above are rewritten when the dictionary is built.
+    raw          : [u8; TEXTURE_SIZE],
+    pub data     : Vec<u8>,
+}
+
+impl Texture {
+    /// Build a texture from a DDS file. `name` is stored as is and hashed lower-case.
+    pub fn from_dds(name: &str, dds: &[u8]) -> Result<Self> {
+        let dds = Dds::parse(dds)?;
+        let mut raw = [0u8; TEXTURE_SIZE];
+        raw[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+        raw[0x30..0x32].copy_from_slice(&1u16.to_le_bytes());
+
+        Ok(Self {
+            name     : name.to_string(),
+            name_hash: rage_joaat(&name.to_lowercase()),
+            width    : dds.width,
+            height   : dds.height,
+            depth    : 1,
+            stride   : dds.stride,
+            format   : dds.format,
+            levels   : dds.levels,
+            raw,
+            data     : dds.data,
+        })
+    }
+
+    pub fn to_dds(&self) -> Vec<u8> {
+        YtdTexture {
+            name      : self.name.clone(),
+            name_hash : self.name_hash,
+            width     : self.width,
+            height    : self.height,
+            depth     : self.depth,
+            format    : self.format,
+            levels    : self.levels,
+            stride    : self.stride,
+            pixel_data: self.data.clone(),
+        }.to_dds()
+    }
+
+    /// Number of slices (cubemap faces or volume layers); each holds a full mip chain.
+    pub fn slices(&self) -> u16 {
+        self.depth.max(1)
+    }
+
+    /// Pixel data of mip `level` of `slice`.
+    pub fn image(&self, slice: u16, level: u8) -> Option<&[u8]> {
+        if slice >= self.slices() || level >= self.levels { return None; }
+        let start = slice as usize * data_size(self.format, self.width, self.height, self.levels)
+            + data_size(self.format, self.width, self.height, level);
+        let len = level_size(self.format, self.width as u32 >> level, self.height as u32 >> level);
+        self.data.get(start..start + len)
+    }
+
+    /// A copy holding only mip `level` of every slice.
+    pub fn mip(&self, level: u8) -> Option<Self> {
+        let data: Vec<u8> = (0..self.slices())
+            .map(|s| self.image(s, level))
+            .collect::<Option<Vec<_>>>()?
+            .concat();
+        let (width, height) = ((self.width >> level).max(1), (self.height >> level).max(1));
+        let stride = (level_size(self.format, width as u32, height as u32) / height as usize) as u16;
+        Some(Self { width, height, stride, levels: 1, data, name: self.name.clone(), ..*self })
+    }
+
+    /// A copy converted to `format` and resized to `width` x `height` with `levels` mips,
+    /// generated from the top level.
+    pub fn reencode(&self, format: TextureFormat, width: u16, height: u16, levels: u8) -> Result<Self> {
+        let top = self.image(0, 0).context("texture has no pixel data")?;
+        let rgba = codec::decode(self.format, self.width as u32, self.height as u32, top)?;
+        let rgba = codec::resize(&rgba, self.width as u32, self.height as u32, width as u32, height as u32);
+        let data = codec::encode_chain(format, width as u32, height as u32, levels, &rgba)?;
+        let stride = (level_size(format, width as u32, height as u32) / height as usize) as u16;
+
+        Ok(Self { width, height, depth: 1, stride, format, levels, data, name: self.name.clone(), ..*self })
+    }
+
+    /// Take the pixels and dimensions of `image`, keeping this texture's name and stored
+    /// fields (usage flags and the like).
+    pub fn set_image(&mut self, image: Texture) {
+        self.width = image.width;
+        self.height = image.height;
+        self.depth = image.depth;
+        self.stride = image.stride;
+        self.format = image.format;
+        self.levels = image.levels;
+        self.data = image.data;
+    }
+
+    /// The name, or the hash when the dictionary stores no name.
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() { format!("0x{:08X}", self.name_hash) } else { self.name.clone() }
+    }
+}
+
+// ─── Dictionaries ────────────────────────────────────────────────────────────
+
+/// Read every texture of a texture dictionary resource.
+pub fn read_dictionary(res: &Resource) -> Result<Vec<Texture>> {
+    if res.version != YTD_VERSION {
+        bail!("not a texture dictionary (resource version {}, expected {})", res.version, YTD_VERSION);
+    }
+    read_dictionary_at(res, SYSTEM_BASE)
+}
+
+/// Read the texture dictionary whose root block is at `va`.
+pub fn read_dictionary_at(res: &Resource, va: u64) -> Result<Vec<Texture>> {
+    let root = res.slice(va, DICT_SIZE)
+        .with_context(|| format!("texture dictionary out of bounds (0x{:X})", va))?;
+    let hash_ptr = u64_at(root, 0x20);
+    let hash_count = u16_at(root, 0x28) as usize;
+    let tex_ptr = u64_at(root, 0x30);
+    let tex_count = u16_at(root, 0x38) as usize;
+
+    let hashes = res.slice(hash_ptr, hash_count * 4);
+    let pointers = res.slice(tex_ptr, tex_count * 8)
+        .with_context(|| format!("texture pointer list out of bounds (0x{:X})", tex_ptr))?;
+
+    let mut textures = Vec::with_capacity(tex_count);
+    for i in 0..tex_count {
+        let va = u64_at(pointers, i * 8);
+        let name_hash = hashes.filter(|_| i < hash_count).map(|h| u32_at(h, i * 4)).unwrap_or(0);
+        textures.push(read_texture(res, va, name_hash).with_context(|| format!("texture {}", i))?);
+    }
+    Ok(textures)
+}
+
+fn read_texture(res: &Resource, va: u64, name_hash: u32) -> Result<Texture> {
+    let raw: [u8; TEXTURE_SIZE] = res.slice(va, TEXTURE_SIZE)
+        .with_context(|| format!("texture struct out of bounds (0x{:X})", va))?
+        .try_into().unwrap();
+
+    let name = res.c_str(u64_at(&raw, 0x28)).unwrap_or_default();
+    let (width, height, depth, stride) = (u16_at(&raw, 0x50), u16_at(&raw, 0x52), u16_at(&raw, 0x54), u16_at(&raw, 0x56));
+    let format = TextureFormat::from_u32(u32_at(&raw, 0x58));
+    let levels = raw[0x5D];
+
+    // Prefer the exact mip chain; unknown formats and some files only have CodeWalker's
+    // shorter estimate.
+    let data_ptr = u64_at(&raw, 0x70);
+    let slices = depth.max(1) as usize;
+    let size = data_size(format, width, height, levels) * slices;
+    let data = res.slice(data_ptr, size).filter(|_| size > 0)
+        .or_else(|| res.slice(data_ptr, stride_size(stride, height, levels) * slices))
+        .with_context(|| format!("{}: pixel data out of bounds (0x{:X}, {} bytes)", name, data_ptr, size))?
+        .to_vec();
+
+    Ok(Texture { name, name_hash, width, height, depth, stride, format, levels, raw, data })
+}
+
+/// Every texture dictionary in a resource, by file extension: the resource itself for a
+/// .ytd, the embedded one of a .ydr or .yft (if any), and one per drawable of a .ydd.
+/// Each comes with a label: empty, or the drawable's name hash for .ydd entries.
+pub fn read_all_dictionaries(res: &Resource, extension: &str) -> Result<Vec<(String, Vec<Texture>)>> {
+    let expect = |version: u32| {
+        if res.version != version {
+            bail!("unexpected resource version {} for a .{} (expected {})", res.version, extension, version);
+        }
+        Ok(())
+    };
+
+    let mut found = Vec::new();
+    match extension {
+        "ytd" => found.push((String::new(), read_dictionary(res)?)),
+        "ydr" => {
+            expect(DRAWABLE_VERSION)?;
+            found.extend(drawable_dictionary(res, SYSTEM_BASE)?.map(|d| (String::new(), d)));
+        }
+        "yft" => {
+            // FragType: the main drawable at 0x30.
+            expect(FRAGMENT_VERSION)?;
+            let root = res.slice(SYSTEM_BASE, 0x38).context("fragment root out of bounds")?;
+            let drawable = u64_at(root, 0x30);
+            if drawable != 0 {
+                found.extend(drawable_dictionary(res, drawable)?.map(|d| (String::new(), d)));
+            }
+        }
+        "ydd" => {
+            // DrawableDictionary: hash and drawable pointer lists laid out as in a .ytd.
+            expect(DRAWABLE_VERSION)?;
+            let root = res.slice(SYSTEM_BASE, DICT_SIZE).context("drawable dictionary root out of bounds")?;
+            let (hash_ptr, hash_count) = (u64_at(root, 0x20), u16_at(root, 0x28) as usize);
+            let (list_ptr, count) = (u64_at(root, 0x30), u16_at(root, 0x38) as usize);
+            let hashes = res.slice(hash_ptr, hash_count * 4);
+            let pointers = res.slice(list_ptr, count * 8)
+                .with_context(|| format!("drawable pointer list out of bounds (0x{:X})", list_ptr))?;
+
+            for i in 0..count {
+                let hash = hashes.filter(|_| i < hash_count).map(|h| u32_at(h, i * 4)).unwrap_or(0);
+                let label = format!("0x{:08X}", hash);
+                if let Some(dict) = drawable_dictionary(res, u64_at(pointers, i * 8)).with_context(|| label.clone())? {
+                    found.push((label, dict));
+                }
+            }
+        }
+        other => bail!("'.{}' files do not hold texture dictionaries", other),
+    }
+    Ok(found)
+}
+
+/// The dictionary embedded in the shader group of the drawable at `va`: the drawable
+/// points at its shader group at 0x10, which points at the dictionary at 0x08.
+fn drawable_dictionary(res: &Resource, va: u64) -> Result<Option<Vec<Texture>>> {
+    let drawable = res.slice(va, 0x18)
+        .with_context(|| format!("drawable out of bounds (0x{:X})", va))?;
+    let shader_group = u64_at(drawable, 0x10);
+    if shader_group == 0 { return Ok(None); }
+    let group = res.slice(shader_group, 0x10)
+        .with_context(|| format!("shader group out of bounds (0x{:X})", shader_group))?;
+    match u64_at(group, 0x08) {
+        0  => Ok(None),
+        va => read_dictionary_at(res, va).map(Some),
+    }
+}
+
+/// Build a texture dictionary resource (a complete RSC7 file) from `textures`.
+pub fn build_dictionary(textures: &[Texture]) -> Result<Vec<u8>> {
+    let mut sorted: Vec<&Texture> = textures.iter().collect();
+    sorted.sort_by_key(|t| t.name_hash);
+    if let Some(w) = sorted.windows(2).find(|w| w[0].name_hash == w[1].name_hash) {
+        bail!("textures '{}' and '{}' have the same name hash", w[0].display_name(), w[1].display_name());
+    }
+    let count = u16::try_from(sorted.len()).context("too many textures for one dictionary")?;
+
+    let mut b = ResourceBuilder::new();
+    let mut root = vec![0u8; DICT_SIZE];
+    root[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+    root[0x18..0x1C].copy_from_slice(&1u32.to_le_bytes());
+    for list in [0x28, 0x38] {
+        root[list..list + 2].copy_from_slice(&count.to_le_bytes());
+        root[list + 2..list + 4].copy_from_slice(&count.to_le_bytes());
+    }
+    let root = b.add(Section::System, root);
+    let pages = b.add_pages_info();
+    b.pointer(root, 0x08, pages);
+
+    let hashes = b.add(Section::System, sorted.iter().flat_map(|t| t.name_hash.to_le_bytes()).collect());
+    b.pointer(root, 0x20, hashes);
+    let pointers = b.add(Section::System, vec![0; sorted.len() * 8]);
+    b.pointer(root, 0x30, pointers);
+
+    for (i, tex) in sorted.iter().enumerate() {
+        let mut raw = tex.raw;
+        raw[0x50..0x52].copy_from_slice(&tex.width.to_le_bytes());
+        raw[0x52..0x54].copy_from_slice(&tex.height.to_le_bytes());
+        raw[0x54..0x56].copy_from_slice(&tex.depth.to_le_bytes());
+        raw[0x56..0x58].copy_from_slice(&tex.stride.to_le_bytes());
+        raw[0x58..0x5C].copy_from_slice(&(tex.format as u32).to_le_bytes());
+        raw[0x5D] = tex.levels;
+
+        let block = b.add(Section::System, raw.to_vec());
+        b.pointer(pointers, i * 8, block);
+
+        let mut name = tex.name.clone().into_bytes();
+        name.push(0);
+        let name = b.add(Section::System, name);
+        b.pointer(block, 0x28, name);
+
+        let data = b.add(Section::Graphics, tex.data.clone());
+        b.pointer(block, 0x70, data);
+    }
+
+    b.build(YTD_VERSION)
+}
+
+// ─── Formats ─────────────────────────────────────────────────────────────────
+
+/// Bytes per 4x4 block for block-compressed formats, per pixel otherwise.
+fn unit_size(format: TextureFormat) -> Option<usize> {
+    Some(match format {
+        TextureFormat::DXT1 | TextureFormat::ATI1 => 8,
+        TextureFormat::DXT3 | TextureFormat::DXT5 | TextureFormat::ATI2 | TextureFormat::BC7 => 16,
+        TextureFormat::A8R8G8B8 | TextureFormat::X8R8G8B8 | TextureFormat::A8B8G8R8 => 4,
+        TextureFormat::A1R5G5B5 => 2,
+        TextureFormat::A8 | TextureFormat::L8 => 1,
+        TextureFormat::Unknown => return None,
+    })
+}
+
+/// Bytes in one mip level of one slice.
+pub fn level_size(format: TextureFormat, width: u32, height: u32) -> usize {
+    let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+    match unit_size(format) {
+        Some(unit) if format.is_block_compressed() => w.div_ceil(4) * h.div_ceil(4) * unit,
+        Some(unit) => w * h * unit,
+        None => 0,
+    }
+}
+
+/// Bytes in a full mip chain of one slice.
+fn data_size(format: TextureFormat, width: u16, height: u16, levels: u8) -> usize {
+    (0..levels as u32).map(|l| level_size(format, width as u32 >> l, height as u32 >> l)).sum()
+}
+
+/// CodeWalker's estimate of the mip chain size: `stride * height`, quartered per level.
+fn stride_size(stride: u16, height: u16, levels: u8) -> usize {
+    (0..levels as u32).map(|l| (stride as usize * height as usize) >> (2 * l)).sum()
+}
+
+// ─── DDS ─────────────────────────────────────────────────────────────────────
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+/// A 2D DDS texture with its mip chain.
+struct Dds {
+    width : u16,
+    height: u16,
+    stride: u16,
+    format: TextureFormat,
+    levels: u8,
+    data  : Vec<u8>,
+}
+
+impl Dds {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 128 || &data[..4] != b"DDS " { bail!("not a DDS file"); }
+        let word = |off: usize| u32_at(data, off);
+
+        let (flags, height, width, mips) = (word(8), word(12), word(16), word(28));
+        let (pf_flags, fourcc, bits) = (word(80), &data[84..88], word(88));
+        let (r_mask, a_mask, caps2) = (word(92), word(104), word(112));
+
+        if caps2 & DDSCAPS2_CUBEMAP != 0 { bail!("cubemap DDS files are not supported"); }
+        if caps2 & DDSCAPS2_VOLUME != 0 { bail!("volume DDS files are not supported"); }
+
+        let mut start = 128;
+        let format = if pf_flags & DDPF_FOURCC != 0 {
+            match fourcc {
+                b"DXT1"           => TextureFormat::DXT1,
+                b"DXT3"           => TextureFormat::DXT3,
+                b"DXT5"           => TextureFormat::DXT5,
+                b"ATI1" | b"BC4U" => TextureFormat::ATI1,
+                b"ATI2" | b"BC5U" => TextureFormat::ATI2,
+                b"DX10" => {
+                    if data.len() < 148 { bail!("truncated DX10 header"); }
+                    start = 148;
+                    match word(128) {
+                        71 | 72 => TextureFormat::DXT1,
+                        74 | 75 => TextureFormat::DXT3,
+                        77 | 78 => TextureFormat::DXT5,
+                        80      => TextureFormat::ATI1,
+                        83      => TextureFormat::ATI2,
+                        98 | 99 => TextureFormat::BC7,
+                        87      => TextureFormat::A8R8G8B8,
+                        88      => TextureFormat::X8R8G8B8,
+                        28      => TextureFormat::A8B8G8R8,
+                        65      => TextureFormat::A8,
+                        61      => TextureFormat::L8,
+                        other   => bail!("unsupported DXGI format {}", other),
+                    }
+                }
+                other => bail!("unsupported DDS FourCC '{}'", String::from_utf8_lossy(other)),
+            }
+        } else if pf_flags & DDPF_RGB != 0 && bits == 32 {
+            match (r_mask, a_mask) {
+                (0x00FF_0000, 0) => TextureFormat::X8R8G8B8,
+                (0x00FF_0000, _) => TextureFormat::A8R8G8B8,
+                (0x0000_00FF, _) => TextureFormat::A8B8G8R8,
+                _ => bail!("unsupported 32-bit DDS channel layout"),
+            }
+        } else if pf_flags & DDPF_RGB != 0 && bits == 16 && r_mask == 0x7C00 {
+            TextureFormat::A1R5G5B5
+        } else if pf_flags & DDPF_ALPHA != 0 && bits == 8 {
+            TextureFormat::A8
+        } else if pf_flags & DDPF_LUMINANCE != 0 && bits == 8 {
+            TextureFormat::L8
+        } else {
+            bail!("unsupported DDS pixel format (flags 0x{:X}, {} bits)", pf_flags, bits);
+        };
+
+        let (width, height) = (u16::try_from(width)?, u16::try_from(height)?);
+        if width == 0 || height == 0 { bail!("DDS has no pixels"); }
+        let levels = if flags & DDSD_MIPMAPCOUNT != 0 || mips > 1 { mips.clamp(1, 16) as u8 } else { 1 };
+
+        let size = data_size(format, width, height, levels);
+        let body = &data[start..];
+        if body.len() < size {
+            bail!("DDS data is {} bytes, {}x{} {} with {} mip(s) needs {}", body.len(), width, height, format, levels, size);
+        }
+
+        // Stride is the top level's size over its height in pixels: the row pitch for plain
+        // formats, a quarter of the block-row pitch for compressed ones.
+        let stride = level_size(format, width as u32, height as u32) / height as usize;
+
+        Ok(Self { width, height, stride: u16::try_from(stride)?, format, levels, data: body[..size].to_vec() })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A DXT1 DDS file with a full mip chain of `levels` levels.
+    pub(crate) fn dds(width: u32, height: u32, levels: u32, fill: u8) -> Vec<u8> {
+        let mut out = vec![0u8; 128];
+        out[..4].copy_from_slice(b"DDS ");
+        for (off, v) in [(4, 124), (8, DDSD_MIPMAPCOUNT), (12, height), (16, width), (28, levels), (76, 32), (80, DDPF_FOURCC)] {
+            out[off..off + 4].copy_from_slice(&v.to_le_bytes());
+        }
+        out[84..88].copy_from_slice(b"DXT1");
+        let size = data_size(TextureFormat::DXT1, width as u16, height as u16, levels as u8);
+        out.extend((0..size).map(|i| fill.wrapping_add(i as u8)));
+        out
+    }
+
+    #[test]
+    fn parses_dds() {
+        let tex = Texture::from_dds("Body_Diff", &dds(64, 32, 3, 1)).unwrap();
+        assert_eq!((tex.width, tex.height, tex.levels, tex.format), (64, 32, 3, TextureFormat::DXT1));
+        assert_eq!(tex.stride, 32, "16 blocks of 8 bytes over 4 rows");
+        assert_eq!(tex.data.len(), 16 * 8 * 8 + 8 * 4 * 8 + 4 * 2 * 8);
+        assert_eq!(tex.name_hash, rage_joaat("body_diff"));
+
+        let mut truncated = dds(64, 32, 3, 1);
+        truncated.truncate(truncated.len() - 1);
+        assert!(Texture::from_dds("t", &truncated).is_err());
+        let mut cube = dds(4, 4, 1, 0);
+        cube[112..116].copy_from_slice(&DDSCAPS2_CUBEMAP.to_le_bytes());
+        assert!(Texture::from_dds("t", &cube).is_err());
+    }
+
+    #[test]
+    fn dictionary_round_trips() {
+        let textures = vec![
+            Texture::from_dds("zeta", &dds(64, 64, 4, 10)).unwrap(),
+            Texture::from_dds("alpha", &dds(16, 8, 1, 20)).unwrap(),
+            Texture::from_dds("mid", &dds(128, 32, 2, 30)).unwrap(),
+        ];
+        let res = Resource::parse(&build_dictionary(&textures).unwrap()).unwrap();
+        let read = read_dictionary(&res).unwrap();
+
+        let hashes: Vec<u32> = read.iter().map(|t| t.name_hash).collect();
+        assert!(hashes.is_sorted(), "the game binary-searches the hashes");
+        assert_eq!(read.len(), 3);
+        for tex in &textures {
+            let back = read.iter().find(|t| t.name == tex.name).unwrap();
+            assert_eq!(back.name_hash, tex.name_hash);
+            assert_eq!((back.width, back.height, back.stride, back.levels), (tex.width, tex.height, tex.stride, tex.levels));
+            assert_eq!(back.format, tex.format);
+            assert_eq!(back.data, tex.data);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let textures = [
+            Texture::from_dds("Same", &dds(4, 4, 1, 0)).unwrap(),
+            Texture::from_dds("same", &dds(4, 4, 1, 1)).unwrap(),
+        ];
+        assert!(build_dictionary(&textures).is_err());
+    }
+
+    #[test]
+    fn finds_dictionaries_by_extension() {
+        let data = build_dictionary(&[Texture::from_dds("a", &dds(4, 4, 1, 0)).unwrap()]).unwrap();
+        let res = Resource::parse(&data).unwrap();
+        let found = read_all_dictionaries(&res, "ytd").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].0.as_str(), found[0].1.len()), ("", 1));
+
+        assert!(read_all_dictionaries(&res, "ydr").is_err(), "wrong resource version");
+        assert!(read_all_dictionaries(&res, "ybn").is_err());
+    }
+}
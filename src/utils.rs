@@ -1,25 +1,161 @@
-pub fn matches_pattern(path: &str, pattern: &str) -> bool {
-    // Simple glob-like pattern matching supporting '*' wildcard.
-    // Currently supports prefix, suffix, and infix patterns with a single '*'.
-    // For anything more sophisticated, consider integrating the glob crate.
-    if pattern.contains('*') {
-        let parts: Vec<&str> = pattern.split('*').collect();
-
-        match parts.as_slice() {
-            // "*suffix" -> ends with suffix
-            ["", suffix] => path.ends_with(suffix),
-            // "prefix*" -> starts with prefix
-            [prefix, ""] => path.starts_with(prefix),
-            // "prefix*suffix" -> starts with prefix AND ends with suffix
-            [prefix, suffix] => path.starts_with(prefix) && path.ends_with(suffix),
-            // For patterns with multiple '*' we fall back to substring containment of all non-empty parts
-            _ => {
-                let pattern_no_star = pattern.replace('*', "");
-                path.contains(&pattern_no_star)
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::{RegexSet, RegexSetBuilder};
+
+/// Path filter options shared by `list`, `extract`, `tree` and `verify`.
+#[derive(clap::Args, Default)]
+pub struct FilterArgs {
+    /// Only include paths matching this pattern (repeatable)
+    #[arg(long = "include", value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Skip paths matching this pattern (repeatable)
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Treat patterns as regular expressions instead of globs
+    #[arg(long)]
+    pub regex: bool,
+}
+
+/// Case-insensitive include/exclude matcher for archive paths.
+///
+/// Globs support `*`, `**`, `?`, `[abc]` and `{ydr,yft}`. `*` also crosses `/`, so
+/// `*.ydr` matches at any depth; a glob also matches when it matches just the file name.
+/// Regexes are unanchored searches over the whole path.
+pub struct PathFilter {
+    include: Option<Patterns>,
+    exclude: Option<Patterns>,
+}
+
+enum Patterns {
+    Glob(GlobSet),
+    Regex(RegexSet),
+}
+
+impl PathFilter {
+    /// Build a filter from an optional positional pattern plus `--include`/`--exclude`.
+    /// A path passes when it matches any include (or there are none) and no exclude.
+    pub fn new(pattern: Option<&str>, args: &FilterArgs) -> Result<Self> {
+        let include: Vec<&str> = pattern.into_iter().chain(args.include.iter().map(String::as_str)).collect();
+        let exclude: Vec<&str> = args.exclude.iter().map(String::as_str).collect();
+        Ok(Self {
+            include: Patterns::build(&include, args.regex)?,
+            exclude: Patterns::build(&exclude, args.regex)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        self.include.as_ref().is_none_or(|p| p.matches(path))
+            && !self.exclude.as_ref().is_some_and(|p| p.matches(path))
+    }
+}
+
+impl Patterns {
+    fn build(patterns: &[&str], regex: bool) -> Result<Option<Self>> {
+        if patterns.is_empty() { return Ok(None); }
+
+        if regex {
+            let set = RegexSetBuilder::new(patterns).case_insensitive(true).build()
+                .context("invalid regex")?;
+            return Ok(Some(Self::Regex(set)));
+        }
+
+        let mut set = GlobSetBuilder::new();
+        for p in patterns {
+            let glob = GlobBuilder::new(p).case_insensitive(true).build()
+                .with_context(|| format!("invalid glob '{}'", p))?;
+            set.add(glob);
+        }
+        Ok(Some(Self::Glob(set.build()?)))
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Glob(set) => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                set.is_match(path) || set.is_match(name)
             }
+            Self::Regex(set) => set.is_match(path),
         }
-    } else {
-        // Exact match or substring match when no wildcard present
-        path == pattern || path.contains(pattern)
     }
-} 
\ No newline at end of file
+}
+
+/// True when `pattern` names a single path rather than a glob.
+pub fn is_literal(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Escape text for use in XML content and attribute values.
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(pattern: Option<&str>, include: &[&str], exclude: &[&str], regex: bool) -> PathFilter {
+        let args = FilterArgs {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            regex,
+        };
+        PathFilter::new(pattern, &args).unwrap()
+    }
+
+    #[test]
+    fn globs_match_paths_and_names() {
+        let f = filter(Some("*.ydr"), &[], &[], false);
+        assert!(f.matches("a.ydr") && f.matches("x/y/B.YDR"));
+        assert!(!f.matches("a.yft"));
+
+        let f = filter(None, &["props/**/*.{ydr,yft}"], &[], false);
+        assert!(f.matches("props/a/b/c.yft") && f.matches("PROPS/x.ydr"));
+        assert!(!f.matches("other/c.yft"));
+
+        let f = filter(None, &["car?.y[dt]d"], &[], false);
+        assert!(f.matches("dir/car1.ytd") && f.matches("car2.ydd"));
+        assert!(!f.matches("car12.ytd"));
+    }
+
+    #[test]
+    fn excludes_win_over_includes() {
+        let f = filter(None, &["*.ydr", "*.ytd"], &["**/lod/**", "*_hi.*"], false);
+        assert!(f.matches("a/b.ydr"));
+        assert!(!f.matches("a/lod/b.ydr"));
+        assert!(!f.matches("b_hi.ytd"));
+        assert!(!f.matches("b.yft"));
+
+        let f = filter(None, &[], &["*.xml"], false);
+        assert!(f.matches("a.meta") && !f.matches("x/a.xml"));
+    }
+
+    #[test]
+    fn regexes_search_the_whole_path() {
+        let f = filter(Some(r"^levels/.*\.ymap$"), &[], &["_lod"], true);
+        assert!(f.matches("LEVELS/gta5/a.ymap"));
+        assert!(!f.matches("levels/a_lod.ymap"));
+        assert!(!f.matches("x/levels/a.ymap"));
+        assert!(filter(None, &["car"], &[], true).matches("vehicles/supercar.yft"));
+    }
+
+    #[test]
+    fn empty_and_invalid_filters() {
+        let f = filter(None, &[], &[], false);
+        assert!(f.is_empty() && f.matches("anything"));
+        let args = FilterArgs { include: vec!["(".into()], regex: true, ..Default::default() };
+        assert!(PathFilter::new(None, &args).is_err());
+        assert!(PathFilter::new(Some("[a"), &FilterArgs::default()).is_err());
+        assert!(is_literal("dir/a.ydr") && !is_literal("*.ydr") && !is_literal("a.{b,c}"));
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(xml_escape("a<b> & \"c\""), "a&lt;b&gt; &amp; &quot;c&quot;");
+    }
+}
@@ -0,0 +1,349 @@
+// Map placements (.ymap): the `CMapData` root of a meta document and its `CEntityDef`s, and
+// the `CMapTypes` archetypes of .ytyp files that give the entities their size.
+//
+// Fields are found by name through the structure definitions stored in the file, not at
+// fixed offsets, so `CMloInstanceDef` entities (which carry the `CEntityDef` fields plus
+// their own) and the archetype definitions derived from `CBaseArchetypeDef` read the same
+// way. The entity extents bound each entity's archetype box, scaled, rotated and placed;
+// the streaming extents bound those boxes grown by the LOD distance. Both are stored in the
+// file and go stale when entities are moved by hand. `computed_extents` follows
+// CodeWalker's `YmapFile.CalcExtents`.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+use crate::meta::{data_type as dt, decode_pointer, EntryInfo, Meta, StructInfo};
+use crate::names::hash;
+use crate::resource::{f32_at, u16_at, u32_at};
+
+/// Size of a structure pointer array element.
+const POINTER_SIZE: usize = 8;
+
+/// An axis-aligned box, `(min, max)`.
+pub type Extents = ([f32; 3], [f32; 3]);
+
+pub struct MapData {
+    pub name             : u32,
+    pub parent           : u32,
+    pub flags            : u32,
+    pub content_flags    : u32,
+    pub streaming_extents: Extents,
+    pub entities_extents : Extents,
+    pub entities         : Vec<Entity>,
+}
+
+pub struct Entity {
+    /// Structure type, `CEntityDef` or `CMloInstanceDef`.
+    pub kind          : u32,
+    pub archetype     : u32,
+    pub guid          : u32,
+    pub position      : [f32; 3],
+    /// Quaternion, x y z w as stored.
+    pub rotation      : [f32; 4],
+    /// `scaleXY`, `scaleXY`, `scaleZ`.
+    pub scale         : [f32; 3],
+    pub lod_dist      : f32,
+    pub child_lod_dist: f32,
+    /// `rage__eLodType` value and the hash of its name, when the enum defines it.
+    pub lod_level     : (i32, Option<u32>),
+    /// Index into the parent map's entities, or -1.
+    pub parent_index  : i32,
+    pub num_children  : u32,
+}
+
+/// The parts of a `CBaseArchetypeDef` (or a derived definition) that extents depend on.
+pub struct Archetype {
+    pub name    : u32,
+    /// Local bounding box.
+    pub bounds  : Extents,
+    pub lod_dist: f32,
+}
+
+/// Extents recomputed from the entities of a map.
+pub struct Computed {
+    pub streaming: Extents,
+    pub entities : Extents,
+    /// Entities whose archetype was not given, counted as points at their position.
+    pub unknown  : usize,
+}
+
+/// One structure of a meta document, with its fields looked up by name.
+struct Fields<'a> {
+    meta: &'a Meta,
+    info: &'a StructInfo,
+    data: Vec<u8>,
+}
+
+impl<'a> Fields<'a> {
+    fn entry(&self, name: &str) -> Result<&'a EntryInfo> {
+        self.info.entry(hash(name)).with_context(|| format!("structure has no '{}' field", name))
+    }
+
+    fn bytes(&self, name: &str, len: usize) -> Result<&[u8]> {
+        let at = self.entry(name)?.offset as usize;
+        self.data.get(at..at + len).with_context(|| format!("field '{}' runs past its structure", name))
+    }
+
+    fn u32(&self, name: &str) -> Result<u32> {
+        Ok(u32_at(self.bytes(name, 4)?, 0))
+    }
+
+    /// A 4-byte field that some files omit.
+    fn u32_or(&self, name: &str, default: u32) -> Result<u32> {
+        if self.info.entry(hash(name)).is_none() { return Ok(default); }
+        self.u32(name)
+    }
+
+    fn f32(&self, name: &str) -> Result<f32> {
+        Ok(f32::from_bits(self.u32(name)?))
+    }
+
+    fn f32_or(&self, name: &str, default: f32) -> Result<f32> {
+        Ok(f32::from_bits(self.u32_or(name, default.to_bits())?))
+    }
+
+    fn vector<const N: usize>(&self, name: &str) -> Result<[f32; N]> {
+        let b = self.bytes(name, N * 4)?;
+        Ok(std::array::from_fn(|i| f32_at(b, i * 4)))
+    }
+
+    /// An enum field: its value and the hash of the value's name.
+    fn enumeration(&self, name: &str) -> Result<(i32, Option<u32>)> {
+        let e = self.entry(name)?;
+        let value = match e.kind {
+            dt::BYTE_ENUM => self.bytes(name, 1)?[0] as i32,
+            _             => self.u32(name)? as i32,
+        };
+        let label = self.meta.enum_info(e.ref_key)
+            .and_then(|info| info.entries.iter().find(|&&(_, v)| v == value))
+            .map(|&(n, _)| n);
+        Ok((value, label))
+    }
+
+    /// The structures of an array field, whether stored inline or as pointers.
+    fn array(&self, name: &str) -> Result<Vec<Fields<'a>>> {
+        let e = self.entry(name)?;
+        if e.kind != dt::ARRAY { bail!("field '{}' is not an array", name); }
+        let header = self.bytes(name, 16)?;
+        let (ptr, count) = (u32_at(header, 0), u16_at(header, 8) as usize);
+        if ptr == 0 || count == 0 { return Ok(Vec::new()); }
+
+        let elem = self.info.array_element(e)?;
+        match elem.kind {
+            dt::STRUCTURE => {
+                let info = struct_info(self.meta, elem.ref_key)?;
+                let size = info.size as usize;
+                let data = self.meta.data(ptr, size * count)?;
+                Ok(data.chunks_exact(size).map(|d| Fields { meta: self.meta, info, data: d.to_vec() }).collect())
+            }
+            dt::STRUCTURE_POINTER => {
+                let pointers = self.meta.data(ptr, POINTER_SIZE * count)?;
+                pointers.chunks_exact(POINTER_SIZE)
+                    .filter(|p| u32_at(p, 0) != 0)
+                    .map(|p| pointed(self.meta, u32_at(p, 0)))
+                    .collect()
+            }
+            kind => bail!("field '{}' is an array of type 0x{:02X}, not of structures", name, kind),
+        }
+    }
+}
+
+fn struct_info(meta: &Meta, name: u32) -> Result<&StructInfo> {
+    meta.struct_info(name).with_context(|| format!("no structure definition for 0x{:08X}", name))
+}
+
+/// The structure at a data block pointer; its type is the name of the block.
+fn pointed(meta: &Meta, ptr: u32) -> Result<Fields<'_>> {
+    let (block, _) = decode_pointer(ptr).context("null pointer")?;
+    let name = meta.blocks.get(block)
+        .with_context(|| format!("pointer 0x{:08X} to missing data block", ptr))?.name;
+    let info = struct_info(meta, name)?;
+    Ok(Fields { meta, info, data: meta.data(ptr, info.size as usize)? })
+}
+
+/// The root structure of a meta document, which must be of type `expected`.
+fn root<'a>(meta: &'a Meta, expected: &str) -> Result<Fields<'a>> {
+    let block = &meta.blocks[meta.root];
+    let info = struct_info(meta, block.name)?;
+    if info.name != hash(expected) {
+        bail!("root structure is 0x{:08X}, not {}", info.name, expected);
+    }
+    Ok(Fields { meta, info, data: block.data.get(..info.size as usize).context("root structure is truncated")?.to_vec() })
+}
+
+/// Read the `CMapData` root of a .ymap.
+pub fn read(meta: &Meta) -> Result<MapData> {
+    let root = root(meta, "CMapData")?;
+    let entities = root.array("entities")?.iter().enumerate()
+        .map(|(i, e)| entity(e).with_context(|| format!("entity {}", i)))
+        .collect::<Result<_>>()?;
+
+    Ok(MapData {
+        name             : root.u32("name")?,
+        parent           : root.u32("parent")?,
+        flags            : root.u32("flags")?,
+        content_flags    : root.u32("contentFlags")?,
+        streaming_extents: (root.vector("streamingExtentsMin")?, root.vector("streamingExtentsMax")?),
+        entities_extents : (root.vector("entitiesExtentsMin")?, root.vector("entitiesExtentsMax")?),
+        entities,
+    })
+}
+
+fn entity(e: &Fields) -> Result<Entity> {
+    Ok(Entity {
+        kind          : e.info.name,
+        archetype     : e.u32("archetypeName")?,
+        guid          : e.u32_or("guid", 0)?,
+        position      : e.vector("position")?,
+        rotation      : e.vector("rotation")?,
+        scale         : {
+            let xy = e.f32_or("scaleXY", 1.0)?;
+            [xy, xy, e.f32_or("scaleZ", 1.0)?]
+        },
+        lod_dist      : e.f32("lodDist")?,
+        child_lod_dist: e.f32("childLodDist")?,
+        lod_level     : e.enumeration("lodLevel")?,
+        parent_index  : e.u32("parentIndex")? as i32,
+        num_children  : e.u32_or("numChildren", 0)?,
+    })
+}
+
+/// Read the archetypes of the `CMapTypes` root of a .ytyp.
+pub fn read_archetypes(meta: &Meta) -> Result<Vec<Archetype>> {
+    root(meta, "CMapTypes")?.array("archetypes")?.iter().enumerate()
+        .map(|(i, a)| archetype(a).with_context(|| format!("archetype {}", i)))
+        .collect()
+}
+
+fn archetype(a: &Fields) -> Result<Archetype> {
+    Ok(Archetype {
+        name    : a.u32("name")?,
+        bounds  : (a.vector("bbMin")?, a.vector("bbMax")?),
+        lod_dist: a.f32("lodDist")?,
+    })
+}
+
+/// Rotate `v` by the unit quaternion `q` (x y z w).
+fn rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let [x, y, z, w] = q;
+    // t = 2 (q × v); v' = v + w t + q × t
+    let t = [2.0 * (y * v[2] - z * v[1]), 2.0 * (z * v[0] - x * v[2]), 2.0 * (x * v[1] - y * v[0])];
+    [
+        v[0] + w * t[0] + y * t[2] - z * t[1],
+        v[1] + w * t[1] + z * t[0] - x * t[2],
+        v[2] + w * t[2] + x * t[1] - y * t[0],
+    ]
+}
+
+impl Entity {
+    /// The world-space box of the entity with the local box `bounds`. Map entities store the
+    /// inverse of their orientation.
+    pub fn world_box(&self, bounds: &Extents) -> Extents {
+        let [x, y, z, w] = self.rotation;
+        let len = (x * x + y * y + z * z + w * w).sqrt();
+        let q = if len > 0.0 { [-x / len, -y / len, -z / len, w / len] } else { [0.0, 0.0, 0.0, 1.0] };
+        let mut world: Extents = ([f32::MAX; 3], [f32::MIN; 3]);
+        for corner in 0..8 {
+            let local: [f32; 3] = std::array::from_fn(|i| {
+                let b = if corner & (1 << i) == 0 { bounds.0[i] } else { bounds.1[i] };
+                b * self.scale[i]
+            });
+            for (i, d) in rotate(q, local).into_iter().enumerate() {
+                world.0[i] = world.0[i].min(self.position[i] + d);
+                world.1[i] = world.1[i].max(self.position[i] + d);
+            }
+        }
+        world
+    }
+}
+
+impl MapData {
+    /// Extents recomputed from the entities and their archetypes, or `None` without
+    /// entities. An entity with no LOD distance of its own uses its archetype's; entities
+    /// whose archetype is not in `archetypes` count as points.
+    pub fn computed_extents(&self, archetypes: &HashMap<u32, Archetype>) -> Option<Computed> {
+        if self.entities.is_empty() { return None; }
+        let mut computed = Computed {
+            streaming: ([f32::MAX; 3], [f32::MIN; 3]),
+            entities : ([f32::MAX; 3], [f32::MIN; 3]),
+            unknown  : 0,
+        };
+        for e in &self.entities {
+            let archetype = archetypes.get(&e.archetype);
+            let (bounds, lod_dist) = match archetype {
+                Some(a) => (e.world_box(&a.bounds), if e.lod_dist > 0.0 { e.lod_dist } else { a.lod_dist }),
+                None    => {
+                    computed.unknown += 1;
+                    ((e.position, e.position), e.lod_dist)
+                }
+            };
+            let reach = lod_dist.max(0.0);
+            for i in 0..3 {
+                computed.entities.0[i] = computed.entities.0[i].min(bounds.0[i]);
+                computed.entities.1[i] = computed.entities.1[i].max(bounds.1[i]);
+                computed.streaming.0[i] = computed.streaming.0[i].min(bounds.0[i] - reach);
+                computed.streaming.1[i] = computed.streaming.1[i].max(bounds.1[i] + reach);
+            }
+        }
+        Some(computed)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn entity(archetype: u32, position: [f32; 3], rotation: [f32; 4], lod_dist: f32) -> Entity {
+        Entity {
+            kind: 0, archetype, guid: 0, position, rotation, lod_dist,
+            scale         : [1.0; 3],
+            child_lod_dist: 0.0,
+            lod_level     : (0, None),
+            parent_index  : -1,
+            num_children  : 0,
+        }
+    }
+
+    pub(crate) fn assert_near(a: &Extents, b: &Extents) {
+        for (x, y) in a.0.iter().chain(&a.1).zip(b.0.iter().chain(&b.1)) {
+            assert!((x - y).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn rotates_boxes_by_the_inverse_quaternion() {
+        let bounds = ([0.0, 0.0, 0.0], [2.0, 1.0, 3.0]);
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+
+        // Stored as the inverse, so this turns the box a quarter turn anticlockwise about Z.
+        let e = entity(0, [10.0, 20.0, 30.0], [0.0, 0.0, -s, s], 0.0);
+        assert_near(&e.world_box(&bounds), &([9.0, 20.0, 30.0], [10.0, 22.0, 33.0]));
+
+        let mut e = entity(0, [10.0, 20.0, 30.0], [0.0, 0.0, 0.0, 1.0], 0.0);
+        e.scale = [2.0, 2.0, 0.5];
+        assert_near(&e.world_box(&bounds), &([10.0, 20.0, 30.0], [14.0, 22.0, 31.5]));
+        // A zero quaternion counts as no rotation.
+        e.rotation = [0.0; 4];
+        assert_near(&e.world_box(&bounds), &([10.0, 20.0, 30.0], [14.0, 22.0, 31.5]));
+    }
+
+    #[test]
+    fn computes_extents_from_archetypes() {
+        let archetypes = HashMap::from([(1, Archetype { name: 1, bounds: ([-1.0; 3], [1.0; 3]), lod_dist: 50.0 })]);
+        let map = MapData {
+            name: 0, parent: 0, flags: 0, content_flags: 0,
+            streaming_extents: ([0.0; 3], [0.0; 3]),
+            entities_extents : ([0.0; 3], [0.0; 3]),
+            entities         : vec![
+                entity(1, [0.0; 3], [0.0, 0.0, 0.0, 1.0], 0.0),
+                entity(1, [10.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], 100.0),
+                entity(2, [0.0, -5.0, 0.0], [0.0, 0.0, 0.0, 1.0], 20.0),
+            ],
+        };
+        let c = map.computed_extents(&archetypes).unwrap();
+        assert_eq!(c.unknown, 1);
+        assert_near(&c.entities, &([-1.0, -5.0, -1.0], [11.0, 1.0, 1.0]));
+        assert_near(&c.streaming, &([-91.0, -101.0, -101.0], [111.0, 101.0, 101.0]));
+        assert!(MapData { entities: Vec::new(), ..map }.computed_extents(&archetypes).is_none());
+    }
+}